@@ -0,0 +1,6 @@
+use hashes::anemoi::AnemoiGadget;
+
+use super::sponge_channel::SpongeChannelGadget;
+
+
+pub type AnemoiChannelGadget<'a, E> = SpongeChannelGadget<'a, E, AnemoiGadget<E>>;
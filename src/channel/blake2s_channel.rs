@@ -0,0 +1,8 @@
+use super::*;
+use super::digest_channel::*;
+use common::blake2s::Blake2sHasher;
+
+
+pub type Blake2sChannelGadget<E> = DigestChannelGadget<E, Blake2sHasher>;
+pub type Blake2sChannel<F> = DigestChannel<F, Blake2sHasher>;
+
@@ -0,0 +1,135 @@
+use super::*;
+use common::boolean::Boolean;
+use common::digest::*;
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+};
+
+
+// Fiat-Shamir transcript compatible with EVM (and any other sha256/keccak-based) verifier:
+// consumed elements are encoded as 32-byte big-endian integers and buffered,
+// every challenge is derived as state = H(state || buffered data),
+// and the challenge itself is the state truncated to the lowest CAPACITY bits
+pub struct DigestChannelGadget<E: Engine, H: DigestHasher> {
+    state: Vec<Boolean>,
+    pending: Vec<Boolean>,
+    _marker: std::marker::PhantomData<(E, H)>,
+}
+
+impl<E: Engine, H: DigestHasher> ChannelGadget<E> for DigestChannelGadget<E, H> {
+    type Params = ();
+
+    fn new(_channel_params: Self::Params) -> Self {
+        Self {
+            state: vec![Boolean::constant(false); DIGEST_SIZE],
+            pending: vec![],
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    fn consume<CS: ConstraintSystem<E>>(&mut self, data: AllocatedNum<E>, cs: CS) -> Result<(), SynthesisError> {
+        let bits = field_element_into_be_bits(cs, &data)?;
+        self.pending.extend(bits);
+
+        Ok(())
+    }
+
+    fn produce_challenge<CS: ConstraintSystem<E>>(&mut self, mut cs: CS) -> Result<AllocatedNum<E>, SynthesisError> {
+        let mut input = std::mem::replace(&mut self.state, vec![]);
+        input.extend(self.pending.drain(..));
+
+        self.state = H::hash(cs.namespace(|| "update channel state"), &input)?;
+
+        let capacity = E::Fr::CAPACITY as usize;
+        pack_be_bits(cs.namespace(|| "truncate state into challenge"), &self.state[(DIGEST_SIZE - capacity)..])
+    }
+}
+
+
+// native counterpart of the gadget, must produce exactly the same challenges
+pub struct DigestChannel<F: PrimeField, H: DigestHasher> {
+    state: [u8; 32],
+    pending: Vec<u8>,
+    _marker: std::marker::PhantomData<(F, H)>,
+}
+
+impl<F: PrimeField, H: DigestHasher> DigestChannel<F, H> {
+    pub fn new() -> Self {
+        Self {
+            state: [0u8; 32],
+            pending: vec![],
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    pub fn consume(&mut self, data: &F) {
+        self.pending.extend_from_slice(&encode_field_element(data));
+    }
+
+    pub fn produce_challenge(&mut self) -> F {
+        let mut input = self.state.to_vec();
+        input.extend(self.pending.drain(..));
+        self.state = H::hash_native(&input);
+
+        let mut truncated = self.state;
+        let num_zero_bits = DIGEST_SIZE - F::CAPACITY as usize;
+        for i in 0..num_zero_bits {
+            truncated[i / 8] &= !(0x80u8 >> (i % 8));
+        }
+        decode_field_element(&truncated)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::ConstraintSystem;
+    use crate::tester::naming_oblivious_cs::NamingObliviousConstraintSystem as TestConstraintSystem;
+    use rand::{XorShiftRng, SeedableRng, Rng};
+
+    use common::sha256::Sha256Hasher;
+    use common::blake2s::Blake2sHasher;
+    use hashes::keccak::Keccak256Hasher;
+    use super::*;
+
+    fn check_channel_against_native<H: DigestHasher>() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let mut channel = DigestChannelGadget::<Bn256, H>::new(());
+        let mut native_channel = DigestChannel::<Fr, H>::new();
+
+        // consume-produce pattern of the verifier: several elements, challenge, one element, two challenges in a row
+        for &num_elems in [3usize, 1, 0].iter() {
+            for _ in 0..num_elems {
+                let x : Fr = rng.gen();
+                let num = AllocatedNum::alloc2(cs.namespace(|| "elem"), Some(x)).unwrap();
+                channel.consume(num, cs.namespace(|| "consume")).unwrap();
+                native_channel.consume(&x);
+            }
+
+            let challenge = channel.produce_challenge(cs.namespace(|| "challenge")).unwrap();
+            assert_eq!(challenge.get_value(), Some(native_channel.produce_challenge()));
+        }
+
+        assert!(cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_sha256_channel_against_native() {
+        check_channel_against_native::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn test_keccak_channel_against_native() {
+        check_channel_against_native::<Keccak256Hasher>();
+    }
+
+    #[test]
+    fn test_blake2s_channel_against_native() {
+        check_channel_against_native::<Blake2sHasher>();
+    }
+}
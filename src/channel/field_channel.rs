@@ -0,0 +1,60 @@
+use super::*;
+use common::field_gadget::*;
+
+
+// transcript over elements of arbitrary (base or extension) fields on top of the native channel:
+// elements are consumed in the form of their native limbs (FieldGadget::into_native_elems),
+// and challenges are assembled from several native challenges (ChallengeFieldGadget),
+// the native counterpart is FriProverChannel implementation for NativeSpongeChannel
+pub struct FieldChannelGadget<E: Engine, T: ChannelGadget<E>> {
+    channel: T,
+    _marker: std::marker::PhantomData<E>,
+}
+
+impl<E: Engine, T: ChannelGadget<E>> FieldChannelGadget<E, T> {
+    pub fn consume_elem<F: FieldGadget<E>, CS: ConstraintSystem<E>>(
+        &mut self,
+        elem: &F,
+        mut cs: CS,
+    ) -> Result<(), SynthesisError>
+    {
+        let native_elems = elem.into_native_elems(cs.namespace(|| "into native elements"))?;
+        for e in native_elems {
+            self.channel.consume(e, cs.namespace(|| "consume native element"))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn produce_field_challenge<F: ChallengeFieldGadget<E>, CS: ConstraintSystem<E>>(
+        &mut self,
+        mut cs: CS,
+    ) -> Result<F, SynthesisError>
+    {
+        let mut challenges = Vec::with_capacity(F::num_native_challenges());
+        for _ in 0..F::num_native_challenges() {
+            challenges.push(self.channel.produce_challenge(cs.namespace(|| "produce native challenge"))?);
+        }
+
+        F::from_native_challenges(cs.namespace(|| "assemble field challenge"), &challenges)
+    }
+}
+
+impl<E: Engine, T: ChannelGadget<E>> ChannelGadget<E> for FieldChannelGadget<E, T> {
+    type Params = T::Params;
+
+    fn new(params: Self::Params) -> Self {
+        FieldChannelGadget {
+            channel: T::new(params),
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    fn consume<CS: ConstraintSystem<E>>(&mut self, data: AllocatedNum<E>, cs: CS) -> Result<(), SynthesisError> {
+        self.channel.consume(data, cs)
+    }
+
+    fn produce_challenge<CS: ConstraintSystem<E>>(&mut self, cs: CS) -> Result<AllocatedNum<E>, SynthesisError> {
+        self.channel.produce_challenge(cs)
+    }
+}
@@ -0,0 +1,6 @@
+use hashes::griffin::GriffinGadget;
+
+use super::sponge_channel::SpongeChannelGadget;
+
+
+pub type GriffinChannelGadget<'a, E> = SpongeChannelGadget<'a, E, GriffinGadget<E>>;
@@ -0,0 +1,8 @@
+use super::*;
+use super::digest_channel::*;
+use hashes::keccak::Keccak256Hasher;
+
+
+pub type KeccakChannelGadget<E> = DigestChannelGadget<E, Keccak256Hasher>;
+pub type KeccakChannel<F> = DigestChannel<F, Keccak256Hasher>;
+
@@ -8,7 +8,17 @@ use bellman::{
     ConstraintSystem,
 };
 
+pub mod sponge_channel;
 pub mod rescue_channel;
+pub mod poseidon_channel;
+pub mod rescue_prime_channel;
+pub mod anemoi_channel;
+pub mod griffin_channel;
+pub mod digest_channel;
+pub mod sha256_channel;
+pub mod keccak_channel;
+pub mod blake2s_channel;
+pub mod field_channel;
 
 
 pub trait ChannelGadget<E: Engine> {
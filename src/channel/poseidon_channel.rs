@@ -0,0 +1,6 @@
+use hashes::poseidon::PoseidonGadget;
+
+use super::sponge_channel::SpongeChannelGadget;
+
+
+pub type PoseidonChannelGadget<'a, E, P> = SpongeChannelGadget<'a, E, PoseidonGadget<E, P>>;
@@ -1,31 +1,6 @@
-use super::*;
-use hashes::rescue::{RescueGadget, RescueSbox};
+use hashes::rescue::RescueGadget;
 
-use bellman::redshift::IOP::hashes::rescue::{Rescue, RescueParams};
+use super::sponge_channel::SpongeChannelGadget;
 
 
-pub struct RescueChannelGadget<'a, E: Engine, RP: RescueParams<E::Fr>, SBOX: RescueSbox<E>> {
-    state: RescueGadget<E, RP, SBOX>,
-    params: &'a RP,
-}
-
-impl<'a, E, RP, SBOX> ChannelGadget<E> for RescueChannelGadget<'a, E, RP, SBOX>
-where E: Engine, RP: RescueParams<E::Fr>, SBOX: RescueSbox<E>
-{
-    type Params = &'a RP;
-
-    fn new(channel_params: Self::Params) -> Self {
-        Self {
-            state: RescueGadget::<E, RP, SBOX>::new(channel_params),
-            params: channel_params,
-        }
-    }
-
-    fn consume<CS: ConstraintSystem<E>>(&mut self, data: AllocatedNum<E>, cs: CS) -> Result<(), SynthesisError> {      
-        self.state.absorb(data, cs, self.params)
-    }
-
-    fn produce_challenge<CS: ConstraintSystem<E>>(&mut self, cs: CS) -> Result<AllocatedNum<E>, SynthesisError> {
-        self.state.squeeze(cs, self.params)
-    }
-}
\ No newline at end of file
+pub type RescueChannelGadget<'a, E, RP, SBOX> = SpongeChannelGadget<'a, E, RescueGadget<E, RP, SBOX>>;
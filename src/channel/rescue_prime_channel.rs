@@ -0,0 +1,6 @@
+use hashes::rescue_prime::RescuePrimeGadget;
+
+use super::sponge_channel::SpongeChannelGadget;
+
+
+pub type RescuePrimeChannelGadget<'a, E, P> = SpongeChannelGadget<'a, E, RescuePrimeGadget<E, P>>;
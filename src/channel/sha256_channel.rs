@@ -0,0 +1,8 @@
+use super::*;
+use super::digest_channel::*;
+use common::sha256::Sha256Hasher;
+
+
+pub type Sha256ChannelGadget<E> = DigestChannelGadget<E, Sha256Hasher>;
+pub type Sha256Channel<F> = DigestChannel<F, Sha256Hasher>;
+
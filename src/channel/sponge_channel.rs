@@ -0,0 +1,59 @@
+use super::*;
+use bellman::pairing::ff::PrimeField;
+use hashes::{SpongeGadget, NativeSponge};
+
+
+// transcript over arbitrary algebraic sponge: data is absorbed, challenges are squeezed
+pub struct SpongeChannelGadget<'a, E: Engine, S: SpongeGadget<E>> {
+    state: S,
+    params: &'a S::Params,
+    _marker: std::marker::PhantomData<E>,
+}
+
+impl<'a, E, S> ChannelGadget<E> for SpongeChannelGadget<'a, E, S>
+where E: Engine, S: SpongeGadget<E>
+{
+    type Params = &'a S::Params;
+
+    fn new(channel_params: Self::Params) -> Self {
+        Self {
+            state: S::new(channel_params),
+            params: channel_params,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    fn consume<CS: ConstraintSystem<E>>(&mut self, data: AllocatedNum<E>, cs: CS) -> Result<(), SynthesisError> {
+        self.state.absorb(data, cs, self.params)
+    }
+
+    fn produce_challenge<CS: ConstraintSystem<E>>(&mut self, cs: CS) -> Result<AllocatedNum<E>, SynthesisError> {
+        self.state.squeeze(cs, self.params)
+    }
+}
+
+
+// native counterpart of SpongeChannelGadget, is used by provers
+pub struct NativeSpongeChannel<'a, F: PrimeField, S: NativeSponge<F, P>, P> {
+    state: S,
+    params: &'a P,
+    _marker: std::marker::PhantomData<F>,
+}
+
+impl<'a, F: PrimeField, S: NativeSponge<F, P>, P> NativeSpongeChannel<'a, F, S, P> {
+    pub fn new(params: &'a P) -> Self {
+        Self {
+            state: S::new(params),
+            params,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    pub fn consume(&mut self, data: F) {
+        self.state.absorb(data, self.params)
+    }
+
+    pub fn produce_challenge(&mut self) -> F {
+        self.state.squeeze(self.params)
+    }
+}
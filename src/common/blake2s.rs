@@ -0,0 +1,247 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+};
+
+use byteorder::{ByteOrder, LittleEndian};
+use blake2_rfc::blake2s::Blake2s;
+
+use super::uint32::UInt32;
+use super::multieq::MultiEq;
+use super::boolean::Boolean;
+use super::digest::{DigestHasher, reverse_bits_in_bytes};
+
+
+const R1: usize = 16;
+const R2: usize = 12;
+const R3: usize = 8;
+const R4: usize = 7;
+
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0]
+];
+
+const IV: [u32; 8] = [
+    0x6A09E667, 0xBB67AE85, 0x3C6EF372, 0xA54FF53A,
+    0x510E527F, 0x9B05688C, 0x1F83D9AB, 0x5BE0CD19
+];
+
+pub const BLAKE2S_BLOCK_SIZE: usize = 512;
+pub const BLAKE2S_PERSONALIZATION_SIZE: usize = 8;
+
+
+fn mixing_g<E, CS, M>(
+    mut cs: M,
+    v: &mut [UInt32],
+    a: usize,
+    b: usize,
+    c: usize,
+    d: usize,
+    x: &UInt32,
+    y: &UInt32
+) -> Result<(), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>,
+          M: ConstraintSystem<E, Root=MultiEq<E, CS>>
+{
+    v[a] = UInt32::addmany(cs.namespace(|| "mixing step 1"), &[v[a].clone(), v[b].clone(), x.clone()])?;
+    v[d] = v[d].xor(cs.namespace(|| "mixing step 2"), &v[a])?.rotr(R1);
+    v[c] = UInt32::addmany(cs.namespace(|| "mixing step 3"), &[v[c].clone(), v[d].clone()])?;
+    v[b] = v[b].xor(cs.namespace(|| "mixing step 4"), &v[c])?.rotr(R2);
+    v[a] = UInt32::addmany(cs.namespace(|| "mixing step 5"), &[v[a].clone(), v[b].clone(), y.clone()])?;
+    v[d] = v[d].xor(cs.namespace(|| "mixing step 6"), &v[a])?.rotr(R3);
+    v[c] = UInt32::addmany(cs.namespace(|| "mixing step 7"), &[v[c].clone(), v[d].clone()])?;
+    v[b] = v[b].xor(cs.namespace(|| "mixing step 8"), &v[c])?.rotr(R4);
+
+    Ok(())
+}
+
+// h is the chaining value, m is the message block, t is the number of bytes hashed so far,
+// f is set for the last block only
+pub fn blake2s_compression<E, CS>(
+    mut cs: CS,
+    h: &mut [UInt32],
+    m: &[UInt32],
+    t: u64,
+    f: bool
+) -> Result<(), SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert_eq!(h.len(), 8);
+    assert_eq!(m.len(), 16);
+
+    let mut v = Vec::with_capacity(16);
+    v.extend_from_slice(h);
+    v.extend(IV.iter().map(|x| UInt32::constant(*x)));
+
+    v[12] = v[12].xor(cs.namespace(|| "first xor"), &UInt32::constant(t as u32))?;
+    v[13] = v[13].xor(cs.namespace(|| "second xor"), &UInt32::constant((t >> 32) as u32))?;
+
+    if f {
+        v[14] = v[14].xor(cs.namespace(|| "third xor"), &UInt32::constant(u32::max_value()))?;
+    }
+
+    {
+        let mut cs = MultiEq::new(&mut cs);
+
+        for i in 0..10 {
+            let mut cs = cs.namespace(|| format!("round {}", i));
+
+            let s = SIGMA[i % 10];
+
+            mixing_g(cs.namespace(|| "mixing invocation 1"), &mut v, 0, 4, 8, 12, &m[s[0]], &m[s[1]])?;
+            mixing_g(cs.namespace(|| "mixing invocation 2"), &mut v, 1, 5, 9, 13, &m[s[2]], &m[s[3]])?;
+            mixing_g(cs.namespace(|| "mixing invocation 3"), &mut v, 2, 6, 10, 14, &m[s[4]], &m[s[5]])?;
+            mixing_g(cs.namespace(|| "mixing invocation 4"), &mut v, 3, 7, 11, 15, &m[s[6]], &m[s[7]])?;
+
+            mixing_g(cs.namespace(|| "mixing invocation 5"), &mut v, 0, 5, 10, 15, &m[s[8]], &m[s[9]])?;
+            mixing_g(cs.namespace(|| "mixing invocation 6"), &mut v, 1, 6, 11, 12, &m[s[10]], &m[s[11]])?;
+            mixing_g(cs.namespace(|| "mixing invocation 7"), &mut v, 2, 7, 8, 13, &m[s[12]], &m[s[13]])?;
+            mixing_g(cs.namespace(|| "mixing invocation 8"), &mut v, 3, 4, 9, 14, &m[s[14]], &m[s[15]])?;
+        }
+    }
+
+    for i in 0..8 {
+        let mut cs = cs.namespace(|| format!("h[{i}] ^ v[{i}] ^ v[{i} + 8]", i=i));
+
+        h[i] = h[i].xor(cs.namespace(|| "first xor"), &v[i])?;
+        h[i] = h[i].xor(cs.namespace(|| "second xor"), &v[i + 8])?;
+    }
+
+    Ok(())
+}
+
+// blake2s-256 without key: input and output are in little-endian bit order inside every byte
+pub fn blake2s<E, CS>(
+    mut cs: CS,
+    input: &[Boolean],
+    personalization: &[u8]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert_eq!(personalization.len(), BLAKE2S_PERSONALIZATION_SIZE);
+    assert!(input.len() % 8 == 0);
+
+    let mut h = Vec::with_capacity(8);
+    // parameter block: digest length = 32, no key, fanout = depth = 1
+    h.push(UInt32::constant(IV[0] ^ 0x01010000 ^ 32));
+    h.extend(IV[1..6].iter().map(|x| UInt32::constant(*x)));
+    // personalization is stored here
+    h.push(UInt32::constant(IV[6] ^ LittleEndian::read_u32(&personalization[0..4])));
+    h.push(UInt32::constant(IV[7] ^ LittleEndian::read_u32(&personalization[4..8])));
+
+    let mut blocks: Vec<Vec<UInt32>> = vec![];
+
+    for block in input.chunks(BLAKE2S_BLOCK_SIZE) {
+        let mut this_block = Vec::with_capacity(16);
+        for word in block.chunks(32) {
+            let mut tmp = word.to_vec();
+            while tmp.len() < 32 {
+                tmp.push(Boolean::constant(false));
+            }
+            this_block.push(UInt32::from_bits(&tmp));
+        }
+        while this_block.len() < 16 {
+            this_block.push(UInt32::constant(0));
+        }
+        blocks.push(this_block);
+    }
+
+    if blocks.len() == 0 {
+        blocks.push((0..16).map(|_| UInt32::constant(0)).collect());
+    }
+
+    for (i, block) in blocks[0..blocks.len() - 1].iter().enumerate() {
+        let cs = cs.namespace(|| format!("block {}", i));
+
+        blake2s_compression(cs, &mut h, block, ((i as u64) + 1) * 64, false)?;
+    }
+
+    {
+        let cs = cs.namespace(|| "final block");
+
+        blake2s_compression(cs, &mut h, &blocks[blocks.len() - 1], (input.len() / 8) as u64, true)?;
+    }
+
+    Ok(h.iter().flat_map(|b| b.into_bits()).collect())
+}
+
+pub fn blake2s_native(input: &[u8], personalization: &[u8]) -> [u8; 32] {
+    let mut h = Blake2s::with_params(32, &[], &[], personalization);
+    h.update(input);
+
+    let mut res = [0u8; 32];
+    res.copy_from_slice(h.finalize().as_bytes());
+    res
+}
+
+
+// plain blake2s-256 (with empty personalization) in the big-endian bit order used by all the digest-based primitives
+pub struct Blake2sHasher;
+
+impl DigestHasher for Blake2sHasher {
+    fn hash<E: Engine, CS: ConstraintSystem<E>>(cs: CS, input: &[Boolean]) -> Result<Vec<Boolean>, SynthesisError> {
+        let res = blake2s(cs, &reverse_bits_in_bytes(input), &[0u8; BLAKE2S_PERSONALIZATION_SIZE])?;
+        Ok(reverse_bits_in_bytes(&res))
+    }
+
+    fn hash_native(input: &[u8]) -> [u8; 32] {
+        blake2s_native(input, &[0u8; BLAKE2S_PERSONALIZATION_SIZE])
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use bellman::pairing::bn256::Bn256;
+    use bellman::ConstraintSystem;
+    use common::boolean::{Boolean, AllocatedBit};
+    use crate::tester::naming_oblivious_cs::NamingObliviousConstraintSystem as TestConstraintSystem;
+    use rand::{XorShiftRng, SeedableRng, Rng};
+    use super::*;
+
+    #[test]
+    fn test_blake2s_gadget_against_native() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let personalization = b"12345678";
+
+        // empty input, partial block and two full blocks
+        for &len in [0usize, 3, 128].iter() {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let data : Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+
+            let mut input_bits = vec![];
+            for (byte_i, input_byte) in data.iter().enumerate() {
+                for bit_i in 0..8 {
+                    let cs = cs.namespace(|| format!("input bit {} {}", byte_i, bit_i));
+                    input_bits.push(AllocatedBit::alloc(cs, Some((input_byte >> bit_i) & 1u8 == 1u8)).unwrap().into());
+                }
+            }
+
+            let r = blake2s(&mut cs, &input_bits, personalization).unwrap();
+            assert!(cs.is_satisfied());
+
+            let expected = blake2s_native(&data, personalization);
+            let mut s = r.iter().map(|b: &Boolean| b.get_value().unwrap());
+            for b in expected.iter() {
+                for i in 0..8 {
+                    let c = s.next().unwrap();
+                    assert_eq!(c, (b >> i) & 1u8 == 1u8);
+                }
+            }
+        }
+    }
+}
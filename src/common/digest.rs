@@ -0,0 +1,97 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+};
+
+use super::boolean::Boolean;
+use super::num::{Num, AllocatedNum};
+
+
+// field elements are hashed as 32-byte big-endian integers (the same as uint256 in EVM)
+pub const FIELD_ELEMENT_ENCODING_SIZE: usize = 256;
+pub const DIGEST_SIZE: usize = 256;
+
+
+// bit-oriented hash function with 256-bit output (sha256, keccak256, blake2s):
+// both input and output are byte strings with big-endian bit order inside every byte,
+// the native counterpart should produce exactly the same digest
+pub trait DigestHasher {
+    fn hash<E: Engine, CS: ConstraintSystem<E>>(cs: CS, input: &[Boolean]) -> Result<Vec<Boolean>, SynthesisError>;
+    fn hash_native(input: &[u8]) -> [u8; 32];
+}
+
+
+// 256-bit big-endian encoding of the field element
+pub fn field_element_into_be_bits<E, CS>(
+    cs: CS,
+    elem: &AllocatedNum<E>
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert!(E::Fr::NUM_BITS as usize <= FIELD_ELEMENT_ENCODING_SIZE);
+
+    let mut bits = elem.into_bits_le_strict(cs)?;
+    bits.resize(FIELD_ELEMENT_ENCODING_SIZE, Boolean::constant(false));
+    bits.reverse();
+    Ok(bits)
+}
+
+// packs big-endian bits into the field element, the caller is responsible for bits.len() < CAPACITY
+pub fn pack_be_bits<E, CS>(
+    cs: CS,
+    bits: &[Boolean]
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert!(bits.len() <= E::Fr::CAPACITY as usize);
+
+    let mut num = Num::<E>::zero();
+    let mut coeff = E::Fr::one();
+    for bit in bits.iter().rev() {
+        num.mut_add_bool_with_coeff(CS::one(), bit, coeff);
+        coeff.double();
+    }
+
+    num.simplify(cs)
+}
+
+// changes the bit order inside every byte: is used to adapt hashes with little-endian bit order (keccak, blake2s)
+pub fn reverse_bits_in_bytes(bits: &[Boolean]) -> Vec<Boolean> {
+    assert!(bits.len() % 8 == 0);
+    bits.chunks(8).flat_map(|byte| byte.iter().rev().cloned()).collect()
+}
+
+
+pub fn encode_field_element<F: PrimeField>(elem: &F) -> [u8; 32] {
+    assert!(F::NUM_BITS as usize <= FIELD_ELEMENT_ENCODING_SIZE);
+
+    let mut res = [0u8; 32];
+    let repr = elem.into_repr();
+    // limbs are little-endian
+    for (i, limb) in repr.as_ref().iter().enumerate().take(4) {
+        for j in 0..8 {
+            res[31 - (i * 8 + j)] = (limb >> (8 * j)) as u8;
+        }
+    }
+    res
+}
+
+// big-endian bytes -> field element, the caller is responsible for the value to fit into the field
+pub fn decode_field_element<F: PrimeField>(bytes: &[u8]) -> F {
+    assert!(bytes.len() <= 32);
+
+    let mut repr = F::Repr::default();
+    for (k, byte) in bytes.iter().rev().enumerate() {
+        repr.as_mut()[k / 8] |= (*byte as u64) << (8 * (k % 8));
+    }
+    F::from_repr(repr).expect("value should fit into the field")
+}
@@ -0,0 +1,640 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+};
+
+use common::num::*;
+use common::boolean::*;
+use common::field_gadget::*;
+use common::goldilocks::*;
+
+use std::fmt;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+
+// only quadratic and cubic extensions are supported
+pub const MAX_EXTENSION_DEGREE: usize = 3;
+
+
+pub trait ExtensionParams<F: Field>: 'static + Send + Sync + Copy + Clone + fmt::Debug + Default + PartialEq + Eq + Hash {
+    // the extension is F[x]/(x^DEGREE - non_residue)
+    const DEGREE: usize;
+    fn non_residue() -> F;
+    // x^p = frobenius_coeff * x, i.e. frobenius_coeff = non_residue^((p - 1) / DEGREE)
+    fn frobenius_coeff() -> F;
+}
+
+
+// element c_0 + c_1 * x + ... + c_{d-1} * x^{d-1}, coefficients with index >= d are always zero
+#[derive(Clone, Copy, Default, Hash, PartialEq, Eq)]
+pub struct ExtensionField<F: Field, P: ExtensionParams<F>> {
+    coeffs: [F; MAX_EXTENSION_DEGREE],
+    _marker: PhantomData<P>,
+}
+
+impl<F: Field, P: ExtensionParams<F>> ExtensionField<F, P> {
+    pub fn from_coeffs(coeffs: &[F]) -> Self {
+        assert_eq!(coeffs.len(), P::DEGREE);
+        let mut res = Self::zero();
+        res.coeffs[..P::DEGREE].copy_from_slice(coeffs);
+        res
+    }
+
+    pub fn from_base(elem: F) -> Self {
+        let mut res = Self::zero();
+        res.coeffs[0] = elem;
+        res
+    }
+
+    pub fn coeffs(&self) -> &[F] {
+        &self.coeffs[..P::DEGREE]
+    }
+
+    pub fn is_in_base_field(&self) -> bool {
+        self.coeffs().iter().skip(1).all(|x| x.is_zero())
+    }
+}
+
+impl<F: Field, P: ExtensionParams<F>> fmt::Debug for ExtensionField<F, P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Ext{}(", P::DEGREE)?;
+        for (i, c) in self.coeffs().iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", c)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl<F: Field, P: ExtensionParams<F>> fmt::Display for ExtensionField<F, P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl<F: Field, P: ExtensionParams<F>> ::rand::Rand for ExtensionField<F, P> {
+    fn rand<R: ::rand::Rng>(rng: &mut R) -> Self {
+        let mut res = Self::zero();
+        for c in res.coeffs[..P::DEGREE].iter_mut() {
+            *c = rng.gen();
+        }
+        res
+    }
+}
+
+impl<F: Field, P: ExtensionParams<F>> Field for ExtensionField<F, P> {
+    fn zero() -> Self {
+        ExtensionField { coeffs: [F::zero(); MAX_EXTENSION_DEGREE], _marker: PhantomData }
+    }
+
+    fn one() -> Self {
+        Self::from_base(F::one())
+    }
+
+    fn is_zero(&self) -> bool {
+        self.coeffs.iter().all(|x| x.is_zero())
+    }
+
+    fn square(&mut self) {
+        let temp = self.clone();
+        self.mul_assign(&temp);
+    }
+
+    fn double(&mut self) {
+        for c in self.coeffs.iter_mut() {
+            c.double();
+        }
+    }
+
+    fn negate(&mut self) {
+        for c in self.coeffs.iter_mut() {
+            c.negate();
+        }
+    }
+
+    fn add_assign(&mut self, other: &Self) {
+        for (a, b) in self.coeffs.iter_mut().zip(other.coeffs.iter()) {
+            a.add_assign(b);
+        }
+    }
+
+    fn sub_assign(&mut self, other: &Self) {
+        for (a, b) in self.coeffs.iter_mut().zip(other.coeffs.iter()) {
+            a.sub_assign(b);
+        }
+    }
+
+    fn mul_assign(&mut self, other: &Self) {
+        // schoolbook multiplication followed by reduction x^d = non_residue
+        let mut product = [F::zero(); 2 * MAX_EXTENSION_DEGREE - 1];
+        for (i, a) in self.coeffs().iter().enumerate() {
+            for (j, b) in other.coeffs().iter().enumerate() {
+                let mut temp = a.clone();
+                temp.mul_assign(b);
+                product[i + j].add_assign(&temp);
+            }
+        }
+
+        let non_residue = P::non_residue();
+        for k in (P::DEGREE..(2 * P::DEGREE - 1)).rev() {
+            let mut temp = product[k];
+            temp.mul_assign(&non_residue);
+            product[k - P::DEGREE].add_assign(&temp);
+        }
+
+        self.coeffs[..P::DEGREE].copy_from_slice(&product[..P::DEGREE]);
+    }
+
+    // (sum c_j x^j)^(p^power) = sum c_j (frobenius_coeff^power * x)^j
+    fn frobenius_map(&mut self, power: usize) {
+        let mut gamma = F::one();
+        for _ in 0..(power % P::DEGREE) {
+            gamma.mul_assign(&P::frobenius_coeff());
+        }
+
+        let mut multiplier = gamma.clone();
+        for c in self.coeffs[1..P::DEGREE].iter_mut() {
+            c.mul_assign(&multiplier);
+            multiplier.mul_assign(&gamma);
+        }
+    }
+
+    // a^{-1} = conj(a) / N(a), where conj(a) = a^p * ... * a^{p^{d-1}} and N(a) = a * conj(a) lies in the base field
+    fn inverse(&self) -> Option<Self> {
+        if self.is_zero() {
+            return None;
+        }
+
+        let mut conj = Self::one();
+        for power in 1..P::DEGREE {
+            let mut temp = self.clone();
+            temp.frobenius_map(power);
+            conj.mul_assign(&temp);
+        }
+
+        let mut norm = self.clone();
+        norm.mul_assign(&conj);
+        debug_assert!(norm.is_in_base_field());
+
+        let norm_inv = norm.coeffs[0].inverse()?;
+        for c in conj.coeffs.iter_mut() {
+            c.mul_assign(&norm_inv);
+        }
+
+        Some(conj)
+    }
+}
+
+impl<F: TwoAdicField, P: ExtensionParams<F>> TwoAdicField for ExtensionField<F, P> {
+    fn two_adicity() -> u32 {
+        F::two_adicity()
+    }
+
+    fn two_adic_root_of_unity() -> Self {
+        Self::from_base(F::two_adic_root_of_unity())
+    }
+
+    fn coset_generator() -> Self {
+        Self::from_base(F::coset_generator())
+    }
+}
+
+impl<F: Field, P: ExtensionParams<F>> NativeExtensionOf<F> for ExtensionField<F, P> {
+    fn from_base_elem(elem: &F) -> Self {
+        Self::from_base(*elem)
+    }
+
+    fn into_base_elem(&self) -> Option<F> {
+        match self.is_in_base_field() {
+            true => Some(self.coeffs[0]),
+            false => None,
+        }
+    }
+}
+
+// coefficients are committed one after another
+impl<N: PrimeField, F: IntoNativeElems<N>, P: ExtensionParams<F>> IntoNativeElems<N> for ExtensionField<F, P> {
+    fn into_native_elems(&self) -> Vec<N> {
+        self.coeffs().iter().flat_map(|c| c.into_native_elems()).collect()
+    }
+}
+
+impl<N: PrimeField, F: FromNativeChallenges<N>, P: ExtensionParams<F>> FromNativeChallenges<N> for ExtensionField<F, P> {
+    fn num_native_challenges() -> usize {
+        P::DEGREE * F::num_native_challenges()
+    }
+
+    fn from_native_challenges(challenges: &[N]) -> Self {
+        assert_eq!(challenges.len(), Self::num_native_challenges());
+        let coeffs: Vec<F> = challenges.chunks(F::num_native_challenges()).map(|chunk| {
+            F::from_native_challenges(chunk)
+        }).collect();
+
+        Self::from_coeffs(&coeffs)
+    }
+}
+
+
+// 7 generates the whole multiplicative group of Goldilocks, hence it is neither a square nor a cube
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub struct GoldilocksExt2Params;
+
+impl ExtensionParams<Goldilocks> for GoldilocksExt2Params {
+    const DEGREE: usize = 2;
+
+    fn non_residue() -> Goldilocks {
+        Goldilocks::from_u64(7)
+    }
+
+    fn frobenius_coeff() -> Goldilocks {
+        Self::non_residue().pow([(GOLDILOCKS_MODULUS - 1) / 2])
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub struct GoldilocksExt3Params;
+
+impl ExtensionParams<Goldilocks> for GoldilocksExt3Params {
+    const DEGREE: usize = 3;
+
+    fn non_residue() -> Goldilocks {
+        Goldilocks::from_u64(7)
+    }
+
+    fn frobenius_coeff() -> Goldilocks {
+        Self::non_residue().pow([(GOLDILOCKS_MODULUS - 1) / 3])
+    }
+}
+
+pub type GoldilocksExt2 = ExtensionField<Goldilocks, GoldilocksExt2Params>;
+pub type GoldilocksExt3 = ExtensionField<Goldilocks, GoldilocksExt3Params>;
+
+
+// in-circuit extension field element, each coefficient is represented by base field gadget,
+// coefficients which are known to be zero (e.g. of the embedded base field elements) are not allocated at all
+#[derive(Clone)]
+pub struct ExtensionFieldGadget<E: Engine, G: FieldGadget<E>, P: ExtensionParams<G::Field>> {
+    // None stands for the constant zero
+    coeffs: Vec<Option<G>>,
+    _marker: PhantomData<(E, P)>,
+}
+
+impl<E: Engine, G: FieldGadget<E>, P: ExtensionParams<G::Field>> ExtensionFieldGadget<E, G, P> {
+    pub fn from_coeffs(coeffs: Vec<G>) -> Self {
+        Self::from_sparse_coeffs(coeffs.into_iter().map(Some).collect())
+    }
+
+    fn from_sparse_coeffs(coeffs: Vec<Option<G>>) -> Self {
+        assert_eq!(coeffs.len(), P::DEGREE);
+        ExtensionFieldGadget { coeffs, _marker: PhantomData }
+    }
+
+    // None stands for the zero coefficient
+    pub fn get_coeffs(&self) -> &[Option<G>] {
+        &self.coeffs[..]
+    }
+
+    // multiplication by the element of the base field (is much cheaper then the full one)
+    pub fn mul_by_base<CS: ConstraintSystem<E>>(&self, mut cs: CS, elem: &G) -> Result<Self, SynthesisError> {
+        let coeffs = self.coeffs.iter().map(|c| match c {
+            Some(c) => c.mul(cs.namespace(|| "mul coefficient by base element"), elem).map(Some),
+            None => Ok(None),
+        }).collect::<Result<_, _>>()?;
+
+        Ok(Self::from_sparse_coeffs(coeffs))
+    }
+
+    pub fn frobenius_map<CS: ConstraintSystem<E>>(&self, mut cs: CS, power: usize) -> Result<Self, SynthesisError> {
+        let mut gamma = G::Field::one();
+        for _ in 0..(power % P::DEGREE) {
+            gamma.mul_assign(&P::frobenius_coeff());
+        }
+
+        let mut coeffs = Vec::with_capacity(P::DEGREE);
+        coeffs.push(self.coeffs[0].clone());
+
+        let mut multiplier = gamma.clone();
+        for c in self.coeffs.iter().skip(1) {
+            coeffs.push(match c {
+                Some(c) => Some(c.mul_by_constant(cs.namespace(|| "frobenius: scale coefficient"), &multiplier)?),
+                None => None,
+            });
+            multiplier.mul_assign(&gamma);
+        }
+
+        Ok(Self::from_sparse_coeffs(coeffs))
+    }
+
+    // zero coefficient is allocated only if it has to be used as a variable
+    fn materialize<CS: ConstraintSystem<E>>(cs: CS, coeff: &Option<G>) -> Result<G, SynthesisError> {
+        match coeff {
+            Some(c) => Ok(c.clone()),
+            None => G::constant(cs, G::Field::zero()),
+        }
+    }
+
+    // the product of two polynomials reduced modulo x^d - non_residue:
+    // the closure computes the product of the left coefficient by the j-th right one (None stands for zero)
+    fn reduced_product<CS, M>(mut cs: CS, left: &[Option<G>], mut mul: M) -> Result<Vec<Option<G>>, SynthesisError>
+    where CS: ConstraintSystem<E>, M: FnMut(&mut CS, &G, usize) -> Result<Option<G>, SynthesisError>
+    {
+        let mut product : Vec<Option<G>> = vec![None; 2 * P::DEGREE - 1];
+        for (i, a) in left.iter().enumerate() {
+            let a = match a {
+                Some(a) => a,
+                None => continue,
+            };
+            for j in 0..P::DEGREE {
+                if let Some(temp) = mul(&mut cs, a, j)? {
+                    product[i + j] = Some(match product[i + j].take() {
+                        None => temp,
+                        Some(acc) => acc.add(cs.namespace(|| "accumulate product"), &temp)?,
+                    });
+                }
+            }
+        }
+
+        let non_residue = P::non_residue();
+        for k in (P::DEGREE..(2 * P::DEGREE - 1)).rev() {
+            if let Some(high) = product[k].take() {
+                let temp = high.mul_by_constant(cs.namespace(|| "reduce by non residue"), &non_residue)?;
+                product[k - P::DEGREE] = Some(match product[k - P::DEGREE].take() {
+                    None => temp,
+                    Some(acc) => acc.add(cs.namespace(|| "reduction"), &temp)?,
+                });
+            }
+        }
+
+        product.truncate(P::DEGREE);
+        Ok(product)
+    }
+}
+
+impl<E: Engine, G: FieldGadget<E>, P: ExtensionParams<G::Field>> FieldGadget<E> for ExtensionFieldGadget<E, G, P> {
+    type Field = ExtensionField<G::Field, P>;
+
+    fn alloc<CS: ConstraintSystem<E>>(mut cs: CS, value: Option<Self::Field>) -> Result<Self, SynthesisError> {
+        let coeffs = (0..P::DEGREE).map(|i| {
+            G::alloc(cs.namespace(|| "allocate coefficient"), value.map(|x| x.coeffs()[i]))
+        }).collect::<Result<_, _>>()?;
+
+        Ok(Self::from_coeffs(coeffs))
+    }
+
+    fn constant<CS: ConstraintSystem<E>>(mut cs: CS, value: Self::Field) -> Result<Self, SynthesisError> {
+        let coeffs = value.coeffs().iter().map(|c| match c.is_zero() {
+            true => Ok(None),
+            false => G::constant(cs.namespace(|| "constant coefficient"), c.clone()).map(Some),
+        }).collect::<Result<_, _>>()?;
+
+        Ok(Self::from_sparse_coeffs(coeffs))
+    }
+
+    fn get_value(&self) -> Option<Self::Field> {
+        let coeffs = self.coeffs.iter().map(|c| match c {
+            Some(c) => c.get_value(),
+            None => Some(G::Field::zero()),
+        }).collect::<Option<Vec<_>>>()?;
+        Some(ExtensionField::from_coeffs(&coeffs[..]))
+    }
+
+    fn num_native_elems() -> usize {
+        P::DEGREE * G::num_native_elems()
+    }
+
+    fn into_native_elems<CS: ConstraintSystem<E>>(&self, mut cs: CS) -> Result<Vec<AllocatedNum<E>>, SynthesisError> {
+        let mut res = Vec::with_capacity(Self::num_native_elems());
+        for c in self.coeffs.iter() {
+            let c = Self::materialize(cs.namespace(|| "materialize coefficient"), c)?;
+            res.extend(c.into_native_elems(cs.namespace(|| "coefficient into native elements"))?);
+        }
+
+        Ok(res)
+    }
+
+    fn from_native_elems<CS: ConstraintSystem<E>>(mut cs: CS, elems: &[AllocatedNum<E>]) -> Result<Self, SynthesisError> {
+        if elems.len() != Self::num_native_elems() {
+            return Err(SynthesisError::Unknown);
+        }
+
+        let coeffs = elems.chunks(G::num_native_elems()).map(|chunk| {
+            G::from_native_elems(cs.namespace(|| "parse coefficient"), chunk)
+        }).collect::<Result<_, _>>()?;
+
+        Ok(Self::from_coeffs(coeffs))
+    }
+
+    fn add<CS: ConstraintSystem<E>>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError> {
+        let coeffs = self.coeffs.iter().zip(other.coeffs.iter()).map(|(a, b)| match (a, b) {
+            (Some(a), Some(b)) => a.add(cs.namespace(|| "add coefficients"), b).map(Some),
+            (Some(x), None) | (None, Some(x)) => Ok(Some(x.clone())),
+            (None, None) => Ok(None),
+        }).collect::<Result<_, _>>()?;
+
+        Ok(Self::from_sparse_coeffs(coeffs))
+    }
+
+    fn sub<CS: ConstraintSystem<E>>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError> {
+        let mut minus_one = G::Field::one();
+        minus_one.negate();
+
+        let coeffs = self.coeffs.iter().zip(other.coeffs.iter()).map(|(a, b)| match (a, b) {
+            (Some(a), Some(b)) => a.sub(cs.namespace(|| "sub coefficients"), b).map(Some),
+            (Some(a), None) => Ok(Some(a.clone())),
+            (None, Some(b)) => b.mul_by_constant(cs.namespace(|| "negate coefficient"), &minus_one).map(Some),
+            (None, None) => Ok(None),
+        }).collect::<Result<_, _>>()?;
+
+        Ok(Self::from_sparse_coeffs(coeffs))
+    }
+
+    fn mul<CS: ConstraintSystem<E>>(&self, cs: CS, other: &Self) -> Result<Self, SynthesisError> {
+        let coeffs = Self::reduced_product(cs, &self.coeffs[..], |cs, a, j| match &other.coeffs[j] {
+            Some(b) => a.mul(cs.namespace(|| "mul coefficients"), b).map(Some),
+            None => Ok(None),
+        })?;
+
+        Ok(Self::from_sparse_coeffs(coeffs))
+    }
+
+    // zero coefficients of the constant are skipped, so multiplication by base field constant is cheap
+    fn mul_by_constant<CS: ConstraintSystem<E>>(&self, cs: CS, coef: &Self::Field) -> Result<Self, SynthesisError> {
+        let coeffs = Self::reduced_product(cs, &self.coeffs[..], |cs, a, j| {
+            let c = coef.coeffs()[j];
+            match c.is_zero() {
+                true => Ok(None),
+                false => a.mul_by_constant(cs.namespace(|| "mul coefficient by constant"), &c).map(Some),
+            }
+        })?;
+
+        Ok(Self::from_sparse_coeffs(coeffs))
+    }
+
+    // the inverse is provided as witness and checked via a * a_inv = 1
+    fn inverse<CS: ConstraintSystem<E>>(&self, mut cs: CS) -> Result<Self, SynthesisError> {
+        let inv_value = match self.get_value() {
+            Some(x) => Some(x.inverse().ok_or(SynthesisError::DivisionByZero)?),
+            None => None,
+        };
+
+        let inv = Self::alloc(cs.namespace(|| "inverse"), inv_value)?;
+        let product = self.mul(cs.namespace(|| "a * a_inv"), &inv)?;
+        let one = Self::constant(cs.namespace(|| "one"), Self::Field::one())?;
+
+        let flag = Self::equals(cs.namespace(|| "a * a_inv == 1"), &product, &one)?;
+        Boolean::enforce_equal(cs.namespace(|| "inversion check"), &flag, &Boolean::constant(true))?;
+
+        Ok(inv)
+    }
+
+    fn equals<CS: ConstraintSystem<E>>(mut cs: CS, a: &Self, b: &Self) -> Result<Boolean, SynthesisError> {
+        let mut res = Boolean::constant(true);
+        for (x, y) in a.coeffs.iter().zip(b.coeffs.iter()) {
+            if x.is_none() && y.is_none() {
+                continue;
+            }
+            let x = Self::materialize(cs.namespace(|| "left coefficient"), x)?;
+            let y = Self::materialize(cs.namespace(|| "right coefficient"), y)?;
+            let flag = G::equals(cs.namespace(|| "compare coefficients"), &x, &y)?;
+            res = Boolean::and(cs.namespace(|| "and"), &res, &flag)?;
+        }
+
+        Ok(res)
+    }
+
+    fn conditionally_select<CS: ConstraintSystem<E>>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+        condition: &Boolean,
+    ) -> Result<Self, SynthesisError>
+    {
+        let coeffs = a.coeffs.iter().zip(b.coeffs.iter()).map(|(x, y)| {
+            if x.is_none() && y.is_none() {
+                return Ok(None);
+            }
+            let x = Self::materialize(cs.namespace(|| "left coefficient"), x)?;
+            let y = Self::materialize(cs.namespace(|| "right coefficient"), y)?;
+            G::conditionally_select(cs.namespace(|| "select coefficient"), &x, &y, condition).map(Some)
+        }).collect::<Result<_, _>>()?;
+
+        Ok(Self::from_sparse_coeffs(coeffs))
+    }
+}
+
+// embedding is free: all the coefficients but the first one are constant zeroes
+impl<E: Engine, G: ChallengeFieldGadget<E>, P: ExtensionParams<G::Field>> ChallengeFieldGadget<E> for ExtensionFieldGadget<E, G, P> {
+    fn num_native_challenges() -> usize {
+        P::DEGREE * G::num_native_challenges()
+    }
+
+    fn from_native_challenges<CS: ConstraintSystem<E>>(
+        mut cs: CS,
+        challenges: &[AllocatedNum<E>],
+    ) -> Result<Self, SynthesisError>
+    {
+        if challenges.len() != Self::num_native_challenges() {
+            return Err(SynthesisError::Unknown);
+        }
+
+        let coeffs = challenges.chunks(G::num_native_challenges()).map(|chunk| {
+            G::from_native_challenges(cs.namespace(|| "challenge coefficient"), chunk)
+        }).collect::<Result<_, _>>()?;
+
+        Ok(Self::from_coeffs(coeffs))
+    }
+}
+
+impl<E: Engine, G: FieldGadget<E>, P: ExtensionParams<G::Field>> ExtensionOf<E, G> for ExtensionFieldGadget<E, G, P> {
+    fn from_base<CS: ConstraintSystem<E>>(_cs: CS, elem: &G) -> Result<Self, SynthesisError> {
+        let mut coeffs = vec![None; P::DEGREE];
+        coeffs[0] = Some(elem.clone());
+
+        Ok(Self::from_sparse_coeffs(coeffs))
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::ConstraintSystem;
+    use bellman::pairing::bn256::Bn256;
+    use bellman::pairing::ff::Field;
+    use crate::tester::naming_oblivious_cs::NamingObliviousConstraintSystem as TestConstraintSystem;
+    use super::*;
+
+    fn test_native_extension_impl<P: ExtensionParams<Goldilocks>>() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for _ in 0..16 {
+            let a : ExtensionField<Goldilocks, P> = rng.gen();
+            let b : ExtensionField<Goldilocks, P> = rng.gen();
+
+            let mut ab = a;
+            ab.mul_assign(&b);
+            let mut ba = b;
+            ba.mul_assign(&a);
+            assert_eq!(ab, ba);
+
+            let mut temp = a.inverse().unwrap();
+            temp.mul_assign(&a);
+            assert_eq!(temp, ExtensionField::one());
+
+            // frobenius is a homomorphism and its DEGREE-th power is identity
+            let mut x = ab;
+            x.frobenius_map(1);
+            let mut y = a;
+            y.frobenius_map(1);
+            let mut z = b;
+            z.frobenius_map(1);
+            y.mul_assign(&z);
+            assert_eq!(x, y);
+
+            x.frobenius_map(P::DEGREE - 1);
+            assert_eq!(x, ab);
+        }
+    }
+
+    #[test]
+    fn test_goldilocks_extensions() {
+        test_native_extension_impl::<GoldilocksExt2Params>();
+        test_native_extension_impl::<GoldilocksExt3Params>();
+    }
+
+    #[test]
+    fn test_extension_gadget_arithmetic() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        type Gadget = ExtensionFieldGadget<Bn256, GoldilocksNum<Bn256>, GoldilocksExt3Params>;
+
+        let a_value : GoldilocksExt3 = rng.gen();
+        let b_value : GoldilocksExt3 = rng.gen();
+        let a = Gadget::alloc(cs.namespace(|| "a"), Some(a_value)).unwrap();
+        let b = Gadget::alloc(cs.namespace(|| "b"), Some(b_value)).unwrap();
+
+        // a^p / b
+        let a_frob = a.frobenius_map(cs.namespace(|| "frobenius"), 1).unwrap();
+        let res = a_frob.div(cs.namespace(|| "div"), &b).unwrap();
+
+        let mut expected = a_value;
+        expected.frobenius_map(1);
+        expected.mul_assign(&b_value.inverse().unwrap());
+
+        assert_eq!(res.get_value().unwrap(), expected);
+        assert!(cs.is_satisfied());
+    }
+}
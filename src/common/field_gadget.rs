@@ -0,0 +1,400 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+};
+
+use common::num::*;
+use common::boolean::*;
+
+
+// field with large enough multiplicative subgroup of order 2^n (FFT-friendly)
+pub trait TwoAdicField: Field {
+    fn two_adicity() -> u32;
+    // generator of multiplicative subgroup of order 2^two_adicity
+    fn two_adic_root_of_unity() -> Self;
+    // generator of the whole multiplicative group, is used as default LDE coset offset
+    fn coset_generator() -> Self;
+
+    // generator of subgroup of order domain_size
+    fn domain_generator(domain_size: usize) -> Self {
+        assert!(domain_size.is_power_of_two());
+        let log_domain_size = domain_size.trailing_zeros();
+        assert!(log_domain_size <= Self::two_adicity());
+
+        let mut res = Self::two_adic_root_of_unity();
+        for _ in log_domain_size..Self::two_adicity() {
+            res.square();
+        }
+        res
+    }
+}
+
+impl<F: PrimeField> TwoAdicField for F {
+    fn two_adicity() -> u32 {
+        F::S
+    }
+
+    fn two_adic_root_of_unity() -> Self {
+        F::root_of_unity()
+    }
+
+    fn coset_generator() -> Self {
+        F::multiplicative_generator()
+    }
+}
+
+
+// native counterpart of FieldGadget::into_native_elems: 
+// provers commit to field elements (and absorb them into transcript) in this form
+pub trait IntoNativeElems<N: PrimeField>: Field {
+    fn into_native_elems(&self) -> Vec<N>;
+}
+
+impl<F: PrimeField> IntoNativeElems<F> for F {
+    fn into_native_elems(&self) -> Vec<F> {
+        vec![*self]
+    }
+}
+
+// field elements derived from the challenges of the native transcript:
+// Fiat-Shamir challenges of non-native (or extension) fields are squeezed as several native elements
+pub trait FromNativeChallenges<N: PrimeField>: Field {
+    fn num_native_challenges() -> usize;
+    fn from_native_challenges(challenges: &[N]) -> Self;
+}
+
+impl<F: PrimeField> FromNativeChallenges<F> for F {
+    fn num_native_challenges() -> usize {
+        1
+    }
+
+    fn from_native_challenges(challenges: &[F]) -> Self {
+        assert_eq!(challenges.len(), 1);
+        challenges[0]
+    }
+}
+
+// native counterpart of ExtensionOf
+pub trait NativeExtensionOf<B: Field>: Field {
+    fn from_base_elem(elem: &B) -> Self;
+    // None if the element doesn't belong to the base field
+    fn into_base_elem(&self) -> Option<B>;
+}
+
+impl<F: Field> NativeExtensionOf<F> for F {
+    fn from_base_elem(elem: &F) -> Self {
+        *elem
+    }
+
+    fn into_base_elem(&self) -> Option<F> {
+        Some(*self)
+    }
+}
+
+
+// in-circuit element of some (not necessarily native) field:
+// all the arithmetic is done modulo the characteristic of Self::Field,
+// the element itself is represented by one or several native field elements
+pub trait FieldGadget<E: Engine>: Sized + Clone {
+    type Field: Field;
+
+    fn alloc<CS: ConstraintSystem<E>>(cs: CS, value: Option<Self::Field>) -> Result<Self, SynthesisError>;
+    fn constant<CS: ConstraintSystem<E>>(cs: CS, value: Self::Field) -> Result<Self, SynthesisError>;
+    fn get_value(&self) -> Option<Self::Field>;
+
+    // number of native elements used to represent Self::Field element
+    fn num_native_elems() -> usize;
+    // native representation (e.g. to be absorbed by sponge or hashed in Merklee tree),
+    // constant parts of the element (if any) are allocated here
+    fn into_native_elems<CS: ConstraintSystem<E>>(&self, cs: CS) -> Result<Vec<AllocatedNum<E>>, SynthesisError>;
+    // the reverse operation: all the range checks (if any) are done here
+    fn from_native_elems<CS: ConstraintSystem<E>>(cs: CS, elems: &[AllocatedNum<E>]) -> Result<Self, SynthesisError>;
+
+    fn add<CS: ConstraintSystem<E>>(&self, cs: CS, other: &Self) -> Result<Self, SynthesisError>;
+    fn sub<CS: ConstraintSystem<E>>(&self, cs: CS, other: &Self) -> Result<Self, SynthesisError>;
+    fn mul<CS: ConstraintSystem<E>>(&self, cs: CS, other: &Self) -> Result<Self, SynthesisError>;
+    fn mul_by_constant<CS: ConstraintSystem<E>>(&self, cs: CS, coef: &Self::Field) -> Result<Self, SynthesisError>;
+    fn inverse<CS: ConstraintSystem<E>>(&self, cs: CS) -> Result<Self, SynthesisError>;
+
+    fn equals<CS: ConstraintSystem<E>>(cs: CS, a: &Self, b: &Self) -> Result<Boolean, SynthesisError>;
+    // returns a if condition is true and b otherwise
+    fn conditionally_select<CS: ConstraintSystem<E>>(
+        cs: CS,
+        a: &Self,
+        b: &Self,
+        condition: &Boolean,
+    ) -> Result<Self, SynthesisError>;
+
+    fn square<CS: ConstraintSystem<E>>(&self, cs: CS) -> Result<Self, SynthesisError> {
+        self.mul(cs, self)
+    }
+
+    fn div<CS: ConstraintSystem<E>>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError> {
+        let other_inv = other.inverse(cs.namespace(|| "inverse of denominator"))?;
+        self.mul(cs.namespace(|| "multiply by inverse"), &other_inv)
+    }
+
+    // sum c_i * x_i: the first coefficient is factored out,
+    // so that c * (x + y) and c * (x - y) require a single multiplication by constant
+    fn linear_combination<CS: ConstraintSystem<E>>(mut cs: CS, terms: &[(Self::Field, &Self)]) -> Result<Self, SynthesisError> {
+        let (first_coef, first) = match terms.first() {
+            Some(x) => x,
+            None => return Self::constant(cs, Self::Field::zero()),
+        };
+        let first_coef_inv = first_coef.inverse().ok_or(SynthesisError::DivisionByZero)?;
+
+        let one = Self::Field::one();
+        let mut minus_one = one.clone();
+        minus_one.negate();
+
+        let mut acc = (*first).clone();
+        for (coef, elem) in terms.iter().skip(1) {
+            let mut ratio = coef.clone();
+            ratio.mul_assign(&first_coef_inv);
+
+            acc = if ratio == one {
+                acc.add(cs.namespace(|| "add term"), elem)?
+            } else if ratio == minus_one {
+                acc.sub(cs.namespace(|| "sub term"), elem)?
+            } else {
+                let temp = elem.mul_by_constant(cs.namespace(|| "scale term"), &ratio)?;
+                acc.add(cs.namespace(|| "add term"), &temp)?
+            };
+        }
+
+        match *first_coef == one {
+            true => Ok(acc),
+            false => acc.mul_by_constant(cs.namespace(|| "scale sum"), first_coef),
+        }
+    }
+
+    // (sum a_i * x_i) * y + sum b_j * z_j, is the building block of FRI folding and Horner scheme
+    fn mul_add<CS: ConstraintSystem<E>>(
+        mut cs: CS,
+        left: &[(Self::Field, &Self)],
+        right: &Self,
+        addend: &[(Self::Field, &Self)],
+    ) -> Result<Self, SynthesisError>
+    {
+        let left = Self::linear_combination(cs.namespace(|| "left multiplier"), left)?;
+        let res = left.mul(cs.namespace(|| "multiplication"), right)?;
+        if addend.is_empty() {
+            return Ok(res);
+        }
+
+        let addend = Self::linear_combination(cs.namespace(|| "addend"), addend)?;
+        res.add(cs.namespace(|| "addition"), &addend)
+    }
+
+    // (sum a_i * x_i) / (sum b_j * y_j)
+    fn div_lc<CS: ConstraintSystem<E>>(
+        mut cs: CS,
+        numerator: &[(Self::Field, &Self)],
+        denominator: &[(Self::Field, &Self)],
+    ) -> Result<Self, SynthesisError>
+    {
+        let numerator = Self::linear_combination(cs.namespace(|| "numerator"), numerator)?;
+        let denominator = Self::linear_combination(cs.namespace(|| "denominator"), denominator)?;
+        numerator.div(cs.namespace(|| "division"), &denominator)
+    }
+
+    // exponent is given in little-endian form
+    fn pow<'a, CS, I>(mut cs: CS, base: &Self, x: I) -> Result<Self, SynthesisError>
+    where CS: ConstraintSystem<E>, I: DoubleEndedIterator<Item = &'a Boolean>
+    {
+        let mut res = Self::constant(cs.namespace(|| "one"), Self::Field::one())?;
+        for b in x.rev() {
+            res = res.square(cs.namespace(|| "square"))?;
+            let multiplied = res.mul(cs.namespace(|| "multiply by base"), base)?;
+            res = Self::conditionally_select(cs.namespace(|| "select"), &multiplied, &res, b)?;
+        }
+
+        Ok(res)
+    }
+}
+
+
+impl<E: Engine> FieldGadget<E> for AllocatedNum<E> {
+    type Field = E::Fr;
+
+    fn alloc<CS: ConstraintSystem<E>>(cs: CS, value: Option<E::Fr>) -> Result<Self, SynthesisError> {
+        AllocatedNum::alloc2(cs, value)
+    }
+
+    fn constant<CS: ConstraintSystem<E>>(cs: CS, value: E::Fr) -> Result<Self, SynthesisError> {
+        AllocatedNum::alloc_const(cs, value)
+    }
+
+    fn get_value(&self) -> Option<E::Fr> {
+        self.get_value()
+    }
+
+    fn num_native_elems() -> usize {
+        1
+    }
+
+    fn into_native_elems<CS: ConstraintSystem<E>>(&self, _cs: CS) -> Result<Vec<AllocatedNum<E>>, SynthesisError> {
+        Ok(vec![self.clone()])
+    }
+
+    fn from_native_elems<CS: ConstraintSystem<E>>(_cs: CS, elems: &[AllocatedNum<E>]) -> Result<Self, SynthesisError> {
+        match elems {
+            [elem] => Ok(elem.clone()),
+            _ => Err(SynthesisError::Unknown),
+        }
+    }
+
+    fn add<CS: ConstraintSystem<E>>(&self, cs: CS, other: &Self) -> Result<Self, SynthesisError> {
+        let mut res : Num<E> = self.clone().into();
+        res += other.clone();
+        res.simplify(cs)
+    }
+
+    fn sub<CS: ConstraintSystem<E>>(&self, cs: CS, other: &Self) -> Result<Self, SynthesisError> {
+        let mut res : Num<E> = self.clone().into();
+        res -= other.clone();
+        res.simplify(cs)
+    }
+
+    fn mul<CS: ConstraintSystem<E>>(&self, cs: CS, other: &Self) -> Result<Self, SynthesisError> {
+        AllocatedNum::mul(self, cs, other)
+    }
+
+    fn mul_by_constant<CS: ConstraintSystem<E>>(&self, cs: CS, coef: &E::Fr) -> Result<Self, SynthesisError> {
+        let mut res : Num<E> = self.clone().into();
+        res.scale(coef.clone());
+        res.simplify(cs)
+    }
+
+    fn inverse<CS: ConstraintSystem<E>>(&self, cs: CS) -> Result<Self, SynthesisError> {
+        let one = Num::from_constant(&E::Fr::one(), &cs);
+        Num::div(cs, &one, &self.clone().into())
+    }
+
+    fn equals<CS: ConstraintSystem<E>>(cs: CS, a: &Self, b: &Self) -> Result<Boolean, SynthesisError> {
+        AllocatedNum::equals(cs, a, b)
+    }
+
+    fn conditionally_select<CS: ConstraintSystem<E>>(
+        cs: CS,
+        a: &Self,
+        b: &Self,
+        condition: &Boolean,
+    ) -> Result<Self, SynthesisError>
+    {
+        AllocatedNum::conditionally_select(cs, a, b, condition)
+    }
+
+    fn square<CS: ConstraintSystem<E>>(&self, cs: CS) -> Result<Self, SynthesisError> {
+        AllocatedNum::square(self, cs)
+    }
+
+    fn pow<'a, CS, I>(cs: CS, base: &Self, x: I) -> Result<Self, SynthesisError>
+    where CS: ConstraintSystem<E>, I: DoubleEndedIterator<Item = &'a Boolean>
+    {
+        AllocatedNum::pow(cs, base, x)
+    }
+
+    // linear combinations are free for the native field: all the methods below cost a single constraint
+    fn linear_combination<CS: ConstraintSystem<E>>(cs: CS, terms: &[(E::Fr, &Self)]) -> Result<Self, SynthesisError> {
+        if terms.is_empty() {
+            return AllocatedNum::alloc_const(cs, E::Fr::zero());
+        }
+
+        Self::collect_terms(terms).simplify(cs)
+    }
+
+    fn mul_add<CS: ConstraintSystem<E>>(
+        mut cs: CS,
+        left: &[(E::Fr, &Self)],
+        right: &Self,
+        addend: &[(E::Fr, &Self)],
+    ) -> Result<Self, SynthesisError>
+    {
+        let left = Self::collect_terms(left);
+        let addend = Self::collect_terms(addend);
+
+        let value = match (left.get_value(), right.get_value(), addend.get_value()) {
+            (Some(mut x), Some(y), Some(z)) => {
+                x.mul_assign(&y);
+                x.add_assign(&z);
+                Some(x)
+            },
+            _ => None,
+        };
+        let res = AllocatedNum::alloc2(cs.namespace(|| "result"), value)?;
+
+        // enforce: left * right = res - addend
+        let mut rhs : Num<E> = res.clone().into();
+        rhs.sub_assign(&addend);
+        Num::enforce(cs.namespace(|| "multiply and add"), &left, &right.clone().into(), &rhs);
+
+        Ok(res)
+    }
+
+    fn div_lc<CS: ConstraintSystem<E>>(
+        cs: CS,
+        numerator: &[(E::Fr, &Self)],
+        denominator: &[(E::Fr, &Self)],
+    ) -> Result<Self, SynthesisError>
+    {
+        Num::div(cs, &Self::collect_terms(numerator), &Self::collect_terms(denominator))
+    }
+}
+
+impl<E: Engine> AllocatedNum<E> {
+    fn collect_terms(terms: &[(E::Fr, &Self)]) -> Num<E> {
+        let mut res = Num::zero();
+        for (coef, elem) in terms.iter() {
+            res.mut_add_number_with_coeff(elem, coef.clone());
+        }
+        res
+    }
+}
+
+
+// in-circuit counterpart of FromNativeChallenges
+pub trait ChallengeFieldGadget<E: Engine>: FieldGadget<E> {
+    fn num_native_challenges() -> usize;
+    fn from_native_challenges<CS: ConstraintSystem<E>>(
+        cs: CS,
+        challenges: &[AllocatedNum<E>],
+    ) -> Result<Self, SynthesisError>;
+}
+
+impl<E: Engine> ChallengeFieldGadget<E> for AllocatedNum<E> {
+    fn num_native_challenges() -> usize {
+        1
+    }
+
+    fn from_native_challenges<CS: ConstraintSystem<E>>(
+        _cs: CS,
+        challenges: &[AllocatedNum<E>],
+    ) -> Result<Self, SynthesisError>
+    {
+        assert_eq!(challenges.len(), 1);
+        Ok(challenges[0].clone())
+    }
+}
+
+
+// F contains the (canonical) embedding of the base field B,
+// e.g. FRI with base field oracles and extension field challenges
+pub trait ExtensionOf<E: Engine, B: FieldGadget<E>>: FieldGadget<E> {
+    fn from_base<CS: ConstraintSystem<E>>(cs: CS, elem: &B) -> Result<Self, SynthesisError>;
+}
+
+impl<E: Engine> ExtensionOf<E, AllocatedNum<E>> for AllocatedNum<E> {
+    fn from_base<CS: ConstraintSystem<E>>(_cs: CS, elem: &Self) -> Result<Self, SynthesisError> {
+        Ok(elem.clone())
+    }
+}
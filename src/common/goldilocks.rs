@@ -0,0 +1,571 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr,
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+};
+
+use common::num::*;
+use common::boolean::*;
+use common::field_gadget::*;
+
+use std::fmt;
+
+
+// Goldilocks field: p = 2^64 - 2^32 + 1
+pub const GOLDILOCKS_MODULUS: u64 = 0xffffffff00000001;
+const GOLDILOCKS_TWO_ADICITY: u32 = 32;
+const GOLDILOCKS_MULTIPLICATIVE_GENERATOR: u64 = 7;
+
+
+// native Goldilocks field element, is always kept in canonical form (< p)
+#[derive(Clone, Copy, Default, Hash, PartialEq, Eq)]
+pub struct Goldilocks(u64);
+
+impl Goldilocks {
+    pub fn from_u64(value: u64) -> Self {
+        Goldilocks(value % GOLDILOCKS_MODULUS)
+    }
+
+    pub fn into_u64(&self) -> u64 {
+        self.0
+    }
+
+    fn reduce(value: u128) -> Self {
+        Goldilocks((value % (GOLDILOCKS_MODULUS as u128)) as u64)
+    }
+
+    fn pow_u64(&self, exp: u64) -> Self {
+        let mut res = Self::one();
+        for i in (0..64).rev() {
+            res.square();
+            if (exp >> i) & 1 == 1 {
+                res.mul_assign(self);
+            }
+        }
+        res
+    }
+}
+
+impl fmt::Debug for Goldilocks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Goldilocks({})", self.0)
+    }
+}
+
+impl fmt::Display for Goldilocks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Goldilocks({})", self.0)
+    }
+}
+
+impl ::rand::Rand for Goldilocks {
+    fn rand<R: ::rand::Rng>(rng: &mut R) -> Self {
+        Goldilocks::from_u64(rng.gen())
+    }
+}
+
+impl Field for Goldilocks {
+    fn zero() -> Self {
+        Goldilocks(0)
+    }
+
+    fn one() -> Self {
+        Goldilocks(1)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+
+    fn square(&mut self) {
+        let temp = self.clone();
+        self.mul_assign(&temp);
+    }
+
+    fn double(&mut self) {
+        let temp = self.clone();
+        self.add_assign(&temp);
+    }
+
+    fn negate(&mut self) {
+        if self.0 != 0 {
+            self.0 = GOLDILOCKS_MODULUS - self.0;
+        }
+    }
+
+    fn add_assign(&mut self, other: &Self) {
+        *self = Self::reduce(self.0 as u128 + other.0 as u128);
+    }
+
+    fn sub_assign(&mut self, other: &Self) {
+        let mut temp = other.clone();
+        temp.negate();
+        self.add_assign(&temp);
+    }
+
+    fn mul_assign(&mut self, other: &Self) {
+        *self = Self::reduce(self.0 as u128 * other.0 as u128);
+    }
+
+    fn frobenius_map(&mut self, _power: usize) {
+        // Frobenius automorphism of prime field is trivial
+    }
+
+    fn inverse(&self) -> Option<Self> {
+        match self.is_zero() {
+            true => None,
+            false => Some(self.pow_u64(GOLDILOCKS_MODULUS - 2)),
+        }
+    }
+}
+
+impl<N: PrimeField> IntoNativeElems<N> for Goldilocks {
+    fn into_native_elems(&self) -> Vec<N> {
+        vec![u64_to_fr(self.0)]
+    }
+}
+
+// the challenge is the lower 64 bits of the native one reduced modulo p
+// (the bias is about 2^-32, which is negligible for Fiat-Shamir)
+impl<N: PrimeField> FromNativeChallenges<N> for Goldilocks {
+    fn num_native_challenges() -> usize {
+        1
+    }
+
+    fn from_native_challenges(challenges: &[N]) -> Self {
+        assert_eq!(challenges.len(), 1);
+        Goldilocks::from_u64(challenges[0].into_repr().as_ref()[0])
+    }
+}
+
+impl TwoAdicField for Goldilocks {
+    fn two_adicity() -> u32 {
+        GOLDILOCKS_TWO_ADICITY
+    }
+
+    fn two_adic_root_of_unity() -> Self {
+        Goldilocks(GOLDILOCKS_MULTIPLICATIVE_GENERATOR).pow_u64((GOLDILOCKS_MODULUS - 1) >> GOLDILOCKS_TWO_ADICITY)
+    }
+
+    fn coset_generator() -> Self {
+        Goldilocks(GOLDILOCKS_MULTIPLICATIVE_GENERATOR)
+    }
+}
+
+
+fn u64_to_fr<F: PrimeField>(value: u64) -> F {
+    F::from_repr(F::Repr::from(value)).expect("should fit into the field")
+}
+
+// is only used for the values which are known to be smaller than 2^64
+fn fr_to_u64<F: PrimeField>(value: &F) -> u64 {
+    value.into_repr().as_ref()[0]
+}
+
+
+// Goldilocks element emulated inside the native field:
+// the value is kept in canonical form, which is ensured by range-checking its 32-bit limbs:
+// x = lo + 2^32 * hi, where hi != 2^32 - 1 or lo == 0
+// all the intermidiate results (such as a * b = q * p + r) are smaller than 2^130 and hence
+// do not wrap around the native modulus
+#[derive(Clone)]
+pub struct GoldilocksNum<E: Engine> {
+    num: AllocatedNum<E>,
+    value: Option<Goldilocks>,
+}
+
+impl<E: Engine> GoldilocksNum<E> {
+    pub fn get_num(&self) -> &AllocatedNum<E> {
+        &self.num
+    }
+
+    // allocate the value of the form sum b_i 2^i, where b_i are the bits of (unchecked) 64-bit value
+    // returns the packed value together with the packings of lower and higher limbs
+    fn alloc_64_bits<CS: ConstraintSystem<E>>(
+        mut cs: CS,
+        value: Option<u64>,
+    ) -> Result<(Num<E>, Num<E>, Num<E>), SynthesisError>
+    {
+        let bits = u64_into_boolean_vec_le(cs.namespace(|| "decompose into bits"), value)?;
+
+        let mut lo = Num::<E>::zero();
+        let mut hi = Num::<E>::zero();
+        let mut packed = Num::<E>::zero();
+
+        let mut coef = E::Fr::one();
+        for (i, bit) in bits.iter().enumerate() {
+            packed.mut_add_bool_with_coeff(CS::one(), bit, coef);
+            coef.double();
+
+            let limb_coef = u64_to_fr::<E::Fr>(1 << (i % 32));
+            match i < 32 {
+                true => lo.mut_add_bool_with_coeff(CS::one(), bit, limb_coef),
+                false => hi.mut_add_bool_with_coeff(CS::one(), bit, limb_coef),
+            };
+        }
+
+        Ok((packed, lo, hi))
+    }
+
+    // allocates the element and checks that it is in canonical form
+    fn alloc_canonical<CS: ConstraintSystem<E>>(mut cs: CS, value: Option<Goldilocks>) -> Result<Self, SynthesisError> {
+        let (mut packed, lo, mut hi) = Self::alloc_64_bits(
+            cs.namespace(|| "range check"),
+            value.map(|x| x.into_u64()),
+        )?;
+
+        // enforce: (hi == 2^32 - 1) => lo == 0
+        let hi = hi.simplify(cs.namespace(|| "higher limb"))?;
+        let hi_max = AllocatedNum::alloc_const(cs.namespace(|| "max higher limb"), u64_to_fr((1 << 32) - 1))?;
+        let hi_is_max = AllocatedNum::equals(cs.namespace(|| "check higher limb"), &hi, &hi_max)?;
+
+        cs.enforce(
+            || "canonical form",
+            |lc| lc + lo.get_lc(),
+            |_| hi_is_max.lc(CS::one(), E::Fr::one()),
+            |lc| lc,
+        );
+
+        let num = packed.simplify(cs.namespace(|| "packed value"))?;
+        Ok(GoldilocksNum { num, value })
+    }
+
+    // enforce a * b = q * p + r, where q is 64-bit long
+    fn enforce_product<CS: ConstraintSystem<E>>(
+        mut cs: CS,
+        a: &Num<E>,
+        b: &Num<E>,
+        r: &Num<E>,
+        quotient: Option<u64>,
+    ) -> Result<(), SynthesisError>
+    {
+        let (q, _, _) = Self::alloc_64_bits(cs.namespace(|| "quotient"), quotient)?;
+
+        let mut rhs = q;
+        rhs.scale(u64_to_fr(GOLDILOCKS_MODULUS));
+        rhs += r;
+
+        Num::enforce(cs.namespace(|| "a * b = q * p + r"), a, b, &rhs);
+        Ok(())
+    }
+
+    // witness for a * b = q * p + r
+    fn divmod(a: Option<Goldilocks>, b: Option<Goldilocks>) -> (Option<u64>, Option<Goldilocks>) {
+        match (a, b) {
+            (Some(a), Some(b)) => {
+                let product = a.into_u64() as u128 * b.into_u64() as u128;
+                let q = (product / (GOLDILOCKS_MODULUS as u128)) as u64;
+                (Some(q), Some(Goldilocks::reduce(product)))
+            },
+            _ => (None, None),
+        }
+    }
+
+    // a + sign * b = r + carry * p, where sign = +/- 1
+    fn add_with_sign<CS: ConstraintSystem<E>>(&self, mut cs: CS, other: &Self, negate: bool) -> Result<Self, SynthesisError> {
+        let (value, carry_value) = match (self.value, other.value) {
+            (Some(a), Some(b)) => {
+                let mut res = a.clone();
+                match negate {
+                    false => res.add_assign(&b),
+                    true => res.sub_assign(&b),
+                };
+                let carry = match negate {
+                    false => (a.into_u64() as u128) + (b.into_u64() as u128) >= GOLDILOCKS_MODULUS as u128,
+                    true => a.into_u64() < b.into_u64(),
+                };
+                (Some(res), Some(carry))
+            },
+            _ => (None, None),
+        };
+
+        let res = Self::alloc_canonical(cs.namespace(|| "result"), value)?;
+        let carry = Boolean::from(AllocatedBit::alloc(cs.namespace(|| "carry"), carry_value)?);
+
+        let mut minus_one = E::Fr::one();
+        minus_one.negate();
+        let mut modulus = u64_to_fr::<E::Fr>(GOLDILOCKS_MODULUS);
+        // for addition: a + b - r - carry * p = 0
+        // for subtraction: a - b - r + carry * p = 0
+        let (b_coef, carry_coef) = match negate {
+            false => {
+                modulus.negate();
+                (E::Fr::one(), modulus)
+            },
+            true => (minus_one.clone(), modulus),
+        };
+
+        let mut lc : Num<E> = self.num.clone().into();
+        lc.mut_add_number_with_coeff(&other.num, b_coef);
+        lc.mut_add_number_with_coeff(&res.num, minus_one);
+        lc.mut_add_bool_with_coeff(CS::one(), &carry, carry_coef);
+
+        cs.enforce(
+            || "addition with carry",
+            |lc| lc,
+            |lc| lc,
+            |_| lc.lc(E::Fr::one()),
+        );
+
+        Ok(res)
+    }
+}
+
+
+impl<E: Engine> FieldGadget<E> for GoldilocksNum<E> {
+    type Field = Goldilocks;
+
+    fn alloc<CS: ConstraintSystem<E>>(cs: CS, value: Option<Goldilocks>) -> Result<Self, SynthesisError> {
+        Self::alloc_canonical(cs, value)
+    }
+
+    fn constant<CS: ConstraintSystem<E>>(cs: CS, value: Goldilocks) -> Result<Self, SynthesisError> {
+        let num = AllocatedNum::alloc_const(cs, u64_to_fr(value.into_u64()))?;
+        Ok(GoldilocksNum { num, value: Some(value) })
+    }
+
+    fn get_value(&self) -> Option<Goldilocks> {
+        self.value
+    }
+
+    fn num_native_elems() -> usize {
+        1
+    }
+
+    fn into_native_elems<CS: ConstraintSystem<E>>(&self, _cs: CS) -> Result<Vec<AllocatedNum<E>>, SynthesisError> {
+        Ok(vec![self.num.clone()])
+    }
+
+    fn from_native_elems<CS: ConstraintSystem<E>>(mut cs: CS, elems: &[AllocatedNum<E>]) -> Result<Self, SynthesisError> {
+        let elem = match elems {
+            [elem] => elem,
+            _ => return Err(SynthesisError::Unknown),
+        };
+
+        let value = elem.get_value().map(|x| Goldilocks(fr_to_u64(&x)));
+        let res = Self::alloc_canonical(cs.namespace(|| "range check"), value)?;
+
+        cs.enforce(
+            || "native element is equal to canonical one",
+            |lc| lc + elem.get_variable() - res.num.get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc,
+        );
+
+        Ok(res)
+    }
+
+    fn add<CS: ConstraintSystem<E>>(&self, cs: CS, other: &Self) -> Result<Self, SynthesisError> {
+        self.add_with_sign(cs, other, false)
+    }
+
+    fn sub<CS: ConstraintSystem<E>>(&self, cs: CS, other: &Self) -> Result<Self, SynthesisError> {
+        self.add_with_sign(cs, other, true)
+    }
+
+    fn mul<CS: ConstraintSystem<E>>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError> {
+        let (q, r) = Self::divmod(self.value, other.value);
+        let res = Self::alloc_canonical(cs.namespace(|| "remainder"), r)?;
+
+        Self::enforce_product(
+            cs.namespace(|| "multiplication"),
+            &self.num.clone().into(),
+            &other.num.clone().into(),
+            &res.num.clone().into(),
+            q,
+        )?;
+
+        Ok(res)
+    }
+
+    fn mul_by_constant<CS: ConstraintSystem<E>>(&self, mut cs: CS, coef: &Goldilocks) -> Result<Self, SynthesisError> {
+        let (q, r) = Self::divmod(self.value, Some(coef.clone()));
+        let res = Self::alloc_canonical(cs.namespace(|| "remainder"), r)?;
+        let coef = Num::from_constant(&u64_to_fr(coef.into_u64()), &cs);
+
+        Self::enforce_product(
+            cs.namespace(|| "multiplication by constant"),
+            &self.num.clone().into(),
+            &coef,
+            &res.num.clone().into(),
+            q,
+        )?;
+
+        Ok(res)
+    }
+
+    fn inverse<CS: ConstraintSystem<E>>(&self, mut cs: CS) -> Result<Self, SynthesisError> {
+        let inv_value = match self.value {
+            Some(x) => Some(x.inverse().ok_or(SynthesisError::DivisionByZero)?),
+            None => None,
+        };
+        let inv = Self::alloc_canonical(cs.namespace(|| "inverse"), inv_value)?;
+
+        // a * a_inv = q * p + 1
+        let (q, _) = Self::divmod(self.value, inv_value);
+        let one = Num::from_constant(&E::Fr::one(), &cs);
+
+        Self::enforce_product(
+            cs.namespace(|| "inversion check"),
+            &self.num.clone().into(),
+            &inv.num.clone().into(),
+            &one,
+            q,
+        )?;
+
+        Ok(inv)
+    }
+
+    // both elements are canonical, so it is enough to compare native representations
+    fn equals<CS: ConstraintSystem<E>>(cs: CS, a: &Self, b: &Self) -> Result<Boolean, SynthesisError> {
+        AllocatedNum::equals(cs, &a.num, &b.num)
+    }
+
+    fn conditionally_select<CS: ConstraintSystem<E>>(
+        cs: CS,
+        a: &Self,
+        b: &Self,
+        condition: &Boolean,
+    ) -> Result<Self, SynthesisError>
+    {
+        let num = AllocatedNum::conditionally_select(cs, &a.num, &b.num, condition)?;
+        let value = match condition.get_value() {
+            Some(true) => a.value,
+            Some(false) => b.value,
+            None => None,
+        };
+
+        Ok(GoldilocksNum { num, value })
+    }
+}
+
+impl<E: Engine> ChallengeFieldGadget<E> for GoldilocksNum<E> {
+    fn num_native_challenges() -> usize {
+        1
+    }
+
+    // enforce v = r + q * p, where v is the lower 64 bits of the (strictly decomposed) challenge,
+    // r is canonical and q is a bit, as v < 2^64 < 2p
+    fn from_native_challenges<CS: ConstraintSystem<E>>(
+        mut cs: CS,
+        challenges: &[AllocatedNum<E>],
+    ) -> Result<Self, SynthesisError>
+    {
+        assert_eq!(challenges.len(), 1);
+        let bits = challenges[0].into_bits_le_strict(cs.namespace(|| "decompose challenge"))?;
+
+        let mut lower = Num::<E>::zero();
+        let mut coef = E::Fr::one();
+        for bit in bits.iter().take(64) {
+            lower.mut_add_bool_with_coeff(CS::one(), bit, coef);
+            coef.double();
+        }
+
+        let lower_value = lower.get_value().map(|x| fr_to_u64(&x));
+        let r = Self::alloc_canonical(
+            cs.namespace(|| "reduced challenge"),
+            lower_value.map(|x| Goldilocks::from_u64(x)),
+        )?;
+        let q = Boolean::from(AllocatedBit::alloc(
+            cs.namespace(|| "reduction quotient"),
+            lower_value.map(|x| x >= GOLDILOCKS_MODULUS),
+        )?);
+
+        cs.enforce(
+            || "reduce challenge",
+            |lc| lc + lower.get_lc() - r.num.get_variable() - &q.lc(CS::one(), u64_to_fr(GOLDILOCKS_MODULUS)),
+            |lc| lc + CS::one(),
+            |lc| lc,
+        );
+
+        Ok(r)
+    }
+}
+
+impl<E: Engine> ExtensionOf<E, GoldilocksNum<E>> for GoldilocksNum<E> {
+    fn from_base<CS: ConstraintSystem<E>>(_cs: CS, elem: &Self) -> Result<Self, SynthesisError> {
+        Ok(elem.clone())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::ConstraintSystem;
+    use bellman::pairing::bn256::Bn256;
+    use bellman::pairing::ff::Field;
+    use crate::tester::naming_oblivious_cs::NamingObliviousConstraintSystem as TestConstraintSystem;
+    use common::field_gadget::*;
+    use common::boolean::*;
+    use super::*;
+
+    #[test]
+    fn test_goldilocks_root_of_unity() {
+        let root = Goldilocks::two_adic_root_of_unity();
+        let mut x = root.clone();
+        for _ in 0..(GOLDILOCKS_TWO_ADICITY - 1) {
+            x.square();
+        }
+        assert_eq!(x, Goldilocks::from_u64(GOLDILOCKS_MODULUS - 1));
+        x.square();
+        assert_eq!(x, Goldilocks::one());
+    }
+
+    #[test]
+    fn test_goldilocks_gadget_arithmetic() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        // take values close to the modulus to trigger all the reductions
+        let a_value = Goldilocks::from_u64(GOLDILOCKS_MODULUS - 3);
+        let b_value = Goldilocks::from_u64(rng.gen());
+        let c_value = Goldilocks::from_u64(5);
+
+        let a = GoldilocksNum::alloc(cs.namespace(|| "a"), Some(a_value)).unwrap();
+        let b = GoldilocksNum::alloc(cs.namespace(|| "b"), Some(b_value)).unwrap();
+        let c = GoldilocksNum::alloc(cs.namespace(|| "c"), Some(c_value)).unwrap();
+
+        // (a + c) * b - c / a
+        let sum = a.add(cs.namespace(|| "a + c"), &c).unwrap();
+        let prod = sum.mul(cs.namespace(|| "(a + c) * b"), &b).unwrap();
+        let quotient = c.div(cs.namespace(|| "c / a"), &a).unwrap();
+        let res = prod.sub(cs.namespace(|| "final sub"), &quotient).unwrap();
+
+        let mut expected = a_value;
+        expected.add_assign(&c_value);
+        expected.mul_assign(&b_value);
+        let mut temp = a_value.inverse().unwrap();
+        temp.mul_assign(&c_value);
+        expected.sub_assign(&temp);
+
+        let expected = GoldilocksNum::constant(cs.namespace(|| "expected"), expected).unwrap();
+        let flag = GoldilocksNum::equals(cs.namespace(|| "check"), &res, &expected).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(flag.get_value(), Some(true));
+    }
+
+    #[test]
+    fn test_goldilocks_non_canonical_input() {
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        // p is not a canonical representation of 0
+        let x = AllocatedNum::alloc2(
+            cs.namespace(|| "x"),
+            Some(u64_to_fr(GOLDILOCKS_MODULUS)),
+        ).unwrap();
+        GoldilocksNum::from_native_elems(cs.namespace(|| "from native"), &[x]).unwrap();
+
+        assert!(!cs.is_satisfied());
+    }
+}
@@ -69,4 +69,229 @@ pub fn do_the_lookup<E: Engine, CS>(
         );
 
         Ok(result)
-    }
\ No newline at end of file
+    }
+
+// coefficients of the multilinear polynomial interpolating the table:
+// table[index] = sum over subsets S of coeffs[S] * prod_{i in S} bit_i, where index is composed of little-endian bits
+fn multilinear_coefficients<F: PrimeField>(table: &[F]) -> Vec<F> {
+    let mut coeffs = table.to_vec();
+
+    let mut step = 1;
+    while step < coeffs.len() {
+        for j in 0..coeffs.len() {
+            if j & step != 0 {
+                let tmp = coeffs[j ^ step];
+                coeffs[j].sub_assign(&tmp);
+            }
+        }
+        step <<= 1;
+    }
+
+    coeffs
+}
+
+fn window_index(bits: &[boolean::Boolean]) -> Option<usize> {
+    bits.iter().enumerate().fold(Some(0usize), |acc, (i, b)| {
+        acc.and_then(|acc| b.get_value().map(|v| acc | ((v as usize) << i)))
+    })
+}
+
+fn combine_monomials<E: Engine, CS: ConstraintSystem<E>>(
+    coeffs: &[E::Fr],
+    monomials: &[boolean::Boolean],
+) -> num::Num<E>
+{
+    coeffs.iter().zip(monomials.iter()).fold(num::Num::zero(), |acc, (c, m)| {
+        acc.add_bool_with_coeff(CS::one(), m, *c)
+    })
+}
+
+// table of 8 elements, monomials are 1, b_1, b_2 and the precomputed b_1 * b_2:
+// table[b] = A(b_1, b_2) + b_0 * B(b_1, b_2) is checked with a single constraint
+fn lookup3_with_precomputed<E: Engine, CS>(
+    mut cs: CS,
+    bit: &boolean::Boolean,
+    monomials: &[boolean::Boolean],
+    table: &[E::Fr],
+) -> Result<num::Num<E>, SynthesisError>
+    where CS: ConstraintSystem<E>
+{
+    assert_eq!(table.len(), 8);
+
+    let coeffs = multilinear_coefficients(table);
+    let a_coeffs : Vec<E::Fr> = coeffs.iter().step_by(2).cloned().collect();
+    let b_coeffs : Vec<E::Fr> = coeffs.iter().skip(1).step_by(2).cloned().collect();
+
+    let a = combine_monomials::<E, CS>(&a_coeffs, monomials);
+    let b = combine_monomials::<E, CS>(&b_coeffs, monomials);
+
+    let index = window_index(&[bit.clone(), monomials[1].clone(), monomials[2].clone()]);
+    let result = num::AllocatedNum::alloc(
+        cs.namespace(|| "lookup result"),
+        || Ok(table[index.ok_or(SynthesisError::AssignmentMissing)?])
+    )?;
+
+    cs.enforce(
+        || "b_0 * B = result - A",
+        |_| bit.lc(CS::one(), E::Fr::one()),
+        |_| b.lc(E::Fr::one()),
+        |lc| lc + result.get_variable() - &a.lc(E::Fr::one())
+    );
+
+    Ok(result.into())
+}
+
+// x_0 if bit is false and x_1 otherwise, costs one constraint (none for constant bit)
+fn select_pair<E: Engine, CS>(
+    mut cs: CS,
+    bit: &boolean::Boolean,
+    x_0: &num::Num<E>,
+    x_1: &num::Num<E>,
+) -> Result<num::Num<E>, SynthesisError>
+    where CS: ConstraintSystem<E>
+{
+    match bit {
+        &boolean::Boolean::Constant(false) => return Ok(x_0.clone()),
+        &boolean::Boolean::Constant(true) => return Ok(x_1.clone()),
+        _ => {},
+    };
+
+    let value = match bit.get_value() {
+        Some(true) => x_1.get_value(),
+        Some(false) => x_0.get_value(),
+        None => None,
+    };
+    let result = num::AllocatedNum::alloc(
+        cs.namespace(|| "selection result"),
+        || value.ok_or(SynthesisError::AssignmentMissing)
+    )?;
+
+    let mut delta = x_1.clone();
+    delta.sub_assign(x_0);
+
+    cs.enforce(
+        || "bit * (x_1 - x_0) = result - x_0",
+        |_| bit.lc(CS::one(), E::Fr::one()),
+        |_| delta.lc(E::Fr::one()),
+        |lc| lc + result.get_variable() - &x_0.lc(E::Fr::one())
+    );
+
+    Ok(result.into())
+}
+
+/// Selects table[index] where the index is given by k little-endian bits, the table has 2^k constant elements.
+/// Costs 0, 1 and 2 constraints for k = 1, 2, 3;
+/// larger windows are split into 3-bit lookups over the chunks of the table followed by the selection
+/// with the remaining bits, costing 2^{k - 2} constraints in total. Constant bits are free
+pub fn lookup_window<E: Engine, CS>(
+    mut cs: CS,
+    bits: &[boolean::Boolean],
+    table: &[E::Fr],
+) -> Result<num::Num<E>, SynthesisError>
+    where CS: ConstraintSystem<E>
+{
+    assert_eq!(table.len(), 1 << bits.len());
+
+    // constant bits just restrict the table, so they are folded before any constraint is generated
+    if let Some(pos) = bits.iter().position(|b| b.is_constant()) {
+        let bit_value = bits[pos].get_value().unwrap() as usize;
+        let restricted_table : Vec<E::Fr> = table.iter().enumerate()
+            .filter(|&(i, _)| (i >> pos) & 1 == bit_value)
+            .map(|(_, x)| *x)
+            .collect();
+        let mut remaining_bits = bits.to_vec();
+        remaining_bits.remove(pos);
+
+        return lookup_window(cs, &remaining_bits, &restricted_table);
+    }
+
+    match bits.len() {
+        0 => Ok(num::Num::from_constant(&table[0], &cs)),
+        1 => {
+            let coeffs = multilinear_coefficients(table);
+            let monomials = [boolean::Boolean::constant(true), bits[0].clone()];
+
+            Ok(combine_monomials::<E, CS>(&coeffs, &monomials))
+        },
+        2 => {
+            let coeffs = multilinear_coefficients(table);
+            let product = boolean::Boolean::and(cs.namespace(|| "b_0 * b_1"), &bits[0], &bits[1])?;
+            let monomials = [boolean::Boolean::constant(true), bits[0].clone(), bits[1].clone(), product];
+
+            Ok(combine_monomials::<E, CS>(&coeffs, &monomials))
+        },
+        _ => {
+            let product = boolean::Boolean::and(cs.namespace(|| "b_1 * b_2"), &bits[1], &bits[2])?;
+            let monomials = [boolean::Boolean::constant(true), bits[1].clone(), bits[2].clone(), product];
+
+            let mut candidates = table.chunks(8).enumerate().map(|(i, chunk)| {
+                lookup3_with_precomputed(cs.namespace(|| format!("lookup in chunk {}", i)), &bits[0], &monomials, chunk)
+            }).collect::<Result<Vec<_>, SynthesisError>>()?;
+
+            for (i, bit) in bits.iter().enumerate().skip(3) {
+                candidates = candidates.chunks(2).enumerate().map(|(j, pair)| {
+                    select_pair(cs.namespace(|| format!("selection by bit {}: pair {}", i, j)), bit, &pair[0], &pair[1])
+                }).collect::<Result<Vec<_>, SynthesisError>>()?;
+            }
+
+            assert_eq!(candidates.len(), 1);
+            Ok(candidates.pop().unwrap())
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use rand::{XorShiftRng, SeedableRng, Rng};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::ConstraintSystem;
+    use crate::tester::naming_oblivious_cs::NamingObliviousConstraintSystem as TestConstraintSystem;
+    use common::boolean::{Boolean, AllocatedBit};
+    use super::*;
+
+    #[test]
+    fn test_lookup_window() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for k in 1..7 {
+            let table : Vec<Fr> = (0..(1 << k)).map(|_| rng.gen()).collect();
+            let expected_cost = match k {
+                1 => 0,
+                2 => 1,
+                3 => 2,
+                _ => 1 << (k - 2),
+            };
+
+            for index in 0..(1 << k) {
+                let mut cs = TestConstraintSystem::<Bn256>::new();
+                let bits = (0..k).map(|i| {
+                    Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some((index >> i) & 1 == 1)).unwrap())
+                }).collect::<Vec<_>>();
+
+                let before = cs.num_constraints();
+                let res = lookup_window(cs.namespace(|| "lookup"), &bits, &table).unwrap();
+
+                assert!(cs.is_satisfied());
+                assert_eq!(res.get_value(), Some(table[index]));
+                assert_eq!(cs.num_constraints() - before, expected_cost);
+
+                // constant bits don't cost anything for the selection
+                let constant_bits = (0..k).map(|i| Boolean::constant((index >> i) & 1 == 1)).collect::<Vec<_>>();
+                let before = cs.num_constraints();
+                let res = lookup_window(cs.namespace(|| "constant lookup"), &constant_bits, &table).unwrap();
+                assert_eq!(res.get_value(), Some(table[index]));
+                assert_eq!(cs.num_constraints() - before, 0);
+
+                // the same holds for a window with only the lowest bit variable
+                let mut mixed_bits = constant_bits.clone();
+                mixed_bits[0] = bits[0].clone();
+                let before = cs.num_constraints();
+                let res = lookup_window(cs.namespace(|| "mixed lookup"), &mixed_bits, &table).unwrap();
+                assert!(cs.is_satisfied());
+                assert_eq!(res.get_value(), Some(table[index]));
+                assert_eq!(cs.num_constraints() - before, 0);
+            }
+        }
+    }
+}
@@ -1,9 +1,17 @@
 pub mod boolean;
 pub mod multieq;
+pub mod digest;
+pub mod sha256;
+pub mod blake2s;
 pub mod uint32;
+pub mod uint64;
+pub mod uint8;
 pub mod num;
 pub mod lookup;
 pub mod multipack;
+pub mod field_gadget;
+pub mod goldilocks;
+pub mod extension_field;
 
 
 use bellman::{
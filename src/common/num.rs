@@ -710,6 +710,174 @@ impl<E: Engine> AllocatedNum<E> {
         Ok(boolean::Boolean::from(r))
     }
 
+    /// Canonical decomposition into `num_bits` little-endian bits,
+    /// enforces 0 <= self < 2^num_bits
+    pub fn into_bits_le_bounded<CS>(
+        &self,
+        mut cs: CS,
+        num_bits: usize
+    ) -> Result<Vec<Boolean>, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        assert!(num_bits <= E::Fr::NUM_BITS as usize);
+
+        if num_bits < E::Fr::CAPACITY as usize {
+            // 2^num_bits < p, so the packing can't wrap around the modulus
+            let repr = self.value.map(|x| x.into_repr());
+            let mut packed = Num::<E>::zero();
+            let mut coeff = E::Fr::one();
+            let mut bits = Vec::with_capacity(num_bits);
+
+            for i in 0..num_bits {
+                let bit_value = repr.as_ref().map(|r| (r.as_ref()[i / 64] >> (i % 64)) & 1 == 1);
+                let bit = Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), bit_value)?);
+                packed = packed.add_bool_with_coeff(CS::one(), &bit, coeff);
+                coeff.double();
+                bits.push(bit);
+            }
+
+            cs.enforce(
+                || "packing constraint",
+                |lc| lc,
+                |lc| lc + CS::one(),
+                |_| packed.lc(E::Fr::one()) - self.variable
+            );
+
+            return Ok(bits);
+        }
+
+        let mut bits = self.into_bits_le_strict(cs.namespace(|| "strict decomposition"))?;
+        let top_bits = bits.split_off(num_bits);
+
+        // the sum of the top bits can't wrap around the modulus, so it is zero only if all of them are zero
+        let mut top_bits_lc = Num::<E>::zero();
+        let mut coeff = E::Fr::one();
+        for bit in top_bits.iter() {
+            top_bits_lc = top_bits_lc.add_bool_with_coeff(CS::one(), bit, coeff);
+            coeff.double();
+        }
+
+        cs.enforce(
+            || "top bits are zero",
+            |lc| lc,
+            |lc| lc + CS::one(),
+            |_| top_bits_lc.lc(E::Fr::one())
+        );
+
+        Ok(bits)
+    }
+
+    /// Returns `true` if a < b. Both numbers should be less than 2^num_bits,
+    /// it's the responsibility of the caller (see `assert_in_range` and `into_bits_le_bounded`)
+    pub fn less_than<CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+        num_bits: usize
+    ) -> Result<Boolean, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        assert!(num_bits + 1 < E::Fr::CAPACITY as usize);
+
+        // 2^num_bits + a - b lies in [1, 2^{num_bits + 1}) and has the bit num_bits set iff a >= b
+        let shift = E::Fr::from_str("2").unwrap().pow(&[num_bits as u64]);
+
+        let shifted_delta = Self::alloc(
+            cs.namespace(|| "shifted delta"),
+            || {
+                let mut tmp = shift;
+                tmp.add_assign(a.get_value().get()?);
+                tmp.sub_assign(b.get_value().get()?);
+
+                Ok(tmp)
+            }
+        )?;
+
+        cs.enforce(
+            || "shifted delta = 2^num_bits + a - b",
+            |lc| lc + (shift, CS::one()) + a.get_variable() - b.get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + shifted_delta.get_variable()
+        );
+
+        let bits = shifted_delta.into_bits_le_bounded(cs.namespace(|| "decompose shifted delta"), num_bits + 1)?;
+
+        Ok(bits[num_bits].not())
+    }
+
+    /// Returns `true` if a <= b, with the same restrictions as `less_than`
+    pub fn less_or_equal<CS>(
+        cs: CS,
+        a: &Self,
+        b: &Self,
+        num_bits: usize
+    ) -> Result<Boolean, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        Ok(Self::less_than(cs, b, a, num_bits)?.not())
+    }
+
+    /// Enforces lo <= self <= hi, where hi - lo < 2^num_bits
+    pub fn assert_in_range<CS>(
+        &self,
+        mut cs: CS,
+        lo: E::Fr,
+        hi: E::Fr,
+        num_bits: usize
+    ) -> Result<(), SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        assert!(num_bits < E::Fr::CAPACITY as usize);
+
+        // both self - lo and hi - self should fit into num_bits:
+        // if self < lo or self > hi one of them wraps around the modulus and becomes too large
+        let mut minus_one = E::Fr::one();
+        minus_one.negate();
+        let mut minus_lo = lo;
+        minus_lo.negate();
+
+        // delta = coeff * self + constant
+        for (i, &(coeff, constant)) in [(E::Fr::one(), minus_lo), (minus_one, hi)].iter().enumerate() {
+            let mut cs = cs.namespace(|| format!("bound {}", i));
+
+            let delta = Self::alloc(
+                cs.namespace(|| "delta"),
+                || {
+                    let mut tmp = *self.get_value().get()?;
+                    tmp.mul_assign(&coeff);
+                    tmp.add_assign(&constant);
+
+                    Ok(tmp)
+                }
+            )?;
+
+            cs.enforce(
+                || "delta = coeff * self + constant",
+                |lc| lc + (coeff, self.get_variable()) + (constant, CS::one()),
+                |lc| lc + CS::one(),
+                |lc| lc + delta.get_variable()
+            );
+
+            delta.into_bits_le_bounded(cs.namespace(|| "range check"), num_bits)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns `true` if a < b as integers in [0, p), no restrictions on the inputs
+    pub fn less_than_full<CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self
+    ) -> Result<Boolean, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let a_bits = a.into_bits_le_strict(cs.namespace(|| "decompose a"))?;
+        let b_bits = b.into_bits_le_strict(cs.namespace(|| "decompose b"))?;
+
+        le_bits_less_than(cs.namespace(|| "compare"), &a_bits, &b_bits)
+    }
+
     pub fn get_value(&self) -> Option<E::Fr> {
         self.value
     }
@@ -762,6 +930,54 @@ impl<E: Engine, SPP> FromStream<E, SPP> for AllocatedNum<E> {
     } 
 }
 
+/// Compares the integers given by little-endian bits of the same length:
+/// returns `true` if a < b, costs up to 5 constraints per bit (less when one of the bits is constant)
+pub fn le_bits_less_than<E, CS>(
+    mut cs: CS,
+    a: &[Boolean],
+    b: &[Boolean]
+) -> Result<Boolean, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert_eq!(a.len(), b.len());
+
+    // scan from the most significant bit: a < b iff the first differing bit is set in b
+    let mut is_less = Boolean::constant(false);
+    let mut is_equal = Boolean::constant(true);
+
+    for (i, (a_bit, b_bit)) in a.iter().zip(b.iter()).enumerate().rev() {
+        let mut cs = cs.namespace(|| format!("bit {}", i));
+
+        let b_is_greater = Boolean::and(cs.namespace(|| "not a and b"), &a_bit.not(), b_bit)?;
+        let is_less_here = Boolean::and(cs.namespace(|| "first difference"), &is_equal, &b_is_greater)?;
+        // is_less and is_less_here are never set simultaneously, so xor is the same as or
+        is_less = Boolean::xor(cs.namespace(|| "update less"), &is_less, &is_less_here)?;
+
+        let bits_differ = Boolean::xor(cs.namespace(|| "a xor b"), a_bit, b_bit)?;
+        is_equal = Boolean::and(cs.namespace(|| "update equal"), &is_equal, &bits_differ.not())?;
+    }
+
+    Ok(is_less)
+}
+
+/// Strict comparison against the field modulus: returns `true` if the integer given
+/// by the little-endian bits is a canonical field element (i.e. less than p)
+pub fn le_bits_less_than_modulus<E, CS>(
+    cs: CS,
+    bits: &[Boolean]
+) -> Result<Boolean, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    let mut modulus_bits : Vec<Boolean> = BitIterator::new(E::Fr::char()).map(|b| Boolean::constant(b)).collect();
+    modulus_bits.reverse();
+
+    assert!(bits.len() <= modulus_bits.len());
+    let mut bits = bits.to_vec();
+    bits.resize(modulus_bits.len(), Boolean::constant(false));
+
+    le_bits_less_than(cs, &bits, &modulus_bits)
+}
+
 pub struct Num<E: Engine> {
     value: Option<E::Fr>,
     lc: LinearCombination<E>
@@ -809,6 +1025,20 @@ impl<E: Engine> Num<E> {
         &self.lc
     }
 
+    /// returns the value if the combination doesn't contain any variable except of the constant one
+    /// (the value is taken from the coefficients, so it is known even without the witness)
+    pub fn get_constant_value<CS: ConstraintSystem<E>>(&self) -> Option<E::Fr> {
+        let mut res = E::Fr::zero();
+        for (var, coeff) in self.lc.as_ref().iter() {
+            if *var != CS::one() {
+                return None;
+            }
+            res.add_assign(coeff);
+        }
+
+        Some(res)
+    }
+
     pub fn lc(&self, coeff: E::Fr) -> LinearCombination<E> {
         LinearCombination::zero() + (coeff, &self.lc)
     }
@@ -1114,9 +1344,9 @@ mod test {
     use rand::{SeedableRng, Rand, Rng, XorShiftRng};
     use bellman::{ConstraintSystem};
     use bellman::pairing::bls12_381::{Bls12, Fr};
-    use bellman::pairing::ff::{Field, PrimeField, BitIterator};
+    use bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr, BitIterator};
     use crate::tester::naming_dependent_cs::NamingDependentConstraintSystem as TestConstraintSystem;
-    use super::{AllocatedNum, Boolean};
+    use super::{AllocatedNum, Boolean, le_bits_less_than_modulus};
 
     #[test]
     fn test_allocated_num() {
@@ -1332,4 +1562,109 @@ mod test {
             }
         }
     }
+
+    fn u64_to_fr(x: u64) -> Fr {
+        Fr::from_str(&x.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_num_less_than() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for i in 0..100 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a: u64 = rng.gen();
+            let b: u64 = if i % 4 == 0 { a } else { rng.gen() };
+
+            let a_num = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(u64_to_fr(a))).unwrap();
+            let b_num = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(u64_to_fr(b))).unwrap();
+
+            let lt = AllocatedNum::less_than(cs.namespace(|| "lt"), &a_num, &b_num, 64).unwrap();
+            let le = AllocatedNum::less_or_equal(cs.namespace(|| "le"), &a_num, &b_num, 64).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(lt.get_value(), Some(a < b));
+            assert_eq!(le.get_value(), Some(a <= b));
+        }
+    }
+
+    #[test]
+    fn test_num_assert_in_range() {
+        let lo = 1000u64;
+        let hi = 2000u64;
+
+        for &(x, expected) in [(999u64, false), (1000, true), (1500, true), (2000, true), (2001, false), (0, false)].iter() {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let n = AllocatedNum::alloc(cs.namespace(|| "n"), || Ok(u64_to_fr(x))).unwrap();
+            n.assert_in_range(cs.namespace(|| "range"), u64_to_fr(lo), u64_to_fr(hi), 11).unwrap();
+
+            assert_eq!(cs.is_satisfied(), expected);
+        }
+    }
+
+    #[test]
+    fn test_num_into_bits_le_bounded() {
+        for &(x, expected) in [(0u64, true), (1023, true), (1024, false)].iter() {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let n = AllocatedNum::alloc(cs.namespace(|| "n"), || Ok(u64_to_fr(x))).unwrap();
+            let bits = n.into_bits_le_bounded(cs.namespace(|| "decompose"), 10).unwrap();
+
+            assert_eq!(cs.is_satisfied(), expected);
+            // one booleanity constraint per bit and a single packing constraint
+            assert_eq!(cs.num_constraints(), 11);
+            if expected {
+                let value = bits.iter().rev().fold(0u64, |acc, b| (acc << 1) | b.get_value().unwrap() as u64);
+                assert_eq!(value, x);
+            }
+        }
+    }
+
+    #[test]
+    fn test_num_less_than_full() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for i in 0..20 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a = Fr::rand(&mut rng);
+            let b = if i % 4 == 0 { a } else { Fr::rand(&mut rng) };
+
+            let a_num = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(a)).unwrap();
+            let b_num = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(b)).unwrap();
+            let lt = AllocatedNum::less_than_full(cs.namespace(|| "lt"), &a_num, &b_num).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(lt.get_value(), Some(a.into_repr() < b.into_repr()));
+        }
+    }
+
+    #[test]
+    fn test_bits_less_than_modulus() {
+        let mut modulus = Fr::char();
+        let mut max = modulus;
+        max.sub_noborrow(&1.into());
+
+        for &(repr, expected) in [(max, true), (modulus, false)].iter() {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let mut bits : Vec<Boolean> = BitIterator::new(repr).map(|b| Boolean::constant(b)).collect();
+            bits.reverse();
+            let res = le_bits_less_than_modulus(cs.namespace(|| "compare"), &bits).unwrap();
+
+            assert_eq!(res.get_value(), Some(expected));
+        }
+
+        modulus.add_nocarry(&1.into());
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let bits = BitIterator::new(modulus).collect::<Vec<_>>().into_iter().rev().enumerate().map(|(i, b)| {
+            Boolean::from(super::AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(b)).unwrap())
+        }).collect::<Vec<_>>();
+        let res = le_bits_less_than_modulus(cs.namespace(|| "compare"), &bits).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(res.get_value(), Some(false));
+    }
 }
@@ -0,0 +1,424 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+};
+
+use super::uint32::UInt32;
+use super::multieq::MultiEq;
+use super::boolean::Boolean;
+use super::digest::DigestHasher;
+
+
+const ROUND_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2
+];
+
+const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+    0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19
+];
+
+pub const SHA256_BLOCK_SIZE: usize = 512;
+pub const SHA256_DIGEST_SIZE: usize = 256;
+
+
+pub fn get_sha256_iv() -> Vec<UInt32> {
+    IV.iter().map(|&v| UInt32::constant(v)).collect()
+}
+
+// all the inputs and outputs are in big-endian bit order (i.e. the most significant bit of the first byte goes first)
+pub fn sha256_block_no_padding<E, CS>(
+    cs: CS,
+    input: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert_eq!(input.len(), SHA256_BLOCK_SIZE);
+
+    Ok(sha256_compression_function(cs, &input, &get_sha256_iv())?
+        .into_iter()
+        .flat_map(|e| e.into_bits_be())
+        .collect())
+}
+
+// standard SHA-256 of the byte string (input length should be a multiple of 8)
+pub fn sha256<E, CS>(
+    mut cs: CS,
+    input: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert!(input.len() % 8 == 0);
+
+    let mut padded = input.to_vec();
+    let plen = padded.len() as u64;
+    // append a single '1' bit
+    padded.push(Boolean::constant(true));
+    // append K '0' bits, where K is the minimum number >= 0 such that L + 1 + K + 64 is a multiple of 512
+    while (padded.len() + 64) % SHA256_BLOCK_SIZE != 0 {
+        padded.push(Boolean::constant(false));
+    }
+    // append L as a 64-bit big-endian integer, making the total post-processed length a multiple of 512 bits
+    for b in (0..64).rev().map(|i| (plen >> i) & 1 == 1) {
+        padded.push(Boolean::constant(b));
+    }
+    assert!(padded.len() % SHA256_BLOCK_SIZE == 0);
+
+    let mut cur = get_sha256_iv();
+    for (i, block) in padded.chunks(SHA256_BLOCK_SIZE).enumerate() {
+        cur = sha256_compression_function(
+            cs.namespace(|| format!("block {}", i)),
+            block,
+            &cur
+        )?;
+    }
+
+    Ok(cur.into_iter()
+          .flat_map(|e| e.into_bits_be())
+          .collect())
+}
+
+pub fn sha256_compression_function<E, CS>(
+    cs: CS,
+    input: &[Boolean],
+    current_hash_value: &[UInt32]
+) -> Result<Vec<UInt32>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert_eq!(input.len(), SHA256_BLOCK_SIZE);
+    assert_eq!(current_hash_value.len(), 8);
+
+    let mut w = input.chunks(32)
+                     .map(|e| UInt32::from_bits_be(e))
+                     .collect::<Vec<_>>();
+
+    // We can save some constraints by combining some of
+    // the constraints in different u32 additions
+    let mut cs = MultiEq::new(cs);
+
+    for i in 16..64 {
+        let cs = &mut cs.namespace(|| format!("w extension {}", i));
+
+        // s0 := (w[i-15] rightrotate 7) xor (w[i-15] rightrotate 18) xor (w[i-15] rightshift 3)
+        let mut s0 = w[i-15].rotr(7);
+        s0 = s0.xor(cs.namespace(|| "first xor for s0"), &w[i-15].rotr(18))?;
+        s0 = s0.xor(cs.namespace(|| "second xor for s0"), &w[i-15].shr(3))?;
+
+        // s1 := (w[i-2] rightrotate 17) xor (w[i-2] rightrotate 19) xor (w[i-2] rightshift 10)
+        let mut s1 = w[i-2].rotr(17);
+        s1 = s1.xor(cs.namespace(|| "first xor for s1"), &w[i-2].rotr(19))?;
+        s1 = s1.xor(cs.namespace(|| "second xor for s1"), &w[i-2].shr(10))?;
+
+        // w[i] := w[i-16] + s0 + w[i-7] + s1
+        let tmp = UInt32::addmany(
+            cs.namespace(|| "computation of w[i]"),
+            &[w[i-16].clone(), s0, w[i-7].clone(), s1]
+        )?;
+
+        w.push(tmp);
+    }
+
+    assert_eq!(w.len(), 64);
+
+    // additions are deferred as long as possible, so that they can be merged into a single addmany
+    enum Maybe {
+        Deferred(Vec<UInt32>),
+        Concrete(UInt32)
+    }
+
+    impl Maybe {
+        fn compute<E, CS, M>(
+            self,
+            cs: M,
+            others: &[UInt32]
+        ) -> Result<UInt32, SynthesisError>
+            where E: Engine,
+                  CS: ConstraintSystem<E>,
+                  M: ConstraintSystem<E, Root=MultiEq<E, CS>>
+        {
+            Ok(match self {
+                Maybe::Concrete(ref v) => {
+                    return Ok(v.clone())
+                },
+                Maybe::Deferred(mut v) => {
+                    v.extend(others.into_iter().cloned());
+                    UInt32::addmany(cs, &v)?
+                }
+            })
+        }
+    }
+
+    let mut a = Maybe::Concrete(current_hash_value[0].clone());
+    let mut b = current_hash_value[1].clone();
+    let mut c = current_hash_value[2].clone();
+    let mut d = current_hash_value[3].clone();
+    let mut e = Maybe::Concrete(current_hash_value[4].clone());
+    let mut f = current_hash_value[5].clone();
+    let mut g = current_hash_value[6].clone();
+    let mut h = current_hash_value[7].clone();
+
+    for i in 0..64 {
+        let cs = &mut cs.namespace(|| format!("compression round {}", i));
+
+        // S1 := (e rightrotate 6) xor (e rightrotate 11) xor (e rightrotate 25)
+        let new_e = e.compute(cs.namespace(|| "deferred e computation"), &[])?;
+        let mut s1 = new_e.rotr(6);
+        s1 = s1.xor(cs.namespace(|| "first xor for s1"), &new_e.rotr(11))?;
+        s1 = s1.xor(cs.namespace(|| "second xor for s1"), &new_e.rotr(25))?;
+
+        // ch := (e and f) xor ((not e) and g)
+        let ch = UInt32::sha256_ch(cs.namespace(|| "ch"), &new_e, &f, &g)?;
+
+        // temp1 := h + S1 + ch + k[i] + w[i]
+        let temp1 = vec![
+            h.clone(),
+            s1,
+            ch,
+            UInt32::constant(ROUND_CONSTANTS[i]),
+            w[i].clone()
+        ];
+
+        // S0 := (a rightrotate 2) xor (a rightrotate 13) xor (a rightrotate 22)
+        let new_a = a.compute(cs.namespace(|| "deferred a computation"), &[])?;
+        let mut s0 = new_a.rotr(2);
+        s0 = s0.xor(cs.namespace(|| "first xor for s0"), &new_a.rotr(13))?;
+        s0 = s0.xor(cs.namespace(|| "second xor for s0"), &new_a.rotr(22))?;
+
+        // maj := (a and b) xor (a and c) xor (b and c)
+        let maj = UInt32::sha256_maj(cs.namespace(|| "maj"), &new_a, &b, &c)?;
+
+        // temp2 := S0 + maj
+        let temp2 = vec![s0, maj];
+
+        h = g;
+        g = f;
+        f = new_e;
+        // e := d + temp1
+        e = Maybe::Deferred(temp1.iter().cloned().chain(Some(d)).collect::<Vec<_>>());
+        d = c;
+        c = b;
+        b = new_a;
+        // a := temp1 + temp2
+        a = Maybe::Deferred(temp1.iter().cloned().chain(temp2.iter().cloned()).collect::<Vec<_>>());
+    }
+
+    // add the compressed chunk to the current hash value
+    let h0 = a.compute(cs.namespace(|| "deferred h0 computation"), &[current_hash_value[0].clone()])?;
+    let h1 = UInt32::addmany(cs.namespace(|| "new h1"), &[current_hash_value[1].clone(), b])?;
+    let h2 = UInt32::addmany(cs.namespace(|| "new h2"), &[current_hash_value[2].clone(), c])?;
+    let h3 = UInt32::addmany(cs.namespace(|| "new h3"), &[current_hash_value[3].clone(), d])?;
+    let h4 = e.compute(cs.namespace(|| "deferred h4 computation"), &[current_hash_value[4].clone()])?;
+    let h5 = UInt32::addmany(cs.namespace(|| "new h5"), &[current_hash_value[5].clone(), f])?;
+    let h6 = UInt32::addmany(cs.namespace(|| "new h6"), &[current_hash_value[6].clone(), g])?;
+    let h7 = UInt32::addmany(cs.namespace(|| "new h7"), &[current_hash_value[7].clone(), h])?;
+
+    Ok(vec![h0, h1, h2, h3, h4, h5, h6, h7])
+}
+
+
+// native counterpart, is used to generate test vectors and by native provers
+pub fn sha256_native_compression_function(state: &mut [u32; 8], block: &[u8]) {
+    assert_eq!(block.len(), SHA256_BLOCK_SIZE / 8);
+
+    let mut w = [0u32; 64];
+    for (i, chunk) in block.chunks(4).enumerate() {
+        w[i] = ((chunk[0] as u32) << 24) | ((chunk[1] as u32) << 16) | ((chunk[2] as u32) << 8) | (chunk[3] as u32);
+    }
+    for i in 16..64 {
+        let s0 = w[i-15].rotate_right(7) ^ w[i-15].rotate_right(18) ^ (w[i-15] >> 3);
+        let s1 = w[i-2].rotate_right(17) ^ w[i-2].rotate_right(19) ^ (w[i-2] >> 10);
+        w[i] = w[i-16].wrapping_add(s0).wrapping_add(w[i-7]).wrapping_add(s1);
+    }
+
+    let mut x = state.clone();
+    for i in 0..64 {
+        let s1 = x[4].rotate_right(6) ^ x[4].rotate_right(11) ^ x[4].rotate_right(25);
+        let ch = (x[4] & x[5]) ^ ((!x[4]) & x[6]);
+        let temp1 = x[7].wrapping_add(s1).wrapping_add(ch).wrapping_add(ROUND_CONSTANTS[i]).wrapping_add(w[i]);
+        let s0 = x[0].rotate_right(2) ^ x[0].rotate_right(13) ^ x[0].rotate_right(22);
+        let maj = (x[0] & x[1]) ^ (x[0] & x[2]) ^ (x[1] & x[2]);
+        let temp2 = s0.wrapping_add(maj);
+
+        x[7] = x[6];
+        x[6] = x[5];
+        x[5] = x[4];
+        x[4] = x[3].wrapping_add(temp1);
+        x[3] = x[2];
+        x[2] = x[1];
+        x[1] = x[0];
+        x[0] = temp1.wrapping_add(temp2);
+    }
+
+    for (s, v) in state.iter_mut().zip(x.iter()) {
+        *s = s.wrapping_add(*v);
+    }
+}
+
+pub fn sha256_native(input: &[u8]) -> [u8; 32] {
+    let mut padded = input.to_vec();
+    let plen = (input.len() as u64) * 8;
+    padded.push(0x80);
+    while (padded.len() + 8) % (SHA256_BLOCK_SIZE / 8) != 0 {
+        padded.push(0);
+    }
+    for i in (0..8).rev() {
+        padded.push((plen >> (8 * i)) as u8);
+    }
+
+    let mut state = IV;
+    for block in padded.chunks(SHA256_BLOCK_SIZE / 8) {
+        sha256_native_compression_function(&mut state, block);
+    }
+
+    let mut res = [0u8; 32];
+    for (chunk, word) in res.chunks_mut(4).zip(state.iter()) {
+        for (i, byte) in chunk.iter_mut().enumerate() {
+            *byte = (word >> (24 - 8 * i)) as u8;
+        }
+    }
+    res
+}
+
+
+pub struct Sha256Hasher;
+
+impl DigestHasher for Sha256Hasher {
+    fn hash<E: Engine, CS: ConstraintSystem<E>>(cs: CS, input: &[Boolean]) -> Result<Vec<Boolean>, SynthesisError> {
+        sha256(cs, input)
+    }
+
+    fn hash_native(input: &[u8]) -> [u8; 32] {
+        sha256_native(input)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use bellman::pairing::bn256::Bn256;
+    use bellman::ConstraintSystem;
+    use common::boolean::{Boolean, AllocatedBit};
+    use crate::tester::naming_oblivious_cs::NamingObliviousConstraintSystem as TestConstraintSystem;
+    use rand::{XorShiftRng, SeedableRng, Rng};
+    use super::*;
+
+    // (message, digest) from FIPS 180-2 examples: the last two take two and three blocks after padding
+    const SHA256_VECTORS: [(&'static str, &'static str); 4] = [
+        ("", "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+        ("abc", "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+        (
+            "abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        ),
+        (
+            "abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu",
+            "cf5b16a778af8380036ce59e7b0492370b249b11e8f07a51afac45037afee9d1"
+        ),
+    ];
+
+    fn hex_to_bytes(hex: &str) -> Vec<u8> {
+        (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i+2], 16).unwrap()).collect()
+    }
+
+    fn alloc_input_bits<CS: ConstraintSystem<Bn256>>(mut cs: CS, data: &[u8]) -> Vec<Boolean> {
+        let mut input_bits = vec![];
+        for (byte_i, input_byte) in data.iter().enumerate() {
+            for bit_i in (0..8).rev() {
+                let cs = cs.namespace(|| format!("input bit {} {}", byte_i, bit_i));
+                input_bits.push(AllocatedBit::alloc(cs, Some((input_byte >> bit_i) & 1u8 == 1u8)).unwrap().into());
+            }
+        }
+        input_bits
+    }
+
+    fn bits_into_bytes(bits: &[Boolean]) -> Vec<u8> {
+        bits.chunks(8).map(|chunk| {
+            chunk.iter().fold(0u8, |acc, b| (acc << 1) | (b.get_value().unwrap() as u8))
+        }).collect()
+    }
+
+    #[test]
+    fn test_native_sha256_vectors() {
+        for &(message, digest) in SHA256_VECTORS.iter() {
+            assert_eq!(sha256_native(message.as_bytes()).to_vec(), hex_to_bytes(digest));
+        }
+    }
+
+    #[test]
+    fn test_sha256_gadget_vectors() {
+        for &(message, digest) in SHA256_VECTORS.iter() {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let input_bits = alloc_input_bits(cs.namespace(|| "input"), message.as_bytes());
+
+            let r = sha256(&mut cs, &input_bits).unwrap();
+            assert!(cs.is_satisfied());
+            assert_eq!(bits_into_bytes(&r), hex_to_bytes(digest));
+        }
+    }
+
+    #[test]
+    fn test_sha256_gadget_against_native() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        // lengths around the padding boundaries: 55 bytes is the longest one-block message, 56 requires two blocks
+        for &len in [3usize, 55, 56, 64, 119, 120].iter() {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let data : Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            let input_bits = alloc_input_bits(cs.namespace(|| "input"), &data);
+
+            let r = sha256(&mut cs, &input_bits).unwrap();
+            assert!(cs.is_satisfied());
+            assert_eq!(bits_into_bytes(&r), sha256_native(&data).to_vec());
+        }
+    }
+
+    #[test]
+    fn test_sha256_compression_function_against_native() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        // arbitrary chaining value, not only IV
+        let mut state = [0u32; 8];
+        for x in state.iter_mut() {
+            *x = rng.gen();
+        }
+        let block : Vec<u8> = (0..(SHA256_BLOCK_SIZE / 8)).map(|_| rng.gen()).collect();
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let input_bits = alloc_input_bits(cs.namespace(|| "input"), &block);
+        let current = state.iter().enumerate().map(|(i, x)| {
+            UInt32::alloc(cs.namespace(|| format!("state word {}", i)), Some(*x)).unwrap()
+        }).collect::<Vec<_>>();
+
+        let r = sha256_compression_function(cs.namespace(|| "compression"), &input_bits, &current).unwrap();
+        assert!(cs.is_satisfied());
+
+        sha256_native_compression_function(&mut state, &block);
+        let r : Vec<Boolean> = r.into_iter().flat_map(|e| e.into_bits_be()).collect();
+        let expected : Vec<u8> = state.iter().flat_map(|w| (0..4).map(move |i| (w >> (24 - 8 * i)) as u8)).collect();
+        assert_eq!(bits_into_bytes(&r), expected);
+    }
+
+    #[test]
+    fn test_sha256_block_constraints_count() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let block : Vec<u8> = (0..(SHA256_BLOCK_SIZE / 8)).map(|_| rng.gen()).collect();
+        let input_bits = alloc_input_bits(cs.namespace(|| "input"), &block);
+
+        sha256_block_no_padding(cs.namespace(|| "block"), &input_bits).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints() - SHA256_BLOCK_SIZE, 25840);
+    }
+}
@@ -0,0 +1,575 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr,
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+    LinearCombination
+};
+
+use super::boolean::{
+    Boolean,
+    AllocatedBit
+};
+
+use super::multieq::MultiEq;
+use super::num::AllocatedNum;
+
+/// Represents an interpretation of 64 `Boolean` objects as an
+/// unsigned integer.
+#[derive(Clone)]
+pub struct UInt64 {
+    // Least significant bit first
+    bits: Vec<Boolean>,
+    value: Option<u64>
+}
+
+impl UInt64 {
+    /// Construct a constant `UInt64` from a `u64`
+    pub fn constant(value: u64) -> Self
+    {
+        let bits = (0..64).map(|i| Boolean::constant((value >> i) & 1 == 1)).collect();
+
+        UInt64 {
+            bits: bits,
+            value: Some(value)
+        }
+    }
+
+    /// Allocate a `UInt64` in the constraint system
+    pub fn alloc<E, CS>(
+        mut cs: CS,
+        value: Option<u64>
+    ) -> Result<Self, SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>
+    {
+        let values = match value {
+            Some(val) => (0..64).map(|i| Some((val >> i) & 1 == 1)).collect(),
+            None => vec![None; 64]
+        };
+
+        let bits = values.into_iter()
+                         .enumerate()
+                         .map(|(i, v)| {
+                            Ok(Boolean::from(AllocatedBit::alloc(
+                                cs.namespace(|| format!("allocated bit {}", i)),
+                                v
+                            )?))
+                         })
+                         .collect::<Result<Vec<_>, SynthesisError>>()?;
+
+        Ok(UInt64 {
+            bits: bits,
+            value: value
+        })
+    }
+
+    pub fn get_value(&self) -> Option<u64> {
+        self.value
+    }
+
+    pub fn into_bits_be(&self) -> Vec<Boolean> {
+        self.bits.iter().rev().cloned().collect()
+    }
+
+    pub fn from_bits_be(bits: &[Boolean]) -> Self {
+        assert_eq!(bits.len(), 64);
+
+        let mut le_bits = bits.to_vec();
+        le_bits.reverse();
+
+        Self::from_bits(&le_bits)
+    }
+
+    /// Turns this `UInt64` into its little-endian byte order representation.
+    pub fn into_bits(&self) -> Vec<Boolean> {
+        self.bits.clone()
+    }
+
+    /// Converts a little-endian byte order representation of bits into a
+    /// `UInt64`.
+    pub fn from_bits(bits: &[Boolean]) -> Self
+    {
+        assert_eq!(bits.len(), 64);
+
+        let mut value = Some(0u64);
+        for b in bits.iter().rev() {
+            value.as_mut().map(|v| *v <<= 1);
+
+            match b.get_value() {
+                Some(true) => { value.as_mut().map(|v| *v |= 1); },
+                Some(false) => {},
+                None => { value = None }
+            }
+        }
+
+        UInt64 {
+            value: value,
+            bits: bits.to_vec()
+        }
+    }
+
+    /// Packs the bits into a single field element: costs one constraint
+    pub fn into_allocated_num<E, CS>(
+        &self,
+        mut cs: CS
+    ) -> Result<AllocatedNum<E>, SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>
+    {
+        let value = self.value.map(|v| {
+            let mut repr = <E::Fr as PrimeField>::Repr::default();
+            repr.as_mut()[0] = v;
+            E::Fr::from_repr(repr).expect("64-bit value fits into the field")
+        });
+
+        let num = AllocatedNum::alloc(
+            cs.namespace(|| "packed value"),
+            || value.ok_or(SynthesisError::AssignmentMissing)
+        )?;
+
+        let mut lc = LinearCombination::zero();
+        let mut coeff = E::Fr::one();
+        for bit in self.bits.iter() {
+            lc = lc + &bit.lc(CS::one(), coeff);
+            coeff.double();
+        }
+
+        cs.enforce(
+            || "packing",
+            |_| lc,
+            |lc| lc + CS::one(),
+            |lc| lc + num.get_variable()
+        );
+
+        Ok(num)
+    }
+
+    /// Decomposes the field element into 64 bits: the constraint system is unsatisfied
+    /// if the value doesn't fit into 64 bits
+    pub fn from_allocated_num<E, CS>(
+        mut cs: CS,
+        num: &AllocatedNum<E>
+    ) -> Result<Self, SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>
+    {
+        let value = num.get_value().map(|v| v.into_repr().as_ref()[0]);
+        let res = Self::alloc(cs.namespace(|| "decomposition"), value)?;
+
+        let mut lc = LinearCombination::zero();
+        let mut coeff = E::Fr::one();
+        for bit in res.bits.iter() {
+            lc = lc + &bit.lc(CS::one(), coeff);
+            coeff.double();
+        }
+
+        cs.enforce(
+            || "unpacking",
+            |_| lc,
+            |lc| lc + CS::one(),
+            |lc| lc + num.get_variable()
+        );
+
+        Ok(res)
+    }
+
+    pub fn rotr(&self, by: usize) -> Self {
+        let by = by % 64;
+
+        let new_bits = self.bits.iter()
+                                .skip(by)
+                                .chain(self.bits.iter())
+                                .take(64)
+                                .cloned()
+                                .collect();
+
+        UInt64 {
+            bits: new_bits,
+            value: self.value.map(|v| v.rotate_right(by as u32))
+        }
+    }
+
+    pub fn rotl(&self, by: usize) -> Self {
+        self.rotr(64 - by % 64)
+    }
+
+    pub fn shr(&self, by: usize) -> Self {
+        let by = by % 64;
+
+        let fill = Boolean::constant(false);
+
+        let new_bits = self.bits
+                           .iter() // The bits are least significant first
+                           .skip(by) // Skip the bits that will be lost during the shift
+                           .chain(Some(&fill).into_iter().cycle()) // Rest will be zeros
+                           .take(64) // Only 64 bits needed!
+                           .cloned()
+                           .collect();
+
+        UInt64 {
+            bits: new_bits,
+            value: self.value.map(|v| v >> by as u32)
+        }
+    }
+
+    /// Bitwise negation is free: it only flips the interpretation of the bits
+    pub fn not(&self) -> Self {
+        UInt64 {
+            bits: self.bits.iter().map(|b| b.not()).collect(),
+            value: self.value.map(|v| !v)
+        }
+    }
+
+    fn binop<E, CS, F, U>(
+        &self,
+        mut cs: CS,
+        other: &Self,
+        bin_fn: F,
+        circuit_fn: U
+    ) -> Result<Self, SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>,
+              F: Fn(u64, u64) -> u64,
+              U: Fn(&mut CS, usize, &Boolean, &Boolean) -> Result<Boolean, SynthesisError>
+    {
+        let new_value = match (self.value, other.value) {
+            (Some(a), Some(b)) => {
+                Some(bin_fn(a, b))
+            },
+            _ => None
+        };
+
+        let bits = self.bits.iter()
+                            .zip(other.bits.iter())
+                            .enumerate()
+                            .map(|(i, (a, b))| circuit_fn(&mut cs, i, a, b))
+                            .collect::<Result<_, _>>()?;
+
+        Ok(UInt64 {
+            bits: bits,
+            value: new_value
+        })
+    }
+
+    /// XOR this `UInt64` with another `UInt64`
+    pub fn xor<E, CS>(
+        &self,
+        cs: CS,
+        other: &Self
+    ) -> Result<Self, SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>
+    {
+        self.binop(cs, other, |a, b| a ^ b,
+            |cs, i, a, b| {
+                Boolean::xor(
+                    cs.namespace(|| format!("xor of bit {}", i)),
+                    a,
+                    b
+                )
+            }
+        )
+    }
+
+    /// AND this `UInt64` with another `UInt64`
+    pub fn and<E, CS>(
+        &self,
+        cs: CS,
+        other: &Self
+    ) -> Result<Self, SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>
+    {
+        self.binop(cs, other, |a, b| a & b,
+            |cs, i, a, b| {
+                Boolean::and(
+                    cs.namespace(|| format!("and of bit {}", i)),
+                    a,
+                    b
+                )
+            }
+        )
+    }
+
+    /// Perform modular addition of several `UInt64` objects.
+    pub fn addmany<E, CS, M>(
+        mut cs: M,
+        operands: &[Self]
+    ) -> Result<Self, SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>,
+              M: ConstraintSystem<E, Root=MultiEq<E, CS>>
+    {
+        // Make some arbitrary bounds for ourselves to avoid overflows
+        // in the scalar field: the sum of 10 operands takes 68 bits
+        assert!(E::Fr::CAPACITY >= 68);
+        assert!(operands.len() >= 2); // Weird trivial cases that should never happen
+        assert!(operands.len() <= 10);
+
+        // Compute the maximum value of the sum so we allocate enough bits for
+        // the result, it doesn't fit into u64 anymore
+        let mut max_value = (operands.len() as u128) * (u64::max_value() as u128);
+
+        // Keep track of the resulting value
+        let mut result_value = Some(0u128);
+
+        // This is a linear combination that we will enforce to equal the
+        // output
+        let mut lc = LinearCombination::zero();
+
+        let mut all_constants = true;
+
+        // Iterate over the operands
+        for op in operands {
+            // Accumulate the value
+            match op.value {
+                Some(val) => {
+                    result_value.as_mut().map(|v| *v += val as u128);
+                },
+                None => {
+                    // If any of our operands have unknown value, we won't
+                    // know the value of the result
+                    result_value = None;
+                }
+            }
+
+            // Iterate over each bit of the operand and add the operand to
+            // the linear combination
+            let mut coeff = E::Fr::one();
+            for bit in &op.bits {
+                lc = lc + &bit.lc(CS::one(), coeff);
+
+                all_constants &= bit.is_constant();
+
+                coeff.double();
+            }
+        }
+
+        // The value of the actual result is modulo 2^64
+        let modular_value = result_value.map(|v| v as u64);
+
+        if all_constants && modular_value.is_some() {
+            // We can just return a constant, rather than
+            // unpacking the result into allocated bits.
+
+            return Ok(UInt64::constant(modular_value.unwrap()));
+        }
+
+        // Storage area for the resulting bits
+        let mut result_bits = vec![];
+
+        // Linear combination representing the output,
+        // for comparison with the sum of the operands
+        let mut result_lc = LinearCombination::zero();
+
+        // Allocate each bit of the result, the bits above 64 are the carry
+        let mut coeff = E::Fr::one();
+        let mut i = 0;
+        while max_value != 0 {
+            // Allocate the bit
+            let b = AllocatedBit::alloc(
+                cs.namespace(|| format!("result bit {}", i)),
+                result_value.map(|v| (v >> i) & 1 == 1)
+            )?;
+
+            // Add this bit to the result combination
+            result_lc = result_lc + (coeff, b.get_variable());
+
+            result_bits.push(b.into());
+
+            max_value >>= 1;
+            i += 1;
+            coeff.double();
+        }
+
+        // Enforce equality between the sum and result
+        cs.get_root().enforce_equal(i, &lc, &result_lc);
+
+        // Discard carry bits that we don't care about
+        result_bits.truncate(64);
+
+        Ok(UInt64 {
+            bits: result_bits,
+            value: modular_value
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{XorShiftRng, SeedableRng, Rng};
+    use common::boolean::{Boolean};
+    use common::num::AllocatedNum;
+    use super::{UInt64};
+    use bellman::pairing::bls12_381::{Bls12, Fr};
+    use bellman::pairing::ff::{Field, PrimeField};
+    use crate::tester::naming_dependent_cs::NamingDependentConstraintSystem as TestConstraintSystem;
+    use bellman::{ConstraintSystem};
+    use common::multieq::MultiEq;
+
+    fn check_bits(r: &UInt64, mut expected: u64) {
+        assert!(r.value == Some(expected));
+
+        for b in r.bits.iter() {
+            assert_eq!(b.get_value().unwrap(), expected & 1 == 1);
+            expected >>= 1;
+        }
+    }
+
+    #[test]
+    fn test_uint64_from_bits() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0653]);
+
+        for _ in 0..1000 {
+            let v = (0..64).map(|_| Boolean::constant(rng.gen())).collect::<Vec<_>>();
+
+            let b = UInt64::from_bits(&v);
+            let value = b.value.unwrap();
+            check_bits(&b, value);
+
+            let b_be = UInt64::from_bits_be(&b.into_bits_be());
+            assert_eq!(b_be.value, Some(value));
+
+            for (x, y) in v.iter().zip(b.into_bits().iter()) {
+                assert_eq!(x.get_value(), y.get_value());
+            }
+        }
+    }
+
+    #[test]
+    fn test_uint64_bitwise() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0653]);
+
+        for _ in 0..1000 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a: u64 = rng.gen();
+            let b: u64 = rng.gen();
+            let c: u64 = rng.gen();
+
+            let a_bit = UInt64::alloc(cs.namespace(|| "a_bit"), Some(a)).unwrap();
+            let b_bit = UInt64::constant(b);
+            let c_bit = UInt64::alloc(cs.namespace(|| "c_bit"), Some(c)).unwrap();
+
+            // (a ^ b) & !c
+            let r = a_bit.xor(cs.namespace(|| "xor"), &b_bit).unwrap();
+            let r = r.and(cs.namespace(|| "and"), &c_bit.not()).unwrap();
+
+            assert!(cs.is_satisfied());
+            check_bits(&r, (a ^ b) & !c);
+        }
+    }
+
+    #[test]
+    fn test_uint64_addmany_constants() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for _ in 0..1000 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a: u64 = rng.gen();
+            let b: u64 = rng.gen();
+            let c: u64 = rng.gen();
+
+            let r = {
+                let mut cs = MultiEq::new(&mut cs);
+                UInt64::addmany(cs.namespace(|| "addition"), &[UInt64::constant(a), UInt64::constant(b), UInt64::constant(c)]).unwrap()
+            };
+
+            assert!(r.bits.iter().all(|b| b.is_constant()));
+            check_bits(&r, a.wrapping_add(b).wrapping_add(c));
+        }
+    }
+
+    #[test]
+    fn test_uint64_addmany() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for _ in 0..1000 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            // values close to the maximum produce the carry into several high bits
+            let a: u64 = u64::max_value() - rng.gen::<u32>() as u64;
+            let b: u64 = rng.gen();
+            let c: u64 = rng.gen();
+            let d: u64 = u64::max_value();
+
+            let expected = (a ^ b).wrapping_add(c).wrapping_add(d);
+
+            let a_bit = UInt64::alloc(cs.namespace(|| "a_bit"), Some(a)).unwrap();
+            let b_bit = UInt64::constant(b);
+            let c_bit = UInt64::constant(c);
+            let d_bit = UInt64::alloc(cs.namespace(|| "d_bit"), Some(d)).unwrap();
+
+            let r = a_bit.xor(cs.namespace(|| "xor"), &b_bit).unwrap();
+            let r = {
+                let mut cs = MultiEq::new(&mut cs);
+                UInt64::addmany(cs.namespace(|| "addition"), &[r, c_bit, d_bit]).unwrap()
+            };
+
+            assert!(cs.is_satisfied());
+            check_bits(&r, expected);
+
+            // Flip a bit and see if the addition constraint still works
+            if cs.get("addition/result bit 0/boolean").is_zero() {
+                cs.set("addition/result bit 0/boolean", Field::one());
+            } else {
+                cs.set("addition/result bit 0/boolean", Field::zero());
+            }
+
+            assert!(!cs.is_satisfied());
+        }
+    }
+
+    #[test]
+    fn test_uint64_rotations_and_shift() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for _ in 0..50 {
+            let num: u64 = rng.gen();
+            let a = UInt64::constant(num);
+
+            for i in 0..70 {
+                check_bits(&a.rotr(i), num.rotate_right(i as u32 % 64));
+                check_bits(&a.rotl(i), num.rotate_left(i as u32 % 64));
+                check_bits(&a.shr(i), num >> (i % 64));
+            }
+        }
+    }
+
+    #[test]
+    fn test_uint64_allocated_num_conversion() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for _ in 0..100 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let a: u64 = rng.gen();
+
+            let a_bit = UInt64::alloc(cs.namespace(|| "a_bit"), Some(a)).unwrap();
+            let num = a_bit.into_allocated_num(cs.namespace(|| "pack")).unwrap();
+            let b_bit = UInt64::from_allocated_num(cs.namespace(|| "unpack"), &num).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(num.get_value(), Some(Fr::from_str(&a.to_string()).unwrap()));
+            check_bits(&b_bit, a);
+        }
+
+        // 2^64 doesn't fit
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let mut value = Fr::from_str(&u64::max_value().to_string()).unwrap();
+        value.add_assign(&Fr::one());
+        let num = AllocatedNum::alloc2(cs.namespace(|| "num"), Some(value)).unwrap();
+        UInt64::from_allocated_num(cs.namespace(|| "unpack"), &num).unwrap();
+
+        assert!(!cs.is_satisfied());
+    }
+}
@@ -0,0 +1,349 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+};
+
+use super::boolean::{
+    Boolean,
+    AllocatedBit
+};
+
+use super::num::AllocatedNum;
+use super::multipack::pack_into_variables;
+use super::digest::{field_element_into_be_bits, FIELD_ELEMENT_ENCODING_SIZE};
+
+/// Represents an interpretation of 8 `Boolean` objects as an
+/// unsigned integer.
+#[derive(Clone)]
+pub struct UInt8 {
+    // Least significant bit first
+    bits: Vec<Boolean>,
+    value: Option<u8>
+}
+
+impl UInt8 {
+    /// Construct a constant `UInt8` from a `u8`
+    pub fn constant(value: u8) -> Self
+    {
+        let bits = (0..8).map(|i| Boolean::constant((value >> i) & 1 == 1)).collect();
+
+        UInt8 {
+            bits: bits,
+            value: Some(value)
+        }
+    }
+
+    pub fn constant_vec(values: &[u8]) -> Vec<Self> {
+        values.iter().map(|v| Self::constant(*v)).collect()
+    }
+
+    /// Allocate a `UInt8` in the constraint system
+    pub fn alloc<E, CS>(
+        mut cs: CS,
+        value: Option<u8>
+    ) -> Result<Self, SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>
+    {
+        let bits = (0..8).map(|i| {
+            Ok(Boolean::from(AllocatedBit::alloc(
+                cs.namespace(|| format!("allocated bit {}", i)),
+                value.map(|v| (v >> i) & 1 == 1)
+            )?))
+        }).collect::<Result<Vec<_>, SynthesisError>>()?;
+
+        Ok(UInt8 {
+            bits: bits,
+            value: value
+        })
+    }
+
+    /// Allocate the byte string, the length should be known at synthesis time
+    pub fn alloc_vec<E, CS>(
+        mut cs: CS,
+        values: &[Option<u8>]
+    ) -> Result<Vec<Self>, SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>
+    {
+        values.iter().enumerate().map(|(i, v)| {
+            Self::alloc(cs.namespace(|| format!("allocated byte {}", i)), *v)
+        }).collect()
+    }
+
+    pub fn get_value(&self) -> Option<u8> {
+        self.value
+    }
+
+    pub fn into_bits_le(&self) -> Vec<Boolean> {
+        self.bits.clone()
+    }
+
+    pub fn into_bits_be(&self) -> Vec<Boolean> {
+        self.bits.iter().rev().cloned().collect()
+    }
+
+    pub fn from_bits_le(bits: &[Boolean]) -> Self {
+        assert_eq!(bits.len(), 8);
+
+        let mut value = Some(0u8);
+        for b in bits.iter().rev() {
+            value.as_mut().map(|v| *v <<= 1);
+
+            match b.get_value() {
+                Some(true) => { value.as_mut().map(|v| *v |= 1); },
+                Some(false) => {},
+                None => { value = None }
+            }
+        }
+
+        UInt8 {
+            value: value,
+            bits: bits.to_vec()
+        }
+    }
+
+    pub fn from_bits_be(bits: &[Boolean]) -> Self {
+        assert_eq!(bits.len(), 8);
+
+        let le_bits : Vec<Boolean> = bits.iter().rev().cloned().collect();
+        Self::from_bits_le(&le_bits)
+    }
+
+    /// XOR this `UInt8` with another `UInt8`
+    pub fn xor<E, CS>(
+        &self,
+        mut cs: CS,
+        other: &Self
+    ) -> Result<Self, SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>
+    {
+        let bits = self.bits.iter()
+                            .zip(other.bits.iter())
+                            .enumerate()
+                            .map(|(i, (a, b))| {
+                                Boolean::xor(
+                                    cs.namespace(|| format!("xor of bit {}", i)),
+                                    a,
+                                    b
+                                )
+                            })
+                            .collect::<Result<_, _>>()?;
+
+        Ok(UInt8 {
+            bits: bits,
+            value: self.value.and_then(|a| other.value.map(|b| a ^ b))
+        })
+    }
+}
+
+
+pub fn bytes_values(bytes: &[UInt8]) -> Option<Vec<u8>> {
+    bytes.iter().map(|b| b.get_value()).collect()
+}
+
+// the bit order of DigestHasher: bytes go in order, the most significant bit of every byte goes first
+pub fn bytes_into_be_bits(bytes: &[UInt8]) -> Vec<Boolean> {
+    bytes.iter().flat_map(|b| b.into_bits_be()).collect()
+}
+
+pub fn be_bits_into_bytes(bits: &[Boolean]) -> Vec<UInt8> {
+    assert!(bits.len() % 8 == 0);
+    bits.chunks(8).map(|chunk| UInt8::from_bits_be(chunk)).collect()
+}
+
+
+// the integer represented by the bytes is always less than 2^{CAPACITY}, so there is no wrap around the modulus
+fn pack_le_bits<E, CS>(
+    cs: CS,
+    bits: &[Boolean]
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert!(bits.len() <= E::Fr::CAPACITY as usize);
+
+    // the empty byte string is packed into zero
+    if bits.is_empty() {
+        return AllocatedNum::alloc_const(cs, E::Fr::zero());
+    }
+
+    let mut packed = pack_into_variables(cs, bits)?;
+    assert_eq!(packed.len(), 1);
+    Ok(packed.pop().unwrap())
+}
+
+// the first byte is the most significant one, costs one constraint
+pub fn pack_bytes_be<E, CS>(
+    cs: CS,
+    bytes: &[UInt8]
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    let bits : Vec<Boolean> = bytes.iter().rev().flat_map(|b| b.into_bits_le()).collect();
+    pack_le_bits(cs, &bits)
+}
+
+// the first byte is the least significant one, costs one constraint
+pub fn pack_bytes_le<E, CS>(
+    cs: CS,
+    bytes: &[UInt8]
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    let bits : Vec<Boolean> = bytes.iter().flat_map(|b| b.into_bits_le()).collect();
+    pack_le_bits(cs, &bits)
+}
+
+
+// 32-byte big-endian encoding of the field element, the encoding is canonical (i.e. less than the modulus)
+pub fn field_element_into_bytes_be<E, CS>(
+    cs: CS,
+    elem: &AllocatedNum<E>
+) -> Result<Vec<UInt8>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    Ok(be_bits_into_bytes(&field_element_into_be_bits(cs, elem)?))
+}
+
+pub fn field_element_into_bytes_le<E, CS>(
+    cs: CS,
+    elem: &AllocatedNum<E>
+) -> Result<Vec<UInt8>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    let mut bytes = field_element_into_bytes_be(cs, elem)?;
+    bytes.reverse();
+    Ok(bytes)
+}
+
+// inverse of field_element_into_bytes_be: 32 bytes are enforced to be the canonical encoding of the result,
+// so every field element has exactly one valid preimage
+pub fn field_element_from_bytes_be<E, CS>(
+    mut cs: CS,
+    bytes: &[UInt8]
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert_eq!(bytes.len() * 8, FIELD_ELEMENT_ENCODING_SIZE);
+
+    // the value is reduced modulo p, so non-canonical encodings lead to unsatisfied constraints rather than panic
+    let value = bytes_values(bytes).map(|bytes| {
+        let base = E::Fr::from_str("256").expect("is a valid field element");
+        bytes.iter().fold(E::Fr::zero(), |mut acc, byte| {
+            acc.mul_assign(&base);
+            acc.add_assign(&E::Fr::from_str(&byte.to_string()).expect("is a valid field element"));
+            acc
+        })
+    });
+
+    let num = AllocatedNum::alloc(
+        cs.namespace(|| "field element"),
+        || value.ok_or(SynthesisError::AssignmentMissing)
+    )?;
+
+    // both decompositions are compared bit by bit: the strict one can't exceed the modulus
+    let canonical_bits = field_element_into_be_bits(cs.namespace(|| "canonical decomposition"), &num)?;
+    for (i, (a, b)) in canonical_bits.iter().zip(bytes_into_be_bits(bytes).iter()).enumerate() {
+        Boolean::enforce_equal(cs.namespace(|| format!("bit {} equality", i)), a, b)?;
+    }
+
+    Ok(num)
+}
+
+
+#[cfg(test)]
+mod test {
+    use rand::{XorShiftRng, SeedableRng, Rng};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
+    use bellman::ConstraintSystem;
+    use crate::tester::naming_oblivious_cs::NamingObliviousConstraintSystem as TestConstraintSystem;
+    use common::digest::{encode_field_element, decode_field_element};
+    use common::num::AllocatedNum;
+    use super::*;
+
+    #[test]
+    fn test_uint8_bits_and_xor() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0653]);
+
+        for _ in 0..100 {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let a: u8 = rng.gen();
+            let b: u8 = rng.gen();
+
+            let a_byte = UInt8::alloc(cs.namespace(|| "a"), Some(a)).unwrap();
+            let b_byte = UInt8::constant(b);
+            let r = a_byte.xor(cs.namespace(|| "xor"), &b_byte).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(r.get_value(), Some(a ^ b));
+            assert_eq!(UInt8::from_bits_be(&r.into_bits_be()).get_value(), Some(a ^ b));
+            assert_eq!(UInt8::from_bits_le(&r.into_bits_le()).get_value(), Some(a ^ b));
+        }
+    }
+
+    #[test]
+    fn test_bytes_packing() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0653]);
+
+        for &len in [0usize, 1, 8, 31].iter() {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let data : Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            let bytes = UInt8::alloc_vec(cs.namespace(|| "bytes"), &data.iter().map(|b| Some(*b)).collect::<Vec<_>>()).unwrap();
+
+            let be = pack_bytes_be(cs.namespace(|| "pack be"), &bytes).unwrap();
+            let le = pack_bytes_le(cs.namespace(|| "pack le"), &bytes).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(be.get_value(), Some(decode_field_element::<Fr>(&data)));
+            let reversed : Vec<u8> = data.iter().rev().cloned().collect();
+            assert_eq!(le.get_value(), Some(decode_field_element::<Fr>(&reversed)));
+        }
+    }
+
+    #[test]
+    fn test_field_element_bytes_roundtrip() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0653]);
+
+        for _ in 0..10 {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let x : Fr = rng.gen();
+
+            let num = AllocatedNum::alloc2(cs.namespace(|| "x"), Some(x)).unwrap();
+            let bytes = field_element_into_bytes_be(cs.namespace(|| "into bytes"), &num).unwrap();
+            let res = field_element_from_bytes_be(cs.namespace(|| "from bytes"), &bytes).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(bytes_values(&bytes), Some(encode_field_element(&x).to_vec()));
+            assert_eq!(res.get_value(), Some(x));
+        }
+    }
+
+    #[test]
+    fn test_field_element_from_non_canonical_bytes() {
+        // p + 1 is congruent to 1, but it is not the canonical encoding
+        let mut repr = Fr::char();
+        repr.add_nocarry(&<Fr as PrimeField>::Repr::from(1));
+        let mut encoding = vec![];
+        for limb in repr.as_ref().iter().rev() {
+            encoding.extend((0..8).rev().map(|i| (limb >> (8 * i)) as u8));
+        }
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let bytes = UInt8::alloc_vec(cs.namespace(|| "bytes"), &encoding.iter().map(|b| Some(*b)).collect::<Vec<_>>()).unwrap();
+        let res = field_element_from_bytes_be(cs.namespace(|| "from bytes"), &bytes).unwrap();
+
+        assert_eq!(res.get_value(), Some(Fr::one()));
+        assert!(!cs.is_satisfied());
+    }
+}
@@ -0,0 +1,322 @@
+use super::fri_utils::*;
+use oracles::*;
+
+use bellman::pairing::{
+    Engine,
+};
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+};
+
+use bellman::pairing::ff::Field;
+
+use common::boolean::*;
+use common::field_gadget::*;
+use common::{Labeled, log2_floor};
+
+
+use super::{UpperLayerCombiner, FriVerifierGadget, FriSingleQueryRoundData, FriFinalLayer};
+use super::fri_verifier::parse_coset_values;
+use super::diagnostics::*;
+
+
+// DEEP-FRI: before the i-th folding the verifier samples an out-of-domain point z_i
+// and the prover sends y_i = f_i(z_i). The next layer is constructed by folding of the quotient
+// q_i(X) = (f_i(X) - y_i) / (X - z_i) instead of f_i itself.
+// This is done for every folded layer, including the topmost one (produced by upper layer combiner),
+// all the layers are evaluated over the same coset coset_factor * H_i.
+// the transcript is organized as follows:
+// z_0, y_0, alpha_0, then for each intermidiate commitment: commitment, z_i, y_i, alpha_i
+
+
+// (f(x) - f(z)) / (x - z) for all x in the coset
+pub(crate) fn deep_quotient_values<E, F, CS>(
+    mut cs: CS,
+    values: &[F],
+    evaluation_points: &[F],
+    deep_point: &F,
+    deep_value: &F,
+) -> Result<Vec<F>, SynthesisError>
+where E: Engine, F: FieldGadget<E>, CS: ConstraintSystem<E>
+{
+    assert_eq!(values.len(), evaluation_points.len());
+
+    let mut minus_one = F::Field::one();
+    minus_one.negate();
+
+    values.iter().zip(evaluation_points.iter()).map(|(value, point)| {
+        F::div_lc(
+            cs.namespace(|| "DEEP quotient"), 
+            &[(F::Field::one(), value), (minus_one, deep_value)],
+            &[(F::Field::one(), point), (minus_one, deep_point)],
+        )
+    }).collect()
+}
+
+
+impl<E, I, C, B, F> FriVerifierGadget<E, I, C, B, F> 
+where E: Engine, I: OracleGadget<E>, C: UpperLayerCombiner<E, F>, B: FieldGadget<E>, F: ExtensionOf<E, B>, F::Field: TwoAdicField
+{
+    fn verify_single_deep_proof_round<CS: ConstraintSystem<E>>(
+        &self,
+        mut cs: CS,
+
+        upper_layer_queries: &[Labeled<Query<E, I>>],
+        upper_layer_commitments: &[Labeled<I::Commitment>],
+        fri_helper: &mut FriUtilsGadget<E, F>,
+
+        queries: &[Query<E, I>],
+        commitments: &[I::Commitment],
+        final_coefficients: &[F],
+
+        natural_index: Vec<Boolean>,
+        fri_challenges: &[F],
+        deep_points: &[F],
+        deep_values: &[F],
+
+        oracle_params: &I::Params,
+
+        query_round: usize,
+        diagnostics: &mut FriDiagnostics,
+
+    ) -> Result<Boolean, SynthesisError>
+    {
+        let collapsing_factor = fri_helper.get_collapsing_factor();
+        let mut coset_idx = &fri_helper.get_coset_idx_for_natural_index(natural_index)[..];
+
+        let oracle = I::new(oracle_params);
+        // intermidiate layers are over F, while the upper layer oracles are over B
+        let intermidiate_oracle = oracle.with_leaf_size((1 << collapsing_factor) * F::num_native_elems());
+
+        let (mut final_result, mut previous_layer_element) = self.verify_upper_layer(
+            cs.namespace(|| "upper layer"),
+            &oracle,
+            upper_layer_queries,
+            upper_layer_commitments,
+            fri_helper,
+            coset_idx,
+            fri_challenges,
+            Some((&deep_points[0], &deep_values[0])),
+            query_round,
+            diagnostics,
+        )?;
+
+        let iter = queries.iter()
+            .zip(commitments.iter())
+            .zip(fri_challenges.chunks(collapsing_factor).skip(1))
+            .zip(deep_points.iter().zip(deep_values.iter()).skip(1));
+
+        for (i, (((query, commitment), challenges), (deep_point, deep_value))) in iter.enumerate()
+        {
+            // adapt fri_helper for smaller domain
+            fri_helper.next_domain(cs.namespace(|| "shrink domain to next layer"));
+            let (new_coset_idx, offset) = fri_helper.get_next_layer_coset_idx_extended(coset_idx);
+            coset_idx = new_coset_idx;
+
+            // oracle proof for current layer!
+            let oracle_check = intermidiate_oracle.validate(
+                cs.namespace(|| "Oracle proof"),
+                fri_helper.get_cur_height(),
+                &query.values,
+                coset_idx,
+                commitment,
+                &query.proof,
+            )?;
+
+            diagnostics.record(INTERMIDIATE_ORACLE_LABEL, i + 1, query_round, oracle_check.get_value());
+            final_result = Boolean::and(cs.namespace(|| "and"), &final_result, &oracle_check)?;
+
+            // round consistency check is done against the committed values of the layer
+            let coset_values : Vec<F> = parse_coset_values(cs.namespace(|| "parse query"), &query.values)?;
+            let cur_layer_element = fri_helper.choose_element_in_coset(
+                cs.namespace(|| "choose element from coset by index"),
+                &coset_values[..],
+                offset.into_iter(),
+            )?;
+
+            let rcc_flag = F::equals(
+                cs.namespace(|| "FRI round consistency check"),
+                &previous_layer_element,
+                &cur_layer_element,
+            )?;
+            diagnostics.record(ROUND_CONSISTENCY_CHECK_LABEL, i + 1, query_round, rcc_flag.get_value());
+            final_result = Boolean::and(cs.namespace(|| "and"), &final_result, &rcc_flag)?;
+
+            // but the next layer is the folding of DEEP quotient
+            let evaluation_points = fri_helper.get_cur_layer_eval_points(
+                cs.namespace(|| "find layer evaluation points"),
+                coset_idx.iter(),
+            )?;
+
+            let quotient_values = deep_quotient_values(
+                cs.namespace(|| "DEEP quotient values"),
+                &coset_values[..],
+                &evaluation_points[..],
+                deep_point,
+                deep_value,
+            )?;
+
+            previous_layer_element = fri_helper.coset_interpolation_value(
+                cs.namespace(|| "coset interpolant computation"),
+                &quotient_values[..],
+                coset_idx.iter(),
+                &challenges,
+            )?;
+        }
+
+        let val = self.evaluate_final_poly(
+            cs.namespace(|| "final polynomial evaluation"),
+            fri_helper,
+            coset_idx,
+            final_coefficients,
+        )?;
+
+        let flag = F::equals(
+            cs.namespace(|| "FRI final round consistency check"),
+            &previous_layer_element,
+            &val,
+        )?;
+
+        diagnostics.record(FINAL_CONSISTENCY_CHECK_LABEL, queries.len() + 1, query_round, flag.get_value());
+        final_result = Boolean::and(cs.namespace(|| "and"), &final_result, &flag)?;
+        Ok(final_result)
+    }
+
+
+    pub fn verify_deep_proof<CS: ConstraintSystem<E>>(
+        &self,
+        cs: CS,
+        oracle_params: &I::Params,
+        // data that is shared among all Fri query rounds
+        upper_layer_commitments: &[Labeled<I::Commitment>],
+        commitments: &[I::Commitment],
+        final_coefficients: &[F],
+        fri_challenges: &[F],
+        // out-of-domain points and corresponding evaluations, one per folded layer
+        deep_points: &[F],
+        deep_values: &[F],
+        natural_first_element_indexes: Vec<Vec<Boolean>>,
+
+        query_rounds_data: &Vec<FriSingleQueryRoundData<E, I>>,
+    ) -> Result<Boolean, SynthesisError>
+    {
+        let (result, _) = self.verify_deep_proof_impl(
+            cs,
+            oracle_params,
+            upper_layer_commitments,
+            commitments,
+            final_coefficients,
+            fri_challenges,
+            deep_points,
+            deep_values,
+            natural_first_element_indexes,
+            query_rounds_data,
+            FriDiagnostics::disabled(),
+        )?;
+
+        Ok(result)
+    }
+
+
+    // the same as verify_deep_proof, but additionally returns all the intermidiate flags
+    pub fn verify_deep_proof_with_diagnostics<CS: ConstraintSystem<E>>(
+        &self,
+        cs: CS,
+        oracle_params: &I::Params,
+        upper_layer_commitments: &[Labeled<I::Commitment>],
+        commitments: &[I::Commitment],
+        final_coefficients: &[F],
+        fri_challenges: &[F],
+        deep_points: &[F],
+        deep_values: &[F],
+        natural_first_element_indexes: Vec<Vec<Boolean>>,
+
+        query_rounds_data: &Vec<FriSingleQueryRoundData<E, I>>,
+    ) -> Result<(Boolean, FriDiagnostics), SynthesisError>
+    {
+        self.verify_deep_proof_impl(
+            cs,
+            oracle_params,
+            upper_layer_commitments,
+            commitments,
+            final_coefficients,
+            fri_challenges,
+            deep_points,
+            deep_values,
+            natural_first_element_indexes,
+            query_rounds_data,
+            FriDiagnostics::new(),
+        )
+    }
+
+
+    pub(crate) fn verify_deep_proof_impl<CS: ConstraintSystem<E>>(
+        &self,
+        mut cs: CS,
+        oracle_params: &I::Params,
+        upper_layer_commitments: &[Labeled<I::Commitment>],
+        commitments: &[I::Commitment],
+        final_coefficients: &[F],
+        fri_challenges: &[F],
+        deep_points: &[F],
+        deep_values: &[F],
+        natural_first_element_indexes: Vec<Vec<Boolean>>,
+
+        query_rounds_data: &Vec<FriSingleQueryRoundData<E, I>>,
+        mut diagnostics: FriDiagnostics,
+    ) -> Result<(Boolean, FriDiagnostics), SynthesisError>
+    {
+        if deep_points.len() != commitments.len() + 1 || deep_values.len() != commitments.len() + 1 {
+            return Err(SynthesisError::Unknown);
+        }
+
+        // construct global parameters
+        let mut final_result = self.check_final_layer_degree(
+            cs.namespace(|| "final layer degree check"), 
+            final_coefficients,
+        )?;
+        let unpacked_fri_challenges = self.unpack_fri_challenges(cs.namespace(|| "unpack challenges"), fri_challenges)?;
+
+        let num_iters = log2_floor(self.initial_degree_plus_one / self.final_degree_plus_one) / self.collapsing_factor;
+        if self.config.final_layer == FriFinalLayer::Evaluations {
+            diagnostics.record(FINAL_LAYER_DEGREE_CHECK_LABEL, num_iters, 0, final_result.get_value());
+        }
+
+        let mut fri_helper = FriUtilsGadget::new(
+            cs.namespace(|| "Fri Utils constructor"),
+            self.initial_degree_plus_one * self.lde_factor,
+            self.collapsing_factor,
+            num_iters,
+            self.config.coset_factor.clone(),
+        );
+
+        for (query_round, (single_round_data, natural_first_element_index)) in
+            query_rounds_data.iter().zip(natural_first_element_indexes).enumerate() {
+
+            let flag = self.verify_single_deep_proof_round(
+                cs.namespace(|| "DEEP-FRI single round verifier"),
+                &single_round_data.upper_layer_queries[..],
+                upper_layer_commitments,
+                &mut fri_helper,
+
+                &single_round_data.queries[..],
+                commitments,
+                final_coefficients,
+
+                natural_first_element_index,
+                &unpacked_fri_challenges[..],
+                deep_points,
+                deep_values,
+                oracle_params,
+                query_round,
+                &mut diagnostics,
+            )?;
+
+            final_result = Boolean::and(cs.namespace(|| "and"), &final_result, &flag)?;
+            fri_helper.to_initial_domain();
+        }
+
+        Ok((final_result, diagnostics))
+    }
+}
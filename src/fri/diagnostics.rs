@@ -0,0 +1,81 @@
+use common::Label;
+
+use std::fmt;
+use std::rc::Rc;
+use std::cell::RefCell;
+
+
+pub const ROUND_CONSISTENCY_CHECK_LABEL: Label = "round consistency check";
+pub const FINAL_CONSISTENCY_CHECK_LABEL: Label = "final round consistency check";
+pub const INTERMIDIATE_ORACLE_LABEL: Label = "intermidiate oracle";
+// is done once for all query rounds and hence is always recorded with query round 0
+pub const FINAL_LAYER_DEGREE_CHECK_LABEL: Label = "final layer degree check";
+
+
+// single flag produced by FRI verifier
+// oracle checks of the upper layer are labeled by the oracle label itself
+#[derive(Debug, Clone)]
+pub struct FriCheckRecord {
+    pub label: Label,
+    // 0 corresponds to the upper layer, the final polynomial is on the layer num_iters
+    pub layer: usize,
+    pub query_round: usize,
+    // None if synthesis is done without witness
+    pub value: Option<bool>,
+}
+
+impl fmt::Display for FriCheckRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = match self.value {
+            Some(true) => "passed",
+            Some(false) => "FAILED",
+            None => "unknown",
+        };
+        write!(f, "query round {}, layer {}, {}: {}", self.query_round, self.layer, self.label, value)
+    }
+}
+
+
+// records all the intermidiate flags of FRI verifier:
+// is built only from witness values and so doesn't add any constraints.
+// Diagnostics are opt-in: the disabled instance (used by the plain verification) records nothing
+#[derive(Debug, Clone, Default)]
+pub struct FriDiagnostics {
+    pub records: Vec<FriCheckRecord>,
+    enabled: bool,
+}
+
+// handle through which the circuits expose diagnostics after synthesis (Circuit::synthesize consumes the circuit)
+pub type SharedFriDiagnostics = Rc<RefCell<FriDiagnostics>>;
+
+impl FriDiagnostics {
+    pub fn new() -> Self {
+        FriDiagnostics { records: vec![], enabled: true }
+    }
+
+    pub fn disabled() -> Self {
+        FriDiagnostics { records: vec![], enabled: false }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn record(&mut self, label: Label, layer: usize, query_round: usize, value: Option<bool>) {
+        if self.enabled {
+            self.records.push(FriCheckRecord { label, layer, query_round, value });
+        }
+    }
+
+    pub fn failed_checks(&self) -> impl Iterator<Item = &FriCheckRecord> {
+        self.records.iter().filter(|x| x.value == Some(false))
+    }
+
+    pub fn first_failure(&self) -> Option<&FriCheckRecord> {
+        self.failed_checks().next()
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.first_failure().is_none()
+    }
+}
@@ -0,0 +1,257 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+};
+
+use bellman::{
+    Circuit,
+    SynthesisError,
+    ConstraintSystem,
+};
+
+use bellman::redshift::IOP::FRI::coset_combining_fri::FriParams;
+
+use common::num::*;
+use common::boolean::*;
+use common::field_gadget::FieldGadget;
+use common::*;
+
+use oracles::*;
+use channel::*;
+
+use super::{UpperLayerCombiner, FriVerifierGadget, FriSingleQueryRoundData, FriGadgetConfig, BatchedFriProof};
+use super::diagnostics::{FriDiagnostics, SharedFriDiagnostics};
+
+
+// the label under which the single committed oracle is queried on the upper layer
+pub const FRI_PROXIMITY_ORACLE_LABEL: Label = "starting oracle";
+
+
+// there is only one oracle on the upper layer, hence there is nothing to combine:
+// the queried values are passed to the folding procedure as they are
+pub struct SingleOracleCombiner {
+    pub label: Label,
+}
+
+impl<E: Engine, F: FieldGadget<E>> UpperLayerCombiner<E, F> for SingleOracleCombiner {
+    fn combine<CS: ConstraintSystem<E>>(
+        &self,
+        _cs: CS,
+        domain_values: Vec<Labeled<&F>>,
+        _evaluation_point : &F
+    ) -> Result<F, SynthesisError>
+    {
+        domain_values.into_iter().find(|elem| elem.label == self.label).map(|elem| elem.data.clone()).ok_or(SynthesisError::Unknown)
+    }
+}
+
+
+// everything the prover sends in a standalone FRI low-degree test
+// NB: the commitment to the tested oracle itself is not a part of the proof - it is a public input of the circuit
+pub struct FriProximityProof<E: Engine, O: OracleGadget<E>> {
+    // commitments to all intermidiate oracles
+    pub commitments: Vec<O::Commitment>,
+    // either coefficients or evaluations of the final layer (depending on FriGadgetConfig)
+    pub final_coefficients: Vec<AllocatedNum<E>>,
+    pub fri_round_queries : Vec<FriSingleQueryRoundData<E, O>>,
+}
+
+
+impl<E: Engine, O: OracleGadget<E>> FromStream<E, (FriParams, FriGadgetConfig<E::Fr>)> for FriProximityProof<E, O> {
+
+    fn from_stream<CS: ConstraintSystem<E>, I: Iterator<Item = Option<E::Fr>>>(
+        cs: CS,
+        iter: &mut I,
+        params: (FriParams, FriGadgetConfig<E::Fr>),
+    ) -> Result<Self, SynthesisError>
+    {
+        let labels = [FRI_PROXIMITY_ORACLE_LABEL];
+        let (fri_params, fri_config) = params;
+        let proof = BatchedFriProof::<E, O>::from_stream(cs, iter, (fri_params, fri_config, &labels[..]))?;
+
+        let BatchedFriProof { commitments, final_coefficients, fri_round_queries, .. } = proof;
+        Ok(FriProximityProof { commitments, final_coefficients, fri_round_queries })
+    }
+}
+
+
+// the transcript is organized as follows:
+// the commitment to the tested oracle is consumed first and the first folding challenge is produced,
+// then every intermidiate commitment is consumed and followed by the next folding challenge
+pub fn get_fri_proximity_challenges<E, O, T, CS>(
+    mut cs: CS,
+    upper_layer_commitment: &AllocatedNum<E>,
+    proof: &FriProximityProof<E, O>,
+    channel: &mut T,
+) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
+where E: Engine, O: OracleGadget<E, Commitment = AllocatedNum<E>>, T: ChannelGadget<E>, CS: ConstraintSystem<E>
+{
+    channel.consume(upper_layer_commitment.clone(), cs.namespace(|| "consume upper layer commitment"))?;
+
+    let mut fri_challenges = vec![];
+    fri_challenges.push(channel.produce_challenge(cs.namespace(|| "produce fri challenge"))?);
+
+    for commitment in proof.commitments.iter().cloned() {
+        let iop_challenge = {
+            channel.consume(commitment, cs.namespace(|| "consume intermidiate commitment"))?;
+            channel.produce_challenge(cs.namespace(|| "produce fri challenge"))?
+        };
+
+        fri_challenges.push(iop_challenge);
+    }
+    Ok(fri_challenges)
+}
+
+
+// proves that the oracle committed to by the public commitment is close to a polynomial of degree < initial_degree_plus_one,
+// FRI parameters define the rate (lde_factor), the folding factor and the number of queries
+// (only plain FRI is supported: synthesis fails if either DEEP or degree correction is requested)
+pub struct FriProximityCircuit<E, O, T, I>
+where E: Engine, O: OracleGadget<E, Commitment = AllocatedNum<E>>, T: ChannelGadget<E>, I: Iterator<Item = Option<E::Fr>>
+{
+    _engine_marker : std::marker::PhantomData<E>,
+    _oracle_marker : std::marker::PhantomData<O>,
+    _channel_marker : std::marker::PhantomData<T>,
+
+    channel_params: T::Params,
+    oracle_params: O::Params,
+    fri_params: FriParams,
+    fri_config: FriGadgetConfig<E::Fr>,
+    input_stream: I,
+    commitment: E::Fr,
+    // is filled during synthesis if set
+    diagnostics: Option<SharedFriDiagnostics>,
+}
+
+
+impl<E, O, T, I> FriProximityCircuit<E, O, T, I>
+where E: Engine, O: OracleGadget<E, Commitment = AllocatedNum<E>>, T: ChannelGadget<E>, I: Iterator<Item = Option<E::Fr>>,
+{
+    pub fn new(
+        channel_params: T::Params, 
+        oracle_params: O::Params, 
+        fri_params: FriParams, 
+        fri_config: FriGadgetConfig<E::Fr>, 
+        stream : I, 
+        commitment: E::Fr,
+    ) -> Self {
+
+        FriProximityCircuit {
+
+            _engine_marker : std::marker::PhantomData::<E>,
+            _oracle_marker : std::marker::PhantomData::<O>,
+            _channel_marker : std::marker::PhantomData::<T>,
+
+            channel_params,
+            oracle_params,
+            fri_params,
+            fri_config,
+            input_stream: stream,
+            commitment,
+            diagnostics: None,
+        }
+    }
+
+    // opt-in: all the intermidiate flags of FRI verifier are recorded into the given container during synthesis
+    pub fn with_diagnostics(mut self, diagnostics: SharedFriDiagnostics) -> Self {
+        self.diagnostics = Some(diagnostics);
+        self
+    }
+}
+
+
+impl<E, O, T, I> Circuit<E> for FriProximityCircuit<E, O, T, I>
+where
+    E: Engine, O: OracleGadget<E, Commitment = AllocatedNum<E>>, T: ChannelGadget<E>, I: Iterator<Item = Option<E::Fr>>,
+{
+    fn synthesize<CS: ConstraintSystem<E>>(
+        mut self,
+        cs: &mut CS,
+    ) -> Result<(), SynthesisError> {
+
+        // there is a single oracle and nothing to combine: DEEP quotients and degree correction are never applied here
+        if self.fri_config.deep || self.fri_config.degree_correction {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let commitment = self.commitment;
+        let upper_layer_commitment = AllocatedNum::alloc_input(
+            cs.namespace(|| "upper layer commitment"),
+            || Ok(commitment),
+        )?;
+
+        let proof = FriProximityProof::<E, O>::from_stream(
+            cs.namespace(|| "initialize proof"),
+            &mut self.input_stream,
+            (self.fri_params.clone(), self.fri_config.clone()),
+        )?;
+
+        let mut channel = T::new(self.channel_params);
+        let fri_challenges = get_fri_proximity_challenges::<E, O, T, _>(
+            cs.namespace(|| "fri challenges"),
+            &upper_layer_commitment,
+            &proof,
+            &mut channel,
+        )?;
+
+        // query positions should depend on the last layer as well
+        for coef in proof.final_coefficients.iter().cloned() {
+            channel.consume(coef, cs.namespace(|| "consume final coefficient"))?;
+        }
+
+        let natural_first_element_indexes = (0..self.fri_params.R).map(|_| {
+            let packed = channel.produce_challenge(cs.namespace(|| "produce query index"))?;
+            let mut bits = packed.into_bits_le(cs.namespace(|| "decompose query index"))?;
+            bits.truncate(64);
+
+            Ok(bits)
+        }).collect::<Result<_, SynthesisError>>()?;
+
+        let upper_layer_commitments = vec![Labeled::new(FRI_PROXIMITY_ORACLE_LABEL, upper_layer_commitment)];
+
+        let fri_verifier_gadget = FriVerifierGadget::<E, O, _> {
+            collapsing_factor : self.fri_params.collapsing_factor as usize,
+            //number of iterations done during FRI query phase
+            num_query_rounds : self.fri_params.R,
+            initial_degree_plus_one : self.fri_params.initial_degree_plus_one.get(),
+            lde_factor: self.fri_params.lde_factor,
+            //the degree of the resulting polynomial at the bottom level of FRI
+            final_degree_plus_one : self.fri_params.final_degree_plus_one.get(),
+            upper_layer_combiner: SingleOracleCombiner { label: FRI_PROXIMITY_ORACLE_LABEL },
+            config: self.fri_config,
+
+            _engine_marker : std::marker::PhantomData::<E>,
+            _oracle_marker : std::marker::PhantomData::<O>,
+            _field_marker : std::marker::PhantomData::<(AllocatedNum<E>, AllocatedNum<E>)>,
+        };
+
+        let diagnostics = match self.diagnostics {
+            Some(_) => FriDiagnostics::new(),
+            None => FriDiagnostics::disabled(),
+        };
+
+        let (is_fri_valid, diagnostics) = fri_verifier_gadget.verify_proof_impl(
+            cs.namespace(|| "FRI verification"),
+            &self.oracle_params,
+            &upper_layer_commitments,
+            &proof.commitments,
+            &proof.final_coefficients,
+            &fri_challenges,
+            natural_first_element_indexes,
+            &proof.fri_round_queries,
+            diagnostics,
+        )?;
+
+        if let Some(container) = self.diagnostics {
+            *container.borrow_mut() = diagnostics;
+        }
+
+        Boolean::enforce_equal(cs.namespace(|| "check output bit"), &is_fri_valid, &Boolean::constant(true))?;
+
+        Ok(())
+    }
+}
@@ -8,23 +8,22 @@ use bellman::{
 
 use bellman::pairing::ff::{
     Field,
-    PrimeField,
 };
-use bellman::redshift::domains::*;
 
 use common::num::*;
 use common::boolean::*;
+use common::field_gadget::*;
 use common::log2_floor;
 
-use std::iter;
 
-
-pub struct FriUtilsGadget<E: Engine> {
+// all the arithmetic is done via FieldGadget, so the same code serves FRI over the native field 
+// as well as over emulated fields and their extensions
+pub struct FriUtilsGadget<E: Engine, F: FieldGadget<E> = AllocatedNum<E>> {
     // these parameters are changed when passing to next domain
     domain_size: usize,
     log_domain_size: usize,
-    omega: E::Fr,
-    omega_inv: E::Fr,
+    omega: F::Field,
+    omega_inv: F::Field,
     layer: usize,
     first_pass: bool,
 
@@ -33,28 +32,30 @@ pub struct FriUtilsGadget<E: Engine> {
     wrapping_factor: usize,
     initial_domain_size: usize,
     initial_log_domain_size: usize,
-    initial_omega: E::Fr,
-    initial_omega_inv: E::Fr,
-    coset_factor: E::Fr,
+    initial_omega: F::Field,
+    initial_omega_inv: F::Field,
+    coset_factor: F::Field,
     num_iters: usize,
-    two: E::Fr,
-    two_inv: E::Fr,
+    two_inv: F::Field,
     // remaining data is filled in on the first passing through all layers
     // it is reused on next acess to the same arrays
 
     // may be it is a dirty Hack(
     // contains inversed generators of the layers
     // simplu to reuse them on each iteration of FRI queries
-    constrainted_omega_inv_arr: Vec<AllocatedNum<E>>,
-    constrainted_top_level_omega: Option<AllocatedNum<E>>,
-    constrainted_bottom_level_omega: Option<AllocatedNum<E>>,
-    constrainted_coset_factor: Option<AllocatedNum<E>>,
+    constrainted_omega_inv_arr: Vec<F>,
+    constrainted_top_level_omega: Option<F>,
+    constrainted_bottom_level_omega: Option<F>,
+    // generators of the layers, are constrainted only on demand
+    constrainted_omega_arr: Vec<Option<F>>,
 
     _marker: std::marker::PhantomData<E>,
 }
 
 
-impl<E: Engine> FriUtilsGadget<E> {
+impl<E: Engine, F: FieldGadget<E>> FriUtilsGadget<E, F> 
+where F::Field: TwoAdicField
+{
 
     pub fn get_domain_size(&self) -> usize {
         self.domain_size
@@ -72,20 +73,7 @@ impl<E: Engine> FriUtilsGadget<E> {
         self.log_domain_size - self.collapsing_factor
     }
 
-    pub fn get_coset_factor<CS>(&mut self, mut cs: CS) -> Result<&AllocatedNum<E>, SynthesisError> 
-    where CS: ConstraintSystem<E> 
-    {
-        let coset_factor = self.coset_factor.clone();
-        let res = self.constrainted_coset_factor.get_or_insert_with(|| {
-            AllocatedNum::alloc_const(
-                cs.namespace(|| "constrainted coset factor"), 
-                coset_factor,
-            ).expect("should create")
-        });
-        Ok(res)
-    }
-
-    pub fn get_topmost_layer_omega<CS>(&mut self, mut cs: CS) -> Result<&AllocatedNum<E>, SynthesisError>
+    pub fn get_topmost_layer_omega<CS>(&mut self, mut cs: CS) -> Result<&F, SynthesisError>
     where CS: ConstraintSystem<E> 
     {
         if self.layer != 0 {
@@ -94,7 +82,7 @@ impl<E: Engine> FriUtilsGadget<E> {
 
         let omega = self.omega.clone();
         let res = self.constrainted_top_level_omega.get_or_insert_with(|| {
-            AllocatedNum::alloc_const(
+            F::constant(
                 cs.namespace(|| "constrainted top-level omega"), 
                 omega,
             ).expect("should create")
@@ -103,7 +91,7 @@ impl<E: Engine> FriUtilsGadget<E> {
         Ok(res)   
     }
 
-    pub fn get_bottom_layer_omega<CS>(&mut self, mut cs: CS) -> Result<&AllocatedNum<E>, SynthesisError>
+    pub fn get_bottom_layer_omega<CS>(&mut self, mut cs: CS) -> Result<&F, SynthesisError>
     where CS: ConstraintSystem<E> 
     {
         // TODO: also investigate here
@@ -113,7 +101,7 @@ impl<E: Engine> FriUtilsGadget<E> {
 
         let omega = self.omega.clone();
         let res = self.constrainted_bottom_level_omega.get_or_insert_with(|| {
-            AllocatedNum::alloc_const(
+            F::constant(
                 cs.namespace(|| "constrainted top-level omega"), 
                 omega,
             ).expect("should create")
@@ -122,33 +110,64 @@ impl<E: Engine> FriUtilsGadget<E> {
         Ok(res)  
     }
 
-    pub fn get_cur_layer_omega_inv(&self) -> &AllocatedNum<E> {
+    pub fn get_cur_layer_omega_inv(&self) -> &F {
         &self.constrainted_omega_inv_arr[self.layer]
     }
 
+    pub fn get_cur_layer_omega<CS>(&mut self, mut cs: CS) -> Result<&F, SynthesisError>
+    where CS: ConstraintSystem<E> 
+    {
+        if self.constrainted_omega_arr.len() <= self.layer {
+            self.constrainted_omega_arr.resize(self.layer + 1, None);
+        }
+
+        let omega = self.omega.clone();
+        let res = self.constrainted_omega_arr[self.layer].get_or_insert_with(|| {
+            F::constant(
+                cs.namespace(|| "constrainted layer omega"), 
+                omega,
+            ).expect("should create")
+        });
+
+        Ok(res)
+    }
+
+    // folding doesn't take the coset factor into account, 
+    // so the domain of every layer is shifted by the same coset_factor
+    pub fn get_coset_factor(&self) -> F::Field {
+        self.coset_factor.clone()
+    }
+
+    pub fn is_coset_factor_trivial(&self) -> bool {
+        self.coset_factor == F::Field::one()
+    }
+
     //wrapping factor here is size of coset: 1 << collapsing_factor
-    pub fn new<CS: ConstraintSystem<E>>(mut cs: CS, domain_size: usize, collapsing_factor: usize, num_iters: usize) -> Self {
+    pub fn new<CS: ConstraintSystem<E>>(
+        mut cs: CS, 
+        domain_size: usize, 
+        collapsing_factor: usize, 
+        num_iters: usize, 
+        coset_factor: F::Field,
+    ) -> Self {
         
         assert!(domain_size.is_power_of_two());
         let log_domain_size = log2_floor(domain_size);
 
-        let domain = Domain::<E::Fr>::new_for_size(domain_size as u64).expect("should construct");
-        let omega = domain.generator;
+        let omega = F::Field::domain_generator(domain_size);
         let omega_inv = omega.inverse().expect("should exist");
 
-        let mut two = E::Fr::one();
+        let mut two = F::Field::one();
         two.double();
         let two_inv = two.inverse().expect("should exist");
 
         let mut constrainted_omega_inv_arr = Vec::with_capacity(num_iters);
-        let constrainted_omega_inv = AllocatedNum::alloc_const(
+        let constrainted_omega_inv = F::constant(
             cs.namespace(|| "generator (inv) of domain constrainted"), 
             omega_inv.clone(),
         ).expect("should create");
         constrainted_omega_inv_arr.push(constrainted_omega_inv);
 
-        let coset_factor = E::Fr::multiplicative_generator();
-
         FriUtilsGadget {
             
             first_pass: true,
@@ -167,13 +186,12 @@ impl<E: Engine> FriUtilsGadget<E> {
             coset_factor,
 
             num_iters,
-            two,
             two_inv,
 
             constrainted_omega_inv_arr,
             constrainted_top_level_omega: None,
             constrainted_bottom_level_omega: None,
-            constrainted_coset_factor: None,
+            constrainted_omega_arr: vec![],
 
             _marker: std::marker::PhantomData::<E>,
         }
@@ -195,7 +213,7 @@ impl<E: Engine> FriUtilsGadget<E> {
 
         if self.first_pass {
             let omega_inv = self.omega_inv.clone();
-            let res = AllocatedNum::alloc_const(
+            let res = F::constant(
                 cs.namespace(|| "constrainteddomain omega"), 
                 omega_inv,
             ).expect("should create");
@@ -213,7 +231,7 @@ impl<E: Engine> FriUtilsGadget<E> {
         self.first_pass = false;
     }
 
-    fn bitreverse(n: usize, l: usize) -> usize {
+    pub(crate) fn bitreverse(n: usize, l: usize) -> usize {
         let mut r = n.reverse_bits();
         // now we need to only use the bits that originally were "last" l, so shift
         r >>= (std::mem::size_of::<usize>() * 8) - l;
@@ -266,18 +284,27 @@ impl<E: Engine> FriUtilsGadget<E> {
     // this method solver the following task: 
     // we are given elements of coset: (a_0, a_1, ..., a_n) and coset_index i \in [0, n]
     // we want to take particular element according to the index
-    pub fn choose_element_in_coset<'a, CS, I>(&self, mut cs: CS, coset: &[AllocatedNum<E>], index: I) -> Result<AllocatedNum<E>, SynthesisError>
+    pub fn choose_element_in_coset<'a, CS, I>(&self, cs: CS, coset: &[F], index: I) -> Result<F, SynthesisError>
     where CS: ConstraintSystem<E>, I : Iterator<Item = &'a Boolean>,
     {
         assert_eq!(coset.len(), self.wrapping_factor);
+        Self::choose_element(cs, coset, index)
+    }
+
+    // the same as above but for array of arbitrary (power of two) length
+    // index is given in little-endian form
+    pub fn choose_element<'a, CS, I>(mut cs: CS, elems: &[F], index: I) -> Result<F, SynthesisError>
+    where CS: ConstraintSystem<E>, I : Iterator<Item = &'a Boolean>,
+    {
+        assert!(elems.len().is_power_of_two());
 
-        let mut array : Vec<AllocatedNum<E>> = Vec::with_capacity(coset.len()/2);
-        let mut input = &coset[..];
+        let mut array : Vec<F> = Vec::with_capacity(elems.len()/2);
+        let mut input = &elems[..];
 
-        for (i, bit) in index.enumerate() {
+        for bit in index {
 
             array = input.chunks(2).map(|chunk| {
-                AllocatedNum::conditionally_select(
+                F::conditionally_select(
                     cs.namespace(|| "chooser"), 
                     &chunk[1], 
                     &chunk[0],
@@ -295,17 +322,17 @@ impl<E: Engine> FriUtilsGadget<E> {
     pub fn coset_interpolation_value<'a, CS: ConstraintSystem<E>, I: DoubleEndedIterator<Item = &'a Boolean>>(
         &self,
         mut cs: CS,
-        coset_values: &[AllocatedNum<E>],
+        coset_values: &[F],
         coset_tree_idx: I,
         // contains alpha, alpha^2, alpha^4, ...
-        challenges: &[AllocatedNum<E>],
-    ) -> Result<AllocatedNum<E>, SynthesisError> {
+        challenges: &[F],
+    ) -> Result<F, SynthesisError> {
 
         let coset_size = self.wrapping_factor;
-        let mut this_level_values : Vec<AllocatedNum<E>> = vec![];
-        let mut next_level_values : Vec<AllocatedNum<E>>;
+        let mut this_level_values : Vec<F> = vec![];
+        let mut next_level_values : Vec<F>;
 
-        let mut coset_omega_inv = AllocatedNum::pow(
+        let mut coset_omega_inv = F::pow(
             cs.namespace(|| "get coset specific omega"),
             self.get_cur_layer_omega_inv(),
             coset_tree_idx.rev(),
@@ -315,7 +342,7 @@ impl<E: Engine> FriUtilsGadget<E> {
         let g = self.omega_inv.pow([1 << shift as u64]);
 
         let mut num_bits_to_bitreverse = self.collapsing_factor;
-        let mut interpolant : Option<AllocatedNum<E>> = None;
+        let mut interpolant : Option<F> = None;
         
         for wrapping_step in 0..self.collapsing_factor {
 
@@ -336,62 +363,38 @@ impl<E: Engine> FriUtilsGadget<E> {
                 // v_even = f0 + f1;
                 // v_odd = (f0 - f1) * pair_omega;
                 // res = (v_odd * challenge + v_even) * two_inv;
-                // the last equation is equal to: 
-                // v_odd * challenge = 2 * res - v_even
+                // the division by two is moved into the coefficients of linear combinations, 
+                // so for the native field each pair costs two constraints:
+                // v_odd * two_inv = (f0 - f1) * two_inv * coef * coset_omega_inv
+                // res = (v_odd * two_inv) * challenge + (f0 + f1) * two_inv
 
-                let one = E::Fr::one();
-                let mut minus_one = one.clone();
-                minus_one.negate();
+                let f0 = &pair[0];
+                let f1 = &pair[1];
 
-                let f0 = pair[0].clone();
-                let f1 = pair[1].clone();
-
-                let coef = match pair_idx {
-                    0 => one.clone(),
+                let mut coef = match pair_idx {
+                    0 => F::Field::one(),
                     _ => {
                         let idx = Self::bitreverse(2 * pair_idx, num_bits_to_bitreverse);
                         g.pow([idx as u64])
                     },
                 };
-           
-                let mut v_even : Num<E> = f0.clone().into();
-                v_even.mut_add_number_with_coeff(&f1, one);
+                coef.mul_assign(&self.two_inv);
+                let mut minus_coef = coef.clone();
+                minus_coef.negate();
 
-                let mut v_odd : Num<E> = f0.clone().into();
-                v_odd.mut_add_number_with_coeff(&f1, minus_one);
-                v_odd = Num::mul_by_var_with_coeff(
+                let half_v_odd = F::mul_add(
                     cs.namespace(|| "scale by coset omega"),
-                    &v_odd, 
-                    &coset_omega_inv, 
-                    coef
-                )?.into();
-
-                // res = (v_odd * challenge + v_even) * two_inv;
-                // enforce: v_odd * challenge = 2 * res - v_even
-                let res = AllocatedNum::alloc(
-                    cs.namespace(|| "FRI round consistency check: allocate next layer"), 
-                    || { 
-                        match (v_odd.get_value(), v_even.get_value(), challenges[wrapping_step].get_value()) {
-                            (Some(mut v_odd), Some(v_even), Some(challenge)) => {
-                                v_odd.mul_assign(&challenge);
-                                v_odd.add_assign(&v_even);
-                                v_odd.mul_assign(&self.two_inv);
-                                Ok(v_odd)
-                            },
-                            (_, _, _) => Err(SynthesisError::AssignmentMissing),
-                        }
-                    })?;
-
-                let mut res_num : Num<E> = res.clone().into();
-                res_num.scale(self.two.clone());
-                res_num.sub_assign(&v_even);
-
-                Num::enforce(
-                    cs.namespace(|| "FRI round consistency check constraint"),
-                    &v_odd,
-                    &challenges[wrapping_step].clone().into(),
-                    &res_num,
-                );
+                    &[(coef, f0), (minus_coef, f1)],
+                    &coset_omega_inv,
+                    &[],
+                )?;
+
+                let res = F::mul_add(
+                    cs.namespace(|| "FRI round consistency check: allocate next layer"),
+                    &[(F::Field::one(), &half_v_odd)],
+                    &challenges[wrapping_step],
+                    &[(self.two_inv.clone(), f0), (self.two_inv.clone(), f1)],
+                )?;
 
                 next_level_values.push(res);
             }
@@ -411,7 +414,7 @@ impl<E: Engine> FriUtilsGadget<E> {
         interpolant.ok_or(SynthesisError::Unknown)   
     }
 
-    pub fn get_combiner_eval_points<'a, CS, I>(&mut self, mut cs: CS, coset_tree_idx : I) -> Result<Vec<Num<E>>, SynthesisError>
+    pub fn get_combiner_eval_points<'a, CS, I>(&mut self, mut cs: CS, coset_tree_idx : I) -> Result<Vec<F>, SynthesisError>
     where CS: ConstraintSystem<E>, I: DoubleEndedIterator<Item = &'a Boolean> {
 
         // let w - generator of current domain
@@ -421,34 +424,44 @@ impl<E: Engine> FriUtilsGadget<E> {
         // this method returns array [coset_omega * g^bitreverse(i) * coset_factor]
         // Note: we don't constraint omegas by default (only omegas inverse)
 
-        let constrainted_omega = self.get_topmost_layer_omega(cs.namespace(|| "top layer omega"))?;
+        let coset_factor = self.coset_factor.clone();
+        let constrainted_omega = self.get_topmost_layer_omega(cs.namespace(|| "top layer omega"))?.clone();
+        self.get_eval_points(cs, &constrainted_omega, coset_tree_idx, coset_factor)
+    }
 
-        let coset_omega = AllocatedNum::pow(
+    // the same as get_combiner_eval_points, but works for any layer (not only for the topmost one):
+    // returns actual points of the (shifted) domain of the current layer, which correspond to the coset
+    pub fn get_cur_layer_eval_points<'a, CS, I>(&mut self, mut cs: CS, coset_tree_idx : I) -> Result<Vec<F>, SynthesisError>
+    where CS: ConstraintSystem<E>, I: DoubleEndedIterator<Item = &'a Boolean> {
+
+        let coset_factor = self.coset_factor.clone();
+        let constrainted_omega = self.get_cur_layer_omega(cs.namespace(|| "layer omega"))?.clone();
+        self.get_eval_points(cs, &constrainted_omega, coset_tree_idx, coset_factor)
+    }
+
+    fn get_eval_points<'a, CS, I>(&self, mut cs: CS, omega: &F, coset_tree_idx : I, coset_factor: F::Field) -> Result<Vec<F>, SynthesisError>
+    where CS: ConstraintSystem<E>, I: DoubleEndedIterator<Item = &'a Boolean> {
+
+        let coset_omega = F::pow(
             cs.namespace(|| "get coset specific omega"), 
-            &constrainted_omega, 
+            omega, 
             coset_tree_idx.rev(),
         )?;
         
         let shift = self.log_domain_size - self.collapsing_factor;
         let g = self.omega.pow([1 << shift as u64]);
 
-        let mut res : Vec<Num<E>> = Vec::with_capacity(self.wrapping_factor);
+        let mut res : Vec<F> = Vec::with_capacity(self.wrapping_factor);
 
         for i in 0..self.wrapping_factor {
 
             let mut coef = g.pow([Self::bitreverse(i, self.collapsing_factor) as u64]);
-            coef.mul_assign(&self.coset_factor);
+            coef.mul_assign(&coset_factor);
 
-            let mut num : Num<E> = coset_omega.clone().into();
-            num.scale(coef); 
-            res.push(num);
+            let point = F::linear_combination(cs.namespace(|| "evaluation point"), &[(coef, &coset_omega)])?;
+            res.push(point);
         }
 
         Ok(res)       
     }
 }
-
-
-
-
-
@@ -11,47 +11,60 @@ use bellman::{
 
 use bellman::pairing::ff::{
     Field,
-    PrimeField,
 };
 
 use common::num::*;
 use common::boolean::*;
-use common::{Labeled, LabeledVec, log2_floor};
+use common::field_gadget::*;
+use common::{Labeled, log2_floor};
 
+use super::{UpperLayerCombiner, FriVerifierGadget, FriSingleQueryRoundData, FriFinalLayer};
+use super::diagnostics::*;
+use super::deep_fri_verifier::deep_quotient_values;
 
-use super::{UpperLayerCombiner, FriVerifierGadget, FriSingleQueryRoundData};
+
+// queried values are native field elements, each element of G occupies G::num_native_elems() consecutive ones
+pub(crate) fn parse_coset_values<E, G, CS>(mut cs: CS, values: &[AllocatedNum<E>]) -> Result<Vec<G>, SynthesisError>
+where E: Engine, G: FieldGadget<E>, CS: ConstraintSystem<E>
+{
+    if values.len() % G::num_native_elems() != 0 {
+        return Err(SynthesisError::Unknown);
+    }
+
+    values.chunks(G::num_native_elems()).map(|chunk| {
+        G::from_native_elems(cs.namespace(|| "parse coset element"), chunk)
+    }).collect()
+}
 
 
-impl<E: Engine, I: OracleGadget<E>, C: UpperLayerCombiner<E>> FriVerifierGadget<E, I, C> 
+impl<E, I, C, B, F> FriVerifierGadget<E, I, C, B, F>
+where E: Engine, I: OracleGadget<E>, C: UpperLayerCombiner<E, F>, B: FieldGadget<E>, F: ExtensionOf<E, B>, F::Field: TwoAdicField
 {
 
-    fn verify_single_proof_round<CS: ConstraintSystem<E>>(
+    // checks oracle proofs for each element in the upper layer, 
+    // combines upper layer queries and folds the result with the first challenge
+    // (DEEP-FRI: the DEEP quotient of the combined layer for the given out-of-domain point and value is folded instead)
+    // returns the result of oracle checks and the element which is expected on the next layer
+    pub(crate) fn verify_upper_layer<CS: ConstraintSystem<E>>(
         &self,
         mut cs: CS,
+        oracle: &I,
 
         upper_layer_queries: &[Labeled<Query<E, I>>],
         upper_layer_commitments: &[Labeled<I::Commitment>], 
-        fri_helper: &mut FriUtilsGadget<E>,
+        fri_helper: &mut FriUtilsGadget<E, F>,
 
-        queries: &[Query<E, I>],
-        commitments: &[I::Commitment],
-        final_coefficients: &[AllocatedNum<E>],
+        coset_idx: &[Boolean],
+        fri_challenges: &[F],
+        deep_opening: Option<(&F, &F)>,
 
-        natural_index: Vec<Boolean>,
-        fri_challenges: &[AllocatedNum<E>],
-
-        oracle_params: &I::Params,
-   
-    ) -> Result<Boolean, SynthesisError>
+        query_round: usize,
+        diagnostics: &mut FriDiagnostics,
+    ) -> Result<(Boolean, F), SynthesisError>
     {
-        let collapsing_factor = fri_helper.get_collapsing_factor();
-        let mut coset_idx = &fri_helper.get_coset_idx_for_natural_index(natural_index)[..];
-
-        let coset_size = 1 << collapsing_factor;
-
-        // check oracle proof for each element in the upper layer!
-        let oracle = I::new(oracle_params);
+        let coset_size = 1 << fri_helper.get_collapsing_factor();
         let mut final_result = Boolean::Constant(true);
+        let mut upper_layer_values = Vec::with_capacity(upper_layer_queries.len());
 
         for labeled_query in upper_layer_queries.iter() {
 
@@ -68,11 +81,16 @@ impl<E: Engine, I: OracleGadget<E>, C: UpperLayerCombiner<E>> FriVerifierGadget<
                 &labeled_query.data.proof, 
             )?;
 
+            diagnostics.record(labeled_query.label, 0, query_round, oracle_check.get_value());
             final_result = Boolean::and(cs.namespace(|| "and"), &final_result, &oracle_check)?;
-            
+
+            let base_values : Vec<B> = parse_coset_values(cs.namespace(|| "parse upper layer query"), &labeled_query.data.values)?;
+            let values = base_values.iter().map(|x| {
+                F::from_base(cs.namespace(|| "embed into extension"), x)
+            }).collect::<Result<Vec<_>, _>>()?;
+            upper_layer_values.push(Labeled::new(labeled_query.label, values));
         }
 
-        
 
         // apply combiner function in order to conduct Fri round consistecy check with respect to the topmost layer
         // let n be the size of coset
@@ -97,8 +115,8 @@ impl<E: Engine, I: OracleGadget<E>, C: UpperLayerCombiner<E>> FriVerifierGadget<
 
         for i in 0..coset_size {
 
-            let labeled_argument : Vec<Labeled<&AllocatedNum<E>>> = upper_layer_queries.iter().map(|x| {
-                Labeled::new(x.label, &x.data.values[i])
+            let labeled_argument : Vec<Labeled<&F>> = upper_layer_values.iter().map(|x| {
+                Labeled::new(x.label, &x.data[i])
                 }).collect();
 
             let res = self.upper_layer_combiner.combine(
@@ -109,13 +127,178 @@ impl<E: Engine, I: OracleGadget<E>, C: UpperLayerCombiner<E>> FriVerifierGadget<
             values.push(res);
         }
 
-        let mut previous_layer_element = fri_helper.coset_interpolation_value(
+        if let Some((deep_point, deep_value)) = deep_opening {
+            values = deep_quotient_values(
+                cs.namespace(|| "DEEP quotient values"),
+                &values[..],
+                &evaluation_points[..],
+                deep_point,
+                deep_value,
+            )?;
+        }
+
+        let interpolant = fri_helper.coset_interpolation_value(
             cs.namespace(|| "coset interpolant computation"),
             &values[..],
             coset_idx.iter(),
             &fri_challenges[0..coset_size], 
         )?;
 
+        Ok((final_result, interpolant))
+    }
+
+
+    // returns the value of the final layer at the point of the bottom layer, 
+    // which corresponds to the coset_idx of the last intermidiate layer:
+    // either evaluates the polynomial defined by final coefficients (using Horner scheme)
+    // or simply selects the value from the final layer evaluations
+    pub(crate) fn evaluate_final_poly<CS: ConstraintSystem<E>>(
+        &self,
+        mut cs: CS,
+        fri_helper: &mut FriUtilsGadget<E, F>,
+        coset_idx: &[Boolean],
+        final_coefficients: &[F],
+    ) -> Result<F, SynthesisError>
+    {
+        assert!(final_coefficients.len() > 0);
+
+        if self.config.final_layer == FriFinalLayer::Evaluations {
+            fri_helper.next_domain(cs.namespace(|| "shrink domain to final layer"));
+            let (coset_idx, offset) = fri_helper.get_next_layer_coset_idx_extended(coset_idx);
+            let natural_index = fri_helper.get_natural_idx_for_coset_idx_offset(&coset_idx[..], &offset[..]);
+
+            return FriUtilsGadget::<E, F>::choose_element(
+                cs.namespace(|| "choose element of the final layer"), 
+                final_coefficients,
+                natural_index,
+            );
+        }
+
+        let val = if final_coefficients.len() == 1 {
+            // if len is 1 there is no need to create additional omega with constraint overhea
+            final_coefficients[0].clone()
+        }
+        else {
+            fri_helper.next_domain(cs.namespace(|| "shrink domain to final layer"));
+            let (coset_idx, offset) = fri_helper.get_next_layer_coset_idx_extended(coset_idx);
+            let natural_index = fri_helper.get_natural_idx_for_coset_idx_offset(&coset_idx[..], &offset[..]);
+
+            let omega = fri_helper.get_bottom_layer_omega(cs.namespace(|| "final layer generator"))?.clone();
+            let mut ev_p = F::pow(
+                cs.namespace(|| "poly eval: evaluation point"), 
+                &omega, 
+                natural_index,
+            )?;
+            // the same shift is used for combiner evaluation points, there is nothing to scale by if LDE is not shifted
+            if !fri_helper.is_coset_factor_trivial() {
+                let coset_factor = fri_helper.get_coset_factor();
+                ev_p = ev_p.mul_by_constant(cs.namespace(|| "scaling of ev_p by coset factor"), &coset_factor)?;
+            }
+
+            // Horner scheme: acc = acc * x + c_i, which requires a single constraint per coefficient for the native field
+            let mut acc = final_coefficients.last().expect("is not empty").clone();
+
+            for c in final_coefficients.iter().rev().skip(1) {
+                acc = F::mul_add(
+                    cs.namespace(|| "Horner step"), 
+                    &[(F::Field::one(), &acc)], 
+                    &ev_p, 
+                    &[(F::Field::one(), c)],
+                )?;
+            }
+
+            acc
+        };
+
+        Ok(val)
+    }
+
+
+    // in the evaluations mode checks that the final layer is indeed the evaluation of the polynomial 
+    // of degree < final_degree_plus_one over the bottom domain
+    // for the values v_k = f(s * w^k) we have: n * s^j * c_j = \sum v_k * w^{-jk}, hence we check that
+    // the righthand side is zero for all j >= final_degree_plus_one (all these sums are linear in v_k)
+    // this is done only once and is shared by all query rounds
+    pub(crate) fn check_final_layer_degree<CS: ConstraintSystem<E>>(
+        &self,
+        mut cs: CS,
+        final_values: &[F],
+    ) -> Result<Boolean, SynthesisError>
+    {
+        let mut final_result = Boolean::constant(true);
+        if self.config.final_layer != FriFinalLayer::Evaluations {
+            return Ok(final_result);
+        }
+
+        let domain_size = final_values.len();
+        let omega_inv = F::Field::domain_generator(domain_size).inverse().expect("should exist");
+        let zero = F::constant(cs.namespace(|| "zero"), F::Field::zero())?;
+
+        for j in self.final_degree_plus_one..domain_size {
+            let step = omega_inv.pow([j as u64]);
+            let mut coef = F::Field::one();
+            let mut terms = Vec::with_capacity(domain_size);
+
+            for value in final_values.iter() {
+                terms.push((coef.clone(), value));
+                coef.mul_assign(&step);
+            }
+
+            let high_coefficient = F::linear_combination(cs.namespace(|| "high coefficient"), &terms)?;
+            let flag = F::equals(
+                cs.namespace(|| "high coefficient is zero"), 
+                &high_coefficient, 
+                &zero,
+            )?;
+            final_result = Boolean::and(cs.namespace(|| "and"), &final_result, &flag)?;
+        }
+
+        Ok(final_result)
+    }
+
+
+    fn verify_single_proof_round<CS: ConstraintSystem<E>>(
+        &self,
+        mut cs: CS,
+
+        upper_layer_queries: &[Labeled<Query<E, I>>],
+        upper_layer_commitments: &[Labeled<I::Commitment>], 
+        fri_helper: &mut FriUtilsGadget<E, F>,
+
+        queries: &[Query<E, I>],
+        commitments: &[I::Commitment],
+        final_coefficients: &[F],
+
+        natural_index: Vec<Boolean>,
+        fri_challenges: &[F],
+
+        oracle_params: &I::Params,
+
+        query_round: usize,
+        diagnostics: &mut FriDiagnostics,
+   
+    ) -> Result<Boolean, SynthesisError>
+    {
+        let collapsing_factor = fri_helper.get_collapsing_factor();
+        let mut coset_idx = &fri_helper.get_coset_idx_for_natural_index(natural_index)[..];
+
+        let oracle = I::new(oracle_params);
+        // intermidiate layers are over F, while the upper layer oracles are over B
+        let intermidiate_oracle = oracle.with_leaf_size((1 << collapsing_factor) * F::num_native_elems());
+
+        let (mut final_result, mut previous_layer_element) = self.verify_upper_layer(
+            cs.namespace(|| "upper layer"),
+            &oracle,
+            upper_layer_queries,
+            upper_layer_commitments,
+            fri_helper,
+            coset_idx,
+            fri_challenges,
+            None,
+            query_round,
+            diagnostics,
+        )?;
+
         for (i, ((query, commitment), challenges)) 
             in queries.into_iter().zip(commitments.iter()).zip(fri_challenges.chunks(collapsing_factor).skip(1)).enumerate() 
         {            
@@ -125,7 +308,7 @@ impl<E: Engine, I: OracleGadget<E>, C: UpperLayerCombiner<E>> FriVerifierGadget<
             coset_idx = new_coset_idx;
 
             // oracle proof for current layer!
-            let oracle_check = oracle.validate(
+            let oracle_check = intermidiate_oracle.validate(
                 cs.namespace(|| "Oracle proof"),
                 fri_helper.get_cur_height(),
                 &query.values, 
@@ -134,34 +317,31 @@ impl<E: Engine, I: OracleGadget<E>, C: UpperLayerCombiner<E>> FriVerifierGadget<
                 &query.proof, 
             )?;
 
+            diagnostics.record(INTERMIDIATE_ORACLE_LABEL, i + 1, query_round, oracle_check.get_value());
             final_result = Boolean::and(cs.namespace(|| "and"), &final_result, &oracle_check)?;
            
             // round consistency check (rcc) : previous layer element interpolant has already been stored
             // compare it with current layer element (which is chosen from query values by offset)
+            let coset_values : Vec<F> = parse_coset_values(cs.namespace(|| "parse query"), &query.values)?;
             let cur_layer_element = fri_helper.choose_element_in_coset(
                 cs.namespace(|| "choose element from coset by index"),
-                &query.values[..],
+                &coset_values[..],
                 offset.into_iter(),
             )?; 
 
-            let ds = AllocatedNum::alloc_const(cs.namespace(|| "error"), E::Fr::one())?;
-            let rcc_flag = AllocatedNum::equals(
+            let rcc_flag = F::equals(
                 cs.namespace(|| "FRI round consistency check"), 
                 &previous_layer_element, 
                 &cur_layer_element,
-                //&ds,
             )?;
+            diagnostics.record(ROUND_CONSISTENCY_CHECK_LABEL, i + 1, query_round, rcc_flag.get_value());
             final_result = Boolean::and(cs.namespace(|| "and"), &final_result, &rcc_flag)?;
-            
-            if i == 1{
-             
-            }
 
             //recompute interpolant (using current layer for now) 
             //and store it for use on the next iteration (or for final check)
             previous_layer_element = fri_helper.coset_interpolation_value(
                 cs.namespace(|| "coset interpolant computation"),
-                &query.values[..],
+                &coset_values[..],
                 coset_idx.iter(),
                 &challenges, 
             )?;
@@ -170,94 +350,138 @@ impl<E: Engine, I: OracleGadget<E>, C: UpperLayerCombiner<E>> FriVerifierGadget<
 
         // finally we compare the last interpolant with the value f(\omega), 
         // where f is built from coefficients
+        let val = self.evaluate_final_poly(
+            cs.namespace(|| "final polynomial evaluation"),
+            fri_helper,
+            coset_idx,
+            final_coefficients,
+        )?;
 
-        assert!(final_coefficients.len() > 0);
-        let val = if final_coefficients.len() == 1 {
-            // if len is 1 there is no need to create additional omega with constraint overhea
-            final_coefficients[0].clone()
-        }
-        else {
-            fri_helper.next_domain(cs.namespace(|| "shrink domain to final layer"));
-            let (coset_idx, offset) = fri_helper.get_next_layer_coset_idx_extended(coset_idx);
-            let natural_index = fri_helper.get_natural_idx_for_coset_idx_offset(&coset_idx[..], &offset[..]);
-
-            let omega = fri_helper.get_bottom_layer_omega(cs.namespace(|| "final layer generator"))?;
-            let mut ev_p = AllocatedNum::pow(
-                cs.namespace(|| "poly eval: evaluation point"), 
-                omega, 
-                natural_index,
-            )?;
-            let coset_factor = fri_helper.get_coset_factor(cs.namespace(|| "coset factor"))?;
-            ev_p = ev_p.mul(cs.namespace(|| "scaling of ev_p by coset factor"), coset_factor)?;
-
-            let mut t = ev_p.clone();
-            let mut running_sum : Num<E> = final_coefficients[0].clone().into();
-
-            for c in final_coefficients.iter().skip(1) {
-
-                let term = t.mul(cs.namespace(|| "next term"), c)?;
-                t = t.mul(cs.namespace(|| "t^i"), &ev_p)?;
-                running_sum.mut_add_number_with_coeff(&term, E::Fr::one());
-            }
-
-            running_sum.simplify(cs.namespace(|| "simplification of running sum"))?
-        };
-
-        let flag = AllocatedNum::equals(
+        let flag = F::equals(
             cs.namespace(|| "FRI final round consistency check"), 
             &previous_layer_element, 
             &val,
         )?;
         
+        diagnostics.record(FINAL_CONSISTENCY_CHECK_LABEL, queries.len() + 1, query_round, flag.get_value());
         final_result = Boolean::and(cs.namespace(|| "and"), &final_result, &flag)?;
         Ok(final_result)
     }
 
 
+    // alpha, alpha^2, alpha^4, ... for each layer
+    pub(crate) fn unpack_fri_challenges<CS: ConstraintSystem<E>>(&self, mut cs: CS, fri_challenges: &[F]) -> Result<Vec<F>, SynthesisError> {
+        let mut unpacked_fri_challenges = Vec::with_capacity(self.collapsing_factor * fri_challenges.len());
+        for challenge in fri_challenges.iter() {
+            let mut cur = challenge.clone();
+            unpacked_fri_challenges.push(challenge.clone());
+            for _ in 1..self.collapsing_factor {
+                cur = cur.square(cs.namespace(|| "square challenge"))?;
+                unpacked_fri_challenges.push(cur.clone());
+            }
+        }
+
+        Ok(unpacked_fri_challenges)
+    }
+
+
     pub fn verify_proof<CS: ConstraintSystem<E>>(
         &self,
-        mut cs: CS,
+        cs: CS,
         oracle_params: &I::Params,
         // data that is shared among all Fri query rounds
         upper_layer_commitments: &[Labeled<I::Commitment>],
         commitments: &[I::Commitment],
-        final_coefficients: &[AllocatedNum<E>],
-        fri_challenges: &[AllocatedNum<E>],
+        final_coefficients: &[F],
+        fri_challenges: &[F],
         natural_first_element_indexes: Vec<Vec<Boolean>>, 
 
         query_rounds_data: &Vec<FriSingleQueryRoundData<E, I>>,
     ) -> Result<Boolean, SynthesisError> 
+    {
+        let (result, _) = self.verify_proof_impl(
+            cs,
+            oracle_params,
+            upper_layer_commitments,
+            commitments,
+            final_coefficients,
+            fri_challenges,
+            natural_first_element_indexes,
+            query_rounds_data,
+            FriDiagnostics::disabled(),
+        )?;
+
+        Ok(result)
+    }
+
+
+    // the same as verify_proof, but additionally returns all the intermidiate flags 
+    // (labeled by layer and query round), which is handy for finding out why the proof is rejected
+    pub fn verify_proof_with_diagnostics<CS: ConstraintSystem<E>>(
+        &self,
+        cs: CS,
+        oracle_params: &I::Params,
+        upper_layer_commitments: &[Labeled<I::Commitment>],
+        commitments: &[I::Commitment],
+        final_coefficients: &[F],
+        fri_challenges: &[F],
+        natural_first_element_indexes: Vec<Vec<Boolean>>, 
+
+        query_rounds_data: &Vec<FriSingleQueryRoundData<E, I>>,
+    ) -> Result<(Boolean, FriDiagnostics), SynthesisError> 
+    {
+        self.verify_proof_impl(
+            cs,
+            oracle_params,
+            upper_layer_commitments,
+            commitments,
+            final_coefficients,
+            fri_challenges,
+            natural_first_element_indexes,
+            query_rounds_data,
+            FriDiagnostics::new(),
+        )
+    }
+
+
+    // flags are recorded only if diagnostics are enabled, no constraints are added in any case
+    pub(crate) fn verify_proof_impl<CS: ConstraintSystem<E>>(
+        &self,
+        mut cs: CS,
+        oracle_params: &I::Params,
+        // data that is shared among all Fri query rounds
+        upper_layer_commitments: &[Labeled<I::Commitment>],
+        commitments: &[I::Commitment],
+        final_coefficients: &[F],
+        fri_challenges: &[F],
+        natural_first_element_indexes: Vec<Vec<Boolean>>, 
+
+        query_rounds_data: &Vec<FriSingleQueryRoundData<E, I>>,
+        mut diagnostics: FriDiagnostics,
+    ) -> Result<(Boolean, FriDiagnostics), SynthesisError> 
     {     
         // construct global parameters
-        let mut final_result = Boolean::Constant(true);
-        let mut temp_arr = Vec::with_capacity(self.collapsing_factor * fri_challenges.len());
-
-        let unpacked_fri_challenges = match self.collapsing_factor {
-            1 => &fri_challenges,
-            _ => {
-                for challenge in fri_challenges.iter().cloned() {
-                    let mut cur = challenge.clone();
-                    temp_arr.push(challenge);
-                    for _ in 1..self.collapsing_factor {
-                        cur = cur.square(cs.namespace(|| "square challenge"))?;
-                        temp_arr.push(cur.clone())
-                    }
-                }
-                &temp_arr[..]
-            }
-        };
+        let mut final_result = self.check_final_layer_degree(
+            cs.namespace(|| "final layer degree check"), 
+            final_coefficients,
+        )?;
+        let unpacked_fri_challenges = self.unpack_fri_challenges(cs.namespace(|| "unpack challenges"), fri_challenges)?;
 
         let num_iters = log2_floor(self.initial_degree_plus_one / self.final_degree_plus_one) / self.collapsing_factor;
+        if self.config.final_layer == FriFinalLayer::Evaluations {
+            diagnostics.record(FINAL_LAYER_DEGREE_CHECK_LABEL, num_iters, 0, final_result.get_value());
+        }
 
         let mut fri_helper = FriUtilsGadget::new(
             cs.namespace(|| "Fri Utils constructor"),
             self.initial_degree_plus_one * self.lde_factor,
             self.collapsing_factor,
             num_iters,
+            self.config.coset_factor.clone(),
         );
 
-        for (single_round_data, natural_first_element_index) in 
-            query_rounds_data.iter().zip(natural_first_element_indexes) {
+        for (query_round, (single_round_data, natural_first_element_index)) in 
+            query_rounds_data.iter().zip(natural_first_element_indexes).enumerate() {
 
             let flag = self.verify_single_proof_round(
                 cs.namespace(|| "FRI single round verifier"),
@@ -272,13 +496,15 @@ impl<E: Engine, I: OracleGadget<E>, C: UpperLayerCombiner<E>> FriVerifierGadget<
                 natural_first_element_index,
                 &unpacked_fri_challenges[..],
                 oracle_params,
+                query_round,
+                &mut diagnostics,
             )?;
 
             final_result = Boolean::and(cs.namespace(|| "and"), &final_result, &flag)?;
             fri_helper.to_initial_domain();
         }
 
-        Ok(final_result)
+        Ok((final_result, diagnostics))
     }
 }
 
@@ -1,9 +1,15 @@
 pub mod fri_utils;
 pub mod fri_verifier;
+pub mod deep_fri_verifier;
+pub mod diagnostics;
+pub mod fri_proximity_circuit;
+pub mod quotient_combiner;
+pub mod native_prover;
 pub mod tests;
 
 use common::*;
 use common::num::*;
+use common::field_gadget::*;
 use oracles::*;
 
 use bellman::{
@@ -15,13 +21,70 @@ use bellman::{
 use bellman::redshift::IOP::FRI::coset_combining_fri::FriParams;
 
 
-pub trait UpperLayerCombiner<E: Engine> {
+// F is the field of FRI layers (the native one by default)
+pub trait UpperLayerCombiner<E: Engine, F: FieldGadget<E> = AllocatedNum<E>> {
     fn combine<CS: ConstraintSystem<E>>(
         &self,
         cs: CS, 
-        domain_values: Vec<Labeled<&AllocatedNum<E>>>,
-        evaluation_point : &Num<E>
-    ) -> Result<AllocatedNum<E>, SynthesisError>; 
+        domain_values: Vec<Labeled<&F>>,
+        evaluation_point : &F
+    ) -> Result<F, SynthesisError>; 
+}
+
+
+// the way the prover sends the final layer of FRI
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FriFinalLayer {
+    // coefficients of the final polynomial, 
+    // which is evaluated separately for each query round
+    Coefficients,
+    // values of the final polynomial over the whole bottom domain (in natural order),
+    // degree is checked once for all query rounds and each round only selects the value by index
+    Evaluations,
+}
+
+
+// gadget-specific settings which are not covered by FriParams
+#[derive(Clone)]
+pub struct FriGadgetConfig<F: TwoAdicField> {
+    // LDE is done over the coset coset_factor * H, 
+    // coset_factor = 1 corresponds to the LDE without any shift.
+    // Folding doesn't take the coset factor into account, so all the subsequent layers 
+    // (the final one included) are evaluated over coset_factor * H_i as well
+    pub coset_factor: F,
+    // DEEP-FRI: an out-of-domain point is sampled for each folded layer (the upper one included)
+    // and the prover supplies the evaluation of the layer at this point
+    pub deep: bool,
+    // upper layer oracles are checked against their own degree bounds (instead of the common one),
+    // degree bounds are then taken from the verification key as circuit constants
+    pub degree_correction: bool,
+    pub final_layer: FriFinalLayer,
+}
+
+impl<F: TwoAdicField> Default for FriGadgetConfig<F> {
+    fn default() -> Self {
+        FriGadgetConfig {
+            coset_factor: F::coset_generator(),
+            deep: false,
+            degree_correction: false,
+            final_layer: FriFinalLayer::Coefficients,
+        }
+    }
+}
+
+impl<F: TwoAdicField> FriGadgetConfig<F> {
+    // the number of elements of the final layer sent by the prover
+    pub fn final_layer_size(&self, fri_params: &FriParams) -> usize {
+        match self.final_layer {
+            FriFinalLayer::Coefficients => fri_params.final_degree_plus_one.get(),
+            FriFinalLayer::Evaluations => {
+                let collapsing_factor = fri_params.collapsing_factor as usize;
+                let num_iters = log2_floor(
+                    fri_params.initial_degree_plus_one.get() / fri_params.final_degree_plus_one.get()) / collapsing_factor;
+                (fri_params.initial_degree_plus_one.get() * fri_params.lde_factor) >> (collapsing_factor * num_iters)
+            },
+        }
+    }
 }
 
 
@@ -32,7 +95,12 @@ pub struct FriSingleQueryRoundData<E: Engine, I: OracleGadget<E>> {
 }
 
 
-pub struct FriVerifierGadget<E: Engine, I: OracleGadget<E>, C: UpperLayerCombiner<E>>
+// upper layer oracles are over the base field B, while challenges and all the subsequent layers are over F:
+// F = B for plain FRI, or F is an extension of B, which is required for small fields to reach enough security.
+// Oracles always commit to the native representations of field elements, 
+// so each element of the coset occupies num_native_elems() consecutive query values
+pub struct FriVerifierGadget<E, I, C, B = AllocatedNum<E>, F = B>
+where E: Engine, I: OracleGadget<E>, C: UpperLayerCombiner<E, F>, B: FieldGadget<E>, F: ExtensionOf<E, B>, F::Field: TwoAdicField
 {
     pub collapsing_factor : usize,
     //number of iterations done during FRI query phase
@@ -42,21 +110,39 @@ pub struct FriVerifierGadget<E: Engine, I: OracleGadget<E>, C: UpperLayerCombine
     //the degree of the resulting polynomial at the bottom level of FRI
     pub final_degree_plus_one : usize,
     pub upper_layer_combiner: C,
+    pub config: FriGadgetConfig<F::Field>,
 
     pub _engine_marker : std::marker::PhantomData<E>,
     pub _oracle_marker : std::marker::PhantomData<I>,
+    pub _field_marker : std::marker::PhantomData<(B, F)>,
 }
 
 
 impl<E: Engine, O: OracleGadget<E>> FromStream<E, (FriParams, &[Label])> for FriSingleQueryRoundData<E, O> {
 
     fn from_stream<CS: ConstraintSystem<E>, I: Iterator<Item = Option<E::Fr>>>(
-        mut cs: CS, 
+        cs: CS, 
         iter: &mut I,
         params: (FriParams, &[Label]),
     ) -> Result<Self, SynthesisError> 
     {
         let (fri_params, labels) = params;
+        Self::from_stream(cs, iter, (fri_params, labels, 1))
+    }
+}
+
+
+// the last parameter is the number of native elements per each element of intermidiate layers
+// (it differs from 1 if intermidiate layers live in extension field, while upper layer oracles are over the base one)
+impl<E: Engine, O: OracleGadget<E>> FromStream<E, (FriParams, &[Label], usize)> for FriSingleQueryRoundData<E, O> {
+
+    fn from_stream<CS: ConstraintSystem<E>, I: Iterator<Item = Option<E::Fr>>>(
+        mut cs: CS, 
+        iter: &mut I,
+        params: (FriParams, &[Label], usize),
+    ) -> Result<Self, SynthesisError> 
+    {
+        let (fri_params, labels, elem_size) = params;
         
         let coset_size = 1 << fri_params.collapsing_factor;
         let top_level_oracle_size = (fri_params.initial_degree_plus_one.get() * fri_params.lde_factor) / coset_size;
@@ -83,7 +169,7 @@ impl<E: Engine, O: OracleGadget<E>> FromStream<E, (FriParams, &[Label])> for Fri
             let query = Query::from_stream(
                 cs.namespace(|| "intermidiate query"), 
                 iter, 
-                (coset_size, cur_height),
+                (coset_size * elem_size, cur_height),
             )?;
             cur_height -= fri_params.collapsing_factor as usize;
             queries.push(query)
@@ -91,4 +177,68 @@ impl<E: Engine, O: OracleGadget<E>> FromStream<E, (FriParams, &[Label])> for Fri
 
         Ok(FriSingleQueryRoundData{ upper_layer_queries, queries })
     }
-}
\ No newline at end of file
+}
+
+
+// everything the prover sends in FRI after the commitments to the upper layer oracles
+pub struct BatchedFriProof<E: Engine, I: OracleGadget<E>> {
+    // commitments to all intermidiate oracles
+    pub commitments: Vec<I::Commitment>,
+    // DEEP-FRI only: evaluations of intermidiate oracles at out-of-domain points (empty otherwise)
+    pub deep_values: Vec<AllocatedNum<E>>,
+    // either coefficients or evaluations of the final layer (depending on FriGadgetConfig)
+    pub final_coefficients: Vec<AllocatedNum<E>>,
+    pub fri_round_queries : Vec<FriSingleQueryRoundData<E, I>>,
+}
+
+
+// the last parameter contains the labels of upper layer oracles (in the order they are queried)
+impl<'a, E: Engine, O: OracleGadget<E>> FromStream<E, (FriParams, FriGadgetConfig<E::Fr>, &'a [Label])> for BatchedFriProof<E, O> {
+
+    fn from_stream<CS: ConstraintSystem<E>, I: Iterator<Item = Option<E::Fr>>>(
+        mut cs: CS, 
+        iter: &mut I,
+        params: (FriParams, FriGadgetConfig<E::Fr>, &'a [Label]),
+    ) -> Result<Self, SynthesisError> 
+    {
+        let (fri_params, fri_config, labels) = params;
+
+        let coset_size = 1 << fri_params.collapsing_factor;
+        let top_level_oracle_size = (fri_params.initial_degree_plus_one.get() * fri_params.lde_factor) / coset_size;
+        let top_leve_height = log2_floor(top_level_oracle_size);
+        
+        let mut num_of_iters = log2_floor(fri_params.initial_degree_plus_one.get() / fri_params.final_degree_plus_one.get()) / fri_params.collapsing_factor as usize;
+        // we do not count the very first and the last iterations
+        num_of_iters -= 1;
+
+        let mut cur_height = top_leve_height - fri_params.collapsing_factor as usize;
+        let mut commitments = Vec::with_capacity(num_of_iters);
+
+        for _ in 0..num_of_iters {
+            let commitment = O::Commitment::from_stream(
+                cs.namespace(|| "intermidiate commitment"), 
+                iter, 
+                cur_height,
+            )?;
+            commitments.push(commitment);
+            cur_height -= fri_params.collapsing_factor as usize;
+        }
+
+        let deep_values = match fri_config.deep {
+            true => Vec::from_stream(cs.namespace(|| "DEEP values"), iter, num_of_iters + 1)?,
+            false => vec![],
+        };
+
+        let final_coefficients = 
+            Vec::from_stream(cs.namespace(|| "final coefficients"), iter, fri_config.final_layer_size(&fri_params))?;
+
+        let mut fri_round_queries = Vec::with_capacity(fri_params.R);
+        for _ in 0..fri_params.R {
+            let fri_round = FriSingleQueryRoundData::from_stream(
+                cs.namespace(|| "FRI round query"), iter, (fri_params.clone(), labels))?;
+            fri_round_queries.push(fri_round);
+        }
+        
+        Ok(BatchedFriProof { commitments, deep_values, final_coefficients, fri_round_queries })
+    }
+}
@@ -0,0 +1,379 @@
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr,
+};
+
+use bellman::redshift::IOP::FRI::coset_combining_fri::FriParams;
+
+use common::field_gadget::*;
+use common::log2_floor;
+use hashes::NativeSponge;
+use channel::sponge_channel::NativeSpongeChannel;
+use oracles::sponge_merklee_proof::NativeSpongeTree;
+
+use super::{FriGadgetConfig, FriFinalLayer};
+
+
+// native FRI (and DEEP-FRI) prover, which follows the conventions of FriVerifierGadget:
+// - every layer is evaluated over coset_factor * H_i and is stored in natural order
+//   (folding doesn't take the coset factor into account, so the shift stays the same for all layers);
+// - leaf j of the layer oracle contains the coset of the elements with natural indexes
+//   bitreverse(j) + bitreverse(k) * 2^height, k = 0..2^collapsing_factor;
+// - transcript: the upper layer commitment is consumed first, then for each folded layer
+//   [commitment (all layers except the upper one), z_i, y_i (DEEP only)], alpha_i;
+//   finally the final layer is consumed and the query indexes are produced
+
+
+// the prover side of the transcript, challenges may belong to some other field than the native one
+pub trait FriProverChannel<F: Field, N: PrimeField> {
+    fn consume(&mut self, data: &[N]);
+    fn produce_challenge(&mut self) -> F;
+    // only the lowest bits of the index are used
+    fn produce_query_index(&mut self) -> u64;
+}
+
+// challenges of non-native fields are assembled from several native ones,
+// the same way as FieldChannelGadget does in the circuit
+impl<'a, F, N, S, P> FriProverChannel<F, N> for NativeSpongeChannel<'a, N, S, P>
+where F: FromNativeChallenges<N>, N: PrimeField, S: NativeSponge<N, P>
+{
+    fn consume(&mut self, data: &[N]) {
+        for elem in data.iter() {
+            NativeSpongeChannel::consume(self, *elem);
+        }
+    }
+
+    fn produce_challenge(&mut self) -> F {
+        let challenges: Vec<N> = (0..F::num_native_challenges()).map(|_| {
+            NativeSpongeChannel::produce_challenge(self)
+        }).collect();
+
+        F::from_native_challenges(&challenges)
+    }
+
+    // the same as the lowest 64 bits of the challenge decomposition in the circuit
+    fn produce_query_index(&mut self) -> u64 {
+        NativeSpongeChannel::produce_challenge(self).into_repr().as_ref()[0]
+    }
+}
+
+
+pub struct NativeQuery<N: PrimeField> {
+    pub values: Vec<N>,
+    pub proof: Vec<N>,
+}
+
+pub struct NativeFriQueryRound<N: PrimeField> {
+    pub upper_layer_query: NativeQuery<N>,
+    pub queries: Vec<NativeQuery<N>>,
+}
+
+pub struct NativeFriProof<F: Field, N: PrimeField> {
+    pub upper_layer_commitment: N,
+    // commitments to all intermidiate oracles
+    pub commitments: Vec<N>,
+    // DEEP-FRI only: evaluations of the folded layers at out-of-domain points
+    pub deep_values: Vec<F>,
+    // either coefficients or evaluations of the final layer (depending on FriGadgetConfig)
+    pub final_coefficients: Vec<F>,
+    pub natural_indexes: Vec<u64>,
+    pub fri_round_queries: Vec<NativeFriQueryRound<N>>,
+}
+
+impl<F: IntoNativeElems<N>, N: PrimeField> NativeFriProof<F, N> {
+    // the layout expected by FromStream of BatchedFriProof with the single upper layer oracle
+    pub fn to_stream(&self) -> Vec<N> {
+        let mut res = self.commitments.clone();
+        for elem in self.deep_values.iter().chain(self.final_coefficients.iter()) {
+            res.extend(elem.into_native_elems());
+        }
+
+        for round in self.fri_round_queries.iter() {
+            for query in Some(&round.upper_layer_query).into_iter().chain(round.queries.iter()) {
+                res.extend(query.values.iter().cloned());
+                res.extend(query.proof.iter().cloned());
+            }
+        }
+
+        res
+    }
+}
+
+
+fn bitreverse(n: usize, l: usize) -> usize {
+    if l == 0 {
+        return 0;
+    }
+    n.reverse_bits() >> ((std::mem::size_of::<usize>() * 8) - l)
+}
+
+// number of elements of the domain as the element of the field
+fn domain_size_inv<F: Field>(domain_size: usize) -> F {
+    let mut res = F::one();
+    for _ in 0..log2_floor(domain_size) {
+        res.double();
+    }
+    res.inverse().expect("is not zero")
+}
+
+fn fft<F: Field>(coeffs: &[F], omega: &F) -> Vec<F> {
+    let n = coeffs.len();
+    if n == 1 {
+        return coeffs.to_vec();
+    }
+
+    let mut omega_squared = *omega;
+    omega_squared.square();
+    let even : Vec<F> = coeffs.iter().step_by(2).cloned().collect();
+    let odd : Vec<F> = coeffs.iter().skip(1).step_by(2).cloned().collect();
+    let even = fft(&even, &omega_squared);
+    let odd = fft(&odd, &omega_squared);
+
+    let mut res = vec![F::zero(); n];
+    let mut w = F::one();
+    for i in 0..(n / 2) {
+        let mut temp = odd[i];
+        temp.mul_assign(&w);
+
+        res[i] = even[i];
+        res[i].add_assign(&temp);
+        res[i + n / 2] = even[i];
+        res[i + n / 2].sub_assign(&temp);
+
+        w.mul_assign(omega);
+    }
+
+    res
+}
+
+// values of the polynomial over coset_factor * H, |H| = domain_size
+pub fn evaluate_over_coset<F: TwoAdicField>(coefficients: &[F], domain_size: usize, coset_factor: &F) -> Vec<F> {
+    assert!(coefficients.len() <= domain_size);
+    let mut scaled = Vec::with_capacity(domain_size);
+    let mut power = F::one();
+    for c in coefficients.iter() {
+        let mut temp = *c;
+        temp.mul_assign(&power);
+        scaled.push(temp);
+        power.mul_assign(coset_factor);
+    }
+    scaled.resize(domain_size, F::zero());
+
+    fft(&scaled, &F::domain_generator(domain_size))
+}
+
+// the reverse of evaluate_over_coset
+pub fn interpolate_over_coset<F: TwoAdicField>(values: &[F], coset_factor: &F) -> Vec<F> {
+    let domain_size = values.len();
+    let omega_inv = F::domain_generator(domain_size).inverse().expect("is not zero");
+    let mut coefficients = fft(values, &omega_inv);
+
+    let size_inv = domain_size_inv::<F>(domain_size);
+    let coset_factor_inv = coset_factor.inverse().expect("is not zero");
+    let mut power = size_inv;
+    for c in coefficients.iter_mut() {
+        c.mul_assign(&power);
+        power.mul_assign(&coset_factor_inv);
+    }
+
+    coefficients
+}
+
+fn evaluate_at<F: Field>(coefficients: &[F], point: &F) -> F {
+    let mut acc = F::zero();
+    for c in coefficients.iter().rev() {
+        acc.mul_assign(point);
+        acc.add_assign(c);
+    }
+    acc
+}
+
+// (f(x) - y) / (x - z) for all x in coset_factor * H
+fn deep_quotient<F: TwoAdicField>(values: &[F], coset_factor: &F, deep_point: &F, deep_value: &F) -> Vec<F> {
+    let omega = F::domain_generator(values.len());
+    let mut x = *coset_factor;
+
+    values.iter().map(|v| {
+        let mut numerator = *v;
+        numerator.sub_assign(deep_value);
+        let mut denominator = x;
+        denominator.sub_assign(deep_point);
+        numerator.mul_assign(&denominator.inverse().expect("out-of-domain point"));
+
+        x.mul_assign(&omega);
+        numerator
+    }).collect()
+}
+
+// f'(x^2) = (f(x) + f(-x)) / 2 + alpha * (f(x) - f(-x)) / 2x, repeated collapsing_factor times with alpha, alpha^2, ...
+// here x runs through the domain without the coset factor, exactly as in FriUtilsGadget::coset_interpolation_value
+fn fold<F: TwoAdicField>(values: &[F], collapsing_factor: usize, challenge: &F) -> Vec<F> {
+    let mut two = F::one();
+    two.double();
+    let two_inv = two.inverse().expect("is not zero");
+
+    let mut values = values.to_vec();
+    let mut challenge = *challenge;
+
+    for _ in 0..collapsing_factor {
+        let half = values.len() / 2;
+        let omega_inv = F::domain_generator(values.len()).inverse().expect("is not zero");
+        let mut x_inv = F::one();
+
+        values = (0..half).map(|i| {
+            let mut v_even = values[i];
+            v_even.add_assign(&values[i + half]);
+            let mut v_odd = values[i];
+            v_odd.sub_assign(&values[i + half]);
+            v_odd.mul_assign(&x_inv);
+            v_odd.mul_assign(&challenge);
+            v_even.add_assign(&v_odd);
+            v_even.mul_assign(&two_inv);
+
+            x_inv.mul_assign(&omega_inv);
+            v_even
+        }).collect();
+
+        challenge.square();
+    }
+
+    values
+}
+
+fn commit_layer<F, N, S, P>(values: &[F], collapsing_factor: usize, sponge_params: &P, domain_separation: bool) -> NativeSpongeTree<N>
+where F: IntoNativeElems<N>, N: PrimeField, S: NativeSponge<N, P>
+{
+    let height = log2_floor(values.len()) - collapsing_factor;
+    let leaves = (0..(1 << height)).map(|j| {
+        let coset_start = bitreverse(j, height);
+        (0..(1 << collapsing_factor)).flat_map(|k| {
+            values[coset_start + (bitreverse(k, collapsing_factor) << height)].into_native_elems()
+        }).collect()
+    }).collect();
+
+    NativeSpongeTree::new::<S, P>(leaves, sponge_params, domain_separation)
+}
+
+fn produce_query<N: PrimeField>(tree: &NativeSpongeTree<N>, natural_index: u64) -> NativeQuery<N> {
+    let height = tree.get_height();
+    let coset_idx = bitreverse((natural_index as usize) & ((1 << height) - 1), height);
+    let (values, proof) = tree.produce_query(coset_idx);
+
+    NativeQuery { values, proof }
+}
+
+
+// proves that the polynomial with the given coefficients is of degree < initial_degree_plus_one:
+// the upper layer is committed over the base field B, all the subsequent layers are over F;
+// domain_separation has to match the oracle params of the verifier (SpongeTreeGadgetParams::new enables it)
+pub fn prove_fri<B, F, N, S, P, T>(
+    coefficients: &[B],
+    fri_params: &FriParams,
+    config: &FriGadgetConfig<F>,
+    sponge_params: &P,
+    domain_separation: bool,
+    channel: &mut T,
+) -> NativeFriProof<F, N>
+where 
+    B: Field + IntoNativeElems<N>, 
+    F: TwoAdicField + IntoNativeElems<N> + NativeExtensionOf<B>, 
+    N: PrimeField, 
+    S: NativeSponge<N, P>, 
+    T: FriProverChannel<F, N>,
+{
+    let initial_degree_plus_one = fri_params.initial_degree_plus_one.get();
+
+    // the domain lies in the base field, hence so do the values of the upper layer
+    let coefficients : Vec<F> = coefficients.iter().map(|c| F::from_base_elem(c)).collect();
+    let values = evaluate_over_coset(&coefficients, initial_degree_plus_one * fri_params.lde_factor, &config.coset_factor);
+    let upper_layer_values : Vec<B> = values.iter().map(|x| {
+        x.into_base_elem().expect("the domain should lie in the base field")
+    }).collect();
+
+    prove_fri_over_values::<B, F, N, S, P, T>(&upper_layer_values, values, fri_params, config, sponge_params, domain_separation, channel)
+}
+
+
+// the upper layer oracle is committed to upper_layer_values, while the folding starts from values 
+// (both are evaluations over coset_factor * H in natural order): 
+// values are the output of some upper layer combiner, applied to upper_layer_values
+pub fn prove_fri_over_values<B, F, N, S, P, T>(
+    upper_layer_values: &[B],
+    mut values: Vec<F>,
+    fri_params: &FriParams,
+    config: &FriGadgetConfig<F>,
+    sponge_params: &P,
+    domain_separation: bool,
+    channel: &mut T,
+) -> NativeFriProof<F, N>
+where 
+    B: Field + IntoNativeElems<N>, 
+    F: TwoAdicField + IntoNativeElems<N>, 
+    N: PrimeField, 
+    S: NativeSponge<N, P>, 
+    T: FriProverChannel<F, N>,
+{
+    assert_eq!(upper_layer_values.len(), values.len());
+
+    let collapsing_factor = fri_params.collapsing_factor as usize;
+    let initial_degree_plus_one = fri_params.initial_degree_plus_one.get();
+    let final_degree_plus_one = fri_params.final_degree_plus_one.get();
+    let num_iters = log2_floor(initial_degree_plus_one / final_degree_plus_one) / collapsing_factor;
+    let coset_factor = config.coset_factor;
+
+    let upper_layer_tree = commit_layer::<B, N, S, P>(upper_layer_values, collapsing_factor, sponge_params, domain_separation);
+    channel.consume(&[upper_layer_tree.get_commitment()]);
+
+    let mut trees = Vec::with_capacity(num_iters - 1);
+    let mut deep_values = vec![];
+
+    for layer in 0..num_iters {
+        if layer > 0 {
+            let tree = commit_layer::<F, N, S, P>(&values, collapsing_factor, sponge_params, domain_separation);
+            channel.consume(&[tree.get_commitment()]);
+            trees.push(tree);
+        }
+
+        if config.deep {
+            let deep_point : F = channel.produce_challenge();
+            let deep_value = evaluate_at(&interpolate_over_coset(&values, &coset_factor), &deep_point);
+            channel.consume(&deep_value.into_native_elems());
+            deep_values.push(deep_value);
+
+            values = deep_quotient(&values, &coset_factor, &deep_point, &deep_value);
+        }
+
+        let challenge : F = channel.produce_challenge();
+        values = fold(&values, collapsing_factor, &challenge);
+    }
+
+    let final_coefficients = match config.final_layer {
+        FriFinalLayer::Coefficients => {
+            let mut coefficients = interpolate_over_coset(&values, &coset_factor);
+            coefficients.truncate(final_degree_plus_one);
+            coefficients
+        },
+        FriFinalLayer::Evaluations => values,
+    };
+    for elem in final_coefficients.iter() {
+        channel.consume(&elem.into_native_elems());
+    }
+
+    let natural_indexes : Vec<u64> = (0..fri_params.R).map(|_| channel.produce_query_index()).collect();
+    let fri_round_queries = natural_indexes.iter().map(|index| {
+        NativeFriQueryRound {
+            upper_layer_query: produce_query(&upper_layer_tree, *index),
+            queries: trees.iter().map(|tree| produce_query(tree, *index)).collect(),
+        }
+    }).collect();
+
+    NativeFriProof {
+        upper_layer_commitment: upper_layer_tree.get_commitment(),
+        commitments: trees.iter().map(|tree| tree.get_commitment()).collect(),
+        deep_values,
+        final_coefficients,
+        natural_indexes,
+        fri_round_queries,
+    }
+}
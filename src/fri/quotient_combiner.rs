@@ -0,0 +1,178 @@
+use bellman::pairing::{
+    Engine,
+};
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+};
+
+use common::field_gadget::*;
+use common::{Label, Labeled};
+
+use super::UpperLayerCombiner;
+
+
+// polynomials with the given labels are claimed to have values at the point z
+pub struct Opening<E: Engine, F: FieldGadget<E>> {
+    pub labels: Vec<Label>,
+    pub point: F,
+    pub values: Vec<F>,
+
+    pub _marker: std::marker::PhantomData<E>,
+}
+
+
+// DEEP-ALI style combiner (the same as Redshift combiner, but without any protocol-specific data and over any field):
+// for the evaluation point x it computes
+// y = \sum_j \sum_{i in opening j} alpha^k [f_i(x) - f_i(z_j)] / [x - z_j],
+// where k = 1, 2, ... runs through all the openings sequentially
+pub struct QuotientCombiner<E: Engine, F: FieldGadget<E>> {
+    openings: Vec<Opening<E, F>>,
+    // alpha^1, alpha^2, ... are computed only once and reused for all evaluation points
+    alpha_powers: Vec<F>,
+}
+
+impl<E: Engine, F: FieldGadget<E>> QuotientCombiner<E, F> {
+    pub fn new<CS: ConstraintSystem<E>>(
+        mut cs: CS,
+        openings: Vec<Opening<E, F>>,
+        alpha: F,
+    ) -> Result<Self, SynthesisError>
+    {
+        let num_of_values : usize = openings.iter().map(|x| x.labels.len()).sum();
+        let mut alpha_powers = Vec::with_capacity(num_of_values);
+
+        for opening in openings.iter() {
+            if opening.labels.len() != opening.values.len() {
+                return Err(SynthesisError::Unknown);
+            }
+        }
+
+        if num_of_values > 0 {
+            alpha_powers.push(alpha.clone());
+        }
+        for _ in 1..num_of_values {
+            let next = alpha_powers.last().expect("is not empty").mul(cs.namespace(|| "next power of alpha"), &alpha)?;
+            alpha_powers.push(next);
+        }
+
+        Ok(QuotientCombiner { openings, alpha_powers })
+    }
+}
+
+impl<E: Engine, F: FieldGadget<E>> UpperLayerCombiner<E, F> for QuotientCombiner<E, F> {
+    fn combine<CS: ConstraintSystem<E>>(
+        &self,
+        mut cs: CS,
+        domain_values: Vec<Labeled<&F>>,
+        evaluation_point : &F,
+    ) -> Result<F, SynthesisError>
+    {
+        let mut res : Option<F> = None;
+        let mut alpha_powers = self.alpha_powers.iter();
+
+        for opening in self.openings.iter() {
+            let mut numerator : Option<F> = None;
+
+            for (label, value) in opening.labels.iter().zip(opening.values.iter()) {
+                let domain_value = domain_values.iter().find(|x| x.label == *label).ok_or(SynthesisError::Unknown)?.data;
+                let alpha = alpha_powers.next().ok_or(SynthesisError::Unknown)?;
+
+                let mut temp = domain_value.sub(cs.namespace(|| "f(x) - f(z)"), value)?;
+                temp = temp.mul(cs.namespace(|| "scale by alpha"), alpha)?;
+
+                numerator = Some(match numerator {
+                    None => temp,
+                    Some(acc) => acc.add(cs.namespace(|| "accumulate numerator"), &temp)?,
+                });
+            }
+
+            let numerator = match numerator {
+                Some(x) => x,
+                None => continue,
+            };
+
+            let denominator = evaluation_point.sub(cs.namespace(|| "x - z"), &opening.point)?;
+            let quotient = numerator.div(cs.namespace(|| "quotient"), &denominator)?;
+
+            res = Some(match res {
+                None => quotient,
+                Some(acc) => acc.add(cs.namespace(|| "accumulate quotients"), &quotient)?,
+            });
+        }
+
+        res.ok_or(SynthesisError::Unknown)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bellman::pairing::ff::Field;
+    use bellman::pairing::bn256::Bn256;
+    use crate::tester::naming_oblivious_cs::NamingObliviousConstraintSystem as TestConstraintSystem;
+    use common::num::AllocatedNum;
+    use common::goldilocks::*;
+    use common::extension_field::*;
+    use rand::*;
+
+    fn quotient_combiner_test_impl<F: FieldGadget<Bn256>>() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let alloc = |cs: &mut TestConstraintSystem<Bn256>, x: F::Field| F::alloc(cs.namespace(|| "alloc"), Some(x)).unwrap();
+
+        let labels = ["a", "b", "c"];
+        let domain : Vec<F::Field> = (0..labels.len()).map(|_| rng.gen()).collect();
+        let z : Vec<F::Field> = (0..2).map(|_| rng.gen()).collect();
+        let opened : Vec<F::Field> = (0..labels.len()).map(|_| rng.gen()).collect();
+        let x : F::Field = rng.gen();
+        let alpha : F::Field = rng.gen();
+
+        // "a" and "b" are opened at z_0, "c" - at z_1
+        let openings = vec![
+            Opening {
+                labels: vec!["a", "b"],
+                point: alloc(&mut cs, z[0]),
+                values: vec![alloc(&mut cs, opened[0]), alloc(&mut cs, opened[1])],
+                _marker: std::marker::PhantomData,
+            },
+            Opening {
+                labels: vec!["c"],
+                point: alloc(&mut cs, z[1]),
+                values: vec![alloc(&mut cs, opened[2])],
+                _marker: std::marker::PhantomData,
+            },
+        ];
+        let alpha_var = alloc(&mut cs, alpha);
+        let combiner = QuotientCombiner::new(cs.namespace(|| "combiner"), openings, alpha_var).unwrap();
+
+        let domain_vars : Vec<F> = domain.iter().map(|v| alloc(&mut cs, *v)).collect();
+        let domain_values = labels.iter().zip(domain_vars.iter()).map(|(l, v)| Labeled::new(*l, v)).collect();
+        let x_var = alloc(&mut cs, x);
+        let res = combiner.combine(cs.namespace(|| "combine"), domain_values, &x_var).unwrap();
+
+        let mut expected = F::Field::zero();
+        let mut alpha_power = alpha;
+        for (i, z_idx) in [0, 0, 1].iter().enumerate() {
+            let mut temp = domain[i];
+            temp.sub_assign(&opened[i]);
+            temp.mul_assign(&alpha_power);
+            let mut denominator = x;
+            denominator.sub_assign(&z[*z_idx]);
+            temp.mul_assign(&denominator.inverse().unwrap());
+            expected.add_assign(&temp);
+            alpha_power.mul_assign(&alpha);
+        }
+
+        assert!(cs.is_satisfied());
+        assert_eq!(res.get_value().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_quotient_combiner() {
+        quotient_combiner_test_impl::<AllocatedNum<Bn256>>();
+        quotient_combiner_test_impl::<GoldilocksNum<Bn256>>();
+        quotient_combiner_test_impl::<ExtensionFieldGadget<Bn256, GoldilocksNum<Bn256>, GoldilocksExt2Params>>();
+    }
+}
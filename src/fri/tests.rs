@@ -30,7 +30,7 @@ mod test {
             &self,
             cs: CS, 
             domain_values: Vec<Labeled<&AllocatedNum<E>>>,
-            evaluation_point : &Num<E>
+            evaluation_point : &AllocatedNum<E>
         ) -> Result<AllocatedNum<E>, SynthesisError>
         {
             let res = domain_values.into_iter().find(|elem| elem.label == "starting oracle").map(|elem| elem.data.clone()).ok_or(SynthesisError::Unknown);
@@ -142,16 +142,14 @@ mod test {
                 lde_factor: self.fri_params.lde_factor,
                 final_degree_plus_one : self.fri_params.final_degree_plus_one.get(),
                 upper_layer_combiner: self.combiner,
+                config: FriGadgetConfig::default(),
 
                 _engine_marker : std::marker::PhantomData::<E>,
                 _oracle_marker : std::marker::PhantomData::<RescueTreeGadget<E, RP, SBOX>>,
+                _field_marker : std::marker::PhantomData::<(AllocatedNum<E>, AllocatedNum<E>)>,
             };
 
-            let oracle_params = RescueTreeGadgetParams {
-                num_elems_per_leaf: 1 << self.fri_params.collapsing_factor,
-                rescue_params: &self.rescue_params,
-                _marker: std::marker::PhantomData::<E::Fr>,
-            };
+            let oracle_params = RescueTreeGadgetParams::without_domain_separation(1 << self.fri_params.collapsing_factor, &self.rescue_params);
             let mut iter = self.iter;
             let fri_params = self.fri_params;
 
@@ -403,6 +401,797 @@ mod test {
         //     {} query rounds contains {} constraints", fri_params.initial_degree_plus_one.get(), fri_params.lde_factor, 
         //     fri_params.collapsing_factor, fri_params.R, cs.num_constraints());
     }
-}
 
-   
\ No newline at end of file
+
+    #[test]
+    fn test_fri_proximity_circuit() 
+    {
+        fri_proximity_circuit_test_impl(FriFinalLayer::Coefficients);
+    }
+
+
+    #[test]
+    fn test_fri_proximity_circuit_with_final_evaluations() 
+    {
+        fri_proximity_circuit_test_impl(FriFinalLayer::Evaluations);
+    }
+
+
+    fn fri_proximity_circuit_test_impl(final_layer: FriFinalLayer) 
+    {
+        use bellman::redshift::IOP::channel::rescue_channel::*;
+        use bellman::multicore::*;
+        use bellman::redshift::IOP::channel::*;
+        use bellman::redshift::IOP::oracle::coset_combining_rescue_tree::*;
+        use bellman::redshift::polynomials::*;
+        use bellman::redshift::fft::cooley_tukey_ntt::*;
+        use bellman::redshift::IOP::oracle::*;
+        use bellman::redshift::IOP::FRI::coset_combining_fri::*;
+        use bellman::redshift::IOP::FRI::coset_combining_fri::precomputation::*;
+        use bellman::redshift::redshift::serialization::ToStream;
+        use bellman::redshift::domains::Domain;
+
+        use hashes::rescue::bn256_rescue_sbox::BN256RescueSbox;
+        use channel::rescue_channel::RescueChannelGadget;
+        use fri::fri_proximity_circuit::*;
+        use tester::naming_oblivious_cs::NamingObliviousConstraintSystem as TestConstraintSystem;
+
+        use rand::*;
+        
+        type E = bellman::pairing::bn256::Bn256;
+        type O<'a> = FriSpecificRescueTree<'a, Fr, BN256Rescue>;
+        type T<'a> = RescueChannel<'a, Fr, BN256Rescue>;
+
+        let bn256_rescue_params = BN256Rescue::default();
+
+        const SIZE: usize = 1024;
+        let worker = Worker::new_with_cpus(1);
+
+        let channel_params = RescueChannelParams {
+            rescue_params: &bn256_rescue_params,
+            _marker: std::marker::PhantomData::<Fr>,
+        };
+
+        let mut channel = RescueChannel::new(&channel_params);
+
+        let fri_params = FriParams {
+            collapsing_factor: 2,
+            R: 5,
+            initial_degree_plus_one: std::cell::Cell::new(SIZE),
+            lde_factor: 4,
+            final_degree_plus_one: std::cell::Cell::new(4),
+        };
+        fri_params.recompute_final_degree(true);
+
+        let oracle_params = RescueTreeParams {
+            values_per_leaf: 1 << fri_params.collapsing_factor,
+            rescue_params: &bn256_rescue_params,
+            _marker: std::marker::PhantomData::<Fr>,
+        };
+
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let coeffs = (0..SIZE).map(|_| Fr::rand(rng)).collect::<Vec<_>>();
+    
+        let poly = Polynomial::<Fr, _>::from_coeffs(coeffs).unwrap();
+        let precomp = BitReversedOmegas::<Fr>::new_for_domain_size(poly.size());
+
+        let coset_factor = Fr::multiplicative_generator();
+        let eval_result = poly.bitreversed_lde_using_bitreversed_ntt(&worker, 4, &precomp, &coset_factor).unwrap();
+
+        let upper_layer_oracle = FriSpecificRescueTree::create(eval_result.as_ref(), &oracle_params);
+        let batched_oracle = BatchedOracle::create(vec![(FRI_PROXIMITY_ORACLE_LABEL, &upper_layer_oracle)]);
+        let commitment = upper_layer_oracle.get_commitment();
+
+        // the transcript should be the same as the one replayed inside the circuit
+        channel.consume(&commitment);
+
+        let fri_precomp = <OmegasInvBitreversed::<Fr> as FriPrecomputations<Fr>>::new_for_domain_size(eval_result.size());
+
+        let fri_proto = FriIop::<Fr, O, T>::proof_from_lde(
+            eval_result.clone(), 
+            &fri_precomp, 
+            &worker, 
+            &mut channel,
+            &fri_params,
+            &oracle_params,
+        ).expect("FRI must succeed");
+
+        let fri_config = FriGadgetConfig { coset_factor, final_layer, ..Default::default() };
+        let final_layer_size = fri_config.final_layer_size(&fri_params);
+
+        // in evaluations mode the prover sends f(s * w^k) for all k instead of coefficients of f
+        let final_layer_values : Vec<Fr> = match final_layer {
+            FriFinalLayer::Coefficients => fri_proto.final_coefficients.clone(),
+            FriFinalLayer::Evaluations => {
+                let omega = Domain::<Fr>::new_for_size(final_layer_size as u64).unwrap().generator;
+                (0..final_layer_size).map(|k| {
+                    let mut point = omega.pow([k as u64]);
+                    point.mul_assign(&coset_factor);
+
+                    let mut res = Fr::zero();
+                    for coef in fri_proto.final_coefficients.iter().rev() {
+                        res.mul_assign(&point);
+                        res.add_assign(coef);
+                    }
+                    res
+                }).collect()
+            },
+        };
+
+        for elem in final_layer_values.iter() {
+            channel.consume(elem);
+        }
+
+        let coset_size = 1 << fri_params.collapsing_factor;
+        let top_level_oracle_size = (fri_params.initial_degree_plus_one.get() * fri_params.lde_factor) / coset_size;
+        let top_level_height = crate::common::log2_floor(top_level_oracle_size);
+
+        // circuit uses only the lowest bits of each challenge as a query index
+        let natural_indexes : Vec<usize> = (0..fri_params.R).map(|_| {
+            let challenge = channel.produce_field_element_challenge();
+            (challenge.into_repr().as_ref()[0] as usize) & (top_level_oracle_size - 1)
+        }).collect();
+
+        let proof = FriIop::<Fr, O, T>::prototype_into_proof(
+            fri_proto,
+            &batched_oracle,
+            vec![eval_result.as_ref()],
+            natural_indexes,
+            &fri_params,
+            &oracle_params,
+        ).expect("Fri Proof must be constrcuted");
+
+        let mut container : Vec<Fr> = Vec::new();
+        for c in proof.commitments.iter() {
+            c.to_stream(&mut container, ());
+        }
+        final_layer_values.to_stream(&mut container, final_layer_size);
+
+        for (top_layer, intermidiate) in proof.upper_layer_queries.into_iter().zip(proof.queries.into_iter()) 
+        {
+            top_layer[0].1.clone().to_stream(&mut container, (coset_size, top_level_height));
+            
+            let mut cur_height = top_level_height - fri_params.collapsing_factor as usize;
+            for query in intermidiate.into_iter() {
+                query.to_stream(&mut container, (coset_size, cur_height as usize));
+                cur_height -= fri_params.collapsing_factor as usize;
+            }
+        }
+
+        let oracle_gadget_params = RescueTreeGadgetParams::without_domain_separation(coset_size, &bn256_rescue_params);
+
+        type OG<'a> = RescueTreeGadget<'a, E, BN256Rescue, BN256RescueSbox>;
+        type TG<'a> = RescueChannelGadget<'a, E, BN256Rescue, BN256RescueSbox>;
+
+        let circuit = FriProximityCircuit::<E, OG, TG, _>::new(
+            &bn256_rescue_params,
+            oracle_gadget_params,
+            fri_params.clone(),
+            fri_config,
+            container.into_iter().map(|x| Some(x)),
+            commitment,
+        );
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        circuit.synthesize(&mut cs).expect("should synthesize");
+
+        if !cs.is_satisfied() {
+            println!("UNSATISFIED at: {}", cs.which_is_unsatisfied().unwrap());
+        }
+        assert!(cs.is_satisfied());
+
+        println!("Fri proximity circuit for polynomials of degree {}, lde-factor {}, collapsing_factor {} and {} query rounds contains {} constraints", 
+            fri_params.initial_degree_plus_one.get(), fri_params.lde_factor, fri_params.collapsing_factor, fri_params.R, cs.num_constraints());
+
+        cs.modify_input(1, "upper layer commitment/num", Fr::one());
+        assert!(!cs.is_satisfied());
+    }
+
+
+    // runs FriProximityCircuit with diagnostics enabled over the proof produced by the native prover,
+    // tamper is applied to the proof before serialization
+    fn fri_proximity_diagnostics_test_impl<M>(tamper: M) -> (bool, diagnostics::FriDiagnostics)
+    where M: FnOnce(&mut native_prover::NativeFriProof<Fr, Fr>)
+    {
+        use hashes::poseidon::Poseidon;
+        use hashes::poseidon::params::DefaultPoseidonParams;
+        use oracles::poseidon_merklee_proof::*;
+        use channel::poseidon_channel::PoseidonChannelGadget;
+        use channel::sponge_channel::NativeSpongeChannel;
+        use fri::native_prover::prove_fri;
+        use fri::fri_proximity_circuit::FriProximityCircuit;
+        use std::rc::Rc;
+        use std::cell::RefCell;
+        use rand::*;
+
+        type S = Poseidon<Fr, DefaultPoseidonParams<Fr>>;
+        type OG<'a> = PoseidonTreeGadget<'a, Bn256, DefaultPoseidonParams<Fr>>;
+        type TG<'a> = PoseidonChannelGadget<'a, Bn256, DefaultPoseidonParams<Fr>>;
+
+        const SIZE: usize = 256;
+        let params = DefaultPoseidonParams::<Fr>::bn256_default();
+        let fri_params = FriParams {
+            collapsing_factor: 2,
+            R: 2,
+            initial_degree_plus_one: std::cell::Cell::new(SIZE),
+            lde_factor: 4,
+            final_degree_plus_one: std::cell::Cell::new(4),
+        };
+        let fri_config = FriGadgetConfig { coset_factor: Fr::multiplicative_generator(), ..Default::default() };
+
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let coeffs : Vec<Fr> = (0..SIZE).map(|_| rng.gen()).collect();
+
+        let mut channel = NativeSpongeChannel::<Fr, S, _>::new(&params);
+        let mut proof = prove_fri::<_, _, _, S, _, _>(&coeffs, &fri_params, &fri_config, &params, true, &mut channel);
+        tamper(&mut proof);
+
+        let oracle_params = PoseidonTreeGadgetParams::new(1 << fri_params.collapsing_factor, &params);
+
+        let diagnostics = Rc::new(RefCell::new(diagnostics::FriDiagnostics::disabled()));
+        let circuit = FriProximityCircuit::<Bn256, OG, TG, _>::new(
+            &params,
+            oracle_params,
+            fri_params.clone(),
+            fri_config,
+            proof.to_stream().into_iter().map(|x| Some(x)),
+            proof.upper_layer_commitment,
+        ).with_diagnostics(diagnostics.clone());
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        circuit.synthesize(&mut cs).expect("should synthesize");
+
+        let diagnostics = diagnostics.borrow().clone();
+        (cs.is_satisfied(), diagnostics)
+    }
+
+    #[test]
+    fn test_fri_proximity_diagnostics() 
+    {
+        let (is_satisfied, diagnostics) = fri_proximity_diagnostics_test_impl(|_| {});
+        assert!(is_satisfied);
+        assert!(diagnostics.is_enabled());
+        assert!(!diagnostics.records.is_empty());
+        assert!(diagnostics.is_ok());
+
+        // Merklee path of the upper layer is broken in the second query round: values and hence all the other checks are intact
+        let (is_satisfied, diagnostics) = fri_proximity_diagnostics_test_impl(|proof| {
+            proof.fri_round_queries[1].upper_layer_query.proof[0].add_assign(&Fr::one());
+        });
+        assert!(!is_satisfied);
+        let failed : Vec<_> = diagnostics.failed_checks().collect();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].label, fri_proximity_circuit::FRI_PROXIMITY_ORACLE_LABEL);
+        assert_eq!(failed[0].layer, 0);
+        assert_eq!(failed[0].query_round, 1);
+
+        // the same for the second intermidiate layer in the first query round
+        let (is_satisfied, diagnostics) = fri_proximity_diagnostics_test_impl(|proof| {
+            proof.fri_round_queries[0].queries[1].proof[0].add_assign(&Fr::one());
+        });
+        assert!(!is_satisfied);
+        let failed : Vec<_> = diagnostics.failed_checks().collect();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].label, diagnostics::INTERMIDIATE_ORACLE_LABEL);
+        assert_eq!(failed[0].layer, 2);
+        assert_eq!(failed[0].query_round, 0);
+    }
+
+    #[test]
+    fn test_fri_proximity_circuit_rejects_unsupported_config() 
+    {
+        use hashes::poseidon::params::DefaultPoseidonParams;
+        use oracles::poseidon_merklee_proof::*;
+        use channel::poseidon_channel::PoseidonChannelGadget;
+        use fri::fri_proximity_circuit::FriProximityCircuit;
+
+        type OG<'a> = PoseidonTreeGadget<'a, Bn256, DefaultPoseidonParams<Fr>>;
+        type TG<'a> = PoseidonChannelGadget<'a, Bn256, DefaultPoseidonParams<Fr>>;
+
+        let params = DefaultPoseidonParams::<Fr>::bn256_default();
+        let fri_params = FriParams {
+            collapsing_factor: 2,
+            R: 2,
+            initial_degree_plus_one: std::cell::Cell::new(256),
+            lde_factor: 4,
+            final_degree_plus_one: std::cell::Cell::new(4),
+        };
+
+        let deep = FriGadgetConfig::<Fr> { deep: true, ..Default::default() };
+        let degree_correction = FriGadgetConfig::<Fr> { degree_correction: true, ..Default::default() };
+
+        for fri_config in vec![deep, degree_correction].into_iter() {
+            let oracle_params = PoseidonTreeGadgetParams::new(1 << fri_params.collapsing_factor, &params);
+            let circuit = FriProximityCircuit::<Bn256, OG, TG, _>::new(
+                &params, oracle_params, fri_params.clone(), fri_config, std::iter::empty(), Fr::zero());
+
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            match circuit.synthesize(&mut cs) {
+                Err(SynthesisError::Unsatisfiable) => {},
+                _ => panic!("the configuration is not enforced by the circuit and should be rejected"),
+            }
+        }
+    }
+
+
+    // parses DEEP-FRI proof (with the single upper layer oracle) produced by the native prover,
+    // replays the transcript and verifies the proof;
+    // the DEEP value of tampered_layer is replaced after the transcript has been fixed
+    fn verify_deep_fri_proof(
+        cs: &mut TestConstraintSystem<Bn256>,
+        stream: Vec<Fr>,
+        commitment: Fr,
+        poseidon_params: &hashes::poseidon::params::DefaultPoseidonParams<Fr>,
+        fri_params: &FriParams,
+        fri_config: &FriGadgetConfig<Fr>,
+        tampered_layer: Option<usize>,
+    ) -> (Option<bool>, diagnostics::FriDiagnostics)
+    {
+        use hashes::poseidon::params::DefaultPoseidonParams;
+        use oracles::poseidon_merklee_proof::*;
+        use channel::ChannelGadget;
+        use channel::poseidon_channel::PoseidonChannelGadget;
+        use fri::BatchedFriProof;
+        use redshift_circuit::circuit::get_deep_fri_challenges;
+        use fri::fri_proximity_circuit::{SingleOracleCombiner, FRI_PROXIMITY_ORACLE_LABEL};
+
+        type OG<'a> = PoseidonTreeGadget<'a, Bn256, DefaultPoseidonParams<Fr>>;
+
+        let upper_layer_commitment = AllocatedNum::alloc_input(cs.namespace(|| "upper layer commitment"), || Ok(commitment)).unwrap();
+        let labels = [FRI_PROXIMITY_ORACLE_LABEL];
+        let mut proof = BatchedFriProof::<Bn256, OG>::from_stream(
+            cs.namespace(|| "proof"), 
+            &mut stream.into_iter().map(|x| Some(x)), 
+            (fri_params.clone(), fri_config.clone(), &labels[..]),
+        ).unwrap();
+
+        let mut channel = PoseidonChannelGadget::<Bn256, DefaultPoseidonParams<Fr>>::new(poseidon_params);
+        channel.consume(upper_layer_commitment.clone(), cs.namespace(|| "consume upper layer commitment")).unwrap();
+        let (fri_challenges, deep_points) = get_deep_fri_challenges(cs, &proof, &mut channel).unwrap();
+
+        for coef in proof.final_coefficients.iter().cloned() {
+            channel.consume(coef, cs.namespace(|| "consume final coefficient")).unwrap();
+        }
+        let natural_first_element_indexes = (0..fri_params.R).map(|_| {
+            let packed = channel.produce_challenge(cs.namespace(|| "produce query index")).unwrap();
+            let mut bits = packed.into_bits_le(cs.namespace(|| "decompose query index")).unwrap();
+            bits.truncate(64);
+            bits
+        }).collect();
+
+        if let Some(layer) = tampered_layer {
+            let mut value = proof.deep_values[layer].get_value().unwrap();
+            value.add_assign(&Fr::one());
+            proof.deep_values[layer] = AllocatedNum::alloc2(cs.namespace(|| "tampered DEEP value"), Some(value)).unwrap();
+        }
+
+        let oracle_params = PoseidonTreeGadgetParams::new(1 << fri_params.collapsing_factor, poseidon_params);
+
+        let fri_verifier_gadget = FriVerifierGadget::<Bn256, OG, _> {
+            collapsing_factor : fri_params.collapsing_factor as usize,
+            num_query_rounds : fri_params.R,
+            initial_degree_plus_one : fri_params.initial_degree_plus_one.get(),
+            lde_factor: fri_params.lde_factor,
+            final_degree_plus_one : fri_params.final_degree_plus_one.get(),
+            upper_layer_combiner: SingleOracleCombiner { label: FRI_PROXIMITY_ORACLE_LABEL },
+            config: fri_config.clone(),
+
+            _engine_marker : std::marker::PhantomData::<Bn256>,
+            _oracle_marker : std::marker::PhantomData::<OG>,
+            _field_marker : std::marker::PhantomData::<(AllocatedNum<Bn256>, AllocatedNum<Bn256>)>,
+        };
+
+        let (is_valid, diagnostics) = fri_verifier_gadget.verify_deep_proof_with_diagnostics(
+            cs.namespace(|| "DEEP-FRI verification"),
+            &oracle_params,
+            &[Labeled::new(FRI_PROXIMITY_ORACLE_LABEL, upper_layer_commitment)],
+            &proof.commitments,
+            &proof.final_coefficients,
+            &fri_challenges,
+            &deep_points,
+            &proof.deep_values,
+            natural_first_element_indexes,
+            &proof.fri_round_queries,
+        ).unwrap();
+
+        (is_valid.get_value(), diagnostics)
+    }
+
+
+    fn deep_fri_round_trip_test_impl(final_layer: FriFinalLayer) 
+    {
+        use hashes::poseidon::Poseidon;
+        use hashes::poseidon::params::DefaultPoseidonParams;
+        use channel::sponge_channel::NativeSpongeChannel;
+        use fri::native_prover::prove_fri;
+        use rand::*;
+
+        type S = Poseidon<Fr, DefaultPoseidonParams<Fr>>;
+
+        const SIZE: usize = 256;
+        let params = DefaultPoseidonParams::<Fr>::bn256_default();
+        let fri_params = FriParams {
+            collapsing_factor: 2,
+            R: 2,
+            initial_degree_plus_one: std::cell::Cell::new(SIZE),
+            lde_factor: 4,
+            final_degree_plus_one: std::cell::Cell::new(4),
+        };
+        let fri_config = FriGadgetConfig { coset_factor: Fr::multiplicative_generator(), deep: true, final_layer, ..Default::default() };
+
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let coeffs : Vec<Fr> = (0..SIZE).map(|_| rng.gen()).collect();
+
+        let mut channel = NativeSpongeChannel::<Fr, S, _>::new(&params);
+        let proof = prove_fri::<_, _, _, S, _, _>(&coeffs, &fri_params, &fri_config, &params, true, &mut channel);
+        
+        // three foldings: the upper layer, two intermidiate ones and the final layer
+        assert_eq!(proof.commitments.len(), 2);
+        assert_eq!(proof.deep_values.len(), 3);
+        let num_iters = proof.deep_values.len();
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let (is_valid, diagnostics) = verify_deep_fri_proof(
+            &mut cs, proof.to_stream(), proof.upper_layer_commitment, &params, &fri_params, &fri_config, None);
+        assert!(cs.is_satisfied());
+        assert_eq!(is_valid, Some(true));
+        assert!(diagnostics.is_ok());
+
+        // the DEEP value is absorbed into transcript, so all the subsequent challenges and query indexes change
+        let mut stream = proof.to_stream();
+        stream[proof.commitments.len() + 1].add_assign(&Fr::one());
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let (is_valid, _) = verify_deep_fri_proof(
+            &mut cs, stream, proof.upper_layer_commitment, &params, &fri_params, &fri_config, None);
+        assert_eq!(is_valid, Some(false));
+
+        // even with the same transcript the wrong DEEP value of layer i breaks the consistency of the next layer
+        for layer in 0..num_iters {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let (is_valid, diagnostics) = verify_deep_fri_proof(
+                &mut cs, proof.to_stream(), proof.upper_layer_commitment, &params, &fri_params, &fri_config, Some(layer));
+            assert_eq!(is_valid, Some(false));
+
+            let expected_label = match layer + 1 == num_iters {
+                true => diagnostics::FINAL_CONSISTENCY_CHECK_LABEL,
+                false => diagnostics::ROUND_CONSISTENCY_CHECK_LABEL,
+            };
+            let failed : Vec<_> = diagnostics.failed_checks().collect();
+            assert_eq!(failed.len(), fri_params.R);
+            for (query_round, record) in failed.into_iter().enumerate() {
+                assert_eq!(record.label, expected_label);
+                assert_eq!(record.layer, layer + 1);
+                assert_eq!(record.query_round, query_round);
+            }
+        }
+    }
+
+    #[test]
+    fn test_deep_fri_round_trip() 
+    {
+        deep_fri_round_trip_test_impl(FriFinalLayer::Coefficients);
+        deep_fri_round_trip_test_impl(FriFinalLayer::Evaluations);
+    }
+
+
+    #[test]
+    fn test_fri_utils_layer_eval_points() 
+    {
+        use fri::fri_utils::FriUtilsGadget;
+        use rand::*;
+
+        const LOG_DOMAIN_SIZE: usize = 8;
+        const COLLAPSING_FACTOR: usize = 2;
+
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let coset_factor = Fr::multiplicative_generator();
+        let mut fri_helper = FriUtilsGadget::<Bn256>::new(
+            cs.namespace(|| "fri utils"), 
+            1 << LOG_DOMAIN_SIZE, 
+            COLLAPSING_FACTOR, 
+            2, 
+            coset_factor,
+        );
+
+        let coset_idx : Vec<Boolean> = (0..LOG_DOMAIN_SIZE - COLLAPSING_FACTOR).map(|i| {
+            let bit = AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(rng.gen())).unwrap();
+            Boolean::from(bit)
+        }).collect();
+
+        // on the topmost layer the points should coincide with the ones used by the combiner
+        let combiner_points = fri_helper.get_combiner_eval_points(cs.namespace(|| "combiner points"), coset_idx.iter()).unwrap();
+        let layer_points = fri_helper.get_cur_layer_eval_points(cs.namespace(|| "layer points"), coset_idx.iter()).unwrap();
+        for (a, b) in combiner_points.iter().zip(layer_points.iter()) {
+            assert_eq!(a.get_value().unwrap(), b.get_value().unwrap());
+        }
+
+        // on the next layer all points should lie in the same coset coset_factor * H'
+        fri_helper.next_domain(cs.namespace(|| "next domain"));
+        let (next_coset_idx, _) = fri_helper.get_next_layer_coset_idx_extended(&coset_idx[..]);
+        let layer_points : Vec<AllocatedNum<Bn256>> = 
+            fri_helper.get_cur_layer_eval_points(cs.namespace(|| "next layer points"), next_coset_idx.iter()).unwrap();
+
+        let domain_size = 1u64 << (LOG_DOMAIN_SIZE - COLLAPSING_FACTOR);
+        let shift = coset_factor.pow([domain_size]);
+        
+        for (i, point) in layer_points.iter().enumerate() {
+            let point = point.get_value().unwrap();
+            assert_eq!(point.pow([domain_size]), shift);
+            for other in layer_points.iter().skip(i + 1) {
+                assert!(other.get_value().unwrap() != point);
+            }
+        }
+
+        assert!(cs.is_satisfied());
+    }
+
+
+    fn fri_utils_folding_test_impl<F>() 
+    where F: common::field_gadget::FieldGadget<Bn256>, F::Field: common::field_gadget::TwoAdicField
+    {
+        use fri::fri_utils::FriUtilsGadget;
+        use rand::*;
+
+        const LOG_DOMAIN_SIZE: usize = 6;
+        const COLLAPSING_FACTOR: usize = 2;
+
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        // folding of the whole coset of the polynomial f of degree < 2^collapsing_factor
+        // with challenges (alpha, alpha^2) results in f(alpha) (if LDE is not shifted)
+        let mut fri_helper = FriUtilsGadget::<Bn256, F>::new(
+            cs.namespace(|| "fri utils"), 
+            1 << LOG_DOMAIN_SIZE, 
+            COLLAPSING_FACTOR, 
+            1, 
+            F::Field::one(),
+        );
+
+        let coset_idx : Vec<Boolean> = (0..LOG_DOMAIN_SIZE - COLLAPSING_FACTOR).map(|i| {
+            let bit = AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(rng.gen())).unwrap();
+            Boolean::from(bit)
+        }).collect();
+
+        let coefficients : Vec<F::Field> = (0..(1 << COLLAPSING_FACTOR)).map(|_| rng.gen()).collect();
+        let evaluate = |x: F::Field| {
+            let mut acc = F::Field::zero();
+            for c in coefficients.iter().rev() {
+                acc.mul_assign(&x);
+                acc.add_assign(c);
+            }
+            acc
+        };
+
+        let points = fri_helper.get_combiner_eval_points(cs.namespace(|| "points"), coset_idx.iter()).unwrap();
+        let values : Vec<F> = points.iter().map(|x| {
+            F::alloc(cs.namespace(|| "value"), Some(evaluate(x.get_value().unwrap())))
+        }).collect::<Result<_, _>>().unwrap();
+
+        let alpha_value : F::Field = rng.gen();
+        let alpha = F::alloc(cs.namespace(|| "alpha"), Some(alpha_value)).unwrap();
+        let alpha_squared = alpha.square(cs.namespace(|| "alpha squared")).unwrap();
+
+        let res = fri_helper.coset_interpolation_value(
+            cs.namespace(|| "folding"), 
+            &values[..], 
+            coset_idx.iter(), 
+            &[alpha, alpha_squared],
+        ).unwrap();
+
+        assert_eq!(res.get_value().unwrap(), evaluate(alpha_value));
+        assert!(cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_fri_utils_folding() 
+    {
+        use common::goldilocks::*;
+        use common::extension_field::*;
+
+        fri_utils_folding_test_impl::<AllocatedNum<Bn256>>();
+        fri_utils_folding_test_impl::<GoldilocksNum<Bn256>>();
+        fri_utils_folding_test_impl::<ExtensionFieldGadget<Bn256, GoldilocksNum<Bn256>, GoldilocksExt2Params>>();
+    }
+
+
+    // FRI over Goldilocks, where all the layers except the upper one are over the extension:
+    // the transcript is the native Poseidon sponge, extension field challenges are assembled from native ones
+    fn extension_fri_round_trip_test_impl<P: common::extension_field::ExtensionParams<common::goldilocks::Goldilocks>>(
+        deep: bool,
+        tamper: bool,
+    ) -> Option<bool>
+    {
+        use common::goldilocks::*;
+        use common::extension_field::*;
+        use common::field_gadget::FieldGadget;
+        use hashes::poseidon::Poseidon;
+        use hashes::poseidon::params::DefaultPoseidonParams;
+        use oracles::Query;
+        use oracles::poseidon_merklee_proof::*;
+        use channel::poseidon_channel::PoseidonChannelGadget;
+        use channel::sponge_channel::NativeSpongeChannel;
+        use channel::field_channel::FieldChannelGadget;
+        use channel::ChannelGadget;
+        use fri::native_prover::*;
+        use fri::fri_proximity_circuit::{SingleOracleCombiner, FRI_PROXIMITY_ORACLE_LABEL};
+        use rand::*;
+
+        type S = Poseidon<Fr, DefaultPoseidonParams<Fr>>;
+        type OG<'a> = PoseidonTreeGadget<'a, Bn256, DefaultPoseidonParams<Fr>>;
+        type TG<'a> = FieldChannelGadget<Bn256, PoseidonChannelGadget<'a, Bn256, DefaultPoseidonParams<Fr>>>;
+        type F<P> = ExtensionFieldGadget<Bn256, GoldilocksNum<Bn256>, P>;
+
+        const SIZE: usize = 64;
+        let params = DefaultPoseidonParams::<Fr>::bn256_default();
+        let fri_params = FriParams {
+            collapsing_factor: 2,
+            R: 2,
+            initial_degree_plus_one: std::cell::Cell::new(SIZE),
+            lde_factor: 4,
+            final_degree_plus_one: std::cell::Cell::new(4),
+        };
+        let fri_config = FriGadgetConfig::<ExtensionField<Goldilocks, P>> { deep, ..Default::default() };
+
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let coeffs : Vec<Goldilocks> = (0..SIZE).map(|_| rng.gen()).collect();
+
+        let mut channel = NativeSpongeChannel::<Fr, S, _>::new(&params);
+        let mut proof = prove_fri::<_, _, _, S, _, _>(&coeffs, &fri_params, &fri_config, &params, true, &mut channel);
+        if tamper {
+            proof.final_coefficients[0].add_assign(&ExtensionField::one());
+        }
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let alloc_native = |cs: &mut TestConstraintSystem<Bn256>, values: &[Fr]| -> Vec<AllocatedNum<Bn256>> {
+            values.iter().map(|x| AllocatedNum::alloc2(cs.namespace(|| "native element"), Some(*x)).unwrap()).collect()
+        };
+        let alloc_ext = |cs: &mut TestConstraintSystem<Bn256>, values: &[ExtensionField<Goldilocks, P>]| -> Vec<F<P>> {
+            values.iter().map(|x| F::<P>::alloc(cs.namespace(|| "extension element"), Some(*x)).unwrap()).collect()
+        };
+
+        let upper_layer_commitment = alloc_native(&mut cs, &[proof.upper_layer_commitment]).pop().unwrap();
+        let commitments = alloc_native(&mut cs, &proof.commitments);
+        let final_coefficients = alloc_ext(&mut cs, &proof.final_coefficients);
+        let deep_values = alloc_ext(&mut cs, &proof.deep_values);
+
+        // replay the transcript of prove_fri
+        let mut transcript = TG::new(&params);
+        transcript.consume(upper_layer_commitment.clone(), cs.namespace(|| "consume upper layer commitment")).unwrap();
+
+        let mut fri_challenges = vec![];
+        let mut deep_points = vec![];
+        for layer in 0..(commitments.len() + 1) {
+            if layer > 0 {
+                transcript.consume(commitments[layer - 1].clone(), cs.namespace(|| "consume commitment")).unwrap();
+            }
+            if deep {
+                let deep_point : F<P> = transcript.produce_field_challenge(cs.namespace(|| "produce deep point")).unwrap();
+                transcript.consume_elem(&deep_values[layer], cs.namespace(|| "consume deep value")).unwrap();
+                deep_points.push(deep_point);
+            }
+            let challenge : F<P> = transcript.produce_field_challenge(cs.namespace(|| "produce fri challenge")).unwrap();
+            fri_challenges.push(challenge);
+        }
+
+        for coef in final_coefficients.iter() {
+            transcript.consume_elem(coef, cs.namespace(|| "consume final coefficient")).unwrap();
+        }
+
+        let natural_first_element_indexes : Vec<Vec<Boolean>> = (0..fri_params.R).map(|_| {
+            let packed = transcript.produce_challenge(cs.namespace(|| "produce query index")).unwrap();
+            let mut bits = packed.into_bits_le(cs.namespace(|| "decompose query index")).unwrap();
+            bits.truncate(64);
+            bits
+        }).collect();
+
+        // the circuit derives exactly the same challenges as the prover (tampering changes the transcript)
+        let indexes : Vec<u64> = natural_first_element_indexes.iter().map(|bits| {
+            bits.iter().rev().fold(0u64, |acc, bit| (acc << 1) | (bit.get_value().unwrap() as u64))
+        }).collect();
+        assert_eq!(indexes == proof.natural_indexes, !tamper);
+
+        let query_rounds_data = proof.fri_round_queries.iter().map(|round| {
+            let mut alloc_query = |query: &NativeQuery<Fr>| Query::<Bn256, OG> {
+                values: alloc_native(&mut cs, &query.values),
+                proof: alloc_native(&mut cs, &query.proof),
+                _marker: std::marker::PhantomData,
+            };
+
+            FriSingleQueryRoundData {
+                upper_layer_queries: vec![Labeled::new(FRI_PROXIMITY_ORACLE_LABEL, alloc_query(&round.upper_layer_query))],
+                queries: round.queries.iter().map(|query| alloc_query(query)).collect(),
+            }
+        }).collect();
+
+        // upper layer leaves contain the elements of the base field
+        let oracle_params = PoseidonTreeGadgetParams::new(1 << fri_params.collapsing_factor, &params);
+
+        let fri_verifier_gadget = FriVerifierGadget::<Bn256, OG, _, GoldilocksNum<Bn256>, F<P>> {
+            collapsing_factor : fri_params.collapsing_factor as usize,
+            num_query_rounds : fri_params.R,
+            initial_degree_plus_one : fri_params.initial_degree_plus_one.get(),
+            lde_factor: fri_params.lde_factor,
+            final_degree_plus_one : fri_params.final_degree_plus_one.get(),
+            upper_layer_combiner: SingleOracleCombiner { label: FRI_PROXIMITY_ORACLE_LABEL },
+            config: fri_config,
+
+            _engine_marker : std::marker::PhantomData::<Bn256>,
+            _oracle_marker : std::marker::PhantomData::<OG>,
+            _field_marker : std::marker::PhantomData::<(GoldilocksNum<Bn256>, F<P>)>,
+        };
+
+        let upper_layer_commitments = [Labeled::new(FRI_PROXIMITY_ORACLE_LABEL, upper_layer_commitment)];
+        let is_valid = match deep {
+            true => fri_verifier_gadget.verify_deep_proof(
+                cs.namespace(|| "DEEP-FRI verification"),
+                &oracle_params,
+                &upper_layer_commitments,
+                &commitments,
+                &final_coefficients,
+                &fri_challenges,
+                &deep_points,
+                &deep_values,
+                natural_first_element_indexes,
+                &query_rounds_data,
+            ),
+            false => fri_verifier_gadget.verify_proof(
+                cs.namespace(|| "FRI verification"),
+                &oracle_params,
+                &upper_layer_commitments,
+                &commitments,
+                &final_coefficients,
+                &fri_challenges,
+                natural_first_element_indexes,
+                &query_rounds_data,
+            ),
+        }.unwrap();
+
+        assert!(cs.is_satisfied());
+        is_valid.get_value()
+    }
+
+    #[test]
+    fn test_extension_fri_round_trip() 
+    {
+        use common::extension_field::*;
+
+        for deep in vec![false, true] {
+            assert_eq!(extension_fri_round_trip_test_impl::<GoldilocksExt2Params>(deep, false), Some(true));
+            assert_eq!(extension_fri_round_trip_test_impl::<GoldilocksExt3Params>(deep, false), Some(true));
+            assert_eq!(extension_fri_round_trip_test_impl::<GoldilocksExt2Params>(deep, true), Some(false));
+            assert_eq!(extension_fri_round_trip_test_impl::<GoldilocksExt3Params>(deep, true), Some(false));
+        }
+    }
+
+    #[test]
+    fn test_extension_embedding_is_free() 
+    {
+        use common::goldilocks::*;
+        use common::extension_field::*;
+        use common::field_gadget::*;
+
+        type Ext3 = ExtensionFieldGadget<Bn256, GoldilocksNum<Bn256>, GoldilocksExt3Params>;
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let x = GoldilocksNum::alloc(cs.namespace(|| "x"), Some(Goldilocks::from_u64(42))).unwrap();
+
+        let num_constraints = cs.num_constraints();
+        let y = Ext3::from_base(cs.namespace(|| "embed"), &x).unwrap();
+        assert_eq!(cs.num_constraints(), num_constraints);
+        assert!(y.get_coeffs().iter().skip(1).all(|c| c.is_none()));
+
+        // multiplication by the embedded element costs as much as multiplication by the base one
+        let z = Ext3::alloc(cs.namespace(|| "z"), Some(GoldilocksExt3::from_coeffs(&[Goldilocks::from_u64(3); 3]))).unwrap();
+        let num_constraints = cs.num_constraints();
+        z.mul(cs.namespace(|| "z * y"), &y).unwrap();
+        let full_mul_constraints = cs.num_constraints() - num_constraints;
+
+        let num_constraints = cs.num_constraints();
+        z.mul_by_base(cs.namespace(|| "z * x"), &x).unwrap();
+        assert_eq!(full_mul_constraints, cs.num_constraints() - num_constraints);
+        assert!(cs.is_satisfied());
+    }
+}
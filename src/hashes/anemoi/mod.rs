@@ -0,0 +1,321 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+};
+use bellman::pairing::bn256;
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+};
+
+use common::num::*;
+use super::sponge::{SpongePermutation, SpongePermutationGadget, DuplexSpongeGadget, DuplexSponge};
+use super::rescue::alpha_sbox::{constrain_pow, inverse_alpha};
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub(crate) static ANEMOI_PERMUTATIONS_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+
+// the first and the second hundred of decimal digits of pi, are used to derive the round constants
+const PI_0: &'static str = "1415926535897932384626433832795028841971693993751058209749445923078164062862089986280348253421170679";
+const PI_1: &'static str = "8214808651328230664709384460955058223172535940812848111745028410270193852110555964462294895493038196";
+
+
+// Anemoi permutation over the state (x, y) of two elements (l = 1), every round is:
+// add constants -> linear layer -> Flystel S-box, and the linear layer is applied once more after the last round.
+// For l = 1 the matrices of the linear layer are trivial, so it is just the Pseudo-Hadamard transform (y += x, x += y).
+// open Flystel with Q(z) = g * z^2:
+// x -= g * y^2, y -= x^{1/alpha}, x += g * y^2 + g^{-1}
+pub struct AnemoiParams<F: PrimeField> {
+    num_rounds: usize,
+    alpha: u64,
+    alpha_inv: Vec<u64>,
+    // the smallest generator of the multiplicative group
+    g: F,
+    g_inv: F,
+    round_constants_x: Vec<F>,
+    round_constants_y: Vec<F>,
+}
+
+impl<F: PrimeField> AnemoiParams<F> {
+    pub fn new(num_rounds: usize, alpha: u64, g: F) -> Self {
+        let g_inv = g.inverse().expect("generator is nonzero");
+        let pi_0 = F::from_str(PI_0).expect("is a valid field element");
+        let pi_1 = F::from_str(PI_1).expect("is a valid field element");
+
+        let mut round_constants_x = Vec::with_capacity(num_rounds);
+        let mut round_constants_y = Vec::with_capacity(num_rounds);
+
+        // single column (l = 1): C_r = g * (pi_0^r)^2 + (pi_0^r + pi_1^0)^alpha,
+        // D_r = g * (pi_1^0)^2 + (pi_0^r + pi_1^0)^alpha + g^{-1}
+        let pi_1_col = pi_1.pow(&[0u64]);
+        for r in 0..num_rounds {
+            let pi_0_r = pi_0.pow(&[r as u64]);
+            let mut sum = pi_0_r;
+            sum.add_assign(&pi_1_col);
+            let pow_alpha = sum.pow(&[alpha]);
+
+            let mut c = pi_0_r;
+            c.square();
+            c.mul_assign(&g);
+            c.add_assign(&pow_alpha);
+
+            let mut d = pi_1_col;
+            d.square();
+            d.mul_assign(&g);
+            d.add_assign(&pow_alpha);
+            d.add_assign(&g_inv);
+
+            round_constants_x.push(c);
+            round_constants_y.push(d);
+        }
+
+        AnemoiParams {
+            num_rounds,
+            alpha,
+            alpha_inv: inverse_alpha::<F>(alpha),
+            g,
+            g_inv,
+            round_constants_x,
+            round_constants_y,
+        }
+    }
+}
+
+impl AnemoiParams<bn256::Fr> {
+    // alpha = 5 (3 divides p - 1), g = 5, 21 rounds for 128 bits of security
+    pub fn bn256_default() -> Self {
+        Self::new(21, 5, bn256::Fr::from_str("5").expect("is a valid field element"))
+    }
+}
+
+
+fn add_constant<E: Engine, CS: ConstraintSystem<E>>(num: &Num<E>, c: &E::Fr, cs: &CS) -> Num<E> {
+    let mut res = num.clone();
+    res.add_assign(&Num::from_constant(c, cs));
+    res
+}
+
+fn add_scaled<E: Engine>(num: &Num<E>, other: &Num<E>, coef: E::Fr) -> Num<E> {
+    let mut scaled = other.clone();
+    scaled.scale(coef);
+    let mut res = num.clone();
+    res.add_assign(&scaled);
+    res
+}
+
+// for l = 1 both M_x and M_y are the identity, so only the Pseudo-Hadamard transform is left
+fn linear_layer<E: Engine>(x: &mut Num<E>, y: &mut Num<E>) {
+    y.add_assign(x);
+    x.add_assign(y);
+}
+
+// costs 5 constraints for alpha = 5: y^2, (x - g * y^2)^{1/alpha} (checked by the chain of 3 multiplications) and v^2.
+// Both outputs are allocated by these constraints without extra cost: v is witnessed and the chain checks (y - v)^alpha = t,
+// and u is the result of g * v * v = u - t - g^{-1}
+fn flystel<E: Engine, CS: ConstraintSystem<E>>(
+    mut cs: CS,
+    x: &Num<E>,
+    y: &Num<E>,
+    params: &AnemoiParams<E::Fr>,
+) -> Result<(Num<E>, Num<E>), SynthesisError> {
+
+    let mut minus_g = params.g;
+    minus_g.negate();
+
+    let y_sq : Num<E> = Num::mul(cs.namespace(|| "y^2"), y, y)?.into();
+    let t = add_scaled(x, &y_sq, minus_g);
+
+    let w_value = t.get_value().map(|t| t.pow(&params.alpha_inv));
+    let v_value = match (y.get_value(), w_value) {
+        (Some(mut y), Some(w)) => {
+            y.sub_assign(&w);
+            Some(y)
+        },
+        _ => None,
+    };
+    let v = AllocatedNum::alloc(cs.namespace(|| "v"), || v_value.ok_or(SynthesisError::AssignmentMissing))?;
+    let w_lc = y.get_lc().clone() - v.get_variable();
+    constrain_pow(&mut cs, &w_lc, t.get_lc(), w_value, params.alpha)?;
+
+    let u_value = match (t.get_value(), v.get_value()) {
+        (Some(t), Some(v)) => {
+            let mut res = v;
+            res.square();
+            res.mul_assign(&params.g);
+            res.add_assign(&t);
+            res.add_assign(&params.g_inv);
+            Some(res)
+        },
+        _ => None,
+    };
+    let u = AllocatedNum::alloc(cs.namespace(|| "u"), || u_value.ok_or(SynthesisError::AssignmentMissing))?;
+
+    cs.enforce(
+        || "v^2",
+        |lc| lc + v.get_variable(),
+        |lc| lc + (params.g, v.get_variable()),
+        |lc| lc + u.get_variable() - t.get_lc() - (params.g_inv, CS::one()),
+    );
+
+    Ok((u.into(), v.into()))
+}
+
+fn anemoi_permutation<E: Engine, CS: ConstraintSystem<E>>(
+    mut cs: CS,
+    state: &mut [Num<E>],
+    params: &AnemoiParams<E::Fr>,
+) -> Result<(), SynthesisError> {
+
+    let mut x = state[0].clone();
+    let mut y = state[1].clone();
+
+    for round in 0..params.num_rounds {
+        let cs = &mut cs.namespace(|| format!("anemoi round {}", round));
+
+        x = add_constant(&x, &params.round_constants_x[round], &cs);
+        y = add_constant(&y, &params.round_constants_y[round], &cs);
+        linear_layer(&mut x, &mut y);
+
+        // the state is allocated once per round by Flystel, so the outputs of the linear layer
+        // are combinations of two variables (otherwise they grow exponentially from round to round)
+        let (u, v) = flystel(cs.namespace(|| "flystel"), &x, &y, params)?;
+        x = u;
+        y = v;
+    }
+    linear_layer(&mut x, &mut y);
+
+    state[0] = x;
+    state[1] = y;
+
+    Ok(())
+}
+
+
+// sponge with rate 1 and capacity 1, padding constant is one
+pub type AnemoiGadget<E> = DuplexSpongeGadget<E, AnemoiParams<<E as Engine>::Fr>>;
+
+impl<F: PrimeField> SpongePermutation<F> for AnemoiParams<F> {
+    fn state_width(&self) -> usize {
+        2
+    }
+
+    fn sponge_rate(&self) -> usize {
+        1
+    }
+
+    fn sponge_padding(&self) -> F {
+        F::one()
+    }
+
+    fn permute_native(&self, state: &mut [F]) {
+        anemoi_permutation_native(state, self)
+    }
+}
+
+impl<E: Engine> SpongePermutationGadget<E> for AnemoiParams<E::Fr> {
+    fn permute<CS: ConstraintSystem<E>>(&self, cs: CS, state: &mut [Num<E>]) -> Result<(), SynthesisError> {
+        ANEMOI_PERMUTATIONS_COUNT.fetch_add(1, Ordering::SeqCst);
+        anemoi_permutation(cs, state, self)
+    }
+}
+
+
+// native counterpart of the gadget: is used by prover and for test vectors generation
+pub fn anemoi_permutation_native<F: PrimeField>(state: &mut [F], params: &AnemoiParams<F>) {
+    let linear_layer = |x: &mut F, y: &mut F| {
+        y.add_assign(x);
+        x.add_assign(y);
+    };
+
+    let (mut x, mut y) = (state[0], state[1]);
+
+    for round in 0..params.num_rounds {
+        x.add_assign(&params.round_constants_x[round]);
+        y.add_assign(&params.round_constants_y[round]);
+        linear_layer(&mut x, &mut y);
+
+        let mut g_y_sq = y;
+        g_y_sq.square();
+        g_y_sq.mul_assign(&params.g);
+        x.sub_assign(&g_y_sq);
+
+        y.sub_assign(&x.pow(&params.alpha_inv));
+
+        let mut g_y_sq = y;
+        g_y_sq.square();
+        g_y_sq.mul_assign(&params.g);
+        x.add_assign(&g_y_sq);
+        x.add_assign(&params.g_inv);
+    }
+    linear_layer(&mut x, &mut y);
+
+    state[0] = x;
+    state[1] = y;
+}
+
+pub type Anemoi<F> = DuplexSponge<F, AnemoiParams<F>>;
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use crate::tester::naming_oblivious_cs::NamingObliviousConstraintSystem as TestConstraintSystem;
+
+    fn fr(s: &str) -> Fr {
+        Fr::from_str(s).unwrap()
+    }
+
+    // computed with the standalone transcription of the reference anemoi.py for l = 1
+    // (constants from pi, identity M_x and M_y followed by PHT, open Flystel), independent of this code
+    #[test]
+    fn test_anemoi_permutation_vectors() {
+        let params = AnemoiParams::<Fr>::bn256_default();
+        let mut minus_one = Fr::one();
+        minus_one.negate();
+
+        let vectors = vec![
+            ((Fr::zero(), Fr::zero()), (
+                "12781024641116938412396488495458428946694972335198159367692885078458658344185",
+                "9211078655966643530904274854247924841832576083874876104173103080297910456399",
+            )),
+            ((fr("1"), fr("2")), (
+                "13159015257597654124084711183921741004498498465173973984598584928673299873404",
+                "19741981498793398797947742327243936021190260438972654994596272906296254350834",
+            )),
+            ((minus_one, fr("7")), (
+                "4748710116434999593155043146102909992357423227973528531636368882330448233315",
+                "15481067622592314602367627840734379877268907578205467369672712129762908197763",
+            )),
+        ];
+
+        for ((x, y), (expected_x, expected_y)) in vectors.into_iter() {
+            let mut state = [x, y];
+            anemoi_permutation_native(&mut state, &params);
+            assert_eq!(state, [fr(expected_x), fr(expected_y)]);
+
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let mut state = [x, y].iter().map(|e| {
+                Num::from(AllocatedNum::alloc2(cs.namespace(|| "input"), Some(*e)).unwrap())
+            }).collect::<Vec<_>>();
+            anemoi_permutation(cs.namespace(|| "permutation"), &mut state, &params).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(state[0].get_value(), Some(fr(expected_x)));
+            assert_eq!(state[1].get_value(), Some(fr(expected_y)));
+            // 5 constraints per round, the linear layer is free
+            assert_eq!(cs.num_constraints(), 5 * 21);
+            // and the outputs are short combinations of the variables allocated by the last Flystel
+            assert!(state[0].get_lc().as_ref().len() <= 4);
+            assert!(state[1].get_lc().as_ref().len() <= 4);
+        }
+    }
+}
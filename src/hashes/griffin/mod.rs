@@ -0,0 +1,244 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr,
+};
+use bellman::pairing::bn256;
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+};
+
+use common::num::*;
+use common::blake2s::blake2s_native;
+use common::digest::decode_field_element;
+use super::sponge::{SpongePermutation, SpongePermutationGadget, DuplexSpongeGadget, DuplexSponge};
+use super::rescue::alpha_sbox::{sbox_alpha, sbox_inalpha, inverse_alpha};
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub(crate) static GRIFFIN_PERMUTATIONS_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+
+const GRIFFIN_PERSONALIZATION: &'static [u8; 8] = b"Griffin_";
+
+
+// Griffin permutation of width 3 (rate 2, capacity 1): the initial linear layer is followed by the rounds of
+// nonlinear layer -> linear layer -> round constants (the last round has no constants).
+// nonlinear layer: y_0 = x_0^{1/d}, y_1 = x_1^d, y_2 = x_2 * (L^2 + alpha_2 * L + beta_2), where L = y_0 + y_1
+// and alpha_2^2 - 4 * beta_2 is a quadratic non-residue (so the last factor never vanishes);
+// linear layer is the circulant matrix circ(2, 1, 1).
+// The round constants, alpha_2 and beta_2 are derived from the seed with blake2s instead of the SHAKE-based generator
+// of the reference implementation, so the outputs don't match other Griffin instances and there are no reference vectors
+pub struct GriffinParams<F: PrimeField> {
+    num_rounds: usize,
+    d: u64,
+    d_inv: Vec<u64>,
+    alpha_2: F,
+    beta_2: F,
+    round_constants: Vec<Vec<F>>,
+}
+
+impl<F: PrimeField> GriffinParams<F> {
+    fn hash_to_field(seed: &[u8], counter: u32) -> F {
+        let mut input = seed.to_vec();
+        input.extend((0..4).map(|i| (counter >> (8 * i)) as u8));
+
+        let mut digest = blake2s_native(&input, GRIFFIN_PERSONALIZATION);
+        let num_zero_bits = 256 - F::CAPACITY as usize;
+        for i in 0..num_zero_bits {
+            digest[i / 8] &= !(0x80u8 >> (i % 8));
+        }
+        decode_field_element(&digest)
+    }
+
+    fn is_quadratic_nonresidue(x: &F) -> bool {
+        // Euler's criterion: x^{(p - 1) / 2} = -1
+        let mut exp = F::char();
+        exp.div2();
+        let mut minus_one = F::one();
+        minus_one.negate();
+
+        x.pow(exp.as_ref()) == minus_one
+    }
+
+    pub fn new(num_rounds: usize, d: u64, seed: &[u8]) -> Self {
+        let mut counter = 0;
+        let mut next = || {
+            counter += 1;
+            Self::hash_to_field(seed, counter)
+        };
+
+        // round constants are derived first, alpha_2 and beta_2 are sampled until the discriminant is a non-residue
+        let round_constants : Vec<Vec<F>> = (0..(num_rounds - 1)).map(|_| (0..3).map(|_| next()).collect()).collect();
+
+        let (alpha_2, beta_2) = loop {
+            let alpha_2 = next();
+            let beta_2 = next();
+
+            let mut discriminant = alpha_2;
+            discriminant.square();
+            let mut four_beta = beta_2;
+            four_beta.double();
+            four_beta.double();
+            discriminant.sub_assign(&four_beta);
+
+            if Self::is_quadratic_nonresidue(&discriminant) {
+                break (alpha_2, beta_2);
+            }
+        };
+
+        GriffinParams {
+            num_rounds,
+            d,
+            d_inv: inverse_alpha::<F>(d),
+            alpha_2,
+            beta_2,
+            round_constants,
+        }
+    }
+}
+
+impl GriffinParams<bn256::Fr> {
+    // width 3, d = 5: 12 rounds give 128 bits of security for BN256
+    pub fn bn256_default() -> Self {
+        Self::new(12, 5, b"BN256 Griffin t = 3")
+    }
+}
+
+
+// circ(2, 1, 1): every output is the sum of all the elements plus the element itself
+fn linear_layer<E: Engine>(state: &mut [Num<E>]) {
+    let mut sum = Num::zero();
+    for elem in state.iter() {
+        sum.add_assign(elem);
+    }
+    for elem in state.iter_mut() {
+        elem.add_assign(&sum);
+    }
+}
+
+// costs 8 constraints for d = 5: 3 for each power map, L^2 and the final multiplication
+fn nonlinear_layer<E: Engine, CS: ConstraintSystem<E>>(
+    mut cs: CS,
+    state: &mut [Num<E>],
+    params: &GriffinParams<E::Fr>,
+) -> Result<(), SynthesisError> {
+
+    state[0] = sbox_inalpha(cs.namespace(|| "inverse power"), &state[0], params.d)?;
+    state[1] = sbox_alpha(cs.namespace(|| "power"), &state[1], params.d)?;
+
+    let mut l = state[0].clone();
+    l.add_assign(&state[1]);
+
+    let l_sq : Num<E> = Num::mul(cs.namespace(|| "L^2"), &l, &l)?.into();
+    let mut factor = l_sq;
+    l.scale(params.alpha_2);
+    factor.add_assign(&l);
+    factor.add_assign(&Num::from_constant(&params.beta_2, &cs));
+
+    state[2] = Num::mul(cs.namespace(|| "x_2 * (L^2 + alpha_2 * L + beta_2)"), &state[2], &factor)?.into();
+
+    Ok(())
+}
+
+fn griffin_permutation<E: Engine, CS: ConstraintSystem<E>>(
+    mut cs: CS,
+    state: &mut [Num<E>],
+    params: &GriffinParams<E::Fr>,
+) -> Result<(), SynthesisError> {
+
+    linear_layer(state);
+
+    for round in 0..params.num_rounds {
+        let cs = &mut cs.namespace(|| format!("griffin round {}", round));
+
+        nonlinear_layer(cs.namespace(|| "nonlinear layer"), state, params)?;
+        linear_layer(state);
+
+        if round < params.num_rounds - 1 {
+            for (elem, c) in state.iter_mut().zip(params.round_constants[round].iter()) {
+                elem.add_assign(&Num::from_constant(c, &cs));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+
+const GRIFFIN_RATE: usize = 2;
+
+// sponge with rate 2 and capacity 1, padding constant is one
+pub type GriffinGadget<E> = DuplexSpongeGadget<E, GriffinParams<<E as Engine>::Fr>>;
+
+impl<F: PrimeField> SpongePermutation<F> for GriffinParams<F> {
+    fn state_width(&self) -> usize {
+        3
+    }
+
+    fn sponge_rate(&self) -> usize {
+        GRIFFIN_RATE
+    }
+
+    fn sponge_padding(&self) -> F {
+        F::one()
+    }
+
+    fn permute_native(&self, state: &mut [F]) {
+        griffin_permutation_native(state, self)
+    }
+}
+
+impl<E: Engine> SpongePermutationGadget<E> for GriffinParams<E::Fr> {
+    fn permute<CS: ConstraintSystem<E>>(&self, cs: CS, state: &mut [Num<E>]) -> Result<(), SynthesisError> {
+        GRIFFIN_PERMUTATIONS_COUNT.fetch_add(1, Ordering::SeqCst);
+        griffin_permutation(cs, state, self)
+    }
+}
+
+
+// native counterpart of the gadget: is used by prover and for test vectors generation
+pub fn griffin_permutation_native<F: PrimeField>(state: &mut [F], params: &GriffinParams<F>) {
+    let linear_layer = |state: &mut [F]| {
+        let mut sum = F::zero();
+        for elem in state.iter() {
+            sum.add_assign(elem);
+        }
+        for elem in state.iter_mut() {
+            elem.add_assign(&sum);
+        }
+    };
+
+    linear_layer(state);
+
+    for round in 0..params.num_rounds {
+        state[0] = state[0].pow(&params.d_inv);
+        state[1] = state[1].pow(&[params.d]);
+
+        let mut l = state[0];
+        l.add_assign(&state[1]);
+        let mut factor = l;
+        factor.square();
+        l.mul_assign(&params.alpha_2);
+        factor.add_assign(&l);
+        factor.add_assign(&params.beta_2);
+        state[2].mul_assign(&factor);
+
+        linear_layer(state);
+
+        if round < params.num_rounds - 1 {
+            for (elem, c) in state.iter_mut().zip(params.round_constants[round].iter()) {
+                elem.add_assign(c);
+            }
+        }
+    }
+}
+
+pub type Griffin<F> = DuplexSponge<F, GriffinParams<F>>;
+
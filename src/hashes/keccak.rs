@@ -0,0 +1,321 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+};
+
+use common::boolean::Boolean;
+use common::digest::{DigestHasher, reverse_bits_in_bytes};
+
+
+const KECCAK_ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001, 0x0000000000008082, 0x800000000000808A, 0x8000000080008000,
+    0x000000000000808B, 0x0000000080000001, 0x8000000080008081, 0x8000000000008009,
+    0x000000000000008A, 0x0000000000000088, 0x0000000080008009, 0x000000008000000A,
+    0x000000008000808B, 0x800000000000008B, 0x8000000000008089, 0x8000000000008003,
+    0x8000000000008002, 0x8000000000000080, 0x000000000000800A, 0x800000008000000A,
+    0x8000000080008081, 0x8000000000008080, 0x0000000080000001, 0x8000000080008008
+];
+
+// rotation offsets of rho step, lane (x, y) is stored at index x + 5 * y
+const KECCAK_ROTATION_OFFSETS: [usize; 25] = [
+    0, 1, 62, 28, 27,
+    36, 44, 6, 55, 20,
+    3, 10, 43, 25, 39,
+    41, 45, 15, 21, 8,
+    18, 2, 61, 56, 14
+];
+
+pub const KECCAK_NUM_ROUNDS: usize = 24;
+pub const KECCAK_LANE_SIZE: usize = 64;
+pub const KECCAK_STATE_SIZE: usize = 25 * KECCAK_LANE_SIZE;
+// rate of keccak256: 1088 bits = 136 bytes
+pub const KECCAK256_RATE: usize = 1088;
+pub const KECCAK256_DIGEST_SIZE: usize = 256;
+
+
+// every lane is represented by 64 bits in little-endian order (as it is in the reference implementation)
+type Lane = Vec<Boolean>;
+
+fn xor_lanes<E, CS>(mut cs: CS, a: &Lane, b: &Lane) -> Result<Lane, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    a.iter().zip(b.iter()).enumerate().map(|(i, (x, y))| {
+        Boolean::xor(cs.namespace(|| format!("xor bit {}", i)), x, y)
+    }).collect()
+}
+
+fn rotl_lane(a: &Lane, by: usize) -> Lane {
+    let by = by % KECCAK_LANE_SIZE;
+    (0..KECCAK_LANE_SIZE).map(|i| a[(i + KECCAK_LANE_SIZE - by) % KECCAK_LANE_SIZE].clone()).collect()
+}
+
+fn keccak_round<E, CS>(mut cs: CS, a: Vec<Lane>, round: usize) -> Result<Vec<Lane>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    // theta
+    let mut c = Vec::with_capacity(5);
+    for x in 0..5 {
+        let cs = &mut cs.namespace(|| format!("theta: column parity {}", x));
+        let mut acc = a[x].clone();
+        for y in 1..5 {
+            acc = xor_lanes(cs.namespace(|| format!("xor row {}", y)), &acc, &a[x + 5 * y])?;
+        }
+        c.push(acc);
+    }
+
+    let mut a_theta = Vec::with_capacity(25);
+    let mut d = Vec::with_capacity(5);
+    for x in 0..5 {
+        d.push(xor_lanes(
+            cs.namespace(|| format!("theta: d {}", x)),
+            &c[(x + 4) % 5],
+            &rotl_lane(&c[(x + 1) % 5], 1)
+        )?);
+    }
+    for (i, lane) in a.iter().enumerate() {
+        a_theta.push(xor_lanes(cs.namespace(|| format!("theta: lane {}", i)), lane, &d[i % 5])?);
+    }
+
+    // rho and pi (free of constraints)
+    let mut b = vec![vec![]; 25];
+    for x in 0..5 {
+        for y in 0..5 {
+            b[y + 5 * ((2 * x + 3 * y) % 5)] = rotl_lane(&a_theta[x + 5 * y], KECCAK_ROTATION_OFFSETS[x + 5 * y]);
+        }
+    }
+
+    // chi
+    let mut res = Vec::with_capacity(25);
+    for y in 0..5 {
+        for x in 0..5 {
+            let cs = &mut cs.namespace(|| format!("chi: lane {}", x + 5 * y));
+            let lane = &b[x + 5 * y];
+            let next = &b[(x + 1) % 5 + 5 * y];
+            let next_next = &b[(x + 2) % 5 + 5 * y];
+
+            let mut new_lane = Vec::with_capacity(KECCAK_LANE_SIZE);
+            for i in 0..KECCAK_LANE_SIZE {
+                let tmp = Boolean::and(cs.namespace(|| format!("and bit {}", i)), &next[i].not(), &next_next[i])?;
+                new_lane.push(Boolean::xor(cs.namespace(|| format!("xor bit {}", i)), &lane[i], &tmp)?);
+            }
+            res.push(new_lane);
+        }
+    }
+
+    // iota: xor with constant is free
+    let rc = KECCAK_ROUND_CONSTANTS[round];
+    for i in 0..KECCAK_LANE_SIZE {
+        if (rc >> i) & 1 == 1 {
+            res[0][i] = res[0][i].not();
+        }
+    }
+
+    Ok(res)
+}
+
+// state is given as 1600 bits: lanes are ordered as x + 5 * y, bits inside the lane are little-endian
+pub fn keccak_f1600<E, CS>(mut cs: CS, state: &[Boolean]) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert_eq!(state.len(), KECCAK_STATE_SIZE);
+
+    let mut lanes : Vec<Lane> = state.chunks(KECCAK_LANE_SIZE).map(|x| x.to_vec()).collect();
+    for round in 0..KECCAK_NUM_ROUNDS {
+        lanes = keccak_round(cs.namespace(|| format!("keccak round {}", round)), lanes, round)?;
+    }
+
+    Ok(lanes.into_iter().flat_map(|x| x.into_iter()).collect())
+}
+
+// keccak256 (the one used in Ethereum, i.e. with original padding rule rather than SHA3 one) of the byte string:
+// input and output are in big-endian bit order inside every byte, the same as for sha256 gadget
+pub fn keccak256<E, CS>(mut cs: CS, input: &[Boolean]) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert!(input.len() % 8 == 0);
+
+    // keccak itself uses little-endian bit order inside every byte
+    let mut padded = reverse_bits_in_bytes(input);
+    // pad10*1
+    padded.push(Boolean::constant(true));
+    while (padded.len() + 1) % KECCAK256_RATE != 0 {
+        padded.push(Boolean::constant(false));
+    }
+    padded.push(Boolean::constant(true));
+
+    let mut state = vec![Boolean::constant(false); KECCAK_STATE_SIZE];
+    for (i, block) in padded.chunks(KECCAK256_RATE).enumerate() {
+        let cs = &mut cs.namespace(|| format!("block {}", i));
+        for (j, bit) in block.iter().enumerate() {
+            state[j] = Boolean::xor(cs.namespace(|| format!("absorb bit {}", j)), &state[j], bit)?;
+        }
+        state = keccak_f1600(cs.namespace(|| "permutation"), &state)?;
+    }
+
+    Ok(reverse_bits_in_bytes(&state[0..KECCAK256_DIGEST_SIZE]))
+}
+
+
+// native counterpart, is used to generate test vectors and by native provers
+pub fn keccak_f1600_native(a: &mut [u64; 25]) {
+    for round in 0..KECCAK_NUM_ROUNDS {
+        let mut c = [0u64; 5];
+        for x in 0..5 {
+            c[x] = a[x] ^ a[x + 5] ^ a[x + 10] ^ a[x + 15] ^ a[x + 20];
+        }
+        for x in 0..5 {
+            let d = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                a[x + 5 * y] ^= d;
+            }
+        }
+
+        let mut b = [0u64; 25];
+        for x in 0..5 {
+            for y in 0..5 {
+                b[y + 5 * ((2 * x + 3 * y) % 5)] = a[x + 5 * y].rotate_left(KECCAK_ROTATION_OFFSETS[x + 5 * y] as u32);
+            }
+        }
+
+        for y in 0..5 {
+            for x in 0..5 {
+                a[x + 5 * y] = b[x + 5 * y] ^ ((!b[(x + 1) % 5 + 5 * y]) & b[(x + 2) % 5 + 5 * y]);
+            }
+        }
+
+        a[0] ^= KECCAK_ROUND_CONSTANTS[round];
+    }
+}
+
+// keccak sponge with the given rate (in bytes) and the first byte of the padding:
+// 0x01 for the original keccak, 0x1f for SHAKE
+fn keccak_sponge_native(input: &[u8], rate: usize, domain_byte: u8, output_len: usize) -> Vec<u8> {
+    let mut padded = input.to_vec();
+    padded.push(domain_byte);
+    while padded.len() % rate != 0 {
+        padded.push(0);
+    }
+    *padded.last_mut().expect("is not empty") |= 0x80;
+
+    let mut state = [0u64; 25];
+    for block in padded.chunks(rate) {
+        for (i, word) in block.chunks(8).enumerate() {
+            let mut lane = 0u64;
+            for (j, byte) in word.iter().enumerate() {
+                lane |= (*byte as u64) << (8 * j);
+            }
+            state[i] ^= lane;
+        }
+        keccak_f1600_native(&mut state);
+    }
+
+    let mut res = Vec::with_capacity(output_len);
+    loop {
+        for i in 0..rate {
+            if res.len() == output_len {
+                return res;
+            }
+            res.push((state[i / 8] >> (8 * (i % 8))) as u8);
+        }
+        keccak_f1600_native(&mut state);
+    }
+}
+
+pub fn keccak256_native(input: &[u8]) -> [u8; 32] {
+    let mut res = [0u8; 32];
+    res.copy_from_slice(&keccak_sponge_native(input, KECCAK256_RATE / 8, 0x01, 32));
+    res
+}
+
+// extendable output function, is used to derive constants (e.g. for Rescue-Prime)
+pub fn shake256_native(input: &[u8], output_len: usize) -> Vec<u8> {
+    keccak_sponge_native(input, KECCAK256_RATE / 8, 0x1f, output_len)
+}
+
+
+pub struct Keccak256Hasher;
+
+impl DigestHasher for Keccak256Hasher {
+    fn hash<E: Engine, CS: ConstraintSystem<E>>(cs: CS, input: &[Boolean]) -> Result<Vec<Boolean>, SynthesisError> {
+        keccak256(cs, input)
+    }
+
+    fn hash_native(input: &[u8]) -> [u8; 32] {
+        keccak256_native(input)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use bellman::pairing::bn256::Bn256;
+    use bellman::ConstraintSystem;
+    use common::boolean::{Boolean, AllocatedBit};
+    use crate::tester::naming_oblivious_cs::NamingObliviousConstraintSystem as TestConstraintSystem;
+    use rand::{XorShiftRng, SeedableRng, Rng};
+    use super::*;
+
+    fn hex_to_bytes(hex: &str) -> Vec<u8> {
+        (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i+2], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_native_keccak256_vectors() {
+        assert_eq!(
+            keccak256_native(b"").to_vec(),
+            hex_to_bytes("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470")
+        );
+        assert_eq!(
+            keccak256_native(b"abc").to_vec(),
+            hex_to_bytes("4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45")
+        );
+    }
+
+    #[test]
+    fn test_native_shake256_vectors() {
+        assert_eq!(
+            shake256_native(b"abc", 32),
+            hex_to_bytes("483366601360a8771c6863080cc4114d8db44530f8f1e1ee4f94ea37e78b5739")
+        );
+        // output longer than the rate requires additional permutations
+        assert_eq!(
+            shake256_native(b"", 200)[168..].to_vec(),
+            hex_to_bytes("b68ceab7a9e0c58d864e8efde4e1b9a46cbe854713672f5caaae314ed9083dab")
+        );
+    }
+
+    #[test]
+    fn test_keccak256_gadget_against_native() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        // the second length requires an additional block for padding
+        for &len in [3usize, 136].iter() {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let data : Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+
+            let mut input_bits = vec![];
+            for (byte_i, input_byte) in data.iter().enumerate() {
+                for bit_i in (0..8).rev() {
+                    let cs = cs.namespace(|| format!("input bit {} {}", byte_i, bit_i));
+                    input_bits.push(AllocatedBit::alloc(cs, Some((input_byte >> bit_i) & 1u8 == 1u8)).unwrap().into());
+                }
+            }
+
+            let r = keccak256(&mut cs, &input_bits).unwrap();
+            assert!(cs.is_satisfied());
+
+            let expected = keccak256_native(&data);
+            let mut s = r.iter().map(|b: &Boolean| b.get_value().unwrap());
+            for b in expected.iter() {
+                for i in (0..8).rev() {
+                    let c = s.next().unwrap();
+                    assert_eq!(c, (b >> i) & 1u8 == 1u8);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr,
+};
+use bellman::pairing::bn256;
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+};
+
+use common::num::*;
+use super::sponge::{SpongePermutation, SpongePermutationGadget, DuplexSpongeGadget, DuplexSponge};
+use super::keccak::keccak256_native;
+use super::rescue::alpha_sbox::constrain_pow;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub(crate) static MIMC_PERMUTATIONS_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+
+// MiMC in Feistel mode over the state (x_L, x_R) with zero key, every round is
+// t = x_L + c_i, (x_L, x_R) = (x_R + t^alpha, x_L), and the last round doesn't swap the halves
+pub struct MiMCParams<F: PrimeField> {
+    num_rounds: usize,
+    alpha: u64,
+    round_constants: Vec<F>,
+}
+
+impl<F: PrimeField> MiMCParams<F> {
+    // constants follow the approach of circomlib's MiMCSponge: the chain of keccak256 hashes started from the seed,
+    // every hash is read as big-endian integer and reduced modulo p; the first and the last constants are zero
+    pub fn new(num_rounds: usize, alpha: u64, seed: &[u8]) -> Self {
+        assert!(num_rounds >= 2);
+
+        let mut base = F::Repr::default();
+        base.as_mut()[0] = 256;
+        let base = F::from_repr(base).expect("is a valid field element");
+
+        let mut digest = keccak256_native(seed);
+        let mut round_constants = vec![F::zero()];
+        for _ in 1..(num_rounds - 1) {
+            digest = keccak256_native(&digest[..]);
+            let c = digest.iter().fold(F::zero(), |mut acc, byte| {
+                let mut repr = F::Repr::default();
+                repr.as_mut()[0] = *byte as u64;
+
+                acc.mul_assign(&base);
+                acc.add_assign(&F::from_repr(repr).expect("is a valid field element"));
+                acc
+            });
+            round_constants.push(c);
+        }
+        round_constants.push(F::zero());
+
+        MiMCParams {
+            num_rounds,
+            alpha,
+            round_constants,
+        }
+    }
+}
+
+impl MiMCParams<bn256::Fr> {
+    // x^5 round function: 220 rounds, as in circomlib
+    pub fn bn256_default() -> Self {
+        Self::new(220, 5, b"mimcsponge")
+    }
+}
+
+
+// costs 3 constraints per round for alpha = 5 and 4 constraints for alpha = 7
+fn mimc_feistel<E: Engine, CS: ConstraintSystem<E>>(
+    mut cs: CS,
+    state: &mut [Num<E>],
+    params: &MiMCParams<E::Fr>,
+) -> Result<(), SynthesisError> {
+
+    let mut x_l = state[0].clone();
+    let mut x_r = state[1].clone();
+
+    for round in 0..params.num_rounds {
+        let cs = &mut cs.namespace(|| format!("mimc round {}", round));
+
+        let mut t = x_l.clone();
+        if !params.round_constants[round].is_zero() {
+            t.add_assign(&Num::from_constant(&params.round_constants[round], &cs));
+        }
+        // x_R + t^alpha is allocated at once: the last constraint of the power chain checks it against t^{alpha - 1} * t,
+        // so the linear combinations don't grow from round to round
+        let new_value = match (t.get_value(), x_r.get_value()) {
+            (Some(t), Some(mut x)) => {
+                x.add_assign(&t.pow(&[params.alpha]));
+                Some(x)
+            },
+            (_, _) => None,
+        };
+        let new_x_l : Num<E> = AllocatedNum::alloc(
+            cs.namespace(|| "x_R + t^alpha"),
+            || new_value.ok_or(SynthesisError::AssignmentMissing)
+        )?.into();
+
+        let mut t_pow = new_x_l.clone();
+        t_pow.sub_assign(&x_r);
+        constrain_pow(cs, t.get_lc(), t_pow.get_lc(), t.get_value(), params.alpha)?;
+
+        if round < params.num_rounds - 1 {
+            x_r = x_l;
+            x_l = new_x_l;
+        }
+        else {
+            x_r = new_x_l;
+        }
+    }
+
+    state[0] = x_l;
+    state[1] = x_r;
+
+    Ok(())
+}
+
+
+// sponge with rate 1 and capacity 1, padding constant is one (so the empty input doesn't collide with [0]);
+// inputs are added to x_L and the outputs are read from x_L, as in circomlib's MiMCSponge with zero key
+pub type MiMCGadget<E> = DuplexSpongeGadget<E, MiMCParams<<E as Engine>::Fr>>;
+
+impl<F: PrimeField> SpongePermutation<F> for MiMCParams<F> {
+    fn state_width(&self) -> usize {
+        2
+    }
+
+    fn sponge_rate(&self) -> usize {
+        1
+    }
+
+    fn sponge_padding(&self) -> F {
+        F::one()
+    }
+
+    fn permute_native(&self, state: &mut [F]) {
+        mimc_feistel_native(state, self)
+    }
+}
+
+impl<E: Engine> SpongePermutationGadget<E> for MiMCParams<E::Fr> {
+    fn permute<CS: ConstraintSystem<E>>(&self, cs: CS, state: &mut [Num<E>]) -> Result<(), SynthesisError> {
+        MIMC_PERMUTATIONS_COUNT.fetch_add(1, Ordering::SeqCst);
+        mimc_feistel(cs, state, self)
+    }
+}
+
+
+// native counterpart of the gadget: is used by prover and for test vectors generation
+pub fn mimc_feistel_native<F: PrimeField>(state: &mut [F], params: &MiMCParams<F>) {
+    let (mut x_l, mut x_r) = (state[0], state[1]);
+
+    for round in 0..params.num_rounds {
+        let mut t = x_l;
+        t.add_assign(&params.round_constants[round]);
+
+        let mut new_x_l = x_r;
+        new_x_l.add_assign(&t.pow(&[params.alpha]));
+
+        if round < params.num_rounds - 1 {
+            x_r = x_l;
+            x_l = new_x_l;
+        }
+        else {
+            x_r = new_x_l;
+        }
+    }
+
+    state[0] = x_l;
+    state[1] = x_r;
+}
+
+pub type MiMC<F> = DuplexSponge<F, MiMCParams<F>>;
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use crate::tester::naming_oblivious_cs::NamingObliviousConstraintSystem as TestConstraintSystem;
+
+    #[test]
+    fn test_mimc_padding() {
+        let params = MiMCParams::<Fr>::bn256_default();
+        let hash = |input: &[Fr]| {
+            let mut hasher = MiMC::new(&params);
+            for x in input.iter() {
+                hasher.absorb(*x, &params);
+            }
+            hasher.squeeze(&params)
+        };
+
+        assert!(hash(&[]) != hash(&[Fr::zero()]));
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let mut gadget = MiMCGadget::<Bn256>::new(&params);
+        let res = gadget.squeeze(cs.namespace(|| "squeeze"), &params).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(res.get_value(), Some(hash(&[])));
+    }
+}
@@ -1,2 +1,75 @@
+use bellman::pairing::{
+    Engine,
+};
+use bellman::pairing::ff::{
+    PrimeField,
+    PrimeFieldRepr,
+};
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+};
+
+use common::num::AllocatedNum;
+
 pub mod rescue;
-//pub mod poseidon;
+pub mod keccak;
+pub mod poseidon;
+pub mod rescue_prime;
+pub mod anemoi;
+pub mod griffin;
+pub mod mimc;
+pub mod sponge;
+
+
+// algebraic hash in sponge mode (Rescue, Poseidon, Anemoi, Griffin, MiMC, ...):
+// any implementation can be plugged into SpongeTreeGadget and SpongeChannelGadget
+pub trait SpongeGadget<E: Engine>: Sized {
+    type Params;
+
+    fn new(params: &Self::Params) -> Self;
+    // the first capacity element is initialized with the domain separator instead of zero
+    fn new_with_domain(params: &Self::Params, domain: E::Fr) -> Self;
+
+    fn absorb<CS: ConstraintSystem<E>>(&mut self, val: AllocatedNum<E>, cs: CS, params: &Self::Params) -> Result<(), SynthesisError>;
+    fn squeeze<CS: ConstraintSystem<E>>(&mut self, cs: CS, params: &Self::Params) -> Result<AllocatedNum<E>, SynthesisError>;
+
+    // returns the sponge into the initial (empty) state
+    fn reset(&mut self, params: &Self::Params) {
+        *self = Self::new(params);
+    }
+
+    // injective hashing of the variable-length input: the header encoding both the domain tag and the length
+    // is put into the capacity, so the padding of the sponge can't produce collisions,
+    // and the header costs no additional rate element (hence no additional permutation)
+    fn hash_varlen<CS: ConstraintSystem<E>>(
+        mut cs: CS,
+        input: &[AllocatedNum<E>],
+        domain_tag: u64,
+        params: &Self::Params
+    ) -> Result<AllocatedNum<E>, SynthesisError>
+    {
+        let mut sponge = Self::new_with_domain(params, varlen_header(domain_tag, input.len()));
+        for elem in input.iter() {
+            sponge.absorb(elem.clone(), cs.namespace(|| "absorb varlen input"), params)?;
+        }
+        sponge.squeeze(cs.namespace(|| "squeeze varlen output"), params)
+    }
+}
+
+// native counterpart of SpongeGadget, is used by provers and to check the gadgets
+pub trait NativeSponge<F: PrimeField, P>: Sized {
+    fn new(params: &P) -> Self;
+    fn new_with_domain(params: &P, domain: F) -> Self;
+    fn absorb(&mut self, val: F, params: &P);
+    fn squeeze(&mut self, params: &P) -> F;
+}
+
+
+// domain_tag * 2^64 + len
+pub fn varlen_header<F: PrimeField>(domain_tag: u64, len: usize) -> F {
+    let mut repr = F::Repr::default();
+    repr.as_mut()[0] = len as u64;
+    repr.as_mut()[1] = domain_tag;
+    F::from_repr(repr).expect("is a valid field element")
+}
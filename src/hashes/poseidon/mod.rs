@@ -1,506 +1,238 @@
-use bellman::pairing::{Engine,};
-use bellman::pairing::ff::{Field, PrimeField};
-use bellman::{ConstraintSystem, SynthesisError};
-
-use common::boolean::Boolean;
-use common::num::{Num, AllocatedNum};
-
-
-trait Sbox<E: Engine> {
-    fn apply_sbox<CS: ConstraintSystem<E>> (
-        cs: CS,
-        num: &AllocatedNum<E>,
-     ) -> Result<Vec<AllocatedNum<E>>, SynthesisError>;
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+};
+
+use common::num::*;
+use super::sponge::{SpongePermutation, SpongePermutationGadget, DuplexSpongeGadget, DuplexSponge};
+
+pub mod params;
+
+use self::params::DefaultPoseidonParams;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub(crate) static POSEIDON_PERMUTATIONS_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+
+// Poseidon with quintic S-box (the field should satisfy gcd(5, p - 1) = 1, which is the case for BN256 and BLS12-381):
+// R_F / 2 full rounds, R_P partial rounds and again R_F / 2 full rounds,
+// every round is: add round constants -> S-box (to all the elements or to the first one only) -> MDS
+pub trait PoseidonParams<F: PrimeField> {
+    // width of the state
+    fn t(&self) -> usize;
+    // number of elements absorbed (and squeezed) per permutation, the rest is the capacity
+    fn rate(&self) -> usize;
+    fn num_full_rounds(&self) -> usize;
+    fn num_partial_rounds(&self) -> usize;
+    // t constants for every round
+    fn round_constants(&self, round: usize) -> &[F];
+    fn mds_matrix(&self) -> &[Vec<F>];
+    fn padding_constant(&self) -> &F;
 }
 
-
-struct QuinticSbox<E: Engine> {
-    _engine_marker: std::marker::PhantomData<E>,
+pub const POSEIDON_ALPHA: u64 = 5;
+
+
+// x -> x^5 costs 3 constraints, input is allowed to be an arbitrary linear combination
+fn quintic_sbox<E: Engine, CS: ConstraintSystem<E>>(mut cs: CS, elem: &Num<E>) -> Result<Num<E>, SynthesisError> {
+    let x = elem.get_value();
+    let x2 = x.map(|mut x| { x.square(); x });
+    let x4 = x2.map(|mut x| { x.square(); x });
+    let x5 = match (x4, x) {
+        (Some(mut a), Some(b)) => { a.mul_assign(&b); Some(a) },
+        _ => None,
+    };
+
+    let x2_var = AllocatedNum::alloc2(cs.namespace(|| "x^2"), x2)?;
+    let x4_var = AllocatedNum::alloc2(cs.namespace(|| "x^4"), x4)?;
+    let x5_var = AllocatedNum::alloc2(cs.namespace(|| "x^5"), x5)?;
+
+    cs.enforce(
+        || "x * x == x^2",
+        |_| elem.lc(E::Fr::one()),
+        |_| elem.lc(E::Fr::one()),
+        |lc| lc + x2_var.get_variable(),
+    );
+
+    cs.enforce(
+        || "x^2 * x^2 == x^4",
+        |lc| lc + x2_var.get_variable(),
+        |lc| lc + x2_var.get_variable(),
+        |lc| lc + x4_var.get_variable(),
+    );
+
+    cs.enforce(
+        || "x^4 * x == x^5",
+        |lc| lc + x4_var.get_variable(),
+        |_| elem.lc(E::Fr::one()),
+        |lc| lc + x5_var.get_variable(),
+    );
+
+    Ok(x5_var.into())
 }
 
-
-fn poseidon_mimc_round<E: PoseidonEngine<SBox = QuinticSBox<E> >, CS>(
-    mut cs: CS,
-    input: &[Num<E>],
-    params: &E::Params
-) -> Result<Vec<Num<E>>, SynthesisError>
-    where CS: ConstraintSystem<E>
-{
-    let expected_input_len = params.t() as usize;
-    assert!(input.len() == expected_input_len);
-
-    let state_len = params.t();
-    // we have to perform R_f -> R_p -> R_f
-
-    // no optimization will be done in the first version in terms of reordering of 
-    // linear transformations, round constants additions and S-Boxes
-
-    let mut round = 0;
-
-    let r_f = params.r_f();
-    let r_p = params.r_p();
-    let t = params.t();
-
-    fn add_round_constants<E: PoseidonEngine, CS>(
-        params: &E::Params,
-        words: &mut [Num<E>], 
-        round: u32, 
-        full_round: bool)
-    where CS: ConstraintSystem<E> {
-        let round_constants = if full_round {
-            params.full_round_key(round)
-        } else {
-            params.partial_round_key(round)
-        };
-        for (el, c) in words.iter_mut().zip(round_constants.iter()) {
-            el.mut_add_bool_with_coeff(CS::one(), &Boolean::constant(true), *c);
-        }
-    }
-
-    // before the first round form linear combinations manually
-
-    let mut state = input.to_vec();
-    add_round_constants::<E, CS>(params, &mut state[..], 0, true);
-
-    // do releated applications of MDS and then round constants and s-boxes
-
-    for full_round in 0..(r_f-1) {
-        let s_box_applied = E::SBox::apply_sbox(
-            cs.namespace(|| format!("apply s-box for full round {}", full_round)),
-            &state[..]
-        )?;
-
-        let mut linear_transformation_results = vec![];
-        for row in 0..t {
-            let row = params.mds_matrix_row(row);
-            let linear_applied = scalar_product(&s_box_applied[..], row);
-            linear_transformation_results.push(linear_applied);
+fn mds<E: Engine, Params: PoseidonParams<E::Fr>>(
+    in_state: &[Num<E>],
+    params: &Params,
+) -> Vec<Num<E>> {
+    let mds_matrix = params.mds_matrix();
+
+    mds_matrix.iter().map(|row| {
+        let mut res = Num::zero();
+        for (elem, coef) in in_state.iter().zip(row.iter()) {
+            let mut temp = elem.clone();
+            temp.scale(*coef);
+            res.add_assign(&temp);
         }
+        res
+    }).collect()
+}
 
-        add_round_constants::<E, CS>(params, &mut linear_transformation_results[..], full_round + 1, true);
+fn poseidon_permutation<E: Engine, CS: ConstraintSystem<E>, Params: PoseidonParams<E::Fr>>(
+    mut cs: CS,
+    state: &mut [Num<E>],
+    params: &Params,
+) -> Result<(), SynthesisError> {
 
-        state = linear_transformation_results;
+    let half_full_rounds = params.num_full_rounds() / 2;
+    let num_rounds = params.num_full_rounds() + params.num_partial_rounds();
 
-        round += 1;
-    }
+    for round in 0..num_rounds {
+        let cs = &mut cs.namespace(|| format!("poseidon round {}", round));
 
-    // up to this point we always made a well-formed LC that later was collapsed into
-    // a signel variable after non-linearity application
-    // now we need to make linear combinations of linear combinations, so basically make
-    // filtering and joining. It's actually possible to just separate MSD matrix into
-    // three in later optimizations
-
-    // now we need to apply full SBox of the last full round, then do linear
-    // transformation and add first round constants before going through partial rounds
-    {
-        let s_box_applied = E::SBox::apply_sbox(
-            cs.namespace(|| format!("apply s-box for full round {}", r_f-1)),
-            &state[..]
-        )?;
-
-        let mut linear_transformation_results = vec![];
-        for row in 0..t {
-            let row = params.mds_matrix_row(row);
-            let linear_applied = scalar_product(&s_box_applied[..], row);
-            linear_transformation_results.push(linear_applied);
+        for (elem, c) in state.iter_mut().zip(params.round_constants(round).iter()) {
+            elem.add_assign(&Num::from_constant(c, &cs));
         }
 
-        add_round_constants::<E, CS>(params, &mut linear_transformation_results[..], 0, false);
-        state = linear_transformation_results;
-
-        // up to this point linear combinations are well-formed and have number
-        // of terms equal to the number of variables in the state
-
-        round += 1;
-    }
-
-    for partial_round in 0..(r_p-1) {
-        let s_box_applied = E::SBox::apply_sbox(
-            cs.namespace(|| format!("apply s-box for partial round {}", partial_round)),
-            &state[0..1]
-        )?;
-
-        // at this point state is a vector of linear combinations except of the first one that has to be replaced
-
-        state[0] = Num::from(s_box_applied[0].clone());
-
-        let mut linear_transformation_results = vec![];
-        for row in 0..t {
-            let row = params.mds_matrix_row(row);
-            let linear_applied = scalar_product_over_lc(&state[..], row);
-            linear_transformation_results.push(linear_applied);
+        let is_full_round = round < half_full_rounds || round >= half_full_rounds + params.num_partial_rounds();
+        if is_full_round {
+            for elem in state.iter_mut() {
+                *elem = quintic_sbox(cs.namespace(|| "full sbox"), elem)?;
+            }
         }
-
-        add_round_constants::<E, CS>(params, &mut linear_transformation_results[..], partial_round + 1, false);
-
-        state = linear_transformation_results;
-
-        round += 1;
-    }
-
-    // do the same after partial round: s-box, linear and add round constants
-    {
-        let s_box_applied = E::SBox::apply_sbox(
-            cs.namespace(|| format!("apply s-box for partial round {}", r_p - 1)),
-            &state[0..1]
-        )?;
-
-        state[0] = Num::from(s_box_applied[0].clone());
-
-        let mut linear_transformation_results = vec![];
-        for row in 0..t {
-            let row = params.mds_matrix_row(row);
-            let linear_applied = scalar_product_over_lc(&state[..], row);
-            linear_transformation_results.push(linear_applied);
+        else {
+            state[0] = quintic_sbox(cs.namespace(|| "partial sbox"), &state[0])?;
+            // the rest of the state is not touched by non-linearity in partial rounds:
+            // we have to collapse it, otherwise linear combinations grow exponentially
+            for elem in state.iter_mut().skip(1) {
+                *elem = elem.simplify(cs.namespace(|| "collapse linear combination"))?.into();
+            }
         }
 
-        add_round_constants::<E, CS>(params, &mut linear_transformation_results[..], r_f, true);
-        state = linear_transformation_results;
-
-        round += 1;
-    }
-
-    for full_round in r_f..(2*r_f - 1) {
-        let s_box_applied = E::SBox::apply_sbox(
-            cs.namespace(|| format!("apply s-box for full round {}", full_round)),
-            &state[..]
-        )?;
-
-        let mut linear_transformation_results = vec![];
-        for row in 0..t {
-            let row = params.mds_matrix_row(row);
-            let linear_applied = scalar_product(&s_box_applied[..], row);
-            linear_transformation_results.push(linear_applied);
+        for (input, output) in mds::<E, Params>(state, params).into_iter().zip(state.iter_mut()) {
+            *output = input;
         }
-
-        add_round_constants::<E, CS>(params, &mut linear_transformation_results[..], full_round + 1, true);
-
-        state = linear_transformation_results;
-
-        round += 1;
     }
 
-    // for a final round we only apply s-box
-
-    let state = E::SBox::apply_sbox(
-            cs.namespace(|| format!("apply s-box for full round {}", 2*r_f - 1)),
-            &state[..]
-        )?;
-
-    Ok(state.into_iter().map(|el| Num::from(el)).collect())
+    Ok(())
 }
 
+// the same sponge construction and API as RescueGadget
+pub type PoseidonGadget<E, P> = DuplexSpongeGadget<E, P>;
 
-impl<E: Engine> QuinticSBox<E> {
-    fn constrain_rescue_pow_bn256<CS: ConstraintSystem<E>>(
-        cs: &mut CS,
-        input_var: &LinearCombination<E>,
-        output_var: &LinearCombination<E>,
-        x: Option<Fr>,
-    ) -> Result<(), SynthesisError>
-    {
-        let x2 = x.and_then(|x| {
-            let mut temp = x;
-            temp.square();
-            Some(temp)
-        });
-
-        let x4 = x2.and_then(|x| {
-            let mut temp = x;
-            temp.square();
-            Some(temp)
-        });
-
-        let x5 = match (x4, x) {
-            (Some(a), Some(b)) => {
-                let mut temp = a;
-                temp.mul_assign(&b);
-                Some(temp)
-            }
-            (_, _) => None
-        };
-
-        let x2_var = cs.alloc(|| "x^2", || x2.ok_or(SynthesisError::AssignmentMissing))?;
-        let x4_var = cs.alloc(|| "x^4", || x4.ok_or(SynthesisError::AssignmentMissing))?;
-
-        cs.enforce(
-            || "x * x == x^2",
-            |lc| lc + input_var,
-            |lc| lc + input_var,
-            |lc| lc + x2_var,
-        );
-
-        cs.enforce(
-            || "x^2 * x^2 == x^4",
-            |lc| lc + x2_var,
-            |lc| lc + x2_var,
-            |lc| lc + x4_var,
-        );
-
-        cs.enforce(
-            || "x^4 * x == x^5",
-            |lc| lc + x4_var,
-            |lc| lc + input_var,
-            |lc| lc + output_var,
-        );
-
-        Ok(())
+// the sponge traits are implemented for concrete parameter sets
+// (a blanket impl over PoseidonParams would overlap with the impls of the other permutations)
+impl<F: PrimeField> SpongePermutation<F> for DefaultPoseidonParams<F> {
+    fn state_width(&self) -> usize {
+        self.t()
     }
-}
 
-
-pub fn poseidon_hash<E: PoseidonEngine<SBox = QuinticSBox<E> >, CS>(
-    mut cs: CS,
-    input: &[AllocatedNum<E>],
-    params: &E::Params
-) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
-    where CS: ConstraintSystem<E>
-{
-    let output_len = params.output_len() as usize;
-    let absorbtion_len = params.absorbtion_cycle_len() as usize;
-    let t = params.t();
-
-    let mut absorbtion_cycles = input.len() / absorbtion_len;
-    if input.len() % absorbtion_len != 0 {
-        absorbtion_cycles += 1;
+    fn sponge_rate(&self) -> usize {
+        self.rate()
     }
-    
-    // convert input into Nums
-    let mut input: Vec<Num<E>> = input.iter().map(|el| Num::from(el.clone())).collect();
-    input.resize(absorbtion_cycles * absorbtion_len, Num::zero());
-
-
-    // make initial state: perform mimc round on an empty vector
-    // TODO: make static precomputation
-    let initial_state: Vec<E::Fr> = crate::poseidon::poseidon_mimc::<E>(params, &vec![E::Fr::zero(); t as usize]);
-    let mut state: Vec<Num<E>>  = initial_state.into_iter().map(|el| {
-        let mut lc = Num::zero();
-        lc = lc.add_bool_with_coeff(CS::one(), &Boolean::constant(true), el);
-
-        lc
-    }).collect();
-
-    for i in 0..absorbtion_cycles {
-        // Don't touch top words of the state, only the bottom ones
-        let absorbtion_slice = &input[(i * absorbtion_len)..((i+1)*absorbtion_len)];
-        for (w, abs) in state.iter_mut().zip(absorbtion_slice.iter()) {
-            w.add_assign(abs);
-        }
-        state = poseidon_mimc_round(
-            cs.namespace(|| format!("Poseidon mimc round {}", i)),
-            &state,
-            params
-        )?;
-    }
-
-    let mut result = vec![];
 
-    for (i, num) in state[..output_len].iter().enumerate() {
-        let allocated: AllocatedNum<E> = AllocatedNum::alloc(
-            cs.namespace(|| format!("allocate output word {}", i)), 
-            || {
-                let val = *num.get_value().get()?;
-
-                Ok(val)
-            }
-        )?;
-
-        cs.enforce(
-            || format!("enforce allocaiton for word {}", i),
-            |_| num.lc(E::Fr::one()),
-            |lc| lc + CS::one(),
-            |lc| lc + allocated.get_variable()
-        );
-
-        result.push(allocated);
+    fn sponge_padding(&self) -> F {
+        *self.padding_constant()
     }
 
-    Ok(result)
+    fn permute_native(&self, state: &mut [F]) {
+        poseidon_permutation_native(state, self)
+    }
 }
 
-pub fn poseidon_mimc<E: PoseidonEngine<SBox = QuinticSBox<E> >, CS>(
-    mut cs: CS,
-    input: &[AllocatedNum<E>],
-    params: &E::Params
-) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
-    where CS: ConstraintSystem<E>
-{
-    let expected_input_len = params.t() as usize;
-    assert!(input.len() == expected_input_len);
-
-    let state_len = params.t();
-    // we have to perform R_f -> R_p -> R_f
-
-    // no optimization will be done in the first version in terms of reordering of 
-    // linear transformations, round constants additions and S-Boxes
-
-    // let mut round = 0;
-
-    // let r_f = params.r_f();
-    // let r_p = params.r_p();
-    // let t = params.t();
-
-    // fn form_round_constants_linear_combinations<E: PoseidonEngine, CS>(
-    //     params: &E::Params,
-    //     words: &[AllocatedNum<E>], 
-    //     round: u32, 
-    //     full_round: bool) -> Vec<Num<E>>
-    // where CS: ConstraintSystem<E> {
-    //     let round_constants = if full_round {
-    //         params.full_round_key(round)
-    //     } else {
-    //         params.partial_round_key(round)
-    //     };
-    //     let mut linear_combinations = vec![];
-    //     for (el, c) in words.iter().zip(round_constants.iter()) {
-    //         let mut lc = Num::from(el.clone());
-    //         lc = lc.add_bool_with_coeff(CS::one(), &Boolean::constant(true), *c);
-    //         linear_combinations.push(lc);
-    //     }
-
-    //     linear_combinations
-    // }
-
-    let state: Vec<Num<E>> = input.iter().map(|el| Num::from(el.clone())).collect();
-
-    let round_result = poseidon_mimc_round(cs.namespace(|| "mimc round"), &state, params)?;
-
-    let mut result = vec![];
-
-    for (i, num) in round_result.iter().enumerate() {
-        let allocated: AllocatedNum<E> = AllocatedNum::alloc(
-            cs.namespace(|| format!("allocate output word {}", i)), 
-            || {
-                let val = *num.get_value().get()?;
-
-                Ok(val)
-            }
-        )?;
-
-        cs.enforce(
-            || format!("enforce allocaiton for word {}", i),
-            |_| num.lc(E::Fr::one()),
-            |lc| lc + CS::one(),
-            |lc| lc + allocated.get_variable()
-        );
-
-        result.push(allocated);
+impl<E: Engine> SpongePermutationGadget<E> for DefaultPoseidonParams<E::Fr> {
+    fn permute<CS: ConstraintSystem<E>>(&self, cs: CS, state: &mut [Num<E>]) -> Result<(), SynthesisError> {
+        POSEIDON_PERMUTATIONS_COUNT.fetch_add(1, Ordering::SeqCst);
+        poseidon_permutation(cs, state, self)
     }
-
-    Ok(result)
 }
 
 
+// native counterpart of the gadget: is used by prover and for test vectors generation
+pub fn poseidon_permutation_native<F: PrimeField, Params: PoseidonParams<F>>(state: &mut [F], params: &Params) {
+    let half_full_rounds = params.num_full_rounds() / 2;
+    let num_rounds = params.num_full_rounds() + params.num_partial_rounds();
 
-fn scalar_product<E: Engine> (input: &[AllocatedNum<E>], by: &[E::Fr]) -> Num<E> {
-    assert!(input.len() == by.len());
-    let mut result = Num::zero();
-    for (a, b) in input.iter().zip(by.iter()) {
-        result = result.add_number_with_coeff(a, *b);
-    }
+    for round in 0..num_rounds {
+        for (elem, c) in state.iter_mut().zip(params.round_constants(round).iter()) {
+            elem.add_assign(c);
+        }
 
-    result
-}
+        let is_full_round = round < half_full_rounds || round >= half_full_rounds + params.num_partial_rounds();
+        let num_sboxes = if is_full_round { state.len() } else { 1 };
+        for elem in state.iter_mut().take(num_sboxes) {
+            *elem = elem.pow(&[POSEIDON_ALPHA]);
+        }
 
-fn scalar_product_over_lc<E: Engine> (input: &[Num<E>], by: &[E::Fr]) -> Num<E> {
-    // inputs are already linear combinations, so we have to first multiply each of those by 
-    // scalar and then add them up
-    // THIS IS UNSAFE and can only be used here cause we know that each LC is unique in terms of contained variables
-    assert!(input.len() == by.len());
-    let mut result = Num::zero();
-    for (a, b) in input.iter().zip(by.iter()) {
-        // this is input LC multiplied by scalar
-        let mut this_lc = a.clone();
-        this_lc.scale(*b);
-        result.add_assign(&this_lc);
+        let new_state : Vec<F> = params.mds_matrix().iter().map(|row| {
+            let mut res = F::zero();
+            for (elem, coef) in state.iter().zip(row.iter()) {
+                let mut temp = *elem;
+                temp.mul_assign(coef);
+                res.add_assign(&temp);
+            }
+            res
+        }).collect();
+        state.copy_from_slice(&new_state[..]);
     }
-
-    result
 }
 
-fn print_lc<E: Engine>(input: &[Num<E>]) {
-    for el in input.iter() {
-        println!("{}", el.get_value().unwrap());
-    }
-}
+pub type Poseidon<F, P> = DuplexSponge<F, P>;
 
-fn print_nums<E: Engine>(input: &[AllocatedNum<E>]) {
-    for el in input.iter() {
-        println!("{}", el.get_value().unwrap());
-    }
-}
 
 #[cfg(test)]
 mod test {
-    use rand::{SeedableRng, Rng, XorShiftRng};
     use super::*;
-    use ::circuit::test::*;
+    use super::params::DefaultPoseidonParams;
+    use hashes::SpongeGadget;
+
     use bellman::pairing::bn256::{Bn256, Fr};
-    use bellman::pairing::ff::PrimeField;
-    use crate::poseidon;
-    use crate::poseidon::bn256::*;
-    use crate::group_hash::BlakeHasher;
+    use crate::tester::naming_oblivious_cs::NamingObliviousConstraintSystem as TestConstraintSystem;
 
     #[test]
-    fn test_poseidon_mimc_gadget() {
-        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
-        let params = Bn256PoseidonParams::new::<BlakeHasher>();
-        let input: Vec<Fr> = (0..params.t()).map(|_| rng.gen()).collect();
-        let expected = poseidon::poseidon_mimc::<Bn256>(&params, &input[..]);
-
-        {
-            let mut cs = TestConstraintSystem::<Bn256>::new();
-
-            let input_words: Vec<AllocatedNum<Bn256>> = input.iter().enumerate().map(|(i, b)| {
-                AllocatedNum::alloc(
-                    cs.namespace(|| format!("input {}", i)),
-                    || {
-                        Ok(*b)
-                    }).unwrap()
-            }).collect();
-
-            let res = poseidon_mimc(
-                cs.namespace(|| "poseidon mimc"),
-                &input_words,
-                &params
-            ).unwrap();
-
-            assert!(cs.is_satisfied());
-            assert!(res.len() == (params.t() as usize));
-
-            assert_eq!(res[0].get_value().unwrap(), expected[0]);
-        }
-    }
+    fn test_poseidon_hash_varlen() {
+        let params = DefaultPoseidonParams::<Fr>::bn256_default();
+        let x = Fr::from_str("42").unwrap();
+
+        // plain absorption pads with the constant, so the message ending with it collides with the shorter one
+        let plain = |input: &[Fr]| {
+            let mut hasher = Poseidon::new(&params);
+            for e in input.iter() {
+                hasher.absorb(*e, &params);
+            }
+            hasher.squeeze(&params)
+        };
+        assert_eq!(plain(&[x]), plain(&[x, *params.padding_constant()]));
+        assert!(Poseidon::hash_varlen(&[x], 0, &params) != Poseidon::hash_varlen(&[x, *params.padding_constant()], 0, &params));
+        assert!(Poseidon::hash_varlen(&[x], 0, &params) != Poseidon::hash_varlen(&[x], 1, &params));
 
-    #[test]
-    fn test_poseidon_hash_gadget() {
-        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
-        let params = Bn256PoseidonParams::new::<BlakeHasher>();
-        let input: Vec<Fr> = (0..(params.t()-1)*2).map(|_| rng.gen()).collect();
-        let expected = poseidon::poseidon_hash::<Bn256>(&params, &input[..]);
-
-        {
-            let mut cs = TestConstraintSystem::<Bn256>::new();
-
-            let input_words: Vec<AllocatedNum<Bn256>> = input.iter().enumerate().map(|(i, b)| {
-                AllocatedNum::alloc(
-                    cs.namespace(|| format!("input {}", i)),
-                    || {
-                        Ok(*b)
-                    }).unwrap()
-            }).collect();
-
-            let res = poseidon_hash(
-                cs.namespace(|| "poseidon hash"),
-                &input_words,
-                &params
-            ).unwrap();
-
-            assert!(cs.is_satisfied());
-            assert!(res.len() == 1);
-            println!("Poseidon hash {} to {} taken {} constraints", input.len(), res.len(), cs.num_constraints());
-
-            assert_eq!(res[0].get_value().unwrap(), expected[0]);
-        }
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let input = vec![x, *params.padding_constant()].into_iter().map(|e| {
+            AllocatedNum::alloc2(cs.namespace(|| "input"), Some(e)).unwrap()
+        }).collect::<Vec<_>>();
+        let res = PoseidonGadget::<Bn256, _>::hash_varlen(cs.namespace(|| "hash varlen"), &input, 7, &params).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(res.get_value(), Some(Poseidon::hash_varlen(&[x, *params.padding_constant()], 7, &params)));
     }
-}
\ No newline at end of file
+}
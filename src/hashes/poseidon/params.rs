@@ -0,0 +1,107 @@
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+};
+use bellman::pairing::bn256;
+
+use common::blake2s::blake2s_native;
+use common::digest::decode_field_element;
+
+use super::PoseidonParams;
+
+
+const POSEIDON_PERSONALIZATION: &'static [u8; 8] = b"Poseidon";
+
+
+// round constants are derived from blake2s of (seed || counter) truncated to CAPACITY bits,
+// MDS matrix is the Cauchy matrix M[i][j] = 1 / (i + j + t)
+pub struct DefaultPoseidonParams<F: PrimeField> {
+    t: usize,
+    rate: usize,
+    num_full_rounds: usize,
+    num_partial_rounds: usize,
+    round_constants: Vec<Vec<F>>,
+    mds_matrix: Vec<Vec<F>>,
+    padding_constant: F,
+}
+
+impl<F: PrimeField> DefaultPoseidonParams<F> {
+    fn hash_to_field(seed: &[u8], counter: u32) -> F {
+        let mut input = seed.to_vec();
+        input.extend((0..4).map(|i| (counter >> (8 * i)) as u8));
+
+        let mut digest = blake2s_native(&input, POSEIDON_PERSONALIZATION);
+        let num_zero_bits = 256 - F::CAPACITY as usize;
+        for i in 0..num_zero_bits {
+            digest[i / 8] &= !(0x80u8 >> (i % 8));
+        }
+        decode_field_element(&digest)
+    }
+
+    pub fn new(t: usize, rate: usize, num_full_rounds: usize, num_partial_rounds: usize, seed: &[u8]) -> Self {
+        assert!(rate < t);
+        assert!(num_full_rounds % 2 == 0);
+
+        let mut counter = 0;
+        let round_constants = (0..(num_full_rounds + num_partial_rounds)).map(|_| {
+            (0..t).map(|_| {
+                counter += 1;
+                Self::hash_to_field(seed, counter)
+            }).collect()
+        }).collect();
+
+        let mds_matrix = (0..t).map(|i| {
+            (0..t).map(|j| {
+                let x = F::from_str(&(i + j + t).to_string()).expect("is a valid field element");
+                x.inverse().expect("is nonzero")
+            }).collect()
+        }).collect();
+
+        DefaultPoseidonParams {
+            t,
+            rate,
+            num_full_rounds,
+            num_partial_rounds,
+            round_constants,
+            mds_matrix,
+            padding_constant: F::one(),
+        }
+    }
+}
+
+impl DefaultPoseidonParams<bn256::Fr> {
+    // width 3, quintic S-box: 8 full and 57 partial rounds give 128 bits of security for BN256
+    pub fn bn256_default() -> Self {
+        Self::new(3, 2, 8, 57, b"BN256 Poseidon t = 3")
+    }
+}
+
+impl<F: PrimeField> PoseidonParams<F> for DefaultPoseidonParams<F> {
+    fn t(&self) -> usize {
+        self.t
+    }
+
+    fn rate(&self) -> usize {
+        self.rate
+    }
+
+    fn num_full_rounds(&self) -> usize {
+        self.num_full_rounds
+    }
+
+    fn num_partial_rounds(&self) -> usize {
+        self.num_partial_rounds
+    }
+
+    fn round_constants(&self, round: usize) -> &[F] {
+        &self.round_constants[round][..]
+    }
+
+    fn mds_matrix(&self) -> &[Vec<F>] {
+        &self.mds_matrix[..]
+    }
+
+    fn padding_constant(&self) -> &F {
+        &self.padding_constant
+    }
+}
@@ -0,0 +1,232 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr,
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+    LinearCombination,
+};
+
+use num_bigint::BigUint;
+
+use common::num::*;
+use super::RescueSbox;
+
+
+pub trait SboxAlpha: Clone + Copy {
+    const ALPHA: u64;
+}
+
+#[derive(Clone, Copy)]
+pub struct Alpha3;
+
+#[derive(Clone, Copy)]
+pub struct Alpha5;
+
+#[derive(Clone, Copy)]
+pub struct Alpha7;
+
+impl SboxAlpha for Alpha3 {
+    const ALPHA: u64 = 3;
+}
+
+impl SboxAlpha for Alpha5 {
+    const ALPHA: u64 = 5;
+}
+
+impl SboxAlpha for Alpha7 {
+    const ALPHA: u64 = 7;
+}
+
+
+// alpha^{-1} mod (p - 1): it is equal to (k * (p - 1) + 1) / alpha for the unique k < alpha making it integral,
+// panics if alpha is not coprime to p - 1 (e.g. alpha = 3 for both BN256 and BLS12-381)
+pub fn inverse_alpha<F: PrimeField>(alpha: u64) -> Vec<u64> {
+    let mut bytes = vec![];
+    for limb in F::char().as_ref().iter() {
+        bytes.extend((0..8).map(|i| (limb >> (8 * i)) as u8));
+    }
+    let p_minus_one = BigUint::from_bytes_le(&bytes) - BigUint::from(1u64);
+    let alpha_big = BigUint::from(alpha);
+
+    let numerator = (1..alpha).map(|k| &p_minus_one * BigUint::from(k) + BigUint::from(1u64))
+        .find(|x| x % &alpha_big == BigUint::from(0u64))
+        .expect("alpha should be coprime to p - 1");
+    let inalpha = (numerator / &alpha_big).to_bytes_le();
+
+    inalpha.chunks(8).map(|chunk| {
+        chunk.iter().enumerate().fold(0u64, |acc, (i, byte)| acc | ((*byte as u64) << (8 * i)))
+    }).collect()
+}
+
+// enforces output = input^alpha with the square-and-multiply chain:
+// alpha = 5 requires 3 constraints, alpha = 7 requires 4 constraints
+pub(crate) fn constrain_pow<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    input_var: &LinearCombination<E>,
+    output_var: &LinearCombination<E>,
+    x: Option<E::Fr>,
+    alpha: u64,
+) -> Result<(), SynthesisError>
+{
+    assert!(alpha >= 2);
+
+    // true stands for squaring, false - for multiplication by x
+    let num_bits = 64 - alpha.leading_zeros() as usize;
+    let mut steps = vec![];
+    for i in (0..num_bits - 1).rev() {
+        steps.push(true);
+        if (alpha >> i) & 1 == 1 {
+            steps.push(false);
+        }
+    }
+
+    let mut acc_lc = input_var.clone();
+    let mut acc_value = x;
+
+    for (idx, is_square) in steps.iter().enumerate() {
+        let next_value = match (acc_value, x) {
+            (Some(mut a), Some(b)) => {
+                if *is_square { a.square() } else { a.mul_assign(&b) };
+                Some(a)
+            },
+            (_, _) => None,
+        };
+
+        // the last step of the chain produces the output itself
+        let next_lc = if idx == steps.len() - 1 {
+            output_var.clone()
+        }
+        else {
+            let var = cs.alloc(|| format!("x^alpha chain: step {}", idx), || next_value.ok_or(SynthesisError::AssignmentMissing))?;
+            LinearCombination::zero() + var
+        };
+
+        let other_lc = if *is_square { acc_lc.clone() } else { input_var.clone() };
+        cs.enforce(
+            || format!("x^alpha chain: constraint {}", idx),
+            |lc| lc + &acc_lc,
+            |lc| lc + &other_lc,
+            |lc| lc + &next_lc,
+        );
+
+        acc_lc = next_lc;
+        acc_value = next_value;
+    }
+
+    Ok(())
+}
+
+
+// x -> x^alpha
+pub fn sbox_alpha<E: Engine, CS: ConstraintSystem<E>>(mut cs: CS, elem: &Num<E>, alpha: u64) -> Result<Num<E>, SynthesisError> {
+    let base_value = elem.get_value();
+    let result_value = base_value.map(|x| x.pow(&[alpha]));
+
+    let result_var : Num<E> = AllocatedNum::alloc(
+        cs.namespace(|| "rescue_alpha_sbox"),
+        || result_value.ok_or(SynthesisError::AssignmentMissing)
+    )?.into();
+
+    constrain_pow(&mut cs, elem.get_lc(), result_var.get_lc(), base_value, alpha)?;
+    Ok(result_var)
+}
+
+// x -> x^{1/alpha}: the result y is witnessed and y^alpha = x is enforced, so the cost is the same as for sbox_alpha
+pub fn sbox_inalpha<E: Engine, CS: ConstraintSystem<E>>(mut cs: CS, elem: &Num<E>, alpha: u64) -> Result<Num<E>, SynthesisError> {
+    let result_value = elem.get_value();
+    let base_value = result_value.map(|x| x.pow(&inverse_alpha::<E::Fr>(alpha)));
+
+    let base_var : Num<E> = AllocatedNum::alloc(
+        cs.namespace(|| "rescue_inalpha_sbox"),
+        || base_value.ok_or(SynthesisError::AssignmentMissing)
+    )?.into();
+
+    constrain_pow(&mut cs, base_var.get_lc(), elem.get_lc(), base_value, alpha)?;
+    Ok(base_var)
+}
+
+
+// S-box x -> x^alpha over any engine, the inverse power is computed by witnessing y and checking x = y^alpha
+pub struct AlphaRescueSbox<E: Engine, A: SboxAlpha> {
+    _marker: std::marker::PhantomData<(E, A)>,
+}
+
+impl<E: Engine, A: SboxAlpha> Clone for AlphaRescueSbox<E, A> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<E: Engine, A: SboxAlpha> Copy for AlphaRescueSbox<E, A> {}
+
+impl<E: Engine, A: SboxAlpha> RescueSbox<E> for AlphaRescueSbox<E, A> {
+
+    fn new() -> Self {
+        AlphaRescueSbox { _marker: std::marker::PhantomData }
+    }
+
+    fn rescue_alpha<CS : ConstraintSystem<E>>(elem: &Num<E>, cs: CS) -> Result<Num<E>, SynthesisError> {
+        sbox_alpha(cs, elem, A::ALPHA)
+    }
+
+    fn rescue_inalpha<CS : ConstraintSystem<E>>(elem: &Num<E>, cs: CS) -> Result<Num<E>, SynthesisError> {
+        sbox_inalpha(cs, elem, A::ALPHA)
+    }
+
+    fn rescue_alpha_native(elem: &E::Fr) -> E::Fr {
+        elem.pow(&[A::ALPHA])
+    }
+
+    fn rescue_inalpha_native(elem: &E::Fr) -> E::Fr {
+        elem.pow(&inverse_alpha::<E::Fr>(A::ALPHA))
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use bellman::pairing::bn256::{Bn256, Fr as Bn256Fr};
+    use bellman::pairing::bls12_381::Bls12;
+    use bellman::ConstraintSystem;
+    use crate::tester::naming_oblivious_cs::NamingObliviousConstraintSystem as TestConstraintSystem;
+    use rand::{XorShiftRng, SeedableRng, Rng};
+    use super::*;
+
+    #[test]
+    fn test_inverse_alpha_matches_bn256_constant() {
+        let expected = [14981214993055009997, 6006880321387387405, 10624953561019755799, 2789598613442376532];
+        assert_eq!(inverse_alpha::<Bn256Fr>(5), expected.to_vec());
+    }
+
+    fn check_sbox_roundtrip<E: Engine, A: SboxAlpha>(num_constraints_per_sbox: usize) {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let mut cs = TestConstraintSystem::<E>::new();
+
+        let x_value : E::Fr = rng.gen();
+        let x : Num<E> = AllocatedNum::alloc2(cs.namespace(|| "x"), Some(x_value)).unwrap().into();
+
+        let y = AlphaRescueSbox::<E, A>::rescue_alpha(&x, cs.namespace(|| "alpha")).unwrap();
+        let z = AlphaRescueSbox::<E, A>::rescue_inalpha(&y, cs.namespace(|| "inalpha")).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(y.get_value(), Some(x_value.pow(&[A::ALPHA])));
+        assert_eq!(z.get_value(), Some(x_value));
+        assert_eq!(cs.num_constraints(), 2 * num_constraints_per_sbox);
+    }
+
+    #[test]
+    fn test_alpha_sbox() {
+        check_sbox_roundtrip::<Bn256, Alpha5>(3);
+        check_sbox_roundtrip::<Bn256, Alpha7>(4);
+        check_sbox_roundtrip::<Bls12, Alpha5>(3);
+        check_sbox_roundtrip::<Bls12, Alpha7>(4);
+    }
+}
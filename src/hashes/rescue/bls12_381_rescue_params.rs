@@ -0,0 +1,115 @@
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+};
+use bellman::pairing::bls12_381::{Bls12, Fr};
+
+use bellman::redshift::IOP::hashes::rescue::RescueParams;
+
+use common::blake2s::blake2s_native;
+use common::digest::decode_field_element;
+
+use super::alpha_sbox::{AlphaRescueSbox, Alpha5};
+
+
+const RESCUE_PERSONALIZATION: &'static [u8; 8] = b"Rescue_f";
+const RESCUE_SEED: &'static [u8] = b"BLS12-381 Rescue t = 3";
+
+// alpha = 3 is not coprime to r - 1 for BLS12-381, so the smallest admissible alpha is used
+pub type BLS12RescueSbox = AlphaRescueSbox<Bls12, Alpha5>;
+
+
+// width 3 (rate 2, capacity 1), alpha = 5, 22 rounds - the same shape as BN256Rescue,
+// round constants are derived from blake2s of (seed || counter) truncated to CAPACITY bits,
+// MDS matrix is the Cauchy matrix M[i][j] = 1 / (i + j + t)
+#[derive(Clone)]
+pub struct BLS12Rescue {
+    t: usize,
+    r: usize,
+    c: usize,
+    num_rounds: usize,
+    constants: Vec<Vec<Fr>>,
+    mds_matrix: Vec<Vec<Fr>>,
+    padding_constant: Fr,
+}
+
+impl BLS12Rescue {
+    fn hash_to_field(counter: u32) -> Fr {
+        let mut input = RESCUE_SEED.to_vec();
+        input.extend((0..4).map(|i| (counter >> (8 * i)) as u8));
+
+        let mut digest = blake2s_native(&input, RESCUE_PERSONALIZATION);
+        let num_zero_bits = 256 - Fr::CAPACITY as usize;
+        for i in 0..num_zero_bits {
+            digest[i / 8] &= !(0x80u8 >> (i % 8));
+        }
+        decode_field_element(&digest)
+    }
+
+    pub fn new() -> Self {
+        let t = 3;
+        let num_rounds = 22;
+
+        // one vector for the initial key injection and one after every half-round
+        let mut counter = 0;
+        let constants = (0..(2 * num_rounds + 1)).map(|_| {
+            (0..t).map(|_| {
+                counter += 1;
+                Self::hash_to_field(counter)
+            }).collect()
+        }).collect();
+
+        let mds_matrix = (0..t).map(|i| {
+            (0..t).map(|j| {
+                let x = Fr::from_str(&(i + j + t).to_string()).expect("is a valid field element");
+                x.inverse().expect("is nonzero")
+            }).collect()
+        }).collect();
+
+        BLS12Rescue {
+            t,
+            r: 2,
+            c: 1,
+            num_rounds,
+            constants,
+            mds_matrix,
+            padding_constant: Fr::one(),
+        }
+    }
+}
+
+impl Default for BLS12Rescue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RescueParams<Fr> for BLS12Rescue {
+    fn t(&self) -> usize {
+        self.t
+    }
+
+    fn r(&self) -> usize {
+        self.r
+    }
+
+    fn c(&self) -> usize {
+        self.c
+    }
+
+    fn get_num_rescue_rounds(&self) -> usize {
+        self.num_rounds
+    }
+
+    fn get_constants(&self) -> &Vec<Vec<Fr>> {
+        &self.constants
+    }
+
+    fn get_mds_matrix(&self) -> &Vec<Vec<Fr>> {
+        &self.mds_matrix
+    }
+
+    fn padding_constant(&self) -> &Fr {
+        &self.padding_constant
+    }
+}
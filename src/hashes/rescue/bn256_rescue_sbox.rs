@@ -23,6 +23,10 @@ use bellman::pairing::bn256::Bn256 as E;
 use bellman::pairing::bn256::Fr as Fr;
 
 
+const RESCUE_ALPHA : u64 = 5;
+const RESCUE_INALPHA : [u64; 4] = [14981214993055009997, 6006880321387387405, 10624953561019755799, 2789598613442376532];
+
+
 #[derive(Clone, Copy)]
 pub struct BN256RescueSbox;
 
@@ -92,9 +96,6 @@ impl RescueSbox<E> for BN256RescueSbox {
 
     fn rescue_alpha<CS : ConstraintSystem<E>>(elem: &Num<E>, mut cs: CS) -> Result<Num<E>, SynthesisError> {
 
-        #![allow(non_snake_case)]       
-        let RESCUE_ALPHA : u64 = 5;
-        
         let base_value = elem.get_value();
         let base_lc = elem.get_lc();
 
@@ -111,9 +112,6 @@ impl RescueSbox<E> for BN256RescueSbox {
 
     fn rescue_inalpha<CS : ConstraintSystem<E>>(elem: &Num<E>, mut cs: CS) -> Result<Num<E>, SynthesisError> {
 
-        #![allow(non_snake_case)]
-        let RESCUE_INALPHA : [u64; 4] = [14981214993055009997, 6006880321387387405, 10624953561019755799, 2789598613442376532];
-
         let result_value = elem.get_value();
         let result_lc = elem.get_lc();
 
@@ -128,4 +126,12 @@ impl RescueSbox<E> for BN256RescueSbox {
         Ok(base_var)
 
     }
-}
\ No newline at end of file
+
+    fn rescue_alpha_native(elem: &Fr) -> Fr {
+        elem.pow(&[RESCUE_ALPHA])
+    }
+
+    fn rescue_inalpha_native(elem: &Fr) -> Fr {
+        elem.pow(&RESCUE_INALPHA)
+    }
+}
@@ -19,8 +19,11 @@ use bellman::{
 };
 
 use common::num::*;
+use super::{SpongeGadget, varlen_header};
 
 pub mod bn256_rescue_sbox;
+pub mod alpha_sbox;
+pub mod bls12_381_rescue_params;
 
 use std::sync::atomic::{AtomicUsize, Ordering};
 
@@ -33,31 +36,21 @@ pub trait RescueSbox<E: Engine>: Clone + Copy {
     fn rescue_alpha<CS : ConstraintSystem<E>>(elem: &Num<E>, cs: CS) -> Result<Num<E>, SynthesisError>;
 
     fn rescue_inalpha<CS : ConstraintSystem<E>>(elem: &Num<E>, cs: CS) -> Result<Num<E>, SynthesisError>;
-}
 
+    // out of circuit counterparts, are used to fold S-boxes applied to constants
+    fn rescue_alpha_native(elem: &E::Fr) -> E::Fr;
 
-fn mds<E: Engine, Params: RescueParams<E::Fr>>(
-    in_state: &[Num<E>],
-    params: &Params,
-) -> Vec<Num<E>> {
-    let mut out_state = vec![];
-    let mds_matrix = params.get_mds_matrix();
-    let RESCUE_M = params.t();
-    
-    for i in 0..RESCUE_M {
-        let mut res = Num::zero();
-        for j in 0..RESCUE_M {
-            let mut temp = in_state[j].clone();
-            temp.scale(mds_matrix[i][j]);
-            res.add_assign(&temp);
-        }
-        out_state.push(res);
-    }
-    out_state
+    fn rescue_inalpha_native(elem: &E::Fr) -> E::Fr;
 }
 
 
-fn rescue_f<E: Engine, CS: ConstraintSystem<E>, Params: RescueParams<E::Fr>, SBOX: RescueSbox<E>>(
+// Rescue permutation keeping the state as (linear part, constant part): round constants are merged into
+// the constant part of the MDS output and variables are allocated only at S-box boundaries.
+// S-boxes applied to lanes known at synthesis time (the untouched or domain-tagged capacity and the padding
+// of the fresh sponge) are folded natively and cost no constraints. Every other S-box already costs
+// the minimal 3 constraints, so a permutation over the fully variable state (later duplexes) costs the same
+// 2 * rounds * t * 3 constraints
+fn rescue_f_fused<E: Engine, CS: ConstraintSystem<E>, Params: RescueParams<E::Fr>, SBOX: RescueSbox<E>>(
     cs: &mut CS,
     state: &mut [Num<E>],
     params: &Params,
@@ -66,28 +59,68 @@ fn rescue_f<E: Engine, CS: ConstraintSystem<E>, Params: RescueParams<E::Fr>, SBO
     let RESCUE_M = params.t();
     let RESCUE_ROUNDS = params.get_num_rescue_rounds();
     let constants = params.get_constants();
-   
-    for i in 0..RESCUE_M {
-        state[i].add_assign(&Num::from_constant(&constants[0][i], &cs));
-    }
+    let mds_matrix = params.get_mds_matrix();
+
+    let mut fused : Vec<(Option<Num<E>>, E::Fr)> = state.iter().zip(constants[0].iter()).map(|(elem, c)| {
+        match elem.get_constant_value::<CS>() {
+            Some(mut value) => { value.add_assign(c); (None, value) },
+            None => (Some(elem.clone()), *c),
+        }
+    }).collect();
 
     for r in 0..2 * RESCUE_ROUNDS {
 
-        for entry in state.iter_mut() {
-            if r % 2 == 0 {
-                *entry = SBOX::rescue_inalpha(&entry, cs.namespace(|| "sbox inalpha"))?;
-            }
-            else {
-                *entry = SBOX::rescue_alpha(&entry, cs.namespace(|| "sbox alpha"))?;
-            }
+        let mut sboxed = Vec::with_capacity(RESCUE_M);
+        for (linear, constant) in fused.into_iter() {
+            let is_inalpha = r % 2 == 0;
+            let res = match linear {
+                None => {
+                    let value = if is_inalpha { SBOX::rescue_inalpha_native(&constant) } else { SBOX::rescue_alpha_native(&constant) };
+                    (None, value)
+                },
+                Some(mut linear) => {
+                    if !constant.is_zero() {
+                        linear.add_assign(&Num::from_constant(&constant, &cs));
+                    }
+                    let out = if is_inalpha {
+                        SBOX::rescue_inalpha(&linear, cs.namespace(|| "sbox inalpha"))?
+                    }
+                    else {
+                        SBOX::rescue_alpha(&linear, cs.namespace(|| "sbox alpha"))?
+                    };
+                    (Some(out), E::Fr::zero())
+                }
+            };
+            sboxed.push(res);
         }
 
-        for (input, output) in  mds::<E, Params>(state, params).into_iter().zip(state.iter_mut()) {
-            *output = input;
-        }
-        for i in 0..RESCUE_M {
-            state[i].add_assign(&Num::from_constant(&(constants[r + 1][i]), &cs));
-        }
+        fused = (0..RESCUE_M).map(|i| {
+            let mut linear : Option<Num<E>> = None;
+            let mut constant = constants[r + 1][i];
+
+            for (j, (elem_linear, elem_constant)) in sboxed.iter().enumerate() {
+                let mut tmp = *elem_constant;
+                tmp.mul_assign(&mds_matrix[i][j]);
+                constant.add_assign(&tmp);
+
+                if let Some(elem_linear) = elem_linear {
+                    let mut scaled = elem_linear.clone();
+                    scaled.scale(mds_matrix[i][j]);
+                    linear = Some(match linear.take() {
+                        Some(mut acc) => { acc.add_assign(&scaled); acc },
+                        None => scaled,
+                    });
+                }
+            }
+
+            (linear, constant)
+        }).collect();
+    }
+
+    for (elem, (linear, constant)) in state.iter_mut().zip(fused.into_iter()) {
+        let mut res = linear.unwrap_or(Num::zero());
+        res.add_assign(&Num::from_constant(&constant, &cs));
+        *elem = res;
     }
 
     Ok(())
@@ -107,9 +140,12 @@ fn pad<E: Engine, CS: ConstraintSystem<E>, Params: RescueParams<E::Fr>>(
     input.extend((0..range).map(|_| Num::from_constant(magic_constant, &cs))); 
 }
 
+// domain separator (if any) is added to the first capacity element before the first permutation,
+// it is known at synthesis time, so the S-boxes of this lane are folded
 fn rescue_duplex<E: Engine, CS: ConstraintSystem<E>, Params: RescueParams<E::Fr>, SBOX: RescueSbox<E>>(
     state: &mut Vec<Num<E>>,
     input: &mut Vec<Num<E>>,
+    domain: Option<E::Fr>,
     mut cs: CS,
     params: &Params,
 ) -> Result< Vec<Option<Num<E>>>, SynthesisError> {
@@ -117,12 +153,15 @@ fn rescue_duplex<E: Engine, CS: ConstraintSystem<E>, Params: RescueParams<E::Fr>
     let SPONGE_RATE = params.r();
     let OUTPUT_RATE = params.c();
     pad(input, &mut cs, params);
+    if let Some(domain) = domain {
+        state[SPONGE_RATE].add_assign(&Num::from_constant(&domain, &cs));
+    }
 
     for i in 0..SPONGE_RATE {
         state[i].add_assign(&input[i]);
     }
 
-    rescue_f::<E, CS, Params, SBOX>(&mut cs, state, params)?;
+    rescue_f_fused::<E, CS, Params, SBOX>(&mut cs, state, params)?;
 
     let mut output = Vec::with_capacity(OUTPUT_RATE);
     for i in 0..OUTPUT_RATE {
@@ -151,6 +190,8 @@ impl<E: Engine> SpongeState<E> {
 pub struct RescueGadget<E: Engine, RP: RescueParams<E::Fr>, SBOX: RescueSbox<E>> {
     sponge: SpongeState<E>,
     state: Vec<Num<E>>,
+    // capacity-based domain separator, is put into the state by the first permutation
+    domain: Option<E::Fr>,
     _params_marker: std::marker::PhantomData<RP>,
     _sbox_marker: std::marker::PhantomData<SBOX>,
 }
@@ -166,10 +207,17 @@ impl<E: Engine, RP: RescueParams<E::Fr>, SBOX: RescueSbox<E>> RescueGadget<E, RP
         RescueGadget {
             sponge: SpongeState::Absorbing(vec![]),
             state,
+            domain: None,
             _params_marker: std::marker::PhantomData::<RP>,
             _sbox_marker: std::marker::PhantomData::<SBOX>,
         }
     }
+
+    pub fn new_with_domain(params: &RP, domain: E::Fr) -> Self {
+        let mut res = Self::new(params);
+        res.domain = Some(domain);
+        res
+    }
    
     pub fn absorb<CS: ConstraintSystem<E>>(&mut self, val: AllocatedNum<E>, mut cs: CS, params: &RP) -> Result<(), SynthesisError> {
         let SPONGE_STATE = params.r();
@@ -183,7 +231,7 @@ impl<E: Engine, RP: RescueParams<E::Fr>, SBOX: RescueSbox<E>> RescueGadget<E, RP
 
                 // We've already absorbed as many elements as we can
                 RESCUE_PERMUTATIONS_COUNT.fetch_add(1, Ordering::SeqCst);
-                rescue_duplex::<E, _, RP, SBOX>(&mut self.state, input, cs.namespace(|| "rescue duplex"), params)?;
+                rescue_duplex::<E, _, RP, SBOX>(&mut self.state, input, self.domain.take(), cs.namespace(|| "rescue duplex"), params)?;
                 self.sponge = SpongeState::absorb(val);
             }
             SpongeState::Squeezing(_) => {
@@ -203,6 +251,7 @@ impl<E: Engine, RP: RescueParams<E::Fr>, SBOX: RescueSbox<E>> RescueGadget<E, RP
                     self.sponge = SpongeState::Squeezing(rescue_duplex::<E, _, RP, SBOX>(
                         &mut self.state,
                         input,
+                        self.domain.take(),
                         cs.namespace(|| "rescue duplex"),
                         params,
                     )?);
@@ -224,6 +273,86 @@ impl<E: Engine, RP: RescueParams<E::Fr>, SBOX: RescueSbox<E>> RescueGadget<E, RP
 }
 
 
+impl<E: Engine, RP: RescueParams<E::Fr>, SBOX: RescueSbox<E>> SpongeGadget<E> for RescueGadget<E, RP, SBOX> {
+    type Params = RP;
+
+    fn new(params: &RP) -> Self {
+        RescueGadget::new(params)
+    }
+
+    fn new_with_domain(params: &RP, domain: E::Fr) -> Self {
+        RescueGadget::new_with_domain(params, domain)
+    }
+
+    fn absorb<CS: ConstraintSystem<E>>(&mut self, val: AllocatedNum<E>, cs: CS, params: &RP) -> Result<(), SynthesisError> {
+        RescueGadget::absorb(self, val, cs, params)
+    }
+
+    fn squeeze<CS: ConstraintSystem<E>>(&mut self, cs: CS, params: &RP) -> Result<AllocatedNum<E>, SynthesisError> {
+        RescueGadget::squeeze(self, cs, params)
+    }
+}
+
+// native Rescue permutation, the same as rescue_f_fused (native Rescue of bellman doesn't expose its state)
+pub fn rescue_permutation_native<E: Engine, RP: RescueParams<E::Fr>, SBOX: RescueSbox<E>>(state: &mut [E::Fr], params: &RP) {
+    let constants = params.get_constants();
+    let mds_matrix = params.get_mds_matrix();
+
+    for (elem, c) in state.iter_mut().zip(constants[0].iter()) {
+        elem.add_assign(c);
+    }
+
+    for r in 0..2 * params.get_num_rescue_rounds() {
+        for elem in state.iter_mut() {
+            *elem = if r % 2 == 0 { SBOX::rescue_inalpha_native(elem) } else { SBOX::rescue_alpha_native(elem) };
+        }
+
+        let new_state : Vec<E::Fr> = mds_matrix.iter().zip(constants[r + 1].iter()).map(|(row, c)| {
+            let mut res = *c;
+            for (elem, coef) in state.iter().zip(row.iter()) {
+                let mut temp = *elem;
+                temp.mul_assign(coef);
+                res.add_assign(&temp);
+            }
+            res
+        }).collect();
+        state.copy_from_slice(&new_state[..]);
+    }
+}
+
+// hash of the whole input by the fresh RescueGadget (created with the given capacity), computed natively
+fn rescue_hash_native<E: Engine, RP: RescueParams<E::Fr>, SBOX: RescueSbox<E>>(input: &[E::Fr], domain: Option<E::Fr>, params: &RP) -> E::Fr {
+    let rate = params.r();
+    let mut state = vec![E::Fr::zero(); params.t()];
+    if let Some(domain) = domain {
+        state[rate] = domain;
+    }
+
+    let mut blocks : Vec<Vec<E::Fr>> = input.chunks(rate).map(|chunk| chunk.to_vec()).collect();
+    if blocks.is_empty() {
+        blocks.push(vec![]);
+    }
+    for mut block in blocks.into_iter() {
+        block.resize(rate, *params.padding_constant());
+        for (s, x) in state.iter_mut().zip(block.iter()) {
+            s.add_assign(x);
+        }
+        rescue_permutation_native::<E, RP, SBOX>(&mut state, params);
+    }
+
+    state[0]
+}
+
+// native counterpart of SpongeGadget::hash_varlen for RescueGadget
+pub fn rescue_hash_varlen_native<E: Engine, RP: RescueParams<E::Fr>, SBOX: RescueSbox<E>>(
+    input: &[E::Fr],
+    domain_tag: u64,
+    params: &RP,
+) -> E::Fr
+{
+    rescue_hash_native::<E, RP, SBOX>(input, Some(varlen_header(domain_tag, input.len())), params)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -241,45 +370,45 @@ mod test {
     use bellman::redshift::redshift::test_assembly::*;
     use bellman::redshift::redshift::cs::Circuit as PlonkCircuit;
 
-    #[test]
-    fn test_rescue_gadget() {
-        struct TestCircuit<E: Engine, RP: RescueParams<E::Fr>, SBOX: RescueSbox<E>> {
-            params: RP,
-            sbox: SBOX,
-            inputs: Vec<E::Fr>,
-            expected_outputs: Vec<E::Fr>,
-        }
-
-        impl<E: Engine, RP: RescueParams<E::Fr>, SBOX: RescueSbox<E>> Circuit<E> for TestCircuit<E, RP, SBOX> {
-            fn synthesize<CS: ConstraintSystem<E>>(
-                self,
-                cs: &mut CS,
-            ) -> Result<(), SynthesisError> {
+    struct TestCircuit<E: Engine, RP: RescueParams<E::Fr>, SBOX: RescueSbox<E>> {
+        params: RP,
+        sbox: SBOX,
+        inputs: Vec<E::Fr>,
+        expected_outputs: Vec<E::Fr>,
+    }
 
-                let mut g = RescueGadget::<E, RP, SBOX>::new(&self.params);
+    impl<E: Engine, RP: RescueParams<E::Fr>, SBOX: RescueSbox<E>> Circuit<E> for TestCircuit<E, RP, SBOX> {
+        fn synthesize<CS: ConstraintSystem<E>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
 
-                assert!(self.expected_outputs.len() <= self.params.c());
+            let mut g = RescueGadget::<E, RP, SBOX>::new(&self.params);
 
-                for elem in self.inputs.into_iter() {
-                    let val = AllocatedNum::alloc(cs.namespace(|| "rescue test input"), || Ok(elem))?;
-                    g.absorb(val.into(),  cs.namespace(|| "absorb input"), &self.params)?;
-                }
+            assert!(self.expected_outputs.len() <= self.params.c());
 
-                for elem in self.expected_outputs.into_iter() {
-                    let val = AllocatedNum::alloc_input(cs.namespace(|| "rescue output"), || Ok(elem))?;
-                    let s = g.squeeze(cs.namespace(|| "squeeze s"), &self.params)?;
-                    cs.enforce(
-                        || "check output", 
-                        |lc| lc + s.get_variable(), 
-                        |lc| lc + CS::one(),
-                        |lc| lc + val.get_variable(),
-                    );
-                }
+            for elem in self.inputs.into_iter() {
+                let val = AllocatedNum::alloc(cs.namespace(|| "rescue test input"), || Ok(elem))?;
+                g.absorb(val.into(),  cs.namespace(|| "absorb input"), &self.params)?;
+            }
 
-                Ok(())
+            for elem in self.expected_outputs.into_iter() {
+                let val = AllocatedNum::alloc_input(cs.namespace(|| "rescue output"), || Ok(elem))?;
+                let s = g.squeeze(cs.namespace(|| "squeeze s"), &self.params)?;
+                cs.enforce(
+                    || "check output", 
+                    |lc| lc + s.get_variable(), 
+                    |lc| lc + CS::one(),
+                    |lc| lc + val.get_variable(),
+                );
             }
+
+            Ok(())
         }
+    }
 
+    #[test]
+    fn test_rescue_gadget() {
         {
             // test one iteration of Rescue hash
             println!("RESCUE HASH SINGLE ITERAION");
@@ -408,5 +537,231 @@ mod test {
             assert!(!cs.is_satisfied());
             println!("Rescue 2->1 with 22 rounds requires {} R1CS constraints", cs.num_constraints());
         }
-    }    
-}
\ No newline at end of file
+    }
+
+    #[test]
+    fn test_rescue_gadget_bls12() {
+        use bellman::pairing::bls12_381::{Bls12, Fr};
+        use super::alpha_sbox::inverse_alpha;
+        use super::bls12_381_rescue_params::{BLS12Rescue, BLS12RescueSbox};
+
+        // native reference: a single permutation over the state (a, b, 0)
+        fn native_rescue(inputs: &[Fr], params: &BLS12Rescue) -> Fr {
+            let constants = params.get_constants();
+            let mds_matrix = params.get_mds_matrix();
+            let inalpha = inverse_alpha::<Fr>(5);
+
+            let mut state = vec![Fr::zero(); params.t()];
+            for (s, x) in state.iter_mut().zip(inputs.iter()) {
+                s.add_assign(x);
+            }
+            for (s, c) in state.iter_mut().zip(constants[0].iter()) {
+                s.add_assign(c);
+            }
+
+            for r in 0..2 * params.get_num_rescue_rounds() {
+                let sboxed : Vec<Fr> = state.iter().map(|x| if r % 2 == 0 { x.pow(&inalpha) } else { x.pow(&[5u64]) }).collect();
+                for i in 0..params.t() {
+                    let mut res = constants[r + 1][i];
+                    for j in 0..params.t() {
+                        let mut tmp = sboxed[j];
+                        tmp.mul_assign(&mds_matrix[i][j]);
+                        res.add_assign(&tmp);
+                    }
+                    state[i] = res;
+                }
+            }
+
+            state[0]
+        }
+
+        let params = BLS12Rescue::default();
+        let a = Fr::from_str("3").unwrap();
+        let b = Fr::from_str("9").unwrap();
+
+        let test_circuit = TestCircuit::<Bls12, BLS12Rescue, BLS12RescueSbox> {
+            expected_outputs: vec![native_rescue(&[a, b], &params)],
+            params,
+            sbox: BLS12RescueSbox::new(),
+            inputs: vec![a, b],
+        };
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        test_circuit.synthesize(&mut cs).expect("should synthesize");
+        assert!(cs.is_satisfied());
+
+        cs.modify_input(1, "rescue output/num", Fr::one());
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_rescue_hash_varlen() {
+        let params = BN256Rescue::default();
+        let x = Fr::from_str("42").unwrap();
+        let padding = *params.padding_constant();
+
+        // plain absorption pads with the constant, so the message ending with it collides with the shorter one
+        let plain = |input: &[Fr]| {
+            let mut hasher = Rescue::new(&params);
+            for e in input.iter() {
+                hasher.absorb(*e, &params);
+            }
+            hasher.squeeze(&params)
+        };
+        assert_eq!(plain(&[x]), plain(&[x, padding]));
+
+        // the native sponge with zero capacity is the Rescue of bellman
+        for input in [vec![], vec![x], vec![x, padding], vec![x, x, x]].iter() {
+            assert_eq!(rescue_hash_native::<Bn256, _, BN256RescueSbox>(input, None, &params), plain(input));
+        }
+
+        let varlen = |input: &[Fr], tag: u64| rescue_hash_varlen_native::<Bn256, _, BN256RescueSbox>(input, tag, &params);
+        assert!(varlen(&[x], 0) != varlen(&[x, padding], 0));
+        assert!(varlen(&[x], 0) != varlen(&[x], 1));
+
+        let mut results = vec![];
+        for input in [vec![x], vec![x, padding]].iter() {
+            let mut costs = vec![];
+            for &domain_separation in [false, true].iter() {
+                let mut cs = TestConstraintSystem::<Bn256>::new();
+                let allocated = input.iter().map(|e| {
+                    AllocatedNum::alloc2(cs.namespace(|| "input"), Some(*e)).unwrap()
+                }).collect::<Vec<_>>();
+
+                if domain_separation {
+                    let res = RescueGadget::<Bn256, BN256Rescue, BN256RescueSbox>::hash_varlen(
+                        cs.namespace(|| "hash varlen"), &allocated, 7, &params
+                    ).unwrap();
+                    assert_eq!(res.get_value(), Some(varlen(input, 7)));
+                    results.push(res.get_value().unwrap());
+                }
+                else {
+                    let mut sponge = RescueGadget::<Bn256, BN256Rescue, BN256RescueSbox>::new(&params);
+                    for e in allocated.into_iter() {
+                        sponge.absorb(e, cs.namespace(|| "absorb"), &params).unwrap();
+                    }
+                    sponge.squeeze(cs.namespace(|| "squeeze"), &params).unwrap();
+                }
+
+                assert!(cs.is_satisfied());
+                costs.push(cs.num_constraints());
+            }
+            // the header lives in the capacity: no extra permutation, and its S-boxes are folded as well
+            assert_eq!(costs[0], costs[1]);
+        }
+        assert!(results[0] != results[1]);
+    }
+
+    fn mds<E: Engine, Params: RescueParams<E::Fr>>(
+        in_state: &[Num<E>],
+        params: &Params,
+    ) -> Vec<Num<E>> {
+        let mut out_state = vec![];
+        let mds_matrix = params.get_mds_matrix();
+        let RESCUE_M = params.t();
+
+        for i in 0..RESCUE_M {
+            let mut res = Num::zero();
+            for j in 0..RESCUE_M {
+                let mut temp = in_state[j].clone();
+                temp.scale(mds_matrix[i][j]);
+                res.add_assign(&temp);
+            }
+            out_state.push(res);
+        }
+        out_state
+    }
+
+    // straightforward permutation, is the reference for rescue_f_fused
+    fn rescue_f<E: Engine, CS: ConstraintSystem<E>, Params: RescueParams<E::Fr>, SBOX: RescueSbox<E>>(
+        cs: &mut CS,
+        state: &mut [Num<E>],
+        params: &Params,
+    ) -> Result<(), SynthesisError> {
+
+        let RESCUE_M = params.t();
+        let RESCUE_ROUNDS = params.get_num_rescue_rounds();
+        let constants = params.get_constants();
+
+        for i in 0..RESCUE_M {
+            state[i].add_assign(&Num::from_constant(&constants[0][i], &cs));
+        }
+
+        for r in 0..2 * RESCUE_ROUNDS {
+
+            for entry in state.iter_mut() {
+                if r % 2 == 0 {
+                    *entry = SBOX::rescue_inalpha(&entry, cs.namespace(|| "sbox inalpha"))?;
+                }
+                else {
+                    *entry = SBOX::rescue_alpha(&entry, cs.namespace(|| "sbox alpha"))?;
+                }
+            }
+
+            for (input, output) in  mds::<E, Params>(state, params).into_iter().zip(state.iter_mut()) {
+                *output = input;
+            }
+            for i in 0..RESCUE_M {
+                state[i].add_assign(&Num::from_constant(&(constants[r + 1][i]), &cs));
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rescue_fused_permutation() {
+        let params = BN256Rescue::default();
+        let x = Fr::from_str("3").unwrap();
+        let y = Fr::from_str("9").unwrap();
+        let tag = Fr::from_str("42").unwrap();
+        let padding = *params.padding_constant();
+
+        // lanes of the state before the permutation as (value, is variable) and the number of folded S-boxes
+        let cases = vec![
+            // fresh sponge after absorbing two elements: the capacity is untouched
+            (vec![(x, true), (y, true), (Fr::zero(), false)], 1),
+            // fresh sponge after absorbing one element: the padding lane is known as well
+            (vec![(x, true), (padding, false), (Fr::zero(), false)], 2),
+            // domain-tagged capacity
+            (vec![(x, true), (y, true), (tag, false)], 1),
+            // later duplex: every lane is a variable, nothing to fold
+            (vec![(x, true), (y, true), (tag, true)], 0),
+        ];
+
+        for (lanes, num_folded) in cases.into_iter() {
+            let mut results = vec![];
+            for &fused in [false, true].iter() {
+                let mut cs = TestConstraintSystem::<Bn256>::new();
+                let mut state : Vec<Num<Bn256>> = lanes.iter().map(|&(value, is_variable)| {
+                    if is_variable {
+                        AllocatedNum::alloc2(cs.namespace(|| "input"), Some(value)).unwrap().into()
+                    }
+                    else if value.is_zero() {
+                        Num::zero()
+                    }
+                    else {
+                        Num::from_constant(&value, &cs)
+                    }
+                }).collect();
+
+                let before = cs.num_constraints();
+                if fused {
+                    rescue_f_fused::<_, _, _, BN256RescueSbox>(&mut cs, &mut state[..], &params).unwrap();
+                }
+                else {
+                    rescue_f::<_, _, _, BN256RescueSbox>(&mut cs, &mut state[..], &params).unwrap();
+                }
+
+                assert!(cs.is_satisfied());
+                let values : Vec<_> = state.iter().map(|x| x.get_value().unwrap()).collect();
+                results.push((values, cs.num_constraints() - before));
+            }
+
+            assert_eq!(results[0].0, results[1].0);
+            // 22 rounds of 6 S-boxes costing 3 constraints each
+            assert_eq!(results[0].1, 22 * 6 * 3);
+            assert_eq!(results[1].1, 22 * 6 * 3 - 3 * num_folded);
+        }
+    }
+}
@@ -0,0 +1,434 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+};
+
+use common::num::*;
+use super::{SpongeGadget, NativeSponge, varlen_header};
+use super::rescue::alpha_sbox::{sbox_alpha, sbox_inalpha};
+
+pub mod params;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub(crate) static RESCUE_PRIME_PERMUTATIONS_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+
+// Rescue-Prime (Rescue-XLIX permutation), every round is:
+// S-box x^alpha -> MDS -> first half of round constants -> S-box x^{1/alpha} -> MDS -> second half of round constants,
+// the sponge always pads the input with 1 followed by zeros up to the multiple of rate
+pub trait RescuePrimeParams<F: PrimeField> {
+    // width of the state
+    fn t(&self) -> usize;
+    // number of elements absorbed (and squeezed) per permutation, the rest is the capacity
+    fn rate(&self) -> usize;
+    fn num_rounds(&self) -> usize;
+    fn alpha(&self) -> u64;
+    // alpha^{-1} mod (p - 1) as exponent for the native computation
+    fn alpha_inv(&self) -> &[u64];
+    // 2 * t constants for every round
+    fn round_constants(&self, round: usize) -> &[F];
+    fn mds_matrix(&self) -> &[Vec<F>];
+}
+
+
+fn mds<E: Engine, Params: RescuePrimeParams<E::Fr>>(
+    in_state: &[Num<E>],
+    params: &Params,
+) -> Vec<Num<E>> {
+    let mds_matrix = params.mds_matrix();
+
+    mds_matrix.iter().map(|row| {
+        let mut res = Num::zero();
+        for (elem, coef) in in_state.iter().zip(row.iter()) {
+            let mut temp = elem.clone();
+            temp.scale(*coef);
+            res.add_assign(&temp);
+        }
+        res
+    }).collect()
+}
+
+// every S-box allocates its output, so linear combinations never grow beyond a single MDS layer
+fn rescue_prime_permutation<E: Engine, CS: ConstraintSystem<E>, Params: RescuePrimeParams<E::Fr>>(
+    mut cs: CS,
+    state: &mut [Num<E>],
+    params: &Params,
+) -> Result<(), SynthesisError> {
+
+    let t = params.t();
+
+    for round in 0..params.num_rounds() {
+        let cs = &mut cs.namespace(|| format!("rescue prime round {}", round));
+        let constants = params.round_constants(round);
+
+        for elem in state.iter_mut() {
+            *elem = sbox_alpha(cs.namespace(|| "sbox alpha"), elem, params.alpha())?;
+        }
+        let new_state = mds::<E, Params>(state, params);
+        for ((output, mut input), c) in state.iter_mut().zip(new_state.into_iter()).zip(constants[0..t].iter()) {
+            input.add_assign(&Num::from_constant(c, &cs));
+            *output = input;
+        }
+
+        for elem in state.iter_mut() {
+            *elem = sbox_inalpha(cs.namespace(|| "sbox inalpha"), elem, params.alpha())?;
+        }
+        let new_state = mds::<E, Params>(state, params);
+        for ((output, mut input), c) in state.iter_mut().zip(new_state.into_iter()).zip(constants[t..(2 * t)].iter()) {
+            input.add_assign(&Num::from_constant(c, &cs));
+            *output = input;
+        }
+    }
+
+    Ok(())
+}
+
+
+enum SpongeState<E: Engine> {
+    Absorbing(Vec<Num<E>>),
+    // output elements in reversed order
+    Squeezing(Vec<Num<E>>),
+}
+
+
+// the same API as RescueGadget, but the output of squeeze is the Rescue-Prime hash of all the absorbed elements
+pub struct RescuePrimeGadget<E: Engine, P: RescuePrimeParams<E::Fr>> {
+    sponge: SpongeState<E>,
+    state: Vec<Num<E>>,
+    // capacity-based domain separator, is put into the state by the first permutation
+    domain: Option<E::Fr>,
+    _params_marker: std::marker::PhantomData<P>,
+}
+
+impl<E: Engine, P: RescuePrimeParams<E::Fr>> RescuePrimeGadget<E, P> {
+
+    pub fn new(params: &P) -> Self {
+        RescuePrimeGadget {
+            sponge: SpongeState::Absorbing(vec![]),
+            state: (0..params.t()).map(|_| Num::zero()).collect(),
+            domain: None,
+            _params_marker: std::marker::PhantomData::<P>,
+        }
+    }
+
+    // the first capacity element is initialized with the domain tag,
+    // so that hashes for different purposes never collide
+    pub fn new_with_domain(params: &P, domain: E::Fr) -> Self {
+        let mut res = Self::new(params);
+        res.domain = Some(domain);
+        res
+    }
+
+    fn absorb_block<CS: ConstraintSystem<E>>(&mut self, mut cs: CS, block: &[Num<E>], params: &P) -> Result<(), SynthesisError> {
+        if let Some(domain) = self.domain.take() {
+            self.state[params.rate()].add_assign(&Num::from_constant(&domain, &cs));
+        }
+        for (s, x) in self.state.iter_mut().zip(block.iter()) {
+            s.add_assign(x);
+        }
+
+        RESCUE_PRIME_PERMUTATIONS_COUNT.fetch_add(1, Ordering::SeqCst);
+        rescue_prime_permutation(cs.namespace(|| "rescue prime permutation"), &mut self.state, params)
+    }
+
+    fn output(&self, params: &P) -> Vec<Num<E>> {
+        self.state[0..params.rate()].iter().rev().cloned().collect()
+    }
+
+    pub fn absorb<CS: ConstraintSystem<E>>(&mut self, val: AllocatedNum<E>, mut cs: CS, params: &P) -> Result<(), SynthesisError> {
+        let val = val.into();
+        let full_block = match self.sponge {
+            SpongeState::Absorbing(ref mut input) => {
+                if input.len() < params.rate() {
+                    input.push(val);
+                    return Ok(());
+                }
+                std::mem::replace(input, vec![val])
+            }
+            SpongeState::Squeezing(_) => {
+                // Drop the remaining output elements
+                self.sponge = SpongeState::Absorbing(vec![val]);
+                return Ok(());
+            }
+        };
+
+        // We've already absorbed as many elements as we can
+        self.absorb_block(cs.namespace(|| "rescue prime duplex"), &full_block, params)
+    }
+
+    pub fn squeeze<CS: ConstraintSystem<E>>(&mut self, mut cs: CS, params: &P) -> Result<AllocatedNum<E>, SynthesisError> {
+        loop {
+            let pending = match self.sponge {
+                SpongeState::Absorbing(ref mut input) => std::mem::replace(input, vec![]),
+                SpongeState::Squeezing(ref mut output) => {
+                    if let Some(mut e) = output.pop() {
+                        return e.simplify(cs.namespace(|| "simplification"));
+                    }
+                    // We've already squeezed out all available elements: permute again without any input
+                    self.absorb_block(cs.namespace(|| "rescue prime squeezing"), &[], params)?;
+                    self.sponge = SpongeState::Squeezing(self.output(params));
+                    continue;
+                }
+            };
+
+            // padding: 1 followed by zeros, may result in an additional block
+            let mut padded = pending;
+            padded.push(Num::from_constant(&E::Fr::one(), &cs));
+            while padded.len() % params.rate() != 0 {
+                padded.push(Num::zero());
+            }
+            for block in padded.chunks(params.rate()) {
+                self.absorb_block(cs.namespace(|| "rescue prime duplex"), block, params)?;
+            }
+            self.sponge = SpongeState::Squeezing(self.output(params));
+        }
+    }
+}
+
+
+impl<E: Engine, P: RescuePrimeParams<E::Fr>> SpongeGadget<E> for RescuePrimeGadget<E, P> {
+    type Params = P;
+
+    fn new(params: &P) -> Self {
+        RescuePrimeGadget::new(params)
+    }
+
+    fn new_with_domain(params: &P, domain: E::Fr) -> Self {
+        RescuePrimeGadget::new_with_domain(params, domain)
+    }
+
+    fn absorb<CS: ConstraintSystem<E>>(&mut self, val: AllocatedNum<E>, cs: CS, params: &P) -> Result<(), SynthesisError> {
+        RescuePrimeGadget::absorb(self, val, cs, params)
+    }
+
+    fn squeeze<CS: ConstraintSystem<E>>(&mut self, cs: CS, params: &P) -> Result<AllocatedNum<E>, SynthesisError> {
+        RescuePrimeGadget::squeeze(self, cs, params)
+    }
+}
+
+
+// native counterpart of the gadget: is used by prover and for test vectors generation
+pub fn rescue_prime_permutation_native<F: PrimeField, Params: RescuePrimeParams<F>>(state: &mut [F], params: &Params) {
+    let t = params.t();
+
+    let apply_mds = |state: &mut [F]| {
+        let new_state : Vec<F> = params.mds_matrix().iter().map(|row| {
+            let mut res = F::zero();
+            for (elem, coef) in state.iter().zip(row.iter()) {
+                let mut temp = *elem;
+                temp.mul_assign(coef);
+                res.add_assign(&temp);
+            }
+            res
+        }).collect();
+        state.copy_from_slice(&new_state[..]);
+    };
+
+    for round in 0..params.num_rounds() {
+        let constants = params.round_constants(round);
+
+        for elem in state.iter_mut() {
+            *elem = elem.pow(&[params.alpha()]);
+        }
+        apply_mds(state);
+        for (elem, c) in state.iter_mut().zip(constants[0..t].iter()) {
+            elem.add_assign(c);
+        }
+
+        for elem in state.iter_mut() {
+            *elem = elem.pow(params.alpha_inv());
+        }
+        apply_mds(state);
+        for (elem, c) in state.iter_mut().zip(constants[t..(2 * t)].iter()) {
+            elem.add_assign(c);
+        }
+    }
+}
+
+enum NativeSpongeState<F: PrimeField> {
+    Absorbing(Vec<F>),
+    Squeezing(Vec<F>),
+}
+
+pub struct RescuePrime<F: PrimeField> {
+    sponge: NativeSpongeState<F>,
+    state: Vec<F>,
+    domain: Option<F>,
+}
+
+impl<F: PrimeField> RescuePrime<F> {
+    pub fn new<P: RescuePrimeParams<F>>(params: &P) -> Self {
+        RescuePrime {
+            sponge: NativeSpongeState::Absorbing(vec![]),
+            state: vec![F::zero(); params.t()],
+            domain: None,
+        }
+    }
+
+    pub fn new_with_domain<P: RescuePrimeParams<F>>(params: &P, domain: F) -> Self {
+        let mut res = Self::new(params);
+        res.domain = Some(domain);
+        res
+    }
+
+    fn absorb_block<P: RescuePrimeParams<F>>(&mut self, block: &[F], params: &P) {
+        if let Some(domain) = self.domain.take() {
+            self.state[params.rate()].add_assign(&domain);
+        }
+        for (s, x) in self.state.iter_mut().zip(block.iter()) {
+            s.add_assign(x);
+        }
+        rescue_prime_permutation_native(&mut self.state, params);
+    }
+
+    fn output<P: RescuePrimeParams<F>>(&self, params: &P) -> Vec<F> {
+        self.state[0..params.rate()].iter().rev().cloned().collect()
+    }
+
+    pub fn absorb<P: RescuePrimeParams<F>>(&mut self, val: F, params: &P) {
+        let full_block = match self.sponge {
+            NativeSpongeState::Absorbing(ref mut input) => {
+                if input.len() < params.rate() {
+                    input.push(val);
+                    return;
+                }
+                std::mem::replace(input, vec![val])
+            }
+            NativeSpongeState::Squeezing(_) => {
+                self.sponge = NativeSpongeState::Absorbing(vec![val]);
+                return;
+            }
+        };
+        self.absorb_block(&full_block, params);
+    }
+
+    pub fn squeeze<P: RescuePrimeParams<F>>(&mut self, params: &P) -> F {
+        loop {
+            let pending = match self.sponge {
+                NativeSpongeState::Absorbing(ref mut input) => std::mem::replace(input, vec![]),
+                NativeSpongeState::Squeezing(ref mut output) => {
+                    if let Some(e) = output.pop() {
+                        return e;
+                    }
+                    self.absorb_block(&[], params);
+                    self.sponge = NativeSpongeState::Squeezing(self.output(params));
+                    continue;
+                }
+            };
+
+            let mut padded = pending;
+            padded.push(F::one());
+            while padded.len() % params.rate() != 0 {
+                padded.push(F::zero());
+            }
+            for block in padded.chunks(params.rate()) {
+                self.absorb_block(block, params);
+            }
+            self.sponge = NativeSpongeState::Squeezing(self.output(params));
+        }
+    }
+
+    // native counterpart of SpongeGadget::hash_varlen
+    pub fn hash_varlen<P: RescuePrimeParams<F>>(input: &[F], domain_tag: u64, params: &P) -> F {
+        let mut hasher = Self::new_with_domain(params, varlen_header(domain_tag, input.len()));
+        for x in input.iter() {
+            hasher.absorb(*x, params);
+        }
+        hasher.squeeze(params)
+    }
+}
+
+impl<F: PrimeField, P: RescuePrimeParams<F>> NativeSponge<F, P> for RescuePrime<F> {
+    fn new(params: &P) -> Self {
+        RescuePrime::new(params)
+    }
+
+    fn new_with_domain(params: &P, domain: F) -> Self {
+        RescuePrime::new_with_domain(params, domain)
+    }
+
+    fn absorb(&mut self, val: F, params: &P) {
+        RescuePrime::absorb(self, val, params)
+    }
+
+    fn squeeze(&mut self, params: &P) -> F {
+        RescuePrime::squeeze(self, params)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::params::DefaultRescuePrimeParams;
+    use hashes::sponge::test::check_sponge_against_native;
+
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use crate::tester::naming_oblivious_cs::NamingObliviousConstraintSystem as TestConstraintSystem;
+
+    fn fr(s: &str) -> Fr {
+        Fr::from_str(s).unwrap()
+    }
+
+    // vectors are produced by the reference (sage / python) implementation of rescue_prime_hash
+    #[test]
+    fn test_rescue_prime_native_vectors() {
+        let params = DefaultRescuePrimeParams::<Fr>::bn256_default();
+        assert_eq!(params.num_rounds(), 14);
+
+        let vectors = vec![
+            (vec![], vec![
+                "11859570646544414528448865934361814928682472944063369147923859205431563103349",
+                "21375695955579975596538309438706857741777746532051819135338595035329530298717",
+            ]),
+            (vec!["1", "2"], vec![
+                "19955277490808493510831169602631407111104744046414437667271324145367080531545",
+                "649740822031455595330432760014348331074228589165010691290466708483664201035",
+            ]),
+            (vec!["1", "2", "3"], vec![
+                "10426312538076513787842576207928300055993667092218520332880144400670314798844",
+                "4184464354440461145180986148039895060330502056651856767983333175045473850499",
+            ]),
+        ];
+
+        for (inputs, outputs) in vectors.into_iter() {
+            let mut hasher = RescuePrime::<Fr>::new(&params);
+            for x in inputs.iter() {
+                hasher.absorb(fr(x), &params);
+            }
+            for y in outputs.iter() {
+                assert_eq!(hasher.squeeze(&params), fr(y));
+            }
+        }
+    }
+
+    #[test]
+    fn test_rescue_prime_gadget_against_native() {
+        let params = DefaultRescuePrimeParams::<Fr>::bn256_default();
+
+        // full block requires the additional padding block, squeezing beyond rate requires one more permutation
+        let (num_inputs, num_outputs) = (params.rate(), params.rate() + 1);
+        check_sponge_against_native(
+            RescuePrimeGadget::<Bn256, _>::new(&params), RescuePrime::<Fr>::new(&params), &params, num_inputs, num_outputs
+        );
+
+        let domain = fr("42");
+        check_sponge_against_native(
+            RescuePrimeGadget::<Bn256, _>::new_with_domain(&params, domain),
+            RescuePrime::<Fr>::new_with_domain(&params, domain),
+            &params,
+            num_inputs,
+            num_outputs,
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr,
+};
+use bellman::pairing::bn256;
+
+use num_bigint::BigUint;
+
+use hashes::keccak::shake256_native;
+use hashes::rescue::alpha_sbox::inverse_alpha;
+
+use super::RescuePrimeParams;
+
+
+fn field_characteristic<F: PrimeField>() -> BigUint {
+    let mut bytes = vec![];
+    for limb in F::char().as_ref().iter() {
+        bytes.extend((0..8).map(|i| (limb >> (8 * i)) as u8));
+    }
+    BigUint::from_bytes_le(&bytes)
+}
+
+fn field_element_from_u64<F: PrimeField>(x: u64) -> F {
+    let mut repr = F::Repr::default();
+    repr.as_mut()[0] = x;
+    F::from_repr(repr).expect("is a valid field element")
+}
+
+fn binomial(n: usize, k: usize) -> BigUint {
+    let mut res = BigUint::from(1u64);
+    for i in 0..k {
+        res = res * BigUint::from((n - i) as u64) / BigUint::from((i + 1) as u64);
+    }
+    res
+}
+
+// the smallest number of rounds resisting the Groebner basis attack, at least 5, plus 50% of security margin
+fn get_number_of_rounds(t: usize, capacity: usize, security_level: usize, alpha: u64) -> usize {
+    let rate = t - capacity;
+    let target = BigUint::from(1u64) << security_level;
+
+    let l1 = (1..25).find(|&n: &usize| {
+        let dcon = ((alpha as usize - 1) * t * (n - 1)) / 2 + 2;
+        let v = t * (n - 1) + rate;
+        let b = binomial(v + dcon, v);
+        &b * &b > target
+    }).unwrap_or(24);
+
+    (3 * std::cmp::max(5, l1) + 1) / 2
+}
+
+
+// parameters generated exactly as in the reference implementation of Rescue-Prime:
+// round constants are taken from SHAKE256("Rescue-XLIX(p,m,c,security_level)"), every constant is
+// built from ceil(log2(p) / 8) + 1 little-endian bytes reduced modulo p;
+// MDS matrix is the transposed right half of the echelon form of the t x 2t Vandermonde matrix
+// built on the smallest primitive element g
+pub struct DefaultRescuePrimeParams<F: PrimeField> {
+    t: usize,
+    rate: usize,
+    num_rounds: usize,
+    alpha: u64,
+    alpha_inv: Vec<u64>,
+    round_constants: Vec<Vec<F>>,
+    mds_matrix: Vec<Vec<F>>,
+}
+
+impl<F: PrimeField> DefaultRescuePrimeParams<F> {
+    pub fn new(t: usize, capacity: usize, security_level: usize, alpha: u64, generator: F) -> Self {
+        assert!(capacity < t);
+
+        let num_rounds = get_number_of_rounds(t, capacity, security_level, alpha);
+
+        let bytes_per_int = (F::NUM_BITS as usize + 7) / 8 + 1;
+        let seed = format!("Rescue-XLIX({},{},{},{})", field_characteristic::<F>(), t, capacity, security_level);
+        let bytes = shake256_native(seed.as_bytes(), bytes_per_int * 2 * t * num_rounds);
+
+        let base = field_element_from_u64::<F>(256);
+        let constants : Vec<F> = bytes.chunks(bytes_per_int).map(|chunk| {
+            chunk.iter().rev().fold(F::zero(), |mut acc, byte| {
+                acc.mul_assign(&base);
+                acc.add_assign(&field_element_from_u64(*byte as u64));
+                acc
+            })
+        }).collect();
+        let round_constants = constants.chunks(2 * t).map(|chunk| chunk.to_vec()).collect();
+
+        let mut v : Vec<Vec<F>> = (0..t).map(|i| {
+            (0..(2 * t)).map(|j| generator.pow(&[(i * j) as u64])).collect()
+        }).collect();
+
+        for col in 0..t {
+            let pivot = (col..t).find(|&r| !v[r][col].is_zero()).expect("Vandermonde matrix is nonsingular");
+            v.swap(col, pivot);
+
+            let inv = v[col][col].inverse().expect("is nonzero");
+            for x in v[col].iter_mut() {
+                x.mul_assign(&inv);
+            }
+
+            for r in 0..t {
+                if r == col || v[r][col].is_zero() {
+                    continue;
+                }
+                let factor = v[r][col];
+                for j in 0..(2 * t) {
+                    let mut tmp = v[col][j];
+                    tmp.mul_assign(&factor);
+                    v[r][j].sub_assign(&tmp);
+                }
+            }
+        }
+
+        let mds_matrix = (0..t).map(|i| (0..t).map(|j| v[j][t + i]).collect()).collect();
+
+        DefaultRescuePrimeParams {
+            t,
+            rate: t - capacity,
+            num_rounds,
+            alpha,
+            alpha_inv: inverse_alpha::<F>(alpha),
+            round_constants,
+            mds_matrix,
+        }
+    }
+}
+
+impl DefaultRescuePrimeParams<bn256::Fr> {
+    // width 3, capacity 1, alpha = 5, 128 bits of security: results in 14 rounds, 5 is the smallest primitive element
+    pub fn bn256_default() -> Self {
+        Self::new(3, 1, 128, 5, field_element_from_u64(5))
+    }
+}
+
+impl<F: PrimeField> RescuePrimeParams<F> for DefaultRescuePrimeParams<F> {
+    fn t(&self) -> usize {
+        self.t
+    }
+
+    fn rate(&self) -> usize {
+        self.rate
+    }
+
+    fn num_rounds(&self) -> usize {
+        self.num_rounds
+    }
+
+    fn alpha(&self) -> u64 {
+        self.alpha
+    }
+
+    fn alpha_inv(&self) -> &[u64] {
+        &self.alpha_inv[..]
+    }
+
+    fn round_constants(&self, round: usize) -> &[F] {
+        &self.round_constants[round][..]
+    }
+
+    fn mds_matrix(&self) -> &[Vec<F>] {
+        &self.mds_matrix[..]
+    }
+}
@@ -0,0 +1,376 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+};
+
+use common::num::*;
+use super::{SpongeGadget, NativeSponge, varlen_header};
+
+
+// everything an algebraic hash has to supply to be used in the duplex sponge below:
+// the shape of the state and the permutation itself, both in-circuit and native.
+// Is implemented by the params of the hash (AnemoiParams, GriffinParams, MiMCParams, DefaultPoseidonParams)
+pub trait SpongePermutation<F: PrimeField> {
+    fn state_width(&self) -> usize;
+    // number of elements absorbed (and squeezed) per permutation, the rest is the capacity
+    fn sponge_rate(&self) -> usize;
+    // partially filled blocks are padded with this constant
+    fn sponge_padding(&self) -> F;
+
+    fn permute_native(&self, state: &mut [F]);
+}
+
+pub trait SpongePermutationGadget<E: Engine>: SpongePermutation<E::Fr> {
+    fn permute<CS: ConstraintSystem<E>>(&self, cs: CS, state: &mut [Num<E>]) -> Result<(), SynthesisError>;
+}
+
+
+enum SpongeState<E: Engine> {
+    Absorbing(Vec<Num<E>>),
+    Squeezing(Vec<Option<Num<E>>>),
+}
+
+impl<E: Engine> SpongeState<E> {
+    fn absorb(val: Num<E>) -> Self {
+        SpongeState::Absorbing(vec![val])
+    }
+}
+
+// domain separator (if any) is added to the first capacity element before the first permutation
+fn duplex<E: Engine, CS: ConstraintSystem<E>, P: SpongePermutationGadget<E>>(
+    state: &mut [Num<E>],
+    input: &mut Vec<Num<E>>,
+    domain: Option<E::Fr>,
+    cs: CS,
+    params: &P,
+) -> Result<Vec<Option<Num<E>>>, SynthesisError> {
+
+    let rate = params.sponge_rate();
+    while input.len() < rate {
+        input.push(Num::from_constant(&params.sponge_padding(), &cs));
+    }
+    if let Some(domain) = domain {
+        state[rate].add_assign(&Num::from_constant(&domain, &cs));
+    }
+
+    for (s, x) in state.iter_mut().zip(input.iter()) {
+        s.add_assign(x);
+    }
+
+    params.permute(cs, state)?;
+
+    Ok(state[0..rate].iter().map(|x| Some(x.clone())).collect())
+}
+
+
+// the same sponge construction and API as RescueGadget
+pub struct DuplexSpongeGadget<E: Engine, P: SpongePermutationGadget<E>> {
+    sponge: SpongeState<E>,
+    state: Vec<Num<E>>,
+    // capacity-based domain separator, is put into the state by the first permutation
+    domain: Option<E::Fr>,
+    _params_marker: std::marker::PhantomData<P>,
+}
+
+impl<E: Engine, P: SpongePermutationGadget<E>> DuplexSpongeGadget<E, P> {
+
+    pub fn new(params: &P) -> Self {
+        DuplexSpongeGadget {
+            sponge: SpongeState::Absorbing(vec![]),
+            state: (0..params.state_width()).map(|_| Num::zero()).collect(),
+            domain: None,
+            _params_marker: std::marker::PhantomData::<P>,
+        }
+    }
+
+    pub fn new_with_domain(params: &P, domain: E::Fr) -> Self {
+        let mut res = Self::new(params);
+        res.domain = Some(domain);
+        res
+    }
+
+    pub fn absorb<CS: ConstraintSystem<E>>(&mut self, val: AllocatedNum<E>, mut cs: CS, params: &P) -> Result<(), SynthesisError> {
+        let val = val.into();
+        match self.sponge {
+            SpongeState::Absorbing(ref mut input) => {
+                if input.len() < params.sponge_rate() {
+                    input.push(val);
+                    return Ok(());
+                }
+
+                // We've already absorbed as many elements as we can
+                duplex(&mut self.state, input, self.domain.take(), cs.namespace(|| "duplex"), params)?;
+                self.sponge = SpongeState::absorb(val);
+            }
+            SpongeState::Squeezing(_) => {
+                // Drop the remaining output elements
+                self.sponge = SpongeState::absorb(val);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn squeeze<CS: ConstraintSystem<E>>(&mut self, mut cs: CS, params: &P) -> Result<AllocatedNum<E>, SynthesisError> {
+        loop {
+            match self.sponge {
+                SpongeState::Absorbing(ref mut input) => {
+                    self.sponge = SpongeState::Squeezing(duplex(
+                        &mut self.state,
+                        input,
+                        self.domain.take(),
+                        cs.namespace(|| "duplex"),
+                        params,
+                    )?);
+                }
+                SpongeState::Squeezing(ref mut output) => {
+                    for entry in output.iter_mut() {
+                        if let Some(mut e) = entry.take() {
+                            let e = e.simplify(cs.namespace(|| "simplification"))?;
+                            return Ok(e)
+                        }
+                    }
+                    // We've already squeezed out all available elements
+                    self.sponge = SpongeState::Absorbing(vec![]);
+                }
+            }
+        }
+    }
+}
+
+
+impl<E: Engine, P: SpongePermutationGadget<E>> SpongeGadget<E> for DuplexSpongeGadget<E, P> {
+    type Params = P;
+
+    fn new(params: &P) -> Self {
+        DuplexSpongeGadget::new(params)
+    }
+
+    fn new_with_domain(params: &P, domain: E::Fr) -> Self {
+        DuplexSpongeGadget::new_with_domain(params, domain)
+    }
+
+    fn absorb<CS: ConstraintSystem<E>>(&mut self, val: AllocatedNum<E>, cs: CS, params: &P) -> Result<(), SynthesisError> {
+        DuplexSpongeGadget::absorb(self, val, cs, params)
+    }
+
+    fn squeeze<CS: ConstraintSystem<E>>(&mut self, cs: CS, params: &P) -> Result<AllocatedNum<E>, SynthesisError> {
+        DuplexSpongeGadget::squeeze(self, cs, params)
+    }
+}
+
+
+enum NativeSpongeState<F: PrimeField> {
+    Absorbing(Vec<F>),
+    // output elements in reversed order
+    Squeezing(Vec<F>),
+}
+
+// native counterpart of the gadget: is used by prover and for test vectors generation
+pub struct DuplexSponge<F: PrimeField, P: SpongePermutation<F>> {
+    sponge: NativeSpongeState<F>,
+    state: Vec<F>,
+    domain: Option<F>,
+    _params_marker: std::marker::PhantomData<P>,
+}
+
+impl<F: PrimeField, P: SpongePermutation<F>> DuplexSponge<F, P> {
+    pub fn new(params: &P) -> Self {
+        DuplexSponge {
+            sponge: NativeSpongeState::Absorbing(vec![]),
+            state: vec![F::zero(); params.state_width()],
+            domain: None,
+            _params_marker: std::marker::PhantomData::<P>,
+        }
+    }
+
+    pub fn new_with_domain(params: &P, domain: F) -> Self {
+        let mut res = Self::new(params);
+        res.domain = Some(domain);
+        res
+    }
+
+    fn duplex(state: &mut [F], input: &mut Vec<F>, domain: Option<F>, params: &P) -> Vec<F> {
+        let rate = params.sponge_rate();
+        while input.len() < rate {
+            input.push(params.sponge_padding());
+        }
+        if let Some(domain) = domain {
+            state[rate].add_assign(&domain);
+        }
+        for (s, x) in state.iter_mut().zip(input.iter()) {
+            s.add_assign(x);
+        }
+        params.permute_native(state);
+
+        state[0..rate].iter().rev().cloned().collect()
+    }
+
+    pub fn absorb(&mut self, val: F, params: &P) {
+        match self.sponge {
+            NativeSpongeState::Absorbing(ref mut input) => {
+                if input.len() < params.sponge_rate() {
+                    input.push(val);
+                    return;
+                }
+                Self::duplex(&mut self.state, input, self.domain.take(), params);
+                self.sponge = NativeSpongeState::Absorbing(vec![val]);
+            }
+            NativeSpongeState::Squeezing(_) => {
+                self.sponge = NativeSpongeState::Absorbing(vec![val]);
+            }
+        }
+    }
+
+    pub fn squeeze(&mut self, params: &P) -> F {
+        loop {
+            match self.sponge {
+                NativeSpongeState::Absorbing(ref mut input) => {
+                    self.sponge = NativeSpongeState::Squeezing(Self::duplex(&mut self.state, input, self.domain.take(), params));
+                }
+                NativeSpongeState::Squeezing(ref mut output) => {
+                    if let Some(e) = output.pop() {
+                        return e;
+                    }
+                    self.sponge = NativeSpongeState::Absorbing(vec![]);
+                }
+            }
+        }
+    }
+
+    // native counterpart of SpongeGadget::hash_varlen
+    pub fn hash_varlen(input: &[F], domain_tag: u64, params: &P) -> F {
+        let mut hasher = Self::new_with_domain(params, varlen_header(domain_tag, input.len()));
+        for x in input.iter() {
+            hasher.absorb(*x, params);
+        }
+        hasher.squeeze(params)
+    }
+}
+
+impl<F: PrimeField, P: SpongePermutation<F>> NativeSponge<F, P> for DuplexSponge<F, P> {
+    fn new(params: &P) -> Self {
+        DuplexSponge::new(params)
+    }
+
+    fn new_with_domain(params: &P, domain: F) -> Self {
+        DuplexSponge::new_with_domain(params, domain)
+    }
+
+    fn absorb(&mut self, val: F, params: &P) {
+        DuplexSponge::absorb(self, val, params)
+    }
+
+    fn squeeze(&mut self, params: &P) -> F {
+        DuplexSponge::squeeze(self, params)
+    }
+}
+
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use hashes::anemoi::{AnemoiGadget, AnemoiParams};
+    use hashes::griffin::{GriffinGadget, GriffinParams};
+    use hashes::mimc::MiMCParams;
+    use hashes::poseidon::params::DefaultPoseidonParams;
+    use hashes::rescue::RescueGadget;
+    use hashes::rescue::bn256_rescue_sbox::BN256RescueSbox;
+
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::redshift::IOP::hashes::rescue::bn256_rescue_params::BN256Rescue;
+    use crate::tester::naming_oblivious_cs::NamingObliviousConstraintSystem as TestConstraintSystem;
+    use rand::{XorShiftRng, SeedableRng, Rng};
+
+    // absorbs random inputs into both sponges and checks that every squeezed element matches
+    pub(crate) fn check_sponge_against_native<S, N>(
+        mut gadget: S,
+        mut native: N,
+        params: &S::Params,
+        num_inputs: usize,
+        num_outputs: usize,
+    )
+        where S: SpongeGadget<Bn256>, N: NativeSponge<Fr, S::Params>
+    {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        for _ in 0..num_inputs {
+            let x : Fr = rng.gen();
+            let num = AllocatedNum::alloc2(cs.namespace(|| "input"), Some(x)).unwrap();
+            gadget.absorb(num, cs.namespace(|| "absorb"), params).unwrap();
+            native.absorb(x, params);
+        }
+
+        for _ in 0..num_outputs {
+            let res = gadget.squeeze(cs.namespace(|| "squeeze"), params).unwrap();
+            assert_eq!(res.get_value(), Some(native.squeeze(params)));
+        }
+
+        assert!(cs.is_satisfied());
+    }
+
+    // partially filled absorption, squeezing of all the output elements and one more (requires the next duplex)
+    fn check_duplex_sponge_against_native<P: SpongePermutationGadget<Bn256>>(params: &P) {
+        let rate = params.sponge_rate();
+        check_sponge_against_native(
+            DuplexSpongeGadget::<Bn256, P>::new(params),
+            DuplexSponge::<Fr, P>::new(params),
+            params,
+            rate + 1,
+            rate + 1,
+        );
+    }
+
+    #[test]
+    fn test_duplex_sponges_against_native() {
+        check_duplex_sponge_against_native(&DefaultPoseidonParams::<Fr>::bn256_default());
+        check_duplex_sponge_against_native(&AnemoiParams::<Fr>::bn256_default());
+        check_duplex_sponge_against_native(&GriffinParams::<Fr>::bn256_default());
+        for &alpha in [5u64, 7].iter() {
+            check_duplex_sponge_against_native(&MiMCParams::<Fr>::new(220, alpha, b"mimcsponge"));
+        }
+    }
+
+    // 2 -> 1 hash of the Merkle tree node: two elements absorbed into the fresh sponge, one squeezed
+    fn node_hash_cost<S: SpongeGadget<Bn256>>(params: &S::Params) -> usize {
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let mut sponge = S::new(params);
+        for x in ["1", "2"].iter() {
+            let num = AllocatedNum::alloc2(cs.namespace(|| "input"), Some(Fr::from_str(x).unwrap())).unwrap();
+            sponge.absorb(num, cs.namespace(|| "absorb"), params).unwrap();
+        }
+        sponge.squeeze(cs.namespace(|| "squeeze"), params).unwrap();
+
+        assert!(cs.is_satisfied());
+        cs.num_constraints()
+    }
+
+    #[test]
+    fn test_node_hash_cost_against_rescue() {
+        // 22 rounds of 6 S-boxes costing 3 constraints each, the S-box on the untouched capacity is folded
+        let rescue_cost = node_hash_cost::<RescueGadget<Bn256, BN256Rescue, BN256RescueSbox>>(&BN256Rescue::default());
+        assert_eq!(rescue_cost, 22 * 6 * 3 - 3 + 1);
+
+        // rate 1: two permutations of 21 rounds costing 5 constraints each
+        let anemoi_cost = node_hash_cost::<AnemoiGadget<Bn256>>(&AnemoiParams::bn256_default());
+        assert_eq!(anemoi_cost, 2 * 21 * 5 + 1);
+
+        // rate 2: a single permutation of 12 rounds costing 8 constraints each
+        let griffin_cost = node_hash_cost::<GriffinGadget<Bn256>>(&GriffinParams::bn256_default());
+        assert_eq!(griffin_cost, 12 * 8 + 1);
+
+        // 211 vs 394: Anemoi is about 1.9x cheaper than Rescue for the node hash, Griffin (97) about 4x
+        assert!(10 * anemoi_cost < 6 * rescue_cost);
+        assert!(4 * griffin_cost < rescue_cost);
+    }
+}
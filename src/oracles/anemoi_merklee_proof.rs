@@ -0,0 +1,7 @@
+use hashes::anemoi::AnemoiGadget;
+
+use super::sponge_merklee_proof::*;
+
+
+pub type AnemoiTreeGadgetParams<'a, P> = SpongeTreeGadgetParams<'a, P>;
+pub type AnemoiTreeGadget<'a, E> = SpongeTreeGadget<'a, E, AnemoiGadget<E>>;
@@ -0,0 +1,8 @@
+use common::blake2s::Blake2sHasher;
+
+use super::digest_merklee_proof::*;
+
+
+pub type Blake2sTreeGadget<E> = DigestTreeGadget<E, Blake2sHasher>;
+pub type Blake2sTree = DigestTree<Blake2sHasher>;
+
@@ -0,0 +1,377 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr,
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+};
+
+use common::num::*;
+use common::boolean::*;
+use common::digest::*;
+use common::{FromStream, OracleHeight};
+
+use super::*;
+
+
+// Merklee tree over bit-oriented 256-bit hash (sha256, keccak256, blake2s), which is EVM-friendly:
+// every leaf is the hash of the concatenation of 32-byte big-endian encodings of its elements,
+// every node is the hash of the 64-byte concatenation of its children,
+// 256-bit digests are represented by two field elements holding the upper and lower 128-bit halves
+pub const DIGEST_HALF_SIZE: usize = 128;
+
+
+#[derive(Clone)]
+pub struct Digest256<E: Engine> {
+    pub hi: AllocatedNum<E>,
+    pub lo: AllocatedNum<E>,
+}
+
+// no range checks are done while parsing, the halves are unpacked (and hence checked to fit into 128 bits)
+// when used inside the tree
+impl<E: Engine, SPP> FromStream<E, SPP> for Digest256<E> {
+    fn from_stream<CS: ConstraintSystem<E>, I: Iterator<Item = Option<E::Fr>>>(
+        mut cs: CS,
+        iter: &mut I,
+        _params: SPP,
+    ) -> Result<Self, SynthesisError>
+    {
+        let hi = AllocatedNum::alloc2(cs.namespace(|| "digest upper half"), iter.next().ok_or(SynthesisError::Unknown)?)?;
+        let lo = AllocatedNum::alloc2(cs.namespace(|| "digest lower half"), iter.next().ok_or(SynthesisError::Unknown)?)?;
+
+        Ok(Digest256 { hi, lo })
+    }
+}
+
+
+pub struct DigestTreeGadgetParams {
+    pub num_elems_per_leaf: usize,
+}
+
+
+pub struct DigestTreeGadget<E: Engine, H: DigestHasher> {
+    num_elems_per_leaf: usize,
+    _marker: std::marker::PhantomData<(E, H)>,
+}
+
+impl<E: Engine, H: DigestHasher> DigestTreeGadget<E, H> {
+
+    pub fn new_impl(num_elems_per_leaf: usize) -> Self {
+        Self {
+            num_elems_per_leaf,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    // field element -> big-endian bits, enforces that the element fits into num_bits
+    fn unpack_bits<CS: ConstraintSystem<E>>(mut cs: CS, num: &AllocatedNum<E>, num_bits: usize) -> Result<Vec<Boolean>, SynthesisError> {
+        assert!(num_bits < E::Fr::CAPACITY as usize);
+
+        let repr = num.get_value().map(|x| x.into_repr());
+        let mut lc = Num::<E>::zero();
+        let mut coeff = E::Fr::one();
+        let mut bits = Vec::with_capacity(num_bits);
+
+        for i in 0..num_bits {
+            let bit_value = repr.as_ref().map(|r| (r.as_ref()[i / 64] >> (i % 64)) & 1 == 1);
+            let bit = Boolean::from(AllocatedBit::alloc(cs.namespace(|| "unpacked bit"), bit_value)?);
+
+            lc.mut_add_bool_with_coeff(CS::one(), &bit, coeff);
+            coeff.double();
+            bits.push(bit);
+        }
+
+        cs.enforce(
+            || "unpacking constraint",
+            |_| lc.lc(E::Fr::one()),
+            |lc| lc + CS::one(),
+            |lc| lc + num.get_variable(),
+        );
+
+        bits.reverse();
+        Ok(bits)
+    }
+
+    fn digest_into_bits<CS: ConstraintSystem<E>>(mut cs: CS, digest: &Digest256<E>) -> Result<Vec<Boolean>, SynthesisError> {
+        let mut bits = Self::unpack_bits(cs.namespace(|| "unpack upper half"), &digest.hi, DIGEST_HALF_SIZE)?;
+        bits.extend(Self::unpack_bits(cs.namespace(|| "unpack lower half"), &digest.lo, DIGEST_HALF_SIZE)?);
+        Ok(bits)
+    }
+
+    fn digest_from_bits<CS: ConstraintSystem<E>>(mut cs: CS, bits: &[Boolean]) -> Result<Digest256<E>, SynthesisError> {
+        assert_eq!(bits.len(), DIGEST_SIZE);
+
+        let hi = pack_be_bits(cs.namespace(|| "pack upper half"), &bits[0..DIGEST_HALF_SIZE])?;
+        let lo = pack_be_bits(cs.namespace(|| "pack lower half"), &bits[DIGEST_HALF_SIZE..])?;
+        Ok(Digest256 { hi, lo })
+    }
+
+    fn hash_elems_into_leaf<CS>(&self, mut cs: CS, elems: &[AllocatedNum<E>]) -> Result<Vec<Boolean>, SynthesisError>
+    where CS: ConstraintSystem<E> {
+        assert_eq!(elems.len(), self.num_elems_per_leaf);
+
+        let mut input = Vec::with_capacity(elems.len() * FIELD_ELEMENT_ENCODING_SIZE);
+        for elem in elems {
+            let bits = field_element_into_be_bits(cs.namespace(|| "hashing into leaf: unpacking"), elem)?;
+            input.extend(bits);
+        }
+
+        H::hash(cs.namespace(|| "hashing into leaf"), &input)
+    }
+
+    fn hash_node<CS>(&self, mut cs: CS, left: &[Boolean], right: &[Boolean]) -> Result<Vec<Boolean>, SynthesisError>
+    where CS : ConstraintSystem<E> {
+        let input : Vec<Boolean> = left.iter().chain(right.iter()).cloned().collect();
+        H::hash(cs.namespace(|| "hashing inside Merklee tree"), &input)
+    }
+
+    // checks inclusion of the leaf hash into the root
+    fn check_hash_inclusion_with_parsed_path<CS: ConstraintSystem<E>>(
+        &self,
+        mut cs: CS,
+        height: usize,
+        root: &Digest256<E>,
+        leaf_hash : Vec<Boolean>,
+        path: &[Boolean],
+        witness: &[Digest256<E>]
+    ) -> Result<Boolean, SynthesisError> {
+
+        if height != witness.len() {
+            println!("Height of the tree: {} differs from witness length: {}", height, witness.len());
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let mut cur = leaf_hash;
+
+        // Ascend the merkle tree authentication path
+        for (i, direction_bit) in path.into_iter().take(height).enumerate()
+        {
+            let cs = &mut cs.namespace(|| format!("merkle tree hash {}", i));
+
+            // swapping is done on packed halves: it is much cheaper than selecting bit by bit
+            let cur_digest = Self::digest_from_bits(cs.namespace(|| "pack current digest"), &cur)?;
+            let path_element = &witness[i];
+
+            let (hi_l, hi_r) = AllocatedNum::conditionally_reverse(
+                cs.namespace(|| "conditional reversal of upper halves"),
+                &cur_digest.hi,
+                &path_element.hi,
+                &direction_bit
+            )?;
+            let (lo_l, lo_r) = AllocatedNum::conditionally_reverse(
+                cs.namespace(|| "conditional reversal of lower halves"),
+                &cur_digest.lo,
+                &path_element.lo,
+                &direction_bit
+            )?;
+
+            let xl = Self::digest_into_bits(cs.namespace(|| "unpack left"), &Digest256 { hi: hi_l, lo: lo_l })?;
+            let xr = Self::digest_into_bits(cs.namespace(|| "unpack right"), &Digest256 { hi: hi_r, lo: lo_r })?;
+
+            cur = self.hash_node(
+                cs.namespace(|| "node hash computation"),
+                &xl,
+                &xr
+            )?;
+        }
+
+        let cur_digest = Self::digest_from_bits(cs.namespace(|| "pack root"), &cur)?;
+        let hi_flag = AllocatedNum::equals(cs.namespace(|| "compare upper halves of roots"), &cur_digest.hi, &root.hi)?;
+        let lo_flag = AllocatedNum::equals(cs.namespace(|| "compare lower halves of roots"), &cur_digest.lo, &root.lo)?;
+
+        Boolean::and(cs.namespace(|| "compare roots"), &hi_flag, &lo_flag)
+    }
+
+    pub fn validate_impl<CS: ConstraintSystem<E>>(
+        &self,
+        mut cs: CS,
+        height: usize,
+        root: &Digest256<E>,
+        elems : &[AllocatedNum<E>],
+        path: &[Boolean],
+        witness: &[Digest256<E>]
+    ) -> Result<Boolean, SynthesisError> {
+
+        let leaf_hash = self.hash_elems_into_leaf(cs.namespace(|| "encode elems into leaf"), elems)?;
+        self.check_hash_inclusion_with_parsed_path(
+            cs.namespace(|| "merklee proof"),
+            height,
+            root,
+            leaf_hash,
+            path,
+            witness,
+        )
+    }
+}
+
+
+impl<E: Engine, H: DigestHasher> OracleGadget<E> for DigestTreeGadget<E, H> {
+
+    type Params = DigestTreeGadgetParams;
+    type Commitment = Digest256<E>;
+    type Proof = Vec<Digest256<E>>;
+
+    fn new(params: &Self::Params) -> Self {
+        Self::new_impl(params.num_elems_per_leaf)
+    }
+
+    fn with_leaf_size(&self, num_elems_per_leaf: usize) -> Self {
+        Self::new_impl(num_elems_per_leaf)
+    }
+
+    fn validate<CS: ConstraintSystem<E>>(
+        &self,
+        cs: CS,
+        height: usize,
+        elems : &[AllocatedNum<E>],
+        path: &[Boolean],
+        commitment: &Self::Commitment,
+        proof: &Self::Proof,
+    ) -> Result<Boolean, SynthesisError> {
+
+        self.validate_impl(
+            cs,
+            height,
+            commitment,
+            elems,
+            path,
+            proof,
+        )
+    }
+}
+
+
+// native counterpart of the gadget: is used by prover and for test vectors generation
+pub struct DigestTree<H: DigestHasher> {
+    // layers[0] are leaf hashes, the last layer contains only the root
+    layers: Vec<Vec<[u8; 32]>>,
+    values_per_leaf: usize,
+    _marker: std::marker::PhantomData<H>,
+}
+
+impl<H: DigestHasher> DigestTree<H> {
+    // (upper half, lower half), the format expected by the gadget
+    pub fn digest_into_field_elements<F: PrimeField>(digest: &[u8; 32]) -> (F, F) {
+        (decode_field_element(&digest[0..16]), decode_field_element(&digest[16..32]))
+    }
+
+    pub fn hash_leaf<F: PrimeField>(values: &[F]) -> [u8; 32] {
+        let input : Vec<u8> = values.iter().flat_map(|x| encode_field_element(x).to_vec()).collect();
+        H::hash_native(&input)
+    }
+
+    pub fn hash_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        let input : Vec<u8> = left.iter().chain(right.iter()).cloned().collect();
+        H::hash_native(&input)
+    }
+
+    pub fn create<F: PrimeField>(values: &[F], values_per_leaf: usize) -> Self {
+        assert!(values.len() % values_per_leaf == 0);
+        let num_leaves = values.len() / values_per_leaf;
+        assert!(num_leaves.is_power_of_two());
+
+        let mut layers = vec![values.chunks(values_per_leaf).map(|chunk| Self::hash_leaf(chunk)).collect::<Vec<_>>()];
+        while layers.last().expect("is not empty").len() > 1 {
+            let next = layers.last().expect("is not empty").chunks(2).map(|pair| Self::hash_node(&pair[0], &pair[1])).collect();
+            layers.push(next);
+        }
+
+        DigestTree { layers, values_per_leaf, _marker: std::marker::PhantomData }
+    }
+
+    pub fn height(&self) -> usize {
+        self.layers.len() - 1
+    }
+
+    pub fn get_commitment(&self) -> [u8; 32] {
+        self.layers.last().expect("is not empty")[0]
+    }
+
+    // authentication path from the leaf to the root
+    pub fn produce_query(&self, leaf_idx: usize) -> Vec<[u8; 32]> {
+        let mut idx = leaf_idx;
+        let mut path = Vec::with_capacity(self.height());
+        for layer in self.layers.iter().take(self.height()) {
+            path.push(layer[idx ^ 1]);
+            idx >>= 1;
+        }
+        path
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use bellman::pairing::bn256::{Bn256, Fr};
+
+    use common::sha256::Sha256Hasher;
+    use common::blake2s::Blake2sHasher;
+    use crate::tester::naming_oblivious_cs::NamingObliviousConstraintSystem as TestConstraintSystem;
+
+    use super::*;
+
+    fn check_digest_tree_gadget<H: DigestHasher>() {
+        let num_leaves = 16;
+        let values_per_leaf = 4;
+        let index = 5;
+
+        let values : Vec<Fr> = (0..(num_leaves * values_per_leaf)).scan(Fr::multiplicative_generator(), |cur, _| {
+            let res = cur.clone();
+            cur.double();
+            Some(res)
+        }).collect();
+
+        let tree = DigestTree::<H>::create(&values[..], values_per_leaf);
+        let root = tree.get_commitment();
+        let proof = tree.produce_query(index);
+
+        let mut stream = vec![];
+        let (hi, lo) = DigestTree::<H>::digest_into_field_elements::<Fr>(&root);
+        stream.push(Some(hi));
+        stream.push(Some(lo));
+        for elem in proof.iter() {
+            let (hi, lo) = DigestTree::<H>::digest_into_field_elements::<Fr>(elem);
+            stream.push(Some(hi));
+            stream.push(Some(lo));
+        }
+
+        for tamper in [false, true].iter() {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let mut iter = stream.clone().into_iter();
+
+            let mut root = Digest256::<Bn256>::from_stream(cs.namespace(|| "root"), &mut iter, ()).unwrap();
+            let proof = Vec::<Digest256<Bn256>>::from_stream(cs.namespace(|| "proof"), &mut iter, tree.height()).unwrap();
+            if *tamper {
+                root.lo = AllocatedNum::alloc2(cs.namespace(|| "tampered root"), Some(Fr::one())).unwrap();
+            }
+
+            let elems = values[(index * values_per_leaf)..((index + 1) * values_per_leaf)].iter().map(|e| {
+                AllocatedNum::alloc2(cs.namespace(|| "leaf elem"), Some(*e))
+            }).collect::<Result<Vec<_>, SynthesisError>>().unwrap();
+            let path = u64_into_boolean_vec_le(cs.namespace(|| "path"), Some(index as u64)).unwrap();
+
+            let gadget = DigestTreeGadget::<Bn256, H>::new(&DigestTreeGadgetParams { num_elems_per_leaf: values_per_leaf });
+            let is_valid = gadget.validate(cs.namespace(|| "validate"), tree.height(), &elems, &path, &root, &proof).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(is_valid.get_value(), Some(!*tamper));
+        }
+    }
+
+    #[test]
+    fn test_sha256_merkle_proof_gadget() {
+        check_digest_tree_gadget::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn test_blake2s_merkle_proof_gadget() {
+        check_digest_tree_gadget::<Blake2sHasher>();
+    }
+}
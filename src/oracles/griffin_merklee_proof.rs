@@ -0,0 +1,7 @@
+use hashes::griffin::GriffinGadget;
+
+use super::sponge_merklee_proof::*;
+
+
+pub type GriffinTreeGadgetParams<'a, P> = SpongeTreeGadgetParams<'a, P>;
+pub type GriffinTreeGadget<'a, E> = SpongeTreeGadget<'a, E, GriffinGadget<E>>;
@@ -0,0 +1,139 @@
+use hashes::mimc::MiMCGadget;
+
+use super::sponge_merklee_proof::*;
+
+
+pub type MiMCTreeGadgetParams<'a, P> = SpongeTreeGadgetParams<'a, P>;
+pub type MiMCTreeGadget<'a, E> = SpongeTreeGadget<'a, E, MiMCGadget<E>>;
+
+
+#[cfg(test)]
+mod test {
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::ff::{Field, PrimeField};
+    use bellman::redshift::IOP::FRI::coset_combining_fri::FriParams;
+    use bellman::redshift::IOP::hashes::rescue::Rescue;
+    use bellman::redshift::IOP::hashes::rescue::bn256_rescue_params::BN256Rescue;
+
+    use crate::tester::naming_oblivious_cs::NamingObliviousConstraintSystem as TestConstraintSystem;
+    use hashes::mimc::{MiMC, MiMCParams};
+    use hashes::rescue::bn256_rescue_sbox::BN256RescueSbox;
+    use oracles::OracleGadget;
+    use oracles::sponge_merklee_proof::test::validate_query;
+    use oracles::rescue_merklee_proof::{RescueTreeGadget, RescueTreeGadgetParams};
+
+    use super::*;
+
+    fn native_hash(values: &[Fr], params: &MiMCParams<Fr>) -> Fr {
+        let mut hasher = MiMC::new(params);
+        for x in values.iter() {
+            hasher.absorb(*x, params);
+        }
+        hasher.squeeze(params)
+    }
+
+    #[test]
+    fn test_mimc_merkle_proof_gadget() {
+        let params = MiMCParams::<Fr>::bn256_default();
+        let num_leaves = 8;
+        let values_per_leaf = 2;
+        let index = 5;
+
+        let values : Vec<Fr> = (0..(num_leaves * values_per_leaf)).scan(Fr::multiplicative_generator(), |cur, _| {
+            let res = cur.clone();
+            cur.double();
+            Some(res)
+        }).collect();
+
+        let mut layers = vec![values.chunks(values_per_leaf).map(|chunk| native_hash(chunk, &params)).collect::<Vec<_>>()];
+        while layers.last().unwrap().len() > 1 {
+            let next = layers.last().unwrap().chunks(2).map(|pair| native_hash(pair, &params)).collect();
+            layers.push(next);
+        }
+        let height = layers.len() - 1;
+        let root = layers[height][0];
+        let proof : Vec<Fr> = (0..height).map(|i| layers[i][(index >> i) ^ 1]).collect();
+        let leaf = &values[(index * values_per_leaf)..((index + 1) * values_per_leaf)];
+
+        let tree_params = MiMCTreeGadgetParams::without_domain_separation(values_per_leaf, &params);
+        let tree = MiMCTreeGadget::<Bn256>::new(&tree_params);
+
+        for tamper in [false, true].iter() {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let root_value = if *tamper { Fr::one() } else { root };
+            let is_valid = validate_query(&mut cs, &tree, height, index as u64, leaf, &proof, root_value);
+
+            assert!(cs.is_satisfied());
+            assert_eq!(is_valid, Some(!*tamper));
+        }
+    }
+
+    // root of the tree computed natively along the authentication path of the leaf
+    fn path_root<H: Fn(&[Fr]) -> Fr>(hash: H, index: usize, leaf: &[Fr], proof: &[Fr]) -> Fr {
+        proof.iter().enumerate().fold(hash(leaf), |cur, (i, sibling)| {
+            if (index >> i) & 1 == 0 { hash(&[cur, *sibling]) } else { hash(&[*sibling, cur]) }
+        })
+    }
+
+    #[test]
+    fn test_mimc_tree_against_rescue_tree() {
+        // the same shape of FRI oracles as in redshift recursion tests
+        let fri_params = FriParams {
+            initial_degree_plus_one: std::cell::Cell::new(0),
+            lde_factor: 16,
+            R: 20,
+            collapsing_factor: 2,
+            final_degree_plus_one: std::cell::Cell::new(1),
+        };
+        let log_domain_size = 18;
+        let values_per_leaf = 1 << fri_params.collapsing_factor;
+        let height = log_domain_size - fri_params.collapsing_factor as usize;
+        let index = 0b1011001110001011;
+
+        let leaf : Vec<Fr> = (0..values_per_leaf).map(|i| Fr::from_str(&(i + 1).to_string()).unwrap()).collect();
+        let proof : Vec<Fr> = (0..height).map(|i| Fr::from_str(&(100 + i).to_string()).unwrap()).collect();
+
+        let mimc_params = MiMCParams::<Fr>::bn256_default();
+        let mimc_tree_params = MiMCTreeGadgetParams::without_domain_separation(values_per_leaf, &mimc_params);
+        let mimc_tree = MiMCTreeGadget::<Bn256>::new(&mimc_tree_params);
+        let mimc_root = path_root(|values| native_hash(values, &mimc_params), index, &leaf, &proof);
+
+        let rescue_params = BN256Rescue::default();
+        let rescue_tree_params = RescueTreeGadgetParams::without_domain_separation(values_per_leaf, &rescue_params);
+        let rescue_tree = RescueTreeGadget::<Bn256, BN256Rescue, BN256RescueSbox>::new(&rescue_tree_params);
+        let rescue_hash = |values: &[Fr]| {
+            let mut hasher = Rescue::new(&rescue_params);
+            for x in values.iter() {
+                hasher.absorb(*x, &rescue_params);
+            }
+            hasher.squeeze(&rescue_params)
+        };
+        let rescue_root = path_root(rescue_hash, index, &leaf, &proof);
+
+        // both oracles accept the valid query and reject the one with the wrong root
+        let mut costs = vec![];
+        for tamper in [false, true].iter() {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let root = if *tamper { Fr::one() } else { mimc_root };
+            let is_valid = validate_query(&mut cs, &mimc_tree, height, index as u64, &leaf, &proof, root);
+            assert!(cs.is_satisfied());
+            assert_eq!(is_valid, Some(!*tamper));
+            let mimc_cost = cs.num_constraints();
+
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let root = if *tamper { Fr::one() } else { rescue_root };
+            let is_valid = validate_query(&mut cs, &rescue_tree, height, index as u64, &leaf, &proof, root);
+            assert!(cs.is_satisfied());
+            assert_eq!(is_valid, Some(!*tamper));
+            let rescue_cost = cs.num_constraints();
+
+            costs.push((mimc_cost, rescue_cost));
+        }
+        // the cost doesn't depend on the witness
+        assert_eq!(costs[0], costs[1]);
+
+        let (mimc_cost, rescue_cost) = costs[0];
+        println!("Merkle query of height {} with {} values per leaf: MiMC - {} constraints, Rescue - {} constraints", height, values_per_leaf, mimc_cost, rescue_cost);
+        println!("{} queries: MiMC - {} constraints, Rescue - {} constraints", fri_params.R, fri_params.R * mimc_cost, fri_params.R * rescue_cost);
+    }
+}
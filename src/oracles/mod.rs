@@ -20,7 +20,16 @@ use common::num::*;
 use common::boolean::*;
 use common::{FromStream, OracleHeight, CosetSize};
 
+pub mod sponge_merklee_proof;
 pub mod rescue_merklee_proof;
+pub mod poseidon_merklee_proof;
+pub mod rescue_prime_merklee_proof;
+pub mod anemoi_merklee_proof;
+pub mod griffin_merklee_proof;
+pub mod mimc_merklee_proof;
+pub mod digest_merklee_proof;
+pub mod sha256_merklee_proof;
+pub mod blake2s_merklee_proof;
 
 
 // this trais is used as an abstraction over Merklee proofs
@@ -32,6 +41,9 @@ pub trait OracleGadget<E: Engine> {
     type Commitment : FromStream<E, OracleHeight> + Clone;
 
     fn new(params: &Self::Params) -> Self;
+    // the same oracle for leaves of another size
+    // (e.g. FRI layers over extension field have more native elements per leaf than the upper layer)
+    fn with_leaf_size(&self, num_elems_per_leaf: usize) -> Self;
 
     fn validate<CS: ConstraintSystem<E>>(
         &self, 
@@ -0,0 +1,93 @@
+use hashes::poseidon::PoseidonGadget;
+
+use super::sponge_merklee_proof::*;
+
+
+pub type PoseidonTreeGadgetParams<'a, P> = SpongeTreeGadgetParams<'a, P>;
+pub type PoseidonTreeGadget<'a, E, P> = SpongeTreeGadget<'a, E, PoseidonGadget<E, P>>;
+
+
+#[cfg(test)]
+mod test {
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::ff::{Field, PrimeField};
+
+    use bellman::{ConstraintSystem, SynthesisError};
+
+    use common::num::AllocatedNum;
+    use common::boolean::u64_into_boolean_vec_le;
+    use crate::tester::naming_oblivious_cs::NamingObliviousConstraintSystem as TestConstraintSystem;
+    use hashes::poseidon::Poseidon;
+    use hashes::poseidon::params::DefaultPoseidonParams;
+    use oracles::OracleGadget;
+
+    use super::*;
+
+    fn native_hash(values: &[Fr], domain_tag: Option<u64>, params: &DefaultPoseidonParams<Fr>) -> Fr {
+        if let Some(tag) = domain_tag {
+            return Poseidon::hash_varlen(values, tag, params);
+        }
+
+        let mut hasher = Poseidon::new(params);
+        for x in values.iter() {
+            hasher.absorb(*x, params);
+        }
+        hasher.squeeze(params)
+    }
+
+    #[test]
+    fn test_poseidon_merkle_proof_gadget() {
+        let params = DefaultPoseidonParams::<Fr>::bn256_default();
+        let num_leaves = 8;
+        let values_per_leaf = 2;
+        let index = 3;
+
+        let values : Vec<Fr> = (0..(num_leaves * values_per_leaf)).scan(Fr::multiplicative_generator(), |cur, _| {
+            let res = cur.clone();
+            cur.double();
+            Some(res)
+        }).collect();
+
+        for &domain_separation in [false, true].iter() {
+            let (leaf_tag, node_tag) = if domain_separation {
+                (Some(LEAF_DOMAIN_TAG), Some(NODE_DOMAIN_TAG))
+            } else {
+                (None, None)
+            };
+
+            // build the tree natively layer by layer
+            let mut layers = vec![values.chunks(values_per_leaf).map(|chunk| native_hash(chunk, leaf_tag, &params)).collect::<Vec<_>>()];
+            while layers.last().unwrap().len() > 1 {
+                let next = layers.last().unwrap().chunks(2).map(|pair| native_hash(pair, node_tag, &params)).collect();
+                layers.push(next);
+            }
+            let height = layers.len() - 1;
+            let root = layers[height][0];
+            let proof : Vec<Fr> = (0..height).map(|i| layers[i][(index >> i) ^ 1]).collect();
+
+            for tamper in [false, true].iter() {
+                let mut cs = TestConstraintSystem::<Bn256>::new();
+
+                let root_value = if *tamper { Fr::one() } else { root };
+                let root = AllocatedNum::alloc2(cs.namespace(|| "root"), Some(root_value)).unwrap();
+                let proof = proof.iter().map(|e| {
+                    AllocatedNum::alloc2(cs.namespace(|| "proof elem"), Some(*e))
+                }).collect::<Result<Vec<_>, SynthesisError>>().unwrap();
+                let elems = values[(index * values_per_leaf)..((index + 1) * values_per_leaf)].iter().map(|e| {
+                    AllocatedNum::alloc2(cs.namespace(|| "leaf elem"), Some(*e))
+                }).collect::<Result<Vec<_>, SynthesisError>>().unwrap();
+                let path = u64_into_boolean_vec_le(cs.namespace(|| "path"), Some(index as u64)).unwrap();
+
+                let tree_params = match domain_separation {
+                    true => PoseidonTreeGadgetParams::new(values_per_leaf, &params),
+                    false => PoseidonTreeGadgetParams::without_domain_separation(values_per_leaf, &params),
+                };
+                let tree = PoseidonTreeGadget::<Bn256, DefaultPoseidonParams<Fr>>::new(&tree_params);
+                let is_valid = tree.validate(cs.namespace(|| "validate"), height, &elems, &path, &root, &proof).unwrap();
+
+                assert!(cs.is_satisfied());
+                assert_eq!(is_valid.get_value(), Some(!*tamper));
+            }
+        }
+    }
+}
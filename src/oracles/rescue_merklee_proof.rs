@@ -24,169 +24,11 @@ use common::boolean::*;
 use hashes::rescue::*;
 
 use super::*;
+use super::sponge_merklee_proof::*;
 
 
-pub struct RescueTreeGadgetParams<'a, F: PrimeField, RP: RescueParams<F>> {
-    pub num_elems_per_leaf: usize,
-    pub rescue_params: &'a RP,
-    pub _marker: std::marker::PhantomData<F>,
-}
-
-
-pub struct RescueTreeGadget<'a, E: Engine, RP: RescueParams<E::Fr>, SBOX: RescueSbox<E>> {
-    num_elems_per_leaf: usize,
-    params: &'a RP,
-    sbox: SBOX,
-    _marker: std::marker::PhantomData<E>,
-}
-
-impl<'a, E: Engine, RP: RescueParams<E::Fr>, SBOX: RescueSbox<E>> RescueTreeGadget<'a, E, RP, SBOX> {
-
-    pub fn new_impl(num_elems_per_leaf: usize, params: &'a RP, sbox: SBOX) -> Self {
-        Self {
-            num_elems_per_leaf,
-            params,
-            sbox,
-            _marker: std::marker::PhantomData::<E>,
-        }
-    }
-
-    fn hash_elems_into_leaf<CS>(&self, mut cs: CS, elems: &[AllocatedNum<E>]) -> Result<AllocatedNum<E>, SynthesisError> 
-    where CS: ConstraintSystem<E> {
-        assert_eq!(elems.len(), self.num_elems_per_leaf);
-        
-        let mut hasher = RescueGadget::<E, RP, SBOX>::new(self.params);
-        for elem in elems {
-            hasher.absorb(elem.clone(), cs.namespace(|| "hashing into leaf: absorbing"), &self.params)?;
-        }
-
-        let output = hasher.squeeze(cs.namespace(|| "hashing into leaf: squeezing"), &self.params)?;
-        Ok(output)
-    }
-
-    fn hash_node<CS>(&self, mut cs: CS, left: AllocatedNum<E>, right: AllocatedNum<E>) -> Result<AllocatedNum<E>, SynthesisError> 
-    where CS : ConstraintSystem<E> {
-
-        let mut hasher = RescueGadget::<E, RP, SBOX>::new(self.params);
-
-        hasher.absorb(left.into(), cs.namespace(|| "hashing inside Merklee tree: absorbing"), &self.params)?;
-        hasher.absorb(right.into(), cs.namespace(|| "hashing inside Merklee tree: absorbing"), &self.params)?;
-
-        let output = hasher.squeeze(cs.namespace(|| "hashing inside Merklee tree: squeezing"), &self.params)?;
-        
-        Ok(output)
-    }
-
-    // checks inclusion of the leaf hash into the root
-    fn check_hash_inclusion_with_parsed_path<CS: ConstraintSystem<E>>(
-        &self, 
-        mut cs: CS,
-        height: usize,
-        root: &AllocatedNum<E>, 
-        leaf_hash : AllocatedNum<E>,
-        path: &[Boolean], 
-        witness: &[AllocatedNum<E>]
-    ) -> Result<Boolean, SynthesisError> {
-
-        if height != witness.len() {
-            println!("Height of the tree: {} differs from witness length: {}", height, witness.len());
-            return Err(SynthesisError::Unsatisfiable);
-        }
-
-        let mut cur = leaf_hash;
-
-        // Ascend the merkle tree authentication path
-        for (i, direction_bit) in path.into_iter().take(height).enumerate() 
-        {
-            let cs = &mut cs.namespace(|| format!("merkle tree hash {}", i));
-
-            // "direction_bit" determines if the current subtree
-            // is the "right" leaf at this depth of the tree.
-
-            // Witness the authentication path element adjacent
-            // at this depth.
-            let path_element = &witness[i];
-
-            // Swap the two if the current subtree is on the right
-            let (xl, xr) = AllocatedNum::conditionally_reverse(
-                cs.namespace(|| "conditional reversal of preimage"),
-                &cur,
-                path_element,
-                &direction_bit
-            )?;
-
-            cur = self.hash_node(
-                cs.namespace(|| "node hash computation"), 
-                xl,
-                xr
-            )?;
-        }
-
-        let included = AllocatedNum::equals(
-            cs.namespace(|| "compare roots"), 
-            &cur, 
-            &root
-        )?;
-
-        Ok(included)
-    }
-
-    pub fn validate_impl<CS: ConstraintSystem<E>>(
-        &self, 
-        mut cs: CS,
-        height: usize, 
-        root: &AllocatedNum<E>, 
-        elems : &[AllocatedNum<E>],
-        path: &[Boolean], 
-        witness: &[AllocatedNum<E>]
-    ) -> Result<Boolean, SynthesisError> {
-
-        let leaf_hash = self.hash_elems_into_leaf(cs.namespace(|| "encode elems into leaf"), elems)?;
-        let res = self.check_hash_inclusion_with_parsed_path( 
-            cs.namespace(|| "merklee proof"),
-            height,
-            root,
-            leaf_hash,
-            path, 
-            witness,
-        );
-
-        res
-    }
-}
-
-
-impl<'a, E: Engine, RP: RescueParams<E::Fr>, SBOX: RescueSbox<E>> OracleGadget<E> for RescueTreeGadget<'a, E, RP, SBOX> {
-    
-    type Params = RescueTreeGadgetParams<'a, E::Fr, RP>;
-    type Commitment = AllocatedNum<E>;
-    type Proof = Vec<AllocatedNum<E>>;
-    
-
-    fn new(params: &Self::Params) -> Self {
-        Self::new_impl(params.num_elems_per_leaf, params.rescue_params, SBOX::new())
-    }
-
-    fn validate<CS: ConstraintSystem<E>>(
-        &self, 
-        cs: CS,
-        height: usize, 
-        elems : &[AllocatedNum<E>],
-        path: &[Boolean],
-        commitment: &Self::Commitment, 
-        proof: &Self::Proof,
-    ) -> Result<Boolean, SynthesisError> {
-
-        self.validate_impl(
-            cs,
-            height, 
-            commitment, 
-            elems,
-            path,
-            proof,
-        )
-    } 
-}
+pub type RescueTreeGadgetParams<'a, RP> = SpongeTreeGadgetParams<'a, RP>;
+pub type RescueTreeGadget<'a, E, RP, SBOX> = SpongeTreeGadget<'a, E, RescueGadget<E, RP, SBOX>>;
 
 
 #[cfg(test)]
@@ -212,6 +54,7 @@ mod test {
     use bellman::redshift::redshift::cs::Circuit as PlonkCircuit;
 
     use std::iter::FromIterator;
+    use oracles::sponge_merklee_proof::test::validate_query;
     use super::*;
 
     use std::time::{Duration, Instant};
@@ -271,11 +114,7 @@ mod test {
                 )?;
                 let path = index.into_bits_le(cs.namespace(|| "parse index"))?;
 
-                let tree_params = RescueTreeGadgetParams {
-                    num_elems_per_leaf: self.num_elems_per_leaf,
-                    rescue_params: &self.rescue_params,
-                    _marker: std::marker::PhantomData::<E::Fr>,
-                };
+                let tree_params = RescueTreeGadgetParams::without_domain_separation(self.num_elems_per_leaf, &self.rescue_params);
                 let tree = RescueTreeGadget::<E, RP, SBOX>::new(&tree_params);
 
                 let is_valid = tree.validate(
@@ -403,4 +242,46 @@ mod test {
         println!("Transpiled into {} gates", num_gates);
         println!("Circuit transpilation took {}ms", now.elapsed().as_millis());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_rescue_tree_with_domain_separation() {
+        let params = BN256Rescue::default();
+        let num_leaves = 8;
+        let values_per_leaf = 4;
+        let index = 5;
+
+        let values : Vec<Fr> = (0..(num_leaves * values_per_leaf)).map(|i| Fr::from_str(&(i + 1).to_string()).unwrap()).collect();
+        let mut layers = vec![values.chunks(values_per_leaf).map(|chunk| {
+            rescue_hash_varlen_native::<Bn256, _, BN256RescueSbox>(chunk, LEAF_DOMAIN_TAG, &params)
+        }).collect::<Vec<_>>()];
+        while layers.last().unwrap().len() > 1 {
+            let next = layers.last().unwrap().chunks(2).map(|pair| rescue_hash_varlen_native::<Bn256, _, BN256RescueSbox>(pair, NODE_DOMAIN_TAG, &params)).collect();
+            layers.push(next);
+        }
+        let height = layers.len() - 1;
+        let root = layers[height][0];
+        let proof : Vec<Fr> = (0..height).map(|i| layers[i][(index >> i) ^ 1]).collect();
+        let leaf = &values[(index * values_per_leaf)..((index + 1) * values_per_leaf)];
+
+        // the root is accepted only by the tree hashing leaves and nodes with the same tags
+        for &domain_separation in [true, false].iter() {
+            let tree_params = match domain_separation {
+                true => RescueTreeGadgetParams::new(values_per_leaf, &params),
+                false => RescueTreeGadgetParams::without_domain_separation(values_per_leaf, &params),
+            };
+            let tree = RescueTreeGadget::<Bn256, BN256Rescue, BN256RescueSbox>::new(&tree_params);
+
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let is_valid = validate_query(&mut cs, &tree, height, index as u64, leaf, &proof, root);
+            assert!(cs.is_satisfied());
+            assert_eq!(is_valid, Some(domain_separation));
+
+            if domain_separation {
+                let mut cs = TestConstraintSystem::<Bn256>::new();
+                let is_valid = validate_query(&mut cs, &tree, height, index as u64, leaf, &proof, Fr::one());
+                assert!(cs.is_satisfied());
+                assert_eq!(is_valid, Some(false));
+            }
+        }
+    }
+}
@@ -0,0 +1,7 @@
+use hashes::rescue_prime::RescuePrimeGadget;
+
+use super::sponge_merklee_proof::*;
+
+
+pub type RescuePrimeTreeGadgetParams<'a, P> = SpongeTreeGadgetParams<'a, P>;
+pub type RescuePrimeTreeGadget<'a, E, P> = SpongeTreeGadget<'a, E, RescuePrimeGadget<E, P>>;
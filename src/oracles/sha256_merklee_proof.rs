@@ -0,0 +1,8 @@
+use common::sha256::Sha256Hasher;
+
+use super::digest_merklee_proof::*;
+
+
+pub type Sha256TreeGadget<E> = DigestTreeGadget<E, Sha256Hasher>;
+pub type Sha256Tree = DigestTree<Sha256Hasher>;
+
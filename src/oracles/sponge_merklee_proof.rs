@@ -0,0 +1,283 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+};
+
+use common::num::*;
+use common::boolean::*;
+use hashes::{SpongeGadget, NativeSponge, varlen_header};
+
+use super::*;
+
+
+// tags used for leaves and nodes when domain separation is enabled
+pub const LEAF_DOMAIN_TAG: u64 = 1;
+pub const NODE_DOMAIN_TAG: u64 = 2;
+
+
+pub struct SpongeTreeGadgetParams<'a, P> {
+    pub num_elems_per_leaf: usize,
+    pub sponge_params: &'a P,
+    // leaves and nodes are hashed with hash_varlen and different tags
+    domain_separation: bool,
+}
+
+impl<'a, P> SpongeTreeGadgetParams<'a, P> {
+    // domain separation is enabled by default (NativeSpongeTree has to be built with it as well)
+    pub fn new(num_elems_per_leaf: usize, sponge_params: &'a P) -> Self {
+        SpongeTreeGadgetParams { num_elems_per_leaf, sponge_params, domain_separation: true }
+    }
+
+    // plain hashing of leaves and nodes: is only needed to verify the trees built by
+    // the native FriSpecificRescueTree of bellman (e.g. redshift proofs), which knows nothing about the tags
+    pub fn without_domain_separation(num_elems_per_leaf: usize, sponge_params: &'a P) -> Self {
+        SpongeTreeGadgetParams { num_elems_per_leaf, sponge_params, domain_separation: false }
+    }
+}
+
+
+// Merklee tree over arbitrary algebraic sponge:
+// leaf is the sponge hash of its elements, node is the sponge hash of (left, right)
+pub struct SpongeTreeGadget<'a, E: Engine, S: SpongeGadget<E>> {
+    num_elems_per_leaf: usize,
+    params: &'a S::Params,
+    domain_separation: bool,
+    _marker: std::marker::PhantomData<(E, S)>,
+}
+
+impl<'a, E: Engine, S: SpongeGadget<E>> SpongeTreeGadget<'a, E, S> {
+
+    pub fn new_impl(num_elems_per_leaf: usize, params: &'a S::Params, domain_separation: bool) -> Self {
+        Self {
+            num_elems_per_leaf,
+            params,
+            domain_separation,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    fn hash_elems_into_leaf<CS>(&self, mut cs: CS, elems: &[AllocatedNum<E>]) -> Result<AllocatedNum<E>, SynthesisError>
+    where CS: ConstraintSystem<E> {
+        assert_eq!(elems.len(), self.num_elems_per_leaf);
+
+        if self.domain_separation {
+            return S::hash_varlen(cs.namespace(|| "hashing into leaf"), elems, LEAF_DOMAIN_TAG, self.params);
+        }
+
+        let mut hasher = S::new(self.params);
+        for elem in elems {
+            hasher.absorb(elem.clone(), cs.namespace(|| "hashing into leaf: absorbing"), self.params)?;
+        }
+
+        hasher.squeeze(cs.namespace(|| "hashing into leaf: squeezing"), self.params)
+    }
+
+    fn hash_node<CS>(&self, mut cs: CS, left: AllocatedNum<E>, right: AllocatedNum<E>) -> Result<AllocatedNum<E>, SynthesisError>
+    where CS : ConstraintSystem<E> {
+        if self.domain_separation {
+            return S::hash_varlen(cs.namespace(|| "hashing inside Merklee tree"), &[left, right], NODE_DOMAIN_TAG, self.params);
+        }
+
+        let mut hasher = S::new(self.params);
+
+        hasher.absorb(left, cs.namespace(|| "hashing inside Merklee tree: absorbing"), self.params)?;
+        hasher.absorb(right, cs.namespace(|| "hashing inside Merklee tree: absorbing"), self.params)?;
+
+        hasher.squeeze(cs.namespace(|| "hashing inside Merklee tree: squeezing"), self.params)
+    }
+
+    // checks inclusion of the leaf hash into the root
+    fn check_hash_inclusion_with_parsed_path<CS: ConstraintSystem<E>>(
+        &self,
+        mut cs: CS,
+        height: usize,
+        root: &AllocatedNum<E>,
+        leaf_hash : AllocatedNum<E>,
+        path: &[Boolean],
+        witness: &[AllocatedNum<E>]
+    ) -> Result<Boolean, SynthesisError> {
+
+        if height != witness.len() {
+            println!("Height of the tree: {} differs from witness length: {}", height, witness.len());
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let mut cur = leaf_hash;
+
+        // Ascend the merkle tree authentication path
+        for (i, direction_bit) in path.into_iter().take(height).enumerate()
+        {
+            let cs = &mut cs.namespace(|| format!("merkle tree hash {}", i));
+
+            // Swap the two if the current subtree is on the right
+            let (xl, xr) = AllocatedNum::conditionally_reverse(
+                cs.namespace(|| "conditional reversal of preimage"),
+                &cur,
+                &witness[i],
+                &direction_bit
+            )?;
+
+            cur = self.hash_node(
+                cs.namespace(|| "node hash computation"),
+                xl,
+                xr
+            )?;
+        }
+
+        AllocatedNum::equals(
+            cs.namespace(|| "compare roots"),
+            &cur,
+            &root
+        )
+    }
+
+    pub fn validate_impl<CS: ConstraintSystem<E>>(
+        &self,
+        mut cs: CS,
+        height: usize,
+        root: &AllocatedNum<E>,
+        elems : &[AllocatedNum<E>],
+        path: &[Boolean],
+        witness: &[AllocatedNum<E>]
+    ) -> Result<Boolean, SynthesisError> {
+
+        let leaf_hash = self.hash_elems_into_leaf(cs.namespace(|| "encode elems into leaf"), elems)?;
+        self.check_hash_inclusion_with_parsed_path(
+            cs.namespace(|| "merklee proof"),
+            height,
+            root,
+            leaf_hash,
+            path,
+            witness,
+        )
+    }
+}
+
+
+impl<'a, E: Engine, S: SpongeGadget<E>> OracleGadget<E> for SpongeTreeGadget<'a, E, S> {
+
+    type Params = SpongeTreeGadgetParams<'a, S::Params>;
+    type Commitment = AllocatedNum<E>;
+    type Proof = Vec<AllocatedNum<E>>;
+
+    fn new(params: &Self::Params) -> Self {
+        Self::new_impl(params.num_elems_per_leaf, params.sponge_params, params.domain_separation)
+    }
+
+    fn with_leaf_size(&self, num_elems_per_leaf: usize) -> Self {
+        Self::new_impl(num_elems_per_leaf, self.params, self.domain_separation)
+    }
+
+    fn validate<CS: ConstraintSystem<E>>(
+        &self,
+        cs: CS,
+        height: usize,
+        elems : &[AllocatedNum<E>],
+        path: &[Boolean],
+        commitment: &Self::Commitment,
+        proof: &Self::Proof,
+    ) -> Result<Boolean, SynthesisError> {
+
+        self.validate_impl(
+            cs,
+            height,
+            commitment,
+            elems,
+            path,
+            proof,
+        )
+    }
+}
+
+
+// native counterpart of SpongeTreeGadget, is used by provers
+pub struct NativeSpongeTree<F: PrimeField> {
+    leaves: Vec<Vec<F>>,
+    // the first layer contains hashes of the leaves, the last one consists of the root only
+    nodes: Vec<Vec<F>>,
+}
+
+impl<F: PrimeField> NativeSpongeTree<F> {
+    fn hash<S: NativeSponge<F, P>, P>(elems: &[F], domain_tag: Option<u64>, params: &P) -> F {
+        // the same as SpongeGadget::hash_varlen when the tag is given
+        let mut hasher = match domain_tag {
+            Some(tag) => S::new_with_domain(params, varlen_header(tag, elems.len())),
+            None => S::new(params),
+        };
+        for elem in elems.iter() {
+            hasher.absorb(*elem, params);
+        }
+        hasher.squeeze(params)
+    }
+
+    pub fn new<S: NativeSponge<F, P>, P>(leaves: Vec<Vec<F>>, params: &P, domain_separation: bool) -> Self {
+        assert!(leaves.len().is_power_of_two());
+        let (leaf_tag, node_tag) = match domain_separation {
+            true => (Some(LEAF_DOMAIN_TAG), Some(NODE_DOMAIN_TAG)),
+            false => (None, None),
+        };
+
+        let mut nodes = vec![leaves.iter().map(|leaf| Self::hash::<S, P>(leaf, leaf_tag, params)).collect::<Vec<_>>()];
+        while nodes.last().expect("is not empty").len() > 1 {
+            let next = nodes.last().expect("is not empty").chunks(2).map(|pair| {
+                Self::hash::<S, P>(pair, node_tag, params)
+            }).collect();
+            nodes.push(next);
+        }
+
+        NativeSpongeTree { leaves, nodes }
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    pub fn get_commitment(&self) -> F {
+        self.nodes[self.get_height()][0]
+    }
+
+    // returns the elements of the leaf and the authentication path (from the bottom to the top)
+    pub fn produce_query(&self, index: usize) -> (Vec<F>, Vec<F>) {
+        let path = (0..self.get_height()).map(|i| self.nodes[i][(index >> i) ^ 1]).collect();
+        (self.leaves[index].clone(), path)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use common::boolean::u64_into_boolean_vec_le;
+    use crate::tester::naming_oblivious_cs::NamingObliviousConstraintSystem as TestConstraintSystem;
+
+    use super::*;
+
+    // allocates the leaf, the path and the proof of the given height and validates them against the root
+    pub(crate) fn validate_query<E: Engine, O: OracleGadget<E, Commitment = AllocatedNum<E>, Proof = Vec<AllocatedNum<E>>>>(
+        cs: &mut TestConstraintSystem<E>,
+        oracle: &O,
+        height: usize,
+        index: u64,
+        leaf: &[E::Fr],
+        proof: &[E::Fr],
+        root: E::Fr,
+    ) -> Option<bool> {
+        let root = AllocatedNum::alloc2(cs.namespace(|| "root"), Some(root)).unwrap();
+        let proof = proof.iter().map(|e| {
+            AllocatedNum::alloc2(cs.namespace(|| "proof elem"), Some(*e))
+        }).collect::<Result<Vec<_>, SynthesisError>>().unwrap();
+        let elems = leaf.iter().map(|e| {
+            AllocatedNum::alloc2(cs.namespace(|| "leaf elem"), Some(*e))
+        }).collect::<Result<Vec<_>, SynthesisError>>().unwrap();
+        let path = u64_into_boolean_vec_le(cs.namespace(|| "path"), Some(index)).unwrap();
+
+        oracle.validate(cs.namespace(|| "validate"), height, &elems, &path[0..height], &root, &proof).unwrap().get_value()
+    }
+}
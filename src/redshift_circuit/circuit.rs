@@ -26,7 +26,8 @@ use common::*;
 use oracles::*;
 use channel::*;
 use fri::fri_verifier::*;
-use fri::FriVerifierGadget;
+use fri::{FriVerifierGadget, FriGadgetConfig};
+use fri::diagnostics::{FriDiagnostics, SharedFriDiagnostics};
 
 use super::data_structs::*;
 use super::upper_layer_combiner::*;
@@ -123,6 +124,69 @@ where E: Engine, O: OracleGadget<E, Commitment = AllocatedNum<E>>, T: ChannelGad
 }
 
 
+// the same as get_fri_challenges but for DEEP-FRI: 
+// before each folding an out-of-domain point is produced, 
+// and the corresponding evaluation is consumed before the folding challenge
+// (intermidiate commitments are consumed before out-of-domain points of their layers)
+// returns folding challenges and out-of-domain points
+pub fn get_deep_fri_challenges<E, O, T, CS>(
+    cs : &mut CS,
+    proof: &BatchedFriProof<E, O>,
+    channel: &mut T,
+) -> Result<(Vec<AllocatedNum<E>>, Vec<AllocatedNum<E>>), SynthesisError> 
+where E: Engine, O: OracleGadget<E, Commitment = AllocatedNum<E>>, T: ChannelGadget<E>, CS: ConstraintSystem<E>
+{
+    if proof.deep_values.len() != proof.commitments.len() + 1 {
+        return Err(SynthesisError::Unknown);
+    }
+
+    let mut fri_challenges = vec![];
+    let mut deep_points = vec![];
+
+    for (i, deep_value) in proof.deep_values.iter().cloned().enumerate() {
+        if i > 0 {
+            channel.consume(proof.commitments[i - 1].clone(), unnamed(cs))?;
+        }
+        deep_points.push(channel.produce_challenge(unnamed(cs))?);
+
+        channel.consume(deep_value, unnamed(cs))?;
+        fri_challenges.push(channel.produce_challenge(unnamed(cs))?);
+    }
+    Ok((fri_challenges, deep_points))
+}
+
+
+// D - d_i + k_i for all degree bounds d_i, where D is the common degree bound of FRI 
+// and k_i is the number of opening points of the i-th polynomial (the quotient has degree < d_i - k_i)
+// degree bounds are the part of verification key, so the shifts are circuit constants (little-endian bits)
+pub fn compute_degree_shifts(
+    degree_bounds: &LabeledVec<usize>,
+    common_degree_bound: usize,
+) -> Result<LabeledVec<Vec<Boolean>>, SynthesisError>
+{
+    let num_bits = log2_floor(common_degree_bound) + 1;
+
+    let mut shifts = Vec::with_capacity(degree_bounds.len());
+    for elem in degree_bounds.iter() {
+        if elem.data > common_degree_bound {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        let num_opening_points = match SINGLE_POINT_OPENING_LABELS.contains(&elem.label) {
+            true => 1,
+            false => 2,
+        };
+        let shift = common_degree_bound - elem.data + num_opening_points;
+        if shift >> num_bits != 0 {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        let bits = (0..num_bits).map(|i| Boolean::constant((shift >> i) & 1 == 1)).collect();
+        shifts.push(Labeled::new(elem.label, bits));
+    }
+
+    Ok(shifts)
+}
+
+
 pub struct RedShiftVerifierCircuit<E, O, T, I> 
 where E: Engine, O: OracleGadget<E, Commitment = AllocatedNum<E>>, T: ChannelGadget<E>, I: Iterator<Item = Option<E::Fr>>
 {
@@ -133,8 +197,13 @@ where E: Engine, O: OracleGadget<E, Commitment = AllocatedNum<E>>, T: ChannelGad
     channel_params: T::Params,
     oracle_params: O::Params,
     fri_params: FriParams,
+    fri_config: FriGadgetConfig<E::Fr>,
     input_stream: I,
     public_inputs : Vec<E::Fr>,
+    // is filled during synthesis if set
+    diagnostics: Option<SharedFriDiagnostics>,
+    // degree bounds of all the polynomials, batched in FRI (empty if degree correction is not used)
+    degree_bounds: LabeledVec<usize>,
 }
 
 
@@ -142,6 +211,18 @@ impl<E, O, T, I> RedShiftVerifierCircuit<E, O, T, I>
 where E: Engine, O: OracleGadget<E, Commitment = AllocatedNum<E>>, T: ChannelGadget<E>, I: Iterator<Item = Option<E::Fr>>, 
 {
     pub fn new(channel_params: T::Params, oracle_params: O::Params, fri_params: FriParams, stream : I, public: Vec<E::Fr>) -> Self {
+        // Redshift prover does LDE over the coset generated by multiplicative generator
+        Self::new_with_fri_config(channel_params, oracle_params, fri_params, FriGadgetConfig::default(), stream, public)
+    }
+
+    pub fn new_with_fri_config(
+        channel_params: T::Params, 
+        oracle_params: O::Params, 
+        fri_params: FriParams, 
+        fri_config: FriGadgetConfig<E::Fr>,
+        stream : I, 
+        public: Vec<E::Fr>,
+    ) -> Self {
 
         RedShiftVerifierCircuit {
             
@@ -152,10 +233,27 @@ where E: Engine, O: OracleGadget<E, Commitment = AllocatedNum<E>>, T: ChannelGad
             channel_params,
             oracle_params,
             fri_params,
+            fri_config,
             input_stream: stream,
             public_inputs : public,
+            diagnostics: None,
+            degree_bounds: vec![],
         }
     }
+
+    // opt-in: all the intermidiate flags of FRI verifier are recorded into the given container during synthesis
+    pub fn with_diagnostics(mut self, diagnostics: SharedFriDiagnostics) -> Self {
+        self.diagnostics = Some(diagnostics);
+        self
+    }
+
+    // turns on degree correction: bounds are taken from the verification key, 
+    // one for each label in FRI_UPPER_LAYER_LABELS
+    pub fn with_degree_bounds(mut self, degree_bounds: LabeledVec<usize>) -> Self {
+        self.fri_config.degree_correction = true;
+        self.degree_bounds = degree_bounds;
+        self
+    }
 }
 
 
@@ -186,7 +284,7 @@ where
         let proof = RedshiftProof::<E, O>::from_stream(
             cs.namespace(|| "initialize proof"),
             &mut self.input_stream, 
-            self.fri_params.clone(),
+            (self.fri_params.clone(), self.fri_config.clone()),
         )?;
 
         //self.input_stream.next().unwrap();
@@ -413,13 +511,25 @@ where
         // Fri validation starts from here
         let aggregation_challenge = channel.produce_challenge(unnamed(cs))?;
 
+        let degree_correction = match self.fri_config.degree_correction {
+            true => {
+                let shifts = compute_degree_shifts(&self.degree_bounds, domain_size)?;
+                let challenge = channel.produce_challenge(unnamed(cs))?;
+                Some(DegreeCorrection { challenge, shifts })
+            },
+            false => None,
+        };
+
         let mut upper_layer_commitments = proof.commitments;
         let opening_values = proof.opening_values;
         upper_layer_commitments.extend(precomputation.data.iter().map(|item| {
             Labeled::new(item.label, item.data.commitment.clone())
         }));
       
-        let fri_challenges = get_fri_challenges::<E, O, T, _>(cs, &proof.fri_proof, &mut channel)?;
+        let (fri_challenges, deep_points) = match self.fri_config.deep {
+            true => get_deep_fri_challenges::<E, O, T, _>(cs, &proof.fri_proof, &mut channel)?,
+            false => (get_fri_challenges::<E, O, T, _>(cs, &proof.fri_proof, &mut channel)?, vec![]),
+        };
        
         let natural_first_element_indexes = (0..self.fri_params.R).map(|_| {
             let packed = channel.produce_challenge(unnamed(cs))?;
@@ -436,6 +546,7 @@ where
             z,
             aggr_challenge: aggregation_challenge,
             omega,
+            degree_correction,
         };
 
         let fri_verifier_gadget = FriVerifierGadget::<E, O, _> {
@@ -447,21 +558,48 @@ where
             //the degree of the resulting polynomial at the bottom level of FRI
             final_degree_plus_one : self.fri_params.final_degree_plus_one.get(),
             upper_layer_combiner,
+            config: self.fri_config.clone(),
 
             _engine_marker : std::marker::PhantomData::<E>,
             _oracle_marker : std::marker::PhantomData::<O>,
+            _field_marker : std::marker::PhantomData::<(AllocatedNum<E>, AllocatedNum<E>)>,
         };
        
-        let is_fri_valid = fri_verifier_gadget.verify_proof(
-            cs.namespace(|| "FRI verification"),
-            &self.oracle_params,
-            &upper_layer_commitments,
-            &proof.fri_proof.commitments,
-            &proof.fri_proof.final_coefficients,
-            &fri_challenges,
-            natural_first_element_indexes,
-            &proof.fri_proof.fri_round_queries,
-        )?;
+        let diagnostics = match self.diagnostics {
+            Some(_) => FriDiagnostics::new(),
+            None => FriDiagnostics::disabled(),
+        };
+
+        let (is_fri_valid, diagnostics) = match self.fri_config.deep {
+            true => fri_verifier_gadget.verify_deep_proof_impl(
+                cs.namespace(|| "DEEP-FRI verification"),
+                &self.oracle_params,
+                &upper_layer_commitments,
+                &proof.fri_proof.commitments,
+                &proof.fri_proof.final_coefficients,
+                &fri_challenges,
+                &deep_points,
+                &proof.fri_proof.deep_values,
+                natural_first_element_indexes,
+                &proof.fri_proof.fri_round_queries,
+                diagnostics,
+            )?,
+            false => fri_verifier_gadget.verify_proof_impl(
+                cs.namespace(|| "FRI verification"),
+                &self.oracle_params,
+                &upper_layer_commitments,
+                &proof.fri_proof.commitments,
+                &proof.fri_proof.final_coefficients,
+                &fri_challenges,
+                natural_first_element_indexes,
+                &proof.fri_proof.fri_round_queries,
+                diagnostics,
+            )?,
+        };
+
+        if let Some(container) = self.diagnostics {
+            *container.borrow_mut() = diagnostics;
+        }
 
         Boolean::enforce_equal(cs.namespace(|| "check output bit"), &is_fri_valid, &Boolean::constant(true))?;
 
@@ -18,6 +18,8 @@ use common::boolean::*;
 use oracles::*;
 use common::*;
 use fri::*;
+// the parsing of FRI proof is shared with FriProximityCircuit
+pub use fri::BatchedFriProof;
 
 use bellman::redshift::IOP::FRI::coset_combining_fri::FriParams;
 
@@ -36,14 +38,6 @@ pub struct RedshiftSetupPrecomputation<E: Engine, I: OracleGadget<E>> {
 }
 
 
-pub struct BatchedFriProof<E: Engine, I: OracleGadget<E>> {
-    // commitments to all intermidiate oracles
-    pub commitments: Vec<I::Commitment>,
-    pub final_coefficients: Vec<AllocatedNum<E>>,
-    pub fri_round_queries : Vec<FriSingleQueryRoundData<E, I>>,
-}
-
-
 pub struct RedshiftProof<E: Engine, I: OracleGadget<E>> {
     // containes opening values for:
     // a, b, c, c_shifted, q_l, q_r, q_o, q_m, q_c, q_add_sel, 
@@ -71,6 +65,14 @@ impl<E: Engine, O: OracleGadget<E>> FromStream<E, OracleHeight> for SinglePolySe
 }
 
 
+// all the polynomials which are opened via batched FRI
+pub const FRI_UPPER_LAYER_LABELS: [Label; 18] = ["q_l", "q_r", "q_o", "q_m", "q_c", "q_add_sel", "s_id", "sigma_1", "sigma_2", "sigma_3",
+    "a", "b", "c", "z_1", "z_2", "t_low", "t_mid", "t_high"];
+
+// polynomials which are opened only at z, all the others are opened at two points
+pub const SINGLE_POINT_OPENING_LABELS: [Label; 5] = ["a", "b", "t_low", "t_mid", "t_high"];
+
+
 impl<E: Engine, O: OracleGadget<E>> FromStream<E, OracleHeight> for RedshiftSetupPrecomputation<E, O> {
 
     fn from_stream<CS: ConstraintSystem<E>, I: Iterator<Item = Option<E::Fr>>>(
@@ -100,47 +102,26 @@ impl<E: Engine, O: OracleGadget<E>> FromStream<E, OracleHeight> for RedshiftSetu
 impl<E: Engine, O: OracleGadget<E>> FromStream<E, FriParams> for BatchedFriProof<E, O> {
 
     fn from_stream<CS: ConstraintSystem<E>, I: Iterator<Item = Option<E::Fr>>>(
-        mut cs: CS, 
+        cs: CS, 
         iter: &mut I,
         fri_params: FriParams,
     ) -> Result<Self, SynthesisError> 
     {
+        BatchedFriProof::from_stream(cs, iter, (fri_params, FriGadgetConfig::default()))
+    }
+}
 
-        let coset_size = 1 << fri_params.collapsing_factor;
-        let top_level_oracle_size = (fri_params.initial_degree_plus_one.get() * fri_params.lde_factor) / coset_size;
-        let top_leve_height = log2_floor(top_level_oracle_size);
-        
-        let mut num_of_iters = log2_floor(fri_params.initial_degree_plus_one.get() / fri_params.final_degree_plus_one.get()) / fri_params.collapsing_factor as usize;
-        // we do not count the very first and the last iterations
-        num_of_iters -= 1;
-
-        let mut cur_height = top_leve_height - fri_params.collapsing_factor as usize;
-        let mut commitments = Vec::with_capacity(num_of_iters);
-
-        for _ in 0..num_of_iters {
-            let commitment = O::Commitment::from_stream(
-                cs.namespace(|| "intermidiate commitment"), 
-                iter, 
-                cur_height,
-            )?;
-            commitments.push(commitment);
-            cur_height -= fri_params.collapsing_factor as usize;
-        }
-
-        let final_coefficients = 
-            Vec::from_stream(cs.namespace(|| "final coefficients"), iter, fri_params.final_degree_plus_one.get())?;
 
-        let labels = ["q_l", "q_r", "q_o", "q_m", "q_c", "q_add_sel", "s_id", "sigma_1", "sigma_2", "sigma_3",
-            "a", "b", "c", "z_1", "z_2", "t_low", "t_mid", "t_high"];
+impl<E: Engine, O: OracleGadget<E>> FromStream<E, (FriParams, FriGadgetConfig<E::Fr>)> for BatchedFriProof<E, O> {
 
-        let mut fri_round_queries = Vec::with_capacity(fri_params.R);
-        for _ in 0..fri_params.R {
-            let fri_round = FriSingleQueryRoundData::from_stream(
-                cs.namespace(|| "FRI round query"), iter, (fri_params.clone(), &labels))?;
-            fri_round_queries.push(fri_round);
-        }
-        
-        Ok(BatchedFriProof { commitments, final_coefficients, fri_round_queries })
+    fn from_stream<CS: ConstraintSystem<E>, I: Iterator<Item = Option<E::Fr>>>(
+        cs: CS, 
+        iter: &mut I,
+        params: (FriParams, FriGadgetConfig<E::Fr>),
+    ) -> Result<Self, SynthesisError> 
+    {
+        let (fri_params, fri_config) = params;
+        BatchedFriProof::from_stream(cs, iter, (fri_params, fri_config, &FRI_UPPER_LAYER_LABELS[..]))
     }
 }
 
@@ -148,11 +129,26 @@ impl<E: Engine, O: OracleGadget<E>> FromStream<E, FriParams> for BatchedFriProof
 impl<E: Engine, O: OracleGadget<E>> FromStream<E, FriParams> for RedshiftProof<E, O> {
 
     fn from_stream<CS: ConstraintSystem<E>, I: Iterator<Item = Option<E::Fr>>>(
-        mut cs: CS, 
+        cs: CS, 
         iter: &mut I,
         fri_params: FriParams,
     ) -> Result<Self, SynthesisError> 
-    {             
+    {
+        RedshiftProof::from_stream(cs, iter, (fri_params, FriGadgetConfig::default()))
+    }
+}
+
+
+impl<E: Engine, O: OracleGadget<E>> FromStream<E, (FriParams, FriGadgetConfig<E::Fr>)> for RedshiftProof<E, O> {
+
+    fn from_stream<CS: ConstraintSystem<E>, I: Iterator<Item = Option<E::Fr>>>(
+        mut cs: CS, 
+        iter: &mut I,
+        params: (FriParams, FriGadgetConfig<E::Fr>),
+    ) -> Result<Self, SynthesisError> 
+    {
+        let (fri_params, fri_config) = params;
+             
         // containes opening values for:
         // a, b, c, c_shifted, q_l, q_r, q_o, q_m, q_c, q_add_sel, 
         // s_id, sigma_1, sigma_2, sigma_3,
@@ -191,7 +187,7 @@ impl<E: Engine, O: OracleGadget<E>> FromStream<E, FriParams> for RedshiftProof<E
         let fri_proof = BatchedFriProof::from_stream(
             cs.namespace(|| "batched FRI proof"), 
             iter, 
-            fri_params,
+            (fri_params, fri_config),
         )?;
 
         Ok(RedshiftProof { opening_values, commitments, fri_proof })
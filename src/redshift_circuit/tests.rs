@@ -88,11 +88,7 @@ mod test {
         proof.to_stream(&mut container, fri_params.clone());
 
         let rescue_params = BN256Rescue::default();
-        let oracle_params =  RescueTreeGadgetParams {
-            num_elems_per_leaf: coset_size,
-            rescue_params: &rescue_params,
-            _marker: std::marker::PhantomData::<Fr>,
-        };
+        let oracle_params =  RescueTreeGadgetParams::without_domain_separation(coset_size, &rescue_params);
 
         let iter = container.into_iter().map(|x| Some(x));
 
@@ -165,11 +161,7 @@ mod test {
 
         let coset_size = 1 << fri_params.collapsing_factor;
         let rescue_params = BN256Rescue::default();
-        let oracle_params =  RescueTreeGadgetParams {
-            num_elems_per_leaf: coset_size,
-            rescue_params: &rescue_params,
-            _marker: std::marker::PhantomData::<Fr>,
-        };
+        let oracle_params =  RescueTreeGadgetParams::without_domain_separation(coset_size, &rescue_params);
 
         type OG<'a> = RescueTreeGadget<'a, E, BN256Rescue, BN256RescueSbox>;
         type TG<'a> = RescueChannelGadget<'a, E, BN256Rescue, BN256RescueSbox>;
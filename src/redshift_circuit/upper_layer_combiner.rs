@@ -26,6 +26,7 @@ use super::data_structs::*;
 // this function computes: 
 // y = /sum alpha^i [f_i(x) - f_i(x_1)]/ [x - x_1]
 // and returns the pair (y, final_alpha)
+// if degree correction multipliers m_i(x) are given, the i-th numerator is additionaly multiplied by m_i(x)
 
 fn combine_at_single_point<E: Engine, CS: ConstraintSystem<E>>(
     cs : &mut CS,
@@ -33,6 +34,7 @@ fn combine_at_single_point<E: Engine, CS: ConstraintSystem<E>>(
     x: &Num<E>, 
     x_1: AllocatedNum<E>, 
     alpha: AllocatedNum<E>,
+    multipliers: &[AllocatedNum<E>],
 ) -> Result<(AllocatedNum<E>, AllocatedNum<E>), SynthesisError> 
 {
     let mut res : Num<E> = Num::zero();
@@ -42,6 +44,9 @@ fn combine_at_single_point<E: Engine, CS: ConstraintSystem<E>>(
         // separately compute numerators
         let mut temp : Num<E> = a.into();
         temp -= b;
+        if let Some(m) = multipliers.get(i) {
+            temp = Num::mul_by_var_with_coeff(cs.namespace(|| ""), &temp, m, E::Fr::one())?.into();
+        }
         if i == 0 {
             res += &temp;
         }
@@ -71,6 +76,7 @@ fn combine_at_single_point<E: Engine, CS: ConstraintSystem<E>>(
 // this means that all U_i(x) share the common slope [x - x_1] / [x_2 - x_1]
 // which therefore may be precomputed once and forall
 // funtion returns the pair (y, final_alpha)
+// degree correction multipliers are applied the same way as in combine_at_single_point
 
 fn combine_at_two_points<E: Engine, CS: ConstraintSystem<E>>(
     cs: &mut CS,
@@ -79,6 +85,7 @@ fn combine_at_two_points<E: Engine, CS: ConstraintSystem<E>>(
     x_1: AllocatedNum<E>,
     x_2: AllocatedNum<E>, 
     alpha: AllocatedNum<E>,
+    multipliers: &[AllocatedNum<E>],
 ) -> Result<(AllocatedNum<E>, AllocatedNum<E>), SynthesisError> 
 {
     // precompute the common slope
@@ -102,6 +109,9 @@ fn combine_at_two_points<E: Engine, CS: ConstraintSystem<E>>(
 
         // compute nominator: aggr_mult * (f_x - U_i(x))
         temp += f_x;
+        if let Some(m) = multipliers.get(i) {
+            temp = Num::mul_by_var_with_coeff(cs.namespace(|| ""), &temp, m, E::Fr::one())?.into();
+        }
         if i > 0 {
             temp = Num::mul_by_var_with_coeff(cs.namespace(|| ""), &temp, &aggr_mult, E::Fr::one())?.into();
             aggr_mult = aggr_mult.mul(cs.namespace(|| ""), &alpha)?;
@@ -134,18 +144,64 @@ pub fn find_setup_value_by_label<E: Engine, I: OracleGadget<E>>(
 }
 
 
+// all the upper layer polynomials are checked by FRI against the common degree bound D = initial_degree_plus_one
+// if polynomial f_i has its own (smaller) degree bound d_i, the corresponding quotient is multiplied 
+// by m_i(x) = 1 + gamma * x^(D - d_i + k_i), where k_i is the number of opening points of f_i:
+// the quotient has degree < d_i - k_i, so the combined polynomial has degree < D only if deg(f_i) < d_i
+pub struct DegreeCorrection<E: Engine> {
+    pub challenge: AllocatedNum<E>,
+    // little-endian bit decompositions of D - d_i + k_i
+    pub shifts: LabeledVec<Vec<Boolean>>,
+}
+
+
+fn degree_correction_multipliers<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    labels: &[Label],
+    x: &Option<AllocatedNum<E>>,
+    degree_correction: Option<&DegreeCorrection<E>>,
+) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
+{
+    let (x, degree_correction) = match (x, degree_correction) {
+        (Some(x), Some(degree_correction)) => (x, degree_correction),
+        _ => return Ok(vec![]),
+    };
+
+    let mut res = Vec::with_capacity(labels.len());
+    for label in labels.iter() {
+        let shift = degree_correction.shifts.iter().find(|elem| elem.label == *label).ok_or(SynthesisError::Unknown)?;
+        let x_pow = AllocatedNum::pow(cs.namespace(|| ""), x, shift.data.iter())?;
+        let temp = x_pow.mul(cs.namespace(|| ""), &degree_correction.challenge)?;
+
+        let mut multiplier = Num::from_constant(&E::Fr::one(), &*cs);
+        multiplier += temp;
+        res.push(multiplier.simplify(cs.namespace(|| ""))?);
+    }
+
+    Ok(res)
+}
+
+
 pub fn upper_layer_combiner_impl<E: Engine, I: OracleGadget<E>, CS: ConstraintSystem<E>>(
     mut cs: CS,
     domain_values: Vec<Labeled<&AllocatedNum<E>>>,
-    evaluation_point : &Num<E>,
+    evaluation_point : &AllocatedNum<E>,
     setup_precomp: &RedshiftSetupPrecomputation<E, I>,
     opening_values: &LabeledVec<AllocatedNum<E>>,
     z: AllocatedNum<E>,
     aggr_challenge : AllocatedNum<E>,
     omega: &E::Fr,
+    degree_correction: Option<&DegreeCorrection<E>>,
 ) -> Result<AllocatedNum<E>, SynthesisError> 
 {
     let setup_polys = &setup_precomp.data;
+
+    // x is required in allocated form only for degree correction
+    let x = match degree_correction {
+        Some(_) => Some(evaluation_point.clone()),
+        None => None,
+    };
+    let evaluation_point : Num<E> = evaluation_point.clone().into();
     
     // combine polynomials a, b, t_low, t_mid, t_high,
     // which are opened only at z
@@ -157,8 +213,11 @@ pub fn upper_layer_combiner_impl<E: Engine, I: OracleGadget<E>, CS: ConstraintSy
         (find_by_label("t_high", &domain_values)?.clone(), find_by_label("t_high", opening_values)?.clone()),
     ];
        
+    let multipliers = degree_correction_multipliers(
+        &mut cs, &SINGLE_POINT_OPENING_LABELS, &x, degree_correction)?;
+       
     let (res1, alpha1) = combine_at_single_point(
-        &mut cs, pairs, &evaluation_point, z.clone(), aggr_challenge.clone())?;
+        &mut cs, pairs, &evaluation_point, z.clone(), aggr_challenge.clone(), &multipliers)?;
 
 
     // combine witness polynomials z_1, z_2, c which are opened at z and z * omega
@@ -180,8 +239,10 @@ pub fn upper_layer_combiner_impl<E: Engine, I: OracleGadget<E>, CS: ConstraintSy
           find_by_label("c_shifted", opening_values)?.clone() ),
     ];
 
+    let multipliers = degree_correction_multipliers(&mut cs, &["z_1", "z_2", "c"], &x, degree_correction)?;
+
     let (res2, alpha2) = combine_at_two_points(
-        &mut cs, witness_triples, &evaluation_point, z.clone(), z_shifted.clone(), aggr_challenge.clone())?;
+        &mut cs, witness_triples, &evaluation_point, z.clone(), z_shifted.clone(), aggr_challenge.clone(), &multipliers)?;
 
     // finally combine setup polynomials q_l, q_r, q_o, q_m, q_c, q_add_sel, s_id, sigma_1, sigma_2, sigma_3
     // which are opened at z and z_setup
@@ -231,8 +292,15 @@ pub fn upper_layer_combiner_impl<E: Engine, I: OracleGadget<E>, CS: ConstraintSy
 
     let common_setup_point = setup_precomp.setup_point.clone();
 
+    let multipliers = degree_correction_multipliers(
+        &mut cs, 
+        &["q_l", "q_r", "q_o", "q_m", "q_c", "q_add_sel", "s_id", "sigma_1", "sigma_2", "sigma_3"], 
+        &x, 
+        degree_correction,
+    )?;
+
     let (res3, _) = combine_at_two_points(
-        &mut cs, setup_triples, &evaluation_point, z, common_setup_point, aggr_challenge)?;
+        &mut cs, setup_triples, &evaluation_point, z, common_setup_point, aggr_challenge, &multipliers)?;
 
     // res = res1 + res2 * alpha_1 + res3 * alpha_1 * alpha_2
     // we constraint it in the form res - res1 = alpha_1 * (res2 + res3 * alpha2)
@@ -272,6 +340,7 @@ pub struct ReshiftCombiner<E: Engine, I: OracleGadget<E>> {
     pub z: AllocatedNum<E>,
     pub aggr_challenge : AllocatedNum<E>,
     pub omega: E::Fr,
+    pub degree_correction: Option<DegreeCorrection<E>>,
 }
 
 impl<E: Engine, I: OracleGadget<E>> UpperLayerCombiner<E> for ReshiftCombiner<E, I> {
@@ -280,7 +349,7 @@ impl<E: Engine, I: OracleGadget<E>> UpperLayerCombiner<E> for ReshiftCombiner<E,
         &self,
         cs: CS, 
         domain_values: Vec<Labeled<&AllocatedNum<E>>>,
-        evaluation_point : &Num<E>
+        evaluation_point : &AllocatedNum<E>
     ) -> Result<AllocatedNum<E>, SynthesisError>
     {
         upper_layer_combiner_impl(
@@ -292,8 +361,279 @@ impl<E: Engine, I: OracleGadget<E>> UpperLayerCombiner<E> for ReshiftCombiner<E,
             self.z.clone(),
             self.aggr_challenge.clone(),
             &self.omega,
+            self.degree_correction.as_ref(),
         )
     }
 }
 
 
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::redshift::IOP::FRI::coset_combining_fri::FriParams;
+    use crate::tester::naming_oblivious_cs::NamingObliviousConstraintSystem as TestConstraintSystem;
+
+    use common::field_gadget::TwoAdicField;
+    use fri::{FriVerifierGadget, FriGadgetConfig, FriFinalLayer};
+    use fri::native_prover::{evaluate_over_coset, prove_fri_over_values};
+    use hashes::poseidon::Poseidon;
+    use hashes::poseidon::params::DefaultPoseidonParams;
+    use oracles::poseidon_merklee_proof::*;
+    use channel::ChannelGadget;
+    use channel::poseidon_channel::PoseidonChannelGadget;
+    use channel::sponge_channel::NativeSpongeChannel;
+    use redshift_circuit::circuit::{compute_degree_shifts, get_fri_challenges};
+    use rand::*;
+
+    type S = Poseidon<Fr, DefaultPoseidonParams<Fr>>;
+    type OG<'a> = PoseidonTreeGadget<'a, Bn256, DefaultPoseidonParams<Fr>>;
+
+    const SIZE: usize = 64;
+    const OPENING_LABELS: [Label; 21] = ["a", "b", "c", "c_shifted", "q_l", "q_r", "q_o", "q_m", "q_c", "q_add_sel", 
+        "s_id", "sigma_1", "sigma_2", "sigma_3", "z_1", "z_2", "z_1_shifted", "z_2_shifted", "t_low", "t_mid", "t_high"];
+
+
+    // only one upper layer oracle is committed: all the other polynomials are zero,
+    // so their queried values are filled in before the Redshift combiner is applied
+    struct PaddedReshiftCombiner<'a> {
+        inner: ReshiftCombiner<Bn256, OG<'a>>,
+        zero: AllocatedNum<Bn256>,
+    }
+
+    impl<'a> UpperLayerCombiner<Bn256> for PaddedReshiftCombiner<'a> {
+        fn combine<CS: ConstraintSystem<Bn256>>(
+            &self,
+            cs: CS, 
+            domain_values: Vec<Labeled<&AllocatedNum<Bn256>>>,
+            evaluation_point : &AllocatedNum<Bn256>
+        ) -> Result<AllocatedNum<Bn256>, SynthesisError>
+        {
+            let mut values : Vec<Labeled<&AllocatedNum<Bn256>>> = domain_values.into_iter().map(|elem| Labeled::new(elem.label, elem.data)).collect();
+            for label in FRI_UPPER_LAYER_LABELS.iter() {
+                if values.iter().all(|elem| elem.label != *label) {
+                    values.push(Labeled::new(label, &self.zero));
+                }
+            }
+            self.inner.combine(cs, values, evaluation_point)
+        }
+    }
+
+
+    fn evaluate_at(coeffs: &[Fr], point: &Fr) -> Fr {
+        let mut res = Fr::zero();
+        for c in coeffs.iter().rev() {
+            res.mul_assign(point);
+            res.add_assign(c);
+        }
+        res
+    }
+
+    // the oracle with the given label is committed to f, all the other Redshift polynomials are zero;
+    // the prover folds the combined polynomial (quotient of f multiplied by the degree correction multiplier, if any)
+    // and the verifier recomputes it from the queried values of f with ReshiftCombiner
+    fn degree_correction_test_impl(label: Label, coeffs: &[Fr], degree_bound: Option<usize>) -> Option<bool> 
+    {
+        let params = DefaultPoseidonParams::<Fr>::bn256_default();
+        let fri_params = FriParams {
+            collapsing_factor: 2,
+            R: 2,
+            initial_degree_plus_one: std::cell::Cell::new(SIZE),
+            lde_factor: 4,
+            final_degree_plus_one: std::cell::Cell::new(4),
+        };
+        let fri_config = FriGadgetConfig { 
+            coset_factor: Fr::multiplicative_generator(), 
+            final_layer: FriFinalLayer::Coefficients, 
+            ..Default::default() 
+        };
+        let domain_size = SIZE * fri_params.lde_factor;
+        let num_opening_points = match SINGLE_POINT_OPENING_LABELS.contains(&label) {
+            true => 1,
+            false => 2,
+        };
+
+        let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let gamma : Fr = rng.gen();
+        let z : Fr = rng.gen();
+        let setup_point : Fr = rng.gen();
+        let omega = Fr::domain_generator(SIZE);
+        let mut z_shifted = z;
+        z_shifted.mul_assign(&omega);
+
+        let opening = evaluate_at(coeffs, &z);
+        let opening_shifted = evaluate_at(coeffs, &z_shifted);
+
+        let upper_layer_values = evaluate_over_coset(coeffs, domain_size, &fri_config.coset_factor);
+        let generator = Fr::domain_generator(domain_size);
+        let mut x = fri_config.coset_factor;
+        let mut values = Vec::with_capacity(domain_size);
+        for f_x in upper_layer_values.iter() {
+            let mut x_minus_z = x;
+            x_minus_z.sub_assign(&z);
+
+            // (f(x) - f(z)) / (x - z) or (f(x) - U(x)) / (x - z)(x - z * omega) with linear interpolant U
+            let mut quotient = *f_x;
+            quotient.sub_assign(&opening);
+            let mut denominator = x_minus_z;
+            if num_opening_points == 2 {
+                let mut slope = opening_shifted;
+                slope.sub_assign(&opening);
+                let mut temp = z_shifted;
+                temp.sub_assign(&z);
+                slope.mul_assign(&temp.inverse().unwrap());
+                slope.mul_assign(&x_minus_z);
+                quotient.sub_assign(&slope);
+
+                let mut x_minus_z_shifted = x;
+                x_minus_z_shifted.sub_assign(&z_shifted);
+                denominator.mul_assign(&x_minus_z_shifted);
+            }
+            quotient.mul_assign(&denominator.inverse().unwrap());
+
+            if let Some(degree_bound) = degree_bound {
+                let mut multiplier = x.pow(&[(SIZE - degree_bound + num_opening_points) as u64]);
+                multiplier.mul_assign(&gamma);
+                multiplier.add_assign(&Fr::one());
+                quotient.mul_assign(&multiplier);
+            }
+
+            values.push(quotient);
+            x.mul_assign(&generator);
+        }
+
+        let mut channel = NativeSpongeChannel::<Fr, S, _>::new(&params);
+        let native_proof = prove_fri_over_values::<_, _, _, S, _, _>(
+            &upper_layer_values, values, &fri_params, &fri_config, &params, true, &mut channel);
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let upper_layer_commitment = AllocatedNum::alloc_input(
+            cs.namespace(|| "upper layer commitment"), || Ok(native_proof.upper_layer_commitment)).unwrap();
+        let labels = [label];
+        let proof = BatchedFriProof::<Bn256, OG>::from_stream(
+            cs.namespace(|| "proof"), 
+            &mut native_proof.to_stream().into_iter().map(|x| Some(x)), 
+            (fri_params.clone(), fri_config.clone(), &labels[..]),
+        ).unwrap();
+
+        let mut channel = PoseidonChannelGadget::<Bn256, DefaultPoseidonParams<Fr>>::new(&params);
+        channel.consume(upper_layer_commitment.clone(), cs.namespace(|| "consume upper layer commitment")).unwrap();
+        let fri_challenges = get_fri_challenges(&mut cs, &proof, &mut channel).unwrap();
+
+        for coef in proof.final_coefficients.iter().cloned() {
+            channel.consume(coef, cs.namespace(|| "consume final coefficient")).unwrap();
+        }
+        let natural_first_element_indexes = (0..fri_params.R).map(|_| {
+            let packed = channel.produce_challenge(cs.namespace(|| "produce query index")).unwrap();
+            let mut bits = packed.into_bits_le(cs.namespace(|| "decompose query index")).unwrap();
+            bits.truncate(64);
+            bits
+        }).collect();
+
+        let mut alloc = |value: Fr| AllocatedNum::alloc2(cs.namespace(|| "combiner data"), Some(value)).unwrap();
+        let zero = alloc(Fr::zero());
+
+        let setup_precomp = RedshiftSetupPrecomputation::<Bn256, OG> {
+            setup_point: alloc(setup_point),
+            data: FRI_UPPER_LAYER_LABELS[..10].iter().map(|label| {
+                Labeled::new(label, SinglePolySetupData { setup_value: zero.clone(), commitment: zero.clone() })
+            }).collect(),
+        };
+        let shifted_label = format!("{}_shifted", label);
+        let opening_values = OPENING_LABELS.iter().map(|l| {
+            let value = match (*l == label, *l == shifted_label) {
+                (true, _) => opening,
+                (_, true) => opening_shifted,
+                _ => Fr::zero(),
+            };
+            Labeled::new(l, alloc(value))
+        }).collect();
+
+        // in Redshift gamma is produced by the channel, here it is fixed in advance;
+        // all the zero polynomials are given the common bound
+        let degree_correction = match degree_bound {
+            Some(degree_bound) => {
+                let bounds = FRI_UPPER_LAYER_LABELS.iter().map(|l| {
+                    Labeled::new(l, if *l == label { degree_bound } else { SIZE })
+                }).collect();
+                let shifts = compute_degree_shifts(&bounds, SIZE).unwrap();
+                Some(DegreeCorrection { challenge: alloc(gamma), shifts })
+            },
+            None => None,
+        };
+
+        let upper_layer_combiner = PaddedReshiftCombiner {
+            inner: ReshiftCombiner {
+                setup_precomp,
+                opening_values,
+                z: alloc(z),
+                aggr_challenge: alloc(Fr::one()),
+                omega,
+                degree_correction,
+            },
+            zero,
+        };
+
+        let oracle_params = PoseidonTreeGadgetParams::new(1 << fri_params.collapsing_factor, &params);
+
+        let fri_verifier_gadget = FriVerifierGadget::<Bn256, OG, _> {
+            collapsing_factor : fri_params.collapsing_factor as usize,
+            num_query_rounds : fri_params.R,
+            initial_degree_plus_one : fri_params.initial_degree_plus_one.get(),
+            lde_factor: fri_params.lde_factor,
+            final_degree_plus_one : fri_params.final_degree_plus_one.get(),
+            upper_layer_combiner,
+            config: fri_config.clone(),
+
+            _engine_marker : std::marker::PhantomData::<Bn256>,
+            _oracle_marker : std::marker::PhantomData::<OG>,
+            _field_marker : std::marker::PhantomData::<(AllocatedNum<Bn256>, AllocatedNum<Bn256>)>,
+        };
+
+        let is_valid = fri_verifier_gadget.verify_proof(
+            cs.namespace(|| "FRI verification"),
+            &oracle_params,
+            &[Labeled::new(label, upper_layer_commitment)],
+            &proof.commitments,
+            &proof.final_coefficients,
+            &fri_challenges,
+            natural_first_element_indexes,
+            &proof.fri_round_queries,
+        ).unwrap();
+
+        is_valid.get_value()
+    }
+
+    #[test]
+    fn test_degree_correction() 
+    {
+        const DEGREE_BOUND: usize = 16;
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let honest : Vec<Fr> = (0..DEGREE_BOUND).map(|_| rng.gen()).collect();
+        let over_degree : Vec<Fr> = (0..(DEGREE_BOUND + 1)).map(|_| rng.gen()).collect();
+
+        // "a" is opened at the single point, "z_1" - at two points
+        for label in ["a", "z_1"].iter() {
+            assert_eq!(degree_correction_test_impl(label, &honest, Some(DEGREE_BOUND)), Some(true));
+            // deg(f) = d is far below the common bound D, so only the degree correction catches it:
+            // the quotient has degree d - k, so multiplied by x^(D - d + k) it is of degree D
+            assert_eq!(degree_correction_test_impl(label, &over_degree, None), Some(true));
+            assert_eq!(degree_correction_test_impl(label, &over_degree, Some(DEGREE_BOUND)), Some(false));
+        }
+
+        // the shifts are constants: no constraints and no bounds above the common one
+        let bounds = vec![Labeled::new("a", DEGREE_BOUND), Labeled::new("z_1", DEGREE_BOUND)];
+        let shifts = compute_degree_shifts(&bounds, SIZE).unwrap();
+        for (shift, expected) in shifts.iter().zip([SIZE - DEGREE_BOUND + 1, SIZE - DEGREE_BOUND + 2].iter()) {
+            for (i, bit) in shift.data.iter().enumerate() {
+                match bit {
+                    Boolean::Constant(value) => assert_eq!(*value, (expected >> i) & 1 == 1),
+                    _ => panic!("degree shift should be a constant"),
+                }
+            }
+        }
+        assert!(compute_degree_shifts(&vec![Labeled::new("a", SIZE + 1)], SIZE).is_err());
+    }
+}